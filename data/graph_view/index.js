@@ -9,11 +9,20 @@
 const ZOOM_TRANSITION_DURATION_MS = 200;
 const TRANSITION_DURATION_MS = 400;
 
+// Graph coordinates are in points, where an inch, the unit of `width` and
+// `height`, is 72 points.
+const GRID_SPACING_PT = 18;
+const GRID_MAJOR_LINE_EVERY = 4;
+
 const initEndHandler = window.webkit.messageHandlers.initEnd;
 const errorHandler = window.webkit.messageHandlers.error;
 const isRenderingChangedHandler = window.webkit.messageHandlers.isRenderingChanged;
 const isGraphLoadedChangedHandler = window.webkit.messageHandlers.isGraphLoadedChanged;
 const zoomLevelChangedHandler = window.webkit.messageHandlers.zoomLevelChanged;
+const elementClickedHandler = window.webkit.messageHandlers.elementClicked;
+const addNodeRequestedHandler = window.webkit.messageHandlers.addNodeRequested;
+const addEdgeRequestedHandler = window.webkit.messageHandlers.addEdgeRequested;
+const deleteRequestedHandler = window.webkit.messageHandlers.deleteRequested;
 
 class GraphView {
     constructor() {
@@ -25,19 +34,33 @@ class GraphView {
 
         this._originalAttributes = null;
 
+        this._editTool = "none";
+        this._edgeTail = null;
+        this._selectedElement = null;
+
+        this._isGridVisible = false;
+
         this._setSvg(null);
         this._setRendering(false);
 
         this._pendingUpdate = false;
+        // Increased on every render, so the callbacks of abandoned ones can
+        // be told apart.
+        this._renderGeneration = 0;
 
         this._div = d3.select("#graph");
         this._graphviz = this._div.graphviz()
-            .onerror(this._handleError.bind(this))
             .on("initEnd", this._handleInitEnd.bind(this))
             .transition(() => {
                 return d3.transition().duration(TRANSITION_DURATION_MS);
             });
 
+        this._div.on("click", this._handleClick.bind(this));
+        this._div.on("pointermove", this._handlePointerMove.bind(this));
+        this._div.on("pointerleave", () => this._setCoordinates(null));
+        this._coordinates = d3.select("#coordinates");
+        d3.select(document).on("keydown", this._handleKeyDown.bind(this));
+
         d3.select(window).on("resize", () => {
             if (this._svg) {
                 this._svg.attr("width", window.innerWidth).attr("height", window.innerHeight);
@@ -45,7 +68,11 @@ class GraphView {
         });
     }
 
-    _handleError(error) {
+    _handleError(generation, error) {
+        if (generation !== this._renderGeneration) {
+            return;
+        }
+
         this._setRendering(false);
 
         if (this._pendingUpdate) {
@@ -56,6 +83,175 @@ class GraphView {
         errorHandler.postMessage(error);
     }
 
+    _handleClick(event) {
+        const element = event.target.closest("g.node, g.edge");
+
+        switch (this._editTool) {
+            case "addNode":
+                if (element === null) {
+                    addNodeRequestedHandler.postMessage(null);
+                }
+                return;
+            case "addEdge":
+                this._handleAddEdgeClick(element);
+                return;
+        }
+
+        this._setSelectedElement(element);
+
+        if (element === null) {
+            elementClickedHandler.postMessage(null);
+            return;
+        }
+
+        elementClickedHandler.postMessage(this._elementInfo(element));
+    }
+
+    _handlePointerMove(event) {
+        if (!this._isGridVisible || !this._svg) {
+            return;
+        }
+
+        const graph = this._svg.select("g.graph").node();
+        if (!graph) {
+            return;
+        }
+
+        // Graphviz's y-axis points up, unlike SVG's.
+        const [x, y] = d3.pointer(event, graph);
+        this._setCoordinates({ x, y: -y });
+    }
+
+    _setCoordinates(coordinates) {
+        if (coordinates === null) {
+            this._coordinates.attr("hidden", "");
+            return;
+        }
+
+        this._coordinates
+            .text(`x ${coordinates.x.toFixed(1)}  y ${coordinates.y.toFixed(1)}`)
+            .attr("hidden", null);
+    }
+
+    _updateGrid() {
+        if (!this._svg) {
+            return;
+        }
+
+        this._svg.selectAll("g.grid").remove();
+
+        if (!this._isGridVisible) {
+            return;
+        }
+
+        const graph = this._svg.select("g.graph");
+        if (graph.empty()) {
+            return;
+        }
+
+        // The background polygon spans the whole graph.
+        const background = graph.select(":scope > polygon");
+        const bounds = (background.empty() ? graph : background).node().getBBox();
+
+        const minX = Math.floor(bounds.x / GRID_SPACING_PT) * GRID_SPACING_PT;
+        const maxX = Math.ceil((bounds.x + bounds.width) / GRID_SPACING_PT) * GRID_SPACING_PT;
+        const minY = Math.floor(bounds.y / GRID_SPACING_PT) * GRID_SPACING_PT;
+        const maxY = Math.ceil((bounds.y + bounds.height) / GRID_SPACING_PT) * GRID_SPACING_PT;
+
+        // Below clusters, nodes, and edges, but above the background.
+        const grid = graph.insert("g", ":scope > g").attr("class", "grid");
+        const isMajor = (value) => Math.round(value / GRID_SPACING_PT) % GRID_MAJOR_LINE_EVERY === 0;
+
+        for (let x = minX; x <= maxX; x += GRID_SPACING_PT) {
+            grid.append("line")
+                .attr("x1", x).attr("y1", minY)
+                .attr("x2", x).attr("y2", maxY)
+                .classed("major", isMajor(x));
+            if (isMajor(x)) {
+                grid.append("text")
+                    .attr("x", x + 1).attr("y", maxY - 1)
+                    .text(x);
+            }
+        }
+
+        for (let y = minY; y <= maxY; y += GRID_SPACING_PT) {
+            grid.append("line")
+                .attr("x1", minX).attr("y1", y)
+                .attr("x2", maxX).attr("y2", y)
+                .classed("major", isMajor(y));
+            if (isMajor(y)) {
+                grid.append("text")
+                    .attr("x", minX + 1).attr("y", y - 1)
+                    .text(-y);
+            }
+        }
+    }
+
+    _handleKeyDown(event) {
+        if (event.key !== "Delete" || this._editTool !== "select" || this._selectedElement === null) {
+            return;
+        }
+
+        event.preventDefault();
+
+        deleteRequestedHandler.postMessage(this._elementInfo(this._selectedElement));
+        this._setSelectedElement(null);
+    }
+
+    _setSelectedElement(element) {
+        if (this._selectedElement !== null) {
+            this._selectedElement.classList.remove("selected");
+        }
+
+        this._selectedElement = element;
+
+        if (element !== null) {
+            element.classList.add("selected");
+        }
+    }
+
+    _elementInfo(element) {
+        return {
+            isEdge: element.classList.contains("edge"),
+            title: this._elementTitle(element),
+        };
+    }
+
+    _handleAddEdgeClick(element) {
+        if (element === null || !element.classList.contains("node")) {
+            this._setEdgeTail(null);
+            return;
+        }
+
+        if (this._edgeTail === null) {
+            this._setEdgeTail(element);
+            return;
+        }
+
+        addEdgeRequestedHandler.postMessage({
+            tail: this._elementTitle(this._edgeTail),
+            head: this._elementTitle(element),
+        });
+        this._setEdgeTail(null);
+    }
+
+    _setEdgeTail(element) {
+        if (this._edgeTail !== null) {
+            this._edgeTail.classList.remove("edge-tail");
+        }
+
+        this._edgeTail = element;
+
+        if (element !== null) {
+            element.classList.add("edge-tail");
+        }
+    }
+
+    _elementTitle(element) {
+        const title = element.querySelector(":scope > title");
+        return title ? title.textContent : "";
+    }
+
     _handleInitEnd() {
         initEndHandler.postMessage(null);
         zoomLevelChangedHandler.postMessage(this._getZoomLevel());
@@ -63,7 +259,11 @@ class GraphView {
         this._renderGraph();
     }
 
-    _handleDotLayoutDone() {
+    _handleDotLayoutDone(generation) {
+        if (generation !== this._renderGeneration) {
+            return;
+        }
+
         const attributes = this._graphviz.data().attributes;
         this._originalAttributes = {
             height: attributes.height,
@@ -72,7 +272,11 @@ class GraphView {
         };
     }
 
-    _handleRenderDone() {
+    _handleRenderDone(generation) {
+        if (generation !== this._renderGeneration) {
+            return;
+        }
+
         const svg = this._div.selectWithoutDataPropagation("svg");
         this._originalAttributes.transform = svg.selectWithoutDataPropagation("g").attr("transform");
 
@@ -81,6 +285,8 @@ class GraphView {
 
         this._graphviz.zoomBehavior().on("end", this._handleZoomEnd.bind(this));
 
+        this._updateGrid();
+
         if (this._pendingUpdate) {
             this._pendingUpdate = false;
             this._renderGraph();
@@ -118,6 +324,7 @@ class GraphView {
         }
 
         this._setRendering(true);
+        this._renderGeneration += 1;
 
         if (this._dotSrc.length === 0) {
             if (this._svg) {
@@ -134,19 +341,52 @@ class GraphView {
             return;
         }
 
+        // Graphviz replaces the contents of the graph, so the grid is added
+        // back once it is rendered.
+        if (this._svg) {
+            this._svg.selectAll("g.grid").remove();
+        }
+
+        const generation = this._renderGeneration;
         this._graphviz
             .width(window.innerWidth)
             .height(window.innerHeight)
             .fit(true)
             .engine(this._engine)
-            .dot(this._dotSrc, this._handleDotLayoutDone.bind(this))
-            .render(this._handleRenderDone.bind(this));
+            .onerror((error) => this._handleError(generation, error))
+            .dot(this._dotSrc, () => this._handleDotLayoutDone(generation))
+            .render(() => this._handleRenderDone(generation));
+    }
+
+    _abortRender() {
+        // Layouts run synchronously, so only the transition to an outdated
+        // graph can still be running. It is interrupted by the next one, and
+        // its end is ignored.
+        this._renderGeneration += 1;
+        this._pendingUpdate = false;
+        this._setRendering(false);
     }
 
     graphvizVersion() {
         return this._graphviz.graphvizVersion();
     }
 
+    supportedEngines(engines) {
+        // Only available when not using a web worker.
+        if (typeof this._graphviz.layoutSync !== "function") {
+            return null;
+        }
+
+        return engines.filter((engine) => {
+            try {
+                this._graphviz.layoutSync("graph {}", "svg", engine);
+                return true;
+            } catch {
+                return false;
+            }
+        });
+    }
+
     setData(dotSrc, engine) {
         this._prevDotSrc = this._dotSrc;
         this._prevEngine = this._engine;
@@ -154,9 +394,31 @@ class GraphView {
         this._dotSrc = dotSrc;
         this._engine = engine;
 
+        // Instead of waiting for it to finish, a render of outdated data is
+        // abandoned.
+        if (this._rendering) {
+            this._abortRender();
+        }
+
         this._renderGraph();
     }
 
+    setEditTool(tool) {
+        this._editTool = tool;
+        this._setEdgeTail(null);
+
+        this._div.attr("data-edit-tool", tool);
+    }
+
+    setGridVisible(visible) {
+        this._isGridVisible = visible;
+        this._updateGrid();
+
+        if (!visible) {
+            this._setCoordinates(null);
+        }
+    }
+
     setZoomScaleExtent(min, max) {
         this._graphviz.zoomScaleExtent([min, max]);
     }
@@ -181,6 +443,24 @@ class GraphView {
         this._graphviz.resetZoom(transition);
     }
 
+    getNodePositions() {
+        if (!this._svg) {
+            return null;
+        }
+
+        // The nodes are not transformed, so their coordinates are those of
+        // the graph, except that Graphviz's y-axis points up.
+        const positions = {};
+        for (const node of this._svg.selectAll("g.node").nodes()) {
+            const bounds = node.getBBox();
+            positions[this._elementTitle(node)] = [
+                bounds.x + bounds.width / 2,
+                -(bounds.y + bounds.height / 2),
+            ];
+        }
+        return positions;
+    }
+
     getSvgString() {
         if (!this._svg) {
             return null;
@@ -197,10 +477,50 @@ class GraphView {
         clone.setAttribute("height", this._originalAttributes.height);
         clone.setAttribute("viewBox", this._originalAttributes.viewBox);
         clone.children[0].setAttribute("transform", this._originalAttributes.transform);
+        // The grid is only a guide in the preview.
+        for (const grid of clone.querySelectorAll("g.grid")) {
+            grid.remove();
+        }
 
         const serializer = new XMLSerializer();
         return serializer.serializeToString(clone);
     }
+
+    // Posts the SVG to `uri` rather than returning it, so large graphs are
+    // read as a stream instead of being converted as a whole.
+    async sendSvg(uri) {
+        const svgString = this.getSvgString();
+
+        if (svgString === null) {
+            return false;
+        }
+
+        await this._postSvg(uri, svgString);
+        return true;
+    }
+
+    // Like `sendSvg`, but for a graph that is laid out without being shown.
+    async sendLayout(uri, dotSrc, engine) {
+        // Only available when not using a web worker.
+        if (typeof this._graphviz.layoutSync !== "function") {
+            return false;
+        }
+
+        const svgString = this._graphviz.layoutSync(dotSrc, "svg", engine);
+        await this._postSvg(uri, svgString);
+        return true;
+    }
+
+    async _postSvg(uri, svgString) {
+        const response = await fetch(uri, {
+            method: "POST",
+            body: new Blob([svgString], { type: "image/svg+xml" }),
+        });
+
+        if (!response.ok) {
+            throw new Error(`Failed to send SVG: ${response.status}`);
+        }
+    }
 }
 
 const graphView = new GraphView();
@@ -0,0 +1,78 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use crate::dot;
+
+/// Converts a DOT graph into its adjacency matrix as CSV.
+///
+/// The first row and column are the node IDs, and each cell is the number of
+/// edges from the node of the row to the node of the column. Edges of
+/// undirected graphs are counted in both directions.
+pub fn from_dot(source: &str) -> Result<String> {
+    let graph = dot::parse(source)?;
+    let topology = graph.topology();
+
+    let n_nodes = topology.nodes.len();
+    let mut matrix = vec![vec![0_u32; n_nodes]; n_nodes];
+    for &(source, target) in &topology.edges {
+        matrix[source][target] += 1;
+
+        if !graph.is_directed && source != target {
+            matrix[target][source] += 1;
+        }
+    }
+
+    // Strict graphs don't allow multi-edges.
+    if graph.is_strict {
+        for cell in matrix.iter_mut().flatten() {
+            *cell = (*cell).min(1);
+        }
+    }
+
+    let mut ret = String::new();
+
+    for id in &topology.nodes {
+        write!(ret, ",{}", escape_field(id)).unwrap();
+    }
+    ret.push('\n');
+
+    for (id, row) in topology.nodes.iter().zip(&matrix) {
+        ret.push_str(&escape_field(id));
+        for cell in row {
+            write!(ret, ",{}", cell).unwrap();
+        }
+        ret.push('\n');
+    }
+
+    Ok(ret)
+}
+
+fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directed() {
+        assert_eq!(
+            from_dot("digraph { a -> b; a -> b; b -> \"c, d\"; \"c, d\" -> a }").unwrap(),
+            ",a,b,\"c, d\"\na,0,2,0\nb,0,0,1\n\"c, d\",1,0,0\n"
+        );
+    }
+
+    #[test]
+    fn undirected() {
+        assert_eq!(
+            from_dot("strict graph { a -- b; b -- a; a -- a; c }").unwrap(),
+            ",a,b,c\na,1,1,0\nb,1,0,0\nc,0,0,0\n"
+        );
+    }
+}
@@ -1,23 +1,55 @@
+use std::slice;
+
 use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
 use gtk::{
     gio,
     glib::{self, clone},
 };
+use tracing::Instrument;
 
 use crate::{
     about,
     config::{APP_ID, PKGDATADIR, PROFILE, VERSION},
-    save_changes_dialog,
+    crash_report,
+    file_position::FilePosition,
+    graph_view::GraphView,
+    preferences_dialog::PreferencesDialog,
+    remote_control, save_changes_dialog,
     session::Session,
+    settings::Settings,
     utils,
 };
 
 mod imp {
+    use std::{
+        cell::{Cell, RefCell},
+        ffi::c_char,
+        io::{self, Read},
+    };
+
+    use glib::translate::from_glib_borrow;
+
     use super::*;
 
     #[derive(Debug, Default)]
     pub struct Application {
         pub(super) session: Session,
+        pub(super) settings: Settings,
+
+        /// Whether `--no-restore` was passed
+        pub(super) no_restore: Cell<bool>,
+        /// Contents read from the standard input, to be opened as drafts
+        /// once the session is restored
+        pub(super) pending_drafts: RefCell<Vec<String>>,
+        pub(super) remote_control_id: RefCell<Option<gio::RegistrationId>>,
+        /// Keeps the app running without windows, if enabled
+        pub(super) background_hold_guard: RefCell<Option<gio::ApplicationHoldGuard>>,
+        /// Activation token of the request being handled, if any
+        pub(super) activation_token: RefCell<Option<String>>,
+        /// View that is never shown, used to lay out graphs of pages whose
+        /// own view is not initialized
+        pub(super) shared_graph_renderer: RefCell<Option<GraphView>>,
     }
 
     #[glib::object_subclass]
@@ -25,9 +57,112 @@ mod imp {
         const NAME: &'static str = "DelineateApplication";
         type Type = super::Application;
         type ParentType = adw::Application;
+
+        fn class_init(klass: &mut Self::Class) {
+            // FIXME Override `ApplicationImpl::dbus_register` and
+            // `dbus_unregister` instead once gio-rs binds them.
+            let klass = klass as *mut Self::Class as *mut gio::ffi::GApplicationClass;
+            unsafe {
+                (*klass).dbus_register = Some(dbus_register_trampoline);
+                (*klass).dbus_unregister = Some(dbus_unregister_trampoline);
+            }
+        }
+    }
+
+    unsafe extern "C" fn dbus_register_trampoline(
+        ptr: *mut gio::ffi::GApplication,
+        connection: *mut gio::ffi::GDBusConnection,
+        object_path: *const c_char,
+        error: *mut *mut glib::ffi::GError,
+    ) -> glib::ffi::gboolean {
+        let parent_class =
+            Application::type_data().as_ref().parent_class() as *const gio::ffi::GApplicationClass;
+        if let Some(parent_dbus_register) = (*parent_class).dbus_register {
+            if parent_dbus_register(ptr, connection, object_path, error) == glib::ffi::GFALSE {
+                return glib::ffi::GFALSE;
+            }
+        }
+
+        let instance = &*(ptr as *mut <Application as ObjectSubclass>::Instance);
+        instance.imp().dbus_register(
+            &from_glib_borrow(connection),
+            glib::GStr::from_ptr(object_path),
+        );
+
+        glib::ffi::GTRUE
+    }
+
+    unsafe extern "C" fn dbus_unregister_trampoline(
+        ptr: *mut gio::ffi::GApplication,
+        connection: *mut gio::ffi::GDBusConnection,
+        object_path: *const c_char,
+    ) {
+        let instance = &*(ptr as *mut <Application as ObjectSubclass>::Instance);
+        instance
+            .imp()
+            .dbus_unregister(&from_glib_borrow(connection));
+
+        let parent_class =
+            Application::type_data().as_ref().parent_class() as *const gio::ffi::GApplicationClass;
+        if let Some(parent_dbus_unregister) = (*parent_class).dbus_unregister {
+            parent_dbus_unregister(ptr, connection, object_path);
+        }
     }
 
-    impl ObjectImpl for Application {}
+    impl Application {
+        /// Exports the remote control once the app is on the bus, so no
+        /// method call arrives before it.
+        fn dbus_register(&self, connection: &gio::DBusConnection, object_path: &str) {
+            // Failing to register it must not keep the app from running.
+            match remote_control::register(&self.obj(), connection, object_path) {
+                Ok(id) => {
+                    self.remote_control_id.replace(Some(id));
+                }
+                Err(err) => tracing::error!("Failed to register remote control: {:?}", err),
+            }
+        }
+
+        fn dbus_unregister(&self, connection: &gio::DBusConnection) {
+            if let Some(id) = self.remote_control_id.take() {
+                if let Err(err) = connection.unregister_object(id) {
+                    tracing::warn!("Failed to unregister remote control: {:?}", err);
+                }
+            }
+        }
+    }
+
+    impl ObjectImpl for Application {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+
+            obj.add_main_option(
+                "no-restore",
+                glib::Char::from(0),
+                glib::OptionFlags::NONE,
+                glib::OptionArg::None,
+                &gettext("Start with an empty window instead of restoring the previous session"),
+                None,
+            );
+            obj.add_main_option(
+                "new-window",
+                glib::Char::from(0),
+                glib::OptionFlags::NONE,
+                glib::OptionArg::None,
+                &gettext("Open a new window"),
+                None,
+            );
+            obj.add_main_option(
+                "new-document",
+                glib::Char::from(0),
+                glib::OptionFlags::NONE,
+                glib::OptionArg::None,
+                &gettext("Open a new document in the active window"),
+                None,
+            );
+        }
+    }
 
     impl ApplicationImpl for Application {
         fn activate(&self) {
@@ -49,13 +184,24 @@ mod imp {
 
                     let _hold_guard = hold_guard;
 
+                    let imp = obj.imp();
+                    let restore_windows = imp.settings.restore_session() && !imp.no_restore.get();
+
                     let session = obj.session();
-                    if let Err(err) = session.restore().await {
+                    if let Err(err) = session
+                        .restore(restore_windows)
+                        .instrument(tracing::debug_span!("restore_session"))
+                        .await
+                    {
                         tracing::error!("Failed to restore session: {:?}", err);
 
                         let window = session.add_new_window();
                         window.present();
                     }
+
+                    obj.open_pending_drafts();
+
+                    crash_report::offer_pending(&session.active_window()).await;
                 }
             ));
         }
@@ -69,11 +215,145 @@ mod imp {
 
             obj.setup_gactions();
             obj.setup_accels();
+
+            self.settings.connect_run_in_background_changed(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.update_background_hold();
+                }
+            ));
+            obj.update_background_hold();
+        }
+
+        fn shutdown(&self) {
+            let _ = self.background_hold_guard.take();
+            let _ = self.shared_graph_renderer.take();
+
+            self.session.release_lock();
+
+            self.parent_shutdown();
+        }
+
+        fn local_command_line(
+            &self,
+            arguments: &mut gio::subclass::ArgumentList,
+        ) -> Option<glib::ExitCode> {
+            // Support `some-tool | delineate -`. The standard input must be read
+            // here, as it is not available to an already running instance.
+            if let Some(index) = arguments.iter().skip(1).position(|arg| arg == "-") {
+                arguments.remove(index + 1);
+
+                let mut contents = String::new();
+                if let Err(err) = io::stdin().read_to_string(&mut contents) {
+                    tracing::error!("Failed to read standard input: {:?}", err);
+                    return Some(glib::ExitCode::FAILURE);
+                }
+
+                let obj = self.obj();
+                if let Err(err) = obj.register(gio::Cancellable::NONE) {
+                    tracing::error!("Failed to register: {:?}", err);
+                    return Some(glib::ExitCode::FAILURE);
+                }
+
+                if obj.is_remote() {
+                    obj.activate_action("open-draft", Some(&contents.to_variant()));
+                } else {
+                    self.pending_drafts.borrow_mut().push(contents);
+                }
+            }
+
+            // Support `delineate +LINE file.gv` and `delineate file.gv:LINE:COLUMN`.
+            // The position is passed as the open hint, so it also reaches an
+            // already running instance.
+            let positioned_files = take_positioned_files(arguments);
+            if !positioned_files.is_empty() {
+                let obj = self.obj();
+                if let Err(err) = obj.register(gio::Cancellable::NONE) {
+                    tracing::error!("Failed to register: {:?}", err);
+                    return Some(glib::ExitCode::FAILURE);
+                }
+
+                for (file, position) in positioned_files {
+                    obj.open(&[file], &position.to_hint());
+                }
+
+                if arguments.len() == 1 {
+                    return Some(glib::ExitCode::SUCCESS);
+                }
+            }
+
+            self.parent_local_command_line(arguments)
+        }
+
+        fn handle_local_options(&self, options: &glib::VariantDict) -> glib::ExitCode {
+            self.no_restore.set(options.contains("no-restore"));
+
+            // Used by the desktop file actions.
+            let action_name = if options.contains("new-window") {
+                Some("new-window")
+            } else if options.contains("new-document") {
+                Some("new-document")
+            } else {
+                None
+            };
+            if let Some(action_name) = action_name {
+                let obj = self.obj();
+                if let Err(err) = obj.register(gio::Cancellable::NONE) {
+                    tracing::error!("Failed to register: {:?}", err);
+                    return glib::ExitCode::FAILURE;
+                }
+
+                obj.activate_action(action_name, None);
+                return glib::ExitCode::SUCCESS;
+            }
+
+            self.parent_handle_local_options(options)
+        }
+
+        fn before_emit(&self, platform_data: &glib::Variant) {
+            self.parent_before_emit(platform_data);
+
+            // Sent by the launching app, so windows can be raised even under
+            // focus stealing prevention.
+            let platform_data = glib::VariantDict::new(Some(platform_data));
+            let activation_token = platform_data
+                .lookup::<String>("activation-token")
+                .ok()
+                .flatten()
+                .or_else(|| {
+                    platform_data
+                        .lookup::<String>("desktop-startup-id")
+                        .ok()
+                        .flatten()
+                });
+            self.activation_token.replace(activation_token);
         }
 
-        fn open(&self, files: &[gio::File], _hint: &str) {
-            let window = self.session.active_window();
-            self.session.open_files(files, &window);
+        fn after_emit(&self, platform_data: &glib::Variant) {
+            let _ = self.activation_token.take();
+
+            self.parent_after_emit(platform_data);
+        }
+
+        fn open(&self, files: &[gio::File], hint: &str) {
+            if let (Some(position), [file]) = (FilePosition::from_hint(hint), files) {
+                if self.settings.open_in_new_window() {
+                    self.session.open_file_at_in_new_window(file, position);
+                } else {
+                    let window = self.session.active_window();
+                    self.session.open_file_at(file, position, &window);
+                }
+            } else if self.settings.open_in_new_window() {
+                for file in files {
+                    self.session.open_files_in_new_window(slice::from_ref(file));
+                }
+            } else {
+                let window = self.session.active_window();
+                self.session.open_files(files, &window);
+            }
+
+            self.obj().open_pending_drafts();
         }
     }
 
@@ -113,6 +393,20 @@ impl Application {
         &self.imp().session
     }
 
+    pub fn settings(&self) -> &Settings {
+        &self.imp().settings
+    }
+
+    /// Returns the view shared for laying out graphs without showing them,
+    /// like when exporting from a page that was never shown.
+    pub fn shared_graph_renderer(&self) -> GraphView {
+        self.imp()
+            .shared_graph_renderer
+            .borrow_mut()
+            .get_or_insert_with(GraphView::new)
+            .clone()
+    }
+
     pub fn run(&self) -> glib::ExitCode {
         tracing::info!("Delineate ({})", APP_ID);
         tracing::info!("Version: {} ({})", VERSION, PROFILE);
@@ -139,6 +433,16 @@ impl Application {
         ));
     }
 
+    /// Presents the window, using the activation token of the request being
+    /// handled, if any, so it is raised even if it is already shown.
+    pub fn present_window(&self, window: &impl IsA<gtk::Window>) {
+        if let Some(activation_token) = self.imp().activation_token.take() {
+            window.set_startup_id(&activation_token);
+        }
+
+        window.present();
+    }
+
     /// Returns `Proceed` if quit process shall proceed, `Stop` if it shall be aborted.
     async fn quit_request(&self) -> glib::Propagation {
         let imp = self.imp();
@@ -160,6 +464,48 @@ impl Application {
         save_changes_dialog::run(&window, &unsaved_documents).await
     }
 
+    fn update_background_hold(&self) {
+        let imp = self.imp();
+
+        let run_in_background = imp.settings.run_in_background();
+        if run_in_background == imp.background_hold_guard.borrow().is_some() {
+            return;
+        }
+
+        tracing::debug!(run_in_background, "Updating background hold");
+
+        // Dropping the guard quits the app right away if there are no windows.
+        let hold_guard = run_in_background.then(|| self.hold());
+        imp.background_hold_guard.replace(hold_guard);
+    }
+
+    /// Adds an empty document to the active window, so it can be used even
+    /// when no window is focused, like from the desktop file actions.
+    fn new_document(&self) {
+        let session = self.session();
+
+        // New windows already come with an empty document.
+        let window = if self.windows().is_empty() {
+            session.add_new_window()
+        } else {
+            let window = session.active_window();
+            window.add_new_page();
+            window
+        };
+
+        window.present();
+        window.focus_selected_page();
+    }
+
+    fn open_pending_drafts(&self) {
+        let imp = self.imp();
+
+        for contents in imp.pending_drafts.take() {
+            let window = imp.session.active_window();
+            window.open_draft(&contents);
+        }
+    }
+
     fn setup_gactions(&self) {
         let action_new_window = gio::ActionEntry::builder("new-window")
             .activate(|obj: &Self, _, _| {
@@ -167,9 +513,35 @@ impl Application {
                 window.present();
             })
             .build();
+        let action_new_document = gio::ActionEntry::builder("new-document")
+            .activate(|obj: &Self, _, _| {
+                obj.new_document();
+            })
+            .build();
+        let action_undo_close_window = gio::ActionEntry::builder("undo-close-window")
+            .activate(|obj: &Self, _, _| {
+                obj.session().restore_closed_window();
+            })
+            .build();
+        let action_open_draft = gio::ActionEntry::builder("open-draft")
+            .parameter_type(Some(&String::static_variant_type()))
+            .activate(|obj: &Self, _, arg| {
+                let contents = arg.unwrap().get::<String>().unwrap();
+                let window = obj.session().active_window();
+                window.open_draft(&contents);
+            })
+            .build();
         let action_quit = gio::ActionEntry::builder("quit")
             .activate(move |obj: &Self, _, _| obj.quit())
             .build();
+        let action_preferences = gio::ActionEntry::builder("preferences")
+            .activate(|obj: &Self, _, _| {
+                let imp = obj.imp();
+                let window = imp.session.active_window();
+                let dialog = PreferencesDialog::new();
+                dialog.present(Some(&window));
+            })
+            .build();
         let action_about = gio::ActionEntry::builder("about")
             .activate(|obj: &Self, _, _| {
                 let imp = obj.imp();
@@ -177,11 +549,67 @@ impl Application {
                 about::present_dialog(&window);
             })
             .build();
-        self.add_action_entries([action_new_window, action_quit, action_about]);
+        self.add_action_entries([
+            action_new_window,
+            action_new_document,
+            action_undo_close_window,
+            action_open_draft,
+            action_quit,
+            action_preferences,
+            action_about,
+        ]);
     }
 
     fn setup_accels(&self) {
         self.set_accels_for_action("app.new-window", &["<Control>n"]);
         self.set_accels_for_action("app.quit", &["<Control>q"]);
+        self.set_accels_for_action("app.preferences", &["<Control>comma"]);
+    }
+}
+
+/// Removes the files with a position, and the `+LINE` arguments before
+/// them, from the arguments, returning them.
+///
+/// Arguments like `file.gv:120` are only treated as having a position if no
+/// file with that exact name exists.
+fn take_positioned_files(
+    arguments: &mut gio::subclass::ArgumentList,
+) -> Vec<(gio::File, FilePosition)> {
+    let mut positioned_files = Vec::new();
+    let mut to_remove = Vec::new();
+
+    let mut index = 1;
+    while index < arguments.len() {
+        let Some(arg) = arguments[index].to_str() else {
+            index += 1;
+            continue;
+        };
+
+        if let Some(position) = FilePosition::from_plus_arg(arg) {
+            if let Some(next) = arguments
+                .get(index + 1)
+                .filter(|next| !next.to_str().is_some_and(|next| next.starts_with('-')))
+            {
+                let file = gio::File::for_commandline_arg(next);
+                positioned_files.push((file, position));
+                to_remove.extend([index, index + 1]);
+                index += 2;
+                continue;
+            }
+        } else if let Some((path, position)) = FilePosition::split_from_path(arg) {
+            if !gio::File::for_commandline_arg(arg).query_exists(gio::Cancellable::NONE) {
+                let file = gio::File::for_commandline_arg(path);
+                positioned_files.push((file, position));
+                to_remove.push(index);
+            }
+        }
+
+        index += 1;
     }
+
+    for index in to_remove.into_iter().rev() {
+        arguments.remove(index);
+    }
+
+    positioned_files
 }
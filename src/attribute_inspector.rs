@@ -0,0 +1,236 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::glib::{self, clone, closure_local};
+
+use crate::dot::EffectiveAttr;
+
+mod imp {
+    use std::{cell::RefCell, sync::LazyLock};
+
+    use glib::subclass::Signal;
+
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Delineate/ui/attribute_inspector.ui")]
+    pub struct AttributeInspector {
+        #[template_child]
+        pub(super) stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) empty_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub(super) attrs_page: TemplateChild<adw::PreferencesPage>,
+        #[template_child]
+        pub(super) title_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub(super) own_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub(super) add_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub(super) default_group: TemplateChild<adw::PreferencesGroup>,
+
+        pub(super) element: RefCell<Option<(String, Vec<EffectiveAttr>)>>,
+        pub(super) rows: RefCell<Vec<(adw::PreferencesGroup, adw::EntryRow)>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AttributeInspector {
+        const NAME: &'static str = "DelineateAttributeInspector";
+        type Type = super::AttributeInspector;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for AttributeInspector {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+
+            self.add_row.connect_apply(clone!(
+                #[weak]
+                obj,
+                move |row| {
+                    let text = row.text();
+                    let Some((key, value)) = parse_attr(&text) else {
+                        return;
+                    };
+                    obj.emit_by_name::<()>("attr-set-request", &[&key, &value]);
+                    row.set_text("");
+                }
+            ));
+
+            obj.update_stack();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: LazyLock<Vec<Signal>> = LazyLock::new(|| {
+                vec![
+                    Signal::builder("attr-set-request")
+                        .param_types([String::static_type(), String::static_type()])
+                        .build(),
+                    Signal::builder("attr-remove-request")
+                        .param_types([String::static_type()])
+                        .build(),
+                ]
+            });
+
+            SIGNALS.as_ref()
+        }
+    }
+
+    impl WidgetImpl for AttributeInspector {}
+    impl BinImpl for AttributeInspector {}
+}
+
+glib::wrapper! {
+    /// Lists the attributes of a node or an edge and lets the user change
+    /// them. Changes are only requested, as they are applied to the source.
+    pub struct AttributeInspector(ObjectSubclass<imp::AttributeInspector>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+impl AttributeInspector {
+    pub fn connect_attr_set_request<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &str, &str) + 'static,
+    {
+        self.connect_closure(
+            "attr-set-request",
+            false,
+            closure_local!(|obj: &Self, key: &str, value: &str| {
+                f(obj, key, value);
+            }),
+        )
+    }
+
+    pub fn connect_attr_remove_request<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &str) + 'static,
+    {
+        self.connect_closure(
+            "attr-remove-request",
+            false,
+            closure_local!(|obj: &Self, key: &str| {
+                f(obj, key);
+            }),
+        )
+    }
+
+    /// Shows the title and attributes of an element, or nothing if `element`
+    /// is `None`.
+    pub fn set_element(&self, element: Option<(String, Vec<EffectiveAttr>)>) {
+        let imp = self.imp();
+
+        // Rows are only rebuilt when something changed, so an entry being
+        // edited isn't reset on every cursor movement.
+        if *imp.element.borrow() == element {
+            return;
+        }
+
+        for (group, row) in imp.rows.take() {
+            group.remove(&row);
+        }
+
+        if let Some((title, attrs)) = &element {
+            imp.title_label.set_label(title);
+
+            let mut rows = Vec::with_capacity(attrs.len());
+            for attr in attrs {
+                let group = if attr.is_default {
+                    &imp.default_group
+                } else {
+                    &imp.own_group
+                };
+                let row = self.create_row(attr);
+                group.add(&row);
+                rows.push((group.get(), row));
+            }
+            imp.rows.replace(rows);
+
+            imp.own_group
+                .set_visible(attrs.iter().any(|attr| !attr.is_default));
+            imp.default_group
+                .set_visible(attrs.iter().any(|attr| attr.is_default));
+        }
+
+        imp.element.replace(element);
+
+        self.update_stack();
+    }
+
+    fn create_row(&self, attr: &EffectiveAttr) -> adw::EntryRow {
+        let row = adw::EntryRow::builder()
+            .title(&attr.key)
+            .use_markup(false)
+            .text(&attr.value)
+            .show_apply_button(true)
+            .build();
+        row.connect_apply(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            #[strong(rename_to = key)]
+            attr.key,
+            move |row| {
+                obj.emit_by_name::<()>("attr-set-request", &[&key, &row.text().as_str()]);
+            }
+        ));
+
+        if !attr.is_default {
+            let remove_button = gtk::Button::builder()
+                .tooltip_text(gettext("Remove Attribute"))
+                .icon_name("user-trash-symbolic")
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+            remove_button.connect_clicked(clone!(
+                #[weak(rename_to = obj)]
+                self,
+                #[strong(rename_to = key)]
+                attr.key,
+                move |_| {
+                    obj.emit_by_name::<()>("attr-remove-request", &[&key]);
+                }
+            ));
+            row.add_suffix(&remove_button);
+        }
+
+        row
+    }
+
+    fn update_stack(&self) {
+        let imp = self.imp();
+
+        if imp.element.borrow().is_some() {
+            imp.stack.set_visible_child(&*imp.attrs_page);
+        } else {
+            imp.stack.set_visible_child(&*imp.empty_page);
+        }
+    }
+}
+
+/// Parses `name=value`, where a missing value means `true` like in DOT, and
+/// surrounding quotes are removed as values are quoted as needed anyway.
+fn parse_attr(text: &str) -> Option<(String, String)> {
+    let (key, value) = text.split_once('=').unwrap_or((text, "true"));
+
+    let key = key.trim();
+    if key.is_empty() {
+        return None;
+    }
+
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value);
+
+    Some((key.to_string(), value.to_string()))
+}
@@ -0,0 +1,367 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{
+    gdk,
+    glib::{self, clone, closure_local},
+};
+
+use crate::dot::EffectiveAttr;
+
+/// Number of document and recent colors offered.
+const MAX_SWATCHES: usize = 16;
+/// Size of the swatch of a color, in pixels.
+const SWATCH_SIZE: i32 = 20;
+
+const SHAPES: &[&str] = &[
+    "box",
+    "ellipse",
+    "oval",
+    "circle",
+    "doublecircle",
+    "point",
+    "plain",
+    "plaintext",
+    "none",
+    "square",
+    "rect",
+    "diamond",
+    "triangle",
+    "hexagon",
+    "octagon",
+    "parallelogram",
+    "trapezium",
+    "cylinder",
+    "note",
+    "tab",
+    "folder",
+    "component",
+    "record",
+    "Mrecord",
+];
+
+const NODE_STYLES: &[&str] = &[
+    "solid", "dashed", "dotted", "bold", "rounded", "filled", "striped", "invis",
+];
+
+const EDGE_STYLES: &[&str] = &["solid", "dashed", "dotted", "bold", "tapered", "invis"];
+
+const ARROWHEADS: &[&str] = &[
+    "normal", "inv", "dot", "invdot", "odot", "invodot", "none", "tee", "empty", "invempty",
+    "diamond", "odiamond", "box", "obox", "open", "halfopen", "vee", "crow",
+];
+
+mod imp {
+    use std::{cell::Cell, sync::LazyLock};
+
+    use glib::subclass::Signal;
+
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Delineate/ui/attribute_palette.ui")]
+    pub struct AttributePalette {
+        #[template_child]
+        pub(super) title_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub(super) shape_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) style_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) clear_color_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) color_button: TemplateChild<gtk::ColorDialogButton>,
+        #[template_child]
+        pub(super) arrowhead_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) swatches: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub(super) swatches_box: TemplateChild<gtk::FlowBox>,
+
+        /// Whether the rows are being set to the values of an element, so
+        /// changes must not be requested.
+        pub(super) is_updating: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AttributePalette {
+        const NAME: &'static str = "DelineateAttributePalette";
+        type Type = super::AttributePalette;
+        type ParentType = gtk::Popover;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for AttributePalette {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+
+            for (row, key) in [
+                (&*self.shape_row, "shape"),
+                (&*self.style_row, "style"),
+                (&*self.arrowhead_row, "arrowhead"),
+            ] {
+                row.connect_selected_notify(clone!(
+                    #[weak]
+                    obj,
+                    move |row| {
+                        obj.handle_choice_selected(row, key);
+                    }
+                ));
+            }
+
+            self.color_button.connect_rgba_notify(clone!(
+                #[weak]
+                obj,
+                move |button| {
+                    let imp = obj.imp();
+
+                    if imp.is_updating.get() {
+                        return;
+                    }
+
+                    imp.clear_color_button.set_visible(true);
+                    obj.emit_by_name::<()>(
+                        "attr-set-request",
+                        &[&"color", &color_to_hex(&button.rgba())],
+                    );
+                }
+            ));
+            self.clear_color_button.connect_clicked(clone!(
+                #[weak]
+                obj,
+                move |button| {
+                    button.set_visible(false);
+                    obj.emit_by_name::<()>("attr-remove-request", &[&"color"]);
+                }
+            ));
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: LazyLock<Vec<Signal>> = LazyLock::new(|| {
+                vec![
+                    Signal::builder("attr-set-request")
+                        .param_types([String::static_type(), String::static_type()])
+                        .build(),
+                    Signal::builder("attr-remove-request")
+                        .param_types([String::static_type()])
+                        .build(),
+                ]
+            });
+
+            SIGNALS.as_ref()
+        }
+    }
+
+    impl WidgetImpl for AttributePalette {}
+    impl PopoverImpl for AttributePalette {}
+}
+
+glib::wrapper! {
+    /// Offers common attributes of a node or an edge, with pickers for their
+    /// values. Like in the inspector, changes are only requested.
+    pub struct AttributePalette(ObjectSubclass<imp::AttributePalette>)
+        @extends gtk::Widget, gtk::Popover;
+}
+
+impl AttributePalette {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    pub fn connect_attr_set_request<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &str, &str) + 'static,
+    {
+        self.connect_closure(
+            "attr-set-request",
+            false,
+            closure_local!(|obj: &Self, key: &str, value: &str| {
+                f(obj, key, value);
+            }),
+        )
+    }
+
+    pub fn connect_attr_remove_request<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &str) + 'static,
+    {
+        self.connect_closure(
+            "attr-remove-request",
+            false,
+            closure_local!(|obj: &Self, key: &str| {
+                f(obj, key);
+            }),
+        )
+    }
+
+    /// Shows the values that the statements of an element set, where
+    /// "Default" means that the attribute is not set.
+    pub fn set_element(&self, title: &str, is_edge: bool, attrs: &[EffectiveAttr]) {
+        let imp = self.imp();
+
+        imp.is_updating.set(true);
+
+        let value_of = |key: &str| {
+            attrs
+                .iter()
+                .find(|attr| attr.key == key && !attr.is_default)
+                .map(|attr| attr.value.as_str())
+        };
+
+        imp.title_label.set_label(title);
+
+        imp.shape_row.set_visible(!is_edge);
+        set_choices(&imp.shape_row, SHAPES, value_of("shape"));
+
+        let styles = if is_edge { EDGE_STYLES } else { NODE_STYLES };
+        set_choices(&imp.style_row, styles, value_of("style"));
+
+        imp.arrowhead_row.set_visible(is_edge);
+        set_choices(&imp.arrowhead_row, ARROWHEADS, value_of("arrowhead"));
+
+        // Colors like color lists can't be shown, but can still be cleared.
+        let color = value_of("color");
+        let rgba = color.and_then(|color| gdk::RGBA::parse(color).ok());
+        imp.color_button.set_rgba(&rgba.unwrap_or(gdk::RGBA::BLACK));
+        imp.clear_color_button.set_visible(color.is_some());
+
+        imp.is_updating.set(false);
+    }
+
+    /// Offers `colors` to be picked with a single click, ignoring duplicates
+    /// and colors that can't be shown. The colors used in the document should
+    /// come first, followed by the recently picked ones.
+    pub fn set_colors<'a>(&self, colors: impl IntoIterator<Item = &'a str>) {
+        let imp = self.imp();
+
+        imp.swatches_box.remove_all();
+
+        let mut shown = Vec::new();
+        for color in colors {
+            if shown.len() == MAX_SWATCHES {
+                break;
+            }
+
+            if shown.contains(&color) {
+                continue;
+            }
+
+            let Ok(rgba) = gdk::RGBA::parse(color) else {
+                continue;
+            };
+
+            imp.swatches_box.append(&self.swatch_button(color, rgba));
+            shown.push(color);
+        }
+
+        imp.swatches.set_visible(!shown.is_empty());
+    }
+
+    fn swatch_button(&self, color: &str, rgba: gdk::RGBA) -> gtk::Button {
+        let drawing_area = gtk::DrawingArea::builder()
+            .content_width(SWATCH_SIZE)
+            .content_height(SWATCH_SIZE)
+            .build();
+        drawing_area.set_draw_func(move |_, cr, width, height| {
+            let radius = width.min(height) as f64 / 2.0;
+            cr.arc(
+                width as f64 / 2.0,
+                height as f64 / 2.0,
+                radius - 0.5,
+                0.0,
+                std::f64::consts::TAU,
+            );
+            cr.set_source_color(&rgba);
+            let _ = cr.fill_preserve();
+
+            // Keeps light colors visible on light backgrounds.
+            cr.set_source_color(&gdk::RGBA::new(0.0, 0.0, 0.0, 0.15));
+            cr.set_line_width(1.0);
+            let _ = cr.stroke();
+        });
+
+        let button = gtk::Button::builder()
+            .child(&drawing_area)
+            .tooltip_text(color)
+            .css_classes(["flat", "circular"])
+            .build();
+        button.connect_clicked(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            #[to_owned]
+            color,
+            move |_| {
+                obj.imp().clear_color_button.set_visible(true);
+                obj.emit_by_name::<()>("attr-set-request", &[&"color", &color]);
+            }
+        ));
+        button
+    }
+
+    fn handle_choice_selected(&self, row: &adw::ComboRow, key: &str) {
+        if self.imp().is_updating.get() {
+            return;
+        }
+
+        // The first choice is always "Default".
+        if row.selected() == 0 {
+            self.emit_by_name::<()>("attr-remove-request", &[&key]);
+            return;
+        }
+
+        let Some(value) = row.selected_item().and_downcast::<gtk::StringObject>() else {
+            return;
+        };
+        self.emit_by_name::<()>("attr-set-request", &[&key, &value.string().as_str()]);
+    }
+}
+
+impl Default for AttributePalette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shows `choices` in `row`, after "Default", selecting `current`, which is
+/// added if it is not one of them, like with combined styles.
+fn set_choices(row: &adw::ComboRow, choices: &[&str], current: Option<&str>) {
+    let default_label = gettext("Default");
+
+    let mut items = Vec::with_capacity(choices.len() + 2);
+    items.push(default_label.as_str());
+    items.extend_from_slice(choices);
+    if let Some(current) = current.filter(|current| !choices.contains(current)) {
+        items.push(current);
+    }
+
+    let selected = current
+        .and_then(|current| items[1..].iter().position(|item| *item == current))
+        .map_or(0, |position| position + 1);
+
+    row.set_model(Some(&gtk::StringList::new(&items)));
+    row.set_selected(selected as u32);
+}
+
+/// Returns `color` as `#rrggbb`, or `#rrggbbaa` if it is translucent.
+fn color_to_hex(color: &gdk::RGBA) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    let mut ret = format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.red()),
+        channel(color.green()),
+        channel(color.blue())
+    );
+    if color.alpha() < 1.0 {
+        ret.push_str(&format!("{:02x}", channel(color.alpha())));
+    }
+    ret
+}
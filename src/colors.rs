@@ -0,0 +1,38 @@
+//! Colors drawn outside of the style sheet, with variants for the dark and
+//! high contrast styles.
+//!
+//! Colors here must not be the only way something is shown, as they may not
+//! be told apart by color-blind users, so they are paired with a shape, like
+//! an icon or an underline.
+
+use gtk::gdk;
+
+const ERROR_LIGHT: gdk::RGBA = gdk::RGBA::new(0.753, 0.11, 0.157, 1.0);
+const ERROR_DARK: gdk::RGBA = gdk::RGBA::new(1.0, 0.482, 0.388, 1.0);
+const ERROR_HIGH_CONTRAST_LIGHT: gdk::RGBA = gdk::RGBA::new(0.565, 0.0, 0.0, 1.0);
+const ERROR_HIGH_CONTRAST_DARK: gdk::RGBA = gdk::RGBA::new(1.0, 0.686, 0.62, 1.0);
+
+const ERROR_LINE_BACKGROUND_ALPHA: f32 = 0.12;
+const ERROR_LINE_BACKGROUND_HIGH_CONTRAST_ALPHA: f32 = 0.25;
+
+/// Returns the color used for errors, like in the editor gutter.
+pub fn error() -> gdk::RGBA {
+    let style_manager = adw::StyleManager::default();
+    match (style_manager.is_dark(), style_manager.is_high_contrast()) {
+        (false, false) => ERROR_LIGHT,
+        (true, false) => ERROR_DARK,
+        (false, true) => ERROR_HIGH_CONTRAST_LIGHT,
+        (true, true) => ERROR_HIGH_CONTRAST_DARK,
+    }
+}
+
+/// Returns the background color of lines with errors, which is more visible
+/// in the high contrast style.
+pub fn error_line_background() -> gdk::RGBA {
+    let alpha = if adw::StyleManager::default().is_high_contrast() {
+        ERROR_LINE_BACKGROUND_HIGH_CONTRAST_ALPHA
+    } else {
+        ERROR_LINE_BACKGROUND_ALPHA
+    };
+    error().with_alpha(alpha)
+}
@@ -0,0 +1,186 @@
+//! Crash reports written when the app panics, so they can be attached to bug
+//! reports. They are offered to the user the next time the app starts.
+
+use std::{
+    backtrace::Backtrace,
+    fmt::Write as _,
+    fs,
+    panic::{self, PanicHookInfo},
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex, PoisonError},
+    thread,
+};
+
+use adw::prelude::*;
+use anyhow::{Context, Result};
+use gettextrs::gettext;
+use gtk::{gio, glib};
+
+use crate::{
+    config::{APP_ID, PROFILE, VERSION},
+    i18n::gettext_f,
+    window::Window,
+    APP_DATA_DIR,
+};
+
+static CRASHES_DIR: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_DIR.join("crashes"));
+
+/// Reports that were not offered to the user yet.
+static PENDING_CRASHES_DIR: LazyLock<PathBuf> = LazyLock::new(|| CRASHES_DIR.join("pending"));
+
+/// What was open when the app crashed, as the panic hook can't access the
+/// session, which may live on another thread or be mid-update.
+static CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext {
+    session_summary: String::new(),
+    open_uris: Vec::new(),
+});
+
+#[derive(Debug)]
+struct CrashContext {
+    session_summary: String,
+    open_uris: Vec<String>,
+}
+
+/// Updates the session summary and open file URIs included in reports.
+pub fn set_context(session_summary: String, open_uris: Vec<String>) {
+    let mut context = CONTEXT.lock().unwrap_or_else(PoisonError::into_inner);
+    context.session_summary = session_summary;
+    context.open_uris = open_uris;
+}
+
+/// Installs a panic hook that writes a crash report before running the
+/// default hook.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        match write_report(info) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(err) => eprintln!("Failed to write crash report: {:?}", err),
+        }
+
+        default_hook(info);
+    }));
+}
+
+fn write_report(info: &PanicHookInfo<'_>) -> Result<PathBuf> {
+    let now = glib::DateTime::now_local()?;
+
+    let mut report = String::new();
+    writeln!(report, "Delineate {} ({}, {})", VERSION, APP_ID, PROFILE)?;
+    writeln!(report, "Time: {}", now.format_iso8601()?)?;
+    writeln!(
+        report,
+        "Thread: {}",
+        thread::current().name().unwrap_or("<unnamed>")
+    )?;
+    writeln!(report, "Panic: {}", info)?;
+
+    {
+        let context = CONTEXT.lock().unwrap_or_else(PoisonError::into_inner);
+        writeln!(report)?;
+        writeln!(report, "Session: {}", context.session_summary)?;
+        writeln!(report, "Open Files:")?;
+        for uri in &context.open_uris {
+            writeln!(report, "  {}", uri)?;
+        }
+    }
+
+    writeln!(report)?;
+    writeln!(report, "Backtrace:")?;
+    writeln!(report, "{}", Backtrace::force_capture())?;
+
+    fs::create_dir_all(PENDING_CRASHES_DIR.as_path())?;
+    let path = PENDING_CRASHES_DIR.join(format!("crash-{}.txt", now.format("%Y%m%d-%H%M%S")?));
+    fs::write(&path, report)?;
+
+    Ok(path)
+}
+
+/// Offers to open or copy the report of the last crash, if there is one
+/// that was not offered yet.
+pub async fn offer_pending(window: &Window) {
+    const CLOSE_RESPONSE_ID: &str = "close";
+    const COPY_RESPONSE_ID: &str = "copy";
+    const OPEN_RESPONSE_ID: &str = "open";
+
+    let report_path = match take_pending() {
+        Ok(Some(report_path)) => report_path,
+        Ok(None) => return,
+        Err(err) => {
+            tracing::warn!("Failed to take pending crash reports: {:?}", err);
+            return;
+        }
+    };
+
+    tracing::debug!(path = %report_path.display(), "Found pending crash report");
+
+    let dialog = adw::AlertDialog::builder()
+        .heading(gettext("Delineate Crashed"))
+        .body(gettext_f(
+            "Delineate closed unexpectedly the last time it was used. A crash report was saved to {path}, which can be attached when reporting the issue.",
+            &[("path", &report_path.display().to_string())],
+        ))
+        .close_response(CLOSE_RESPONSE_ID)
+        .default_response(OPEN_RESPONSE_ID)
+        .build();
+
+    dialog.add_response(CLOSE_RESPONSE_ID, &gettext("_Close"));
+    dialog.add_response(COPY_RESPONSE_ID, &gettext("_Copy Report"));
+    dialog.add_response(OPEN_RESPONSE_ID, &gettext("_Open Report"));
+
+    dialog.set_response_appearance(OPEN_RESPONSE_ID, adw::ResponseAppearance::Suggested);
+
+    match dialog.choose_future(window).await.as_str() {
+        CLOSE_RESPONSE_ID => {}
+        COPY_RESPONSE_ID => match fs::read_to_string(&report_path) {
+            Ok(report) => {
+                window.clipboard().set_text(&report);
+                window.add_message_toast(&gettext("Copied crash report"));
+            }
+            Err(err) => {
+                tracing::error!("Failed to read crash report: {:?}", err);
+                window.add_message_toast(&gettext("Failed to copy crash report"));
+            }
+        },
+        OPEN_RESPONSE_ID => {
+            let file = gio::File::for_path(&report_path);
+            let file_launcher = gtk::FileLauncher::new(Some(&file));
+            if let Err(err) = file_launcher.launch_future(Some(window)).await {
+                if !err.matches(gtk::DialogError::Dismissed) {
+                    tracing::error!("Failed to open crash report: {:?}", err);
+                    window.add_message_toast(&gettext("Failed to open crash report"));
+                }
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Moves the pending reports out of the pending directory, so they are only
+/// offered once, returning the path of the most recent one.
+fn take_pending() -> Result<Option<PathBuf>> {
+    let entries = match fs::read_dir(PENDING_CRASHES_DIR.as_path()) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut latest = None;
+    for entry in entries {
+        let pending_path = entry?.path();
+        let Some(file_name) = pending_path.file_name() else {
+            continue;
+        };
+
+        let path = CRASHES_DIR.join(file_name);
+        fs::rename(&pending_path, &path)
+            .with_context(|| format!("Failed to move {}", pending_path.display()))?;
+
+        // Names contain the time, so the greatest is the most recent.
+        if latest.as_deref().is_none_or(|latest: &Path| path > latest) {
+            latest = Some(path);
+        }
+    }
+
+    Ok(latest)
+}
@@ -0,0 +1,156 @@
+use adw::prelude::*;
+use gettextrs::gettext;
+use gtk::glib::{self, clone};
+
+use crate::{
+    i18n::gettext_f,
+    import::csv::{Mapping, Table},
+};
+
+const CANCEL_RESPONSE_ID: &str = "cancel";
+const IMPORT_RESPONSE_ID: &str = "import";
+
+/// Lets the user pick which columns of `table` are the source, target, and
+/// label of each edge.
+///
+/// Returns `None` if the user cancelled the dialog.
+pub async fn run(parent: &impl IsA<gtk::Widget>, table: &Table) -> Option<Mapping> {
+    let dialog = adw::AlertDialog::builder()
+        .heading(gettext("Import CSV"))
+        .body(gettext(
+            "Choose the columns that describe the edges. Each row becomes an edge from its source to its target.",
+        ))
+        .close_response(CANCEL_RESPONSE_ID)
+        .default_response(IMPORT_RESPONSE_ID)
+        .build();
+
+    dialog.add_response(CANCEL_RESPONSE_ID, &gettext("Cancel"));
+    dialog.add_response(IMPORT_RESPONSE_ID, &gettext("_Import"));
+
+    dialog.set_response_appearance(IMPORT_RESPONSE_ID, adw::ResponseAppearance::Suggested);
+
+    let list_box = gtk::ListBox::builder()
+        .selection_mode(gtk::SelectionMode::None)
+        .build();
+    list_box.add_css_class("boxed-list");
+    dialog.set_extra_child(Some(&list_box));
+
+    let header_row = adw::SwitchRow::builder()
+        .title(gettext("First Row Is Header"))
+        .active(true)
+        .build();
+    list_box.append(&header_row);
+
+    let column_names = gtk::StringList::new(&[]);
+    let label_column_names = gtk::StringList::new(&[&gettext("None")]);
+
+    let source_row = adw::ComboRow::builder()
+        .title(gettext("Source"))
+        .model(&column_names)
+        .build();
+    list_box.append(&source_row);
+
+    let target_row = adw::ComboRow::builder()
+        .title(gettext("Target"))
+        .model(&column_names)
+        .build();
+    list_box.append(&target_row);
+
+    let label_row = adw::ComboRow::builder()
+        .title(gettext("Label"))
+        .model(&label_column_names)
+        .build();
+    list_box.append(&label_row);
+
+    let directed_row = adw::SwitchRow::builder()
+        .title(gettext("Directed"))
+        .active(true)
+        .build();
+    list_box.append(&directed_row);
+
+    let numbered_names = (1..=table.n_columns())
+        .map(|number| gettext_f("Column {number}", &[("number", &number.to_string())]))
+        .collect::<Vec<_>>();
+    let header_names = numbered_names
+        .iter()
+        .enumerate()
+        .map(|(index, numbered_name)| {
+            table
+                .first_row()
+                .get(index)
+                .map(|name| name.trim())
+                .filter(|name| !name.is_empty())
+                .map_or_else(|| numbered_name.clone(), |name| name.to_string())
+        })
+        .collect::<Vec<_>>();
+
+    let update_column_names = clone!(
+        #[weak]
+        source_row,
+        #[weak]
+        target_row,
+        #[weak]
+        label_row,
+        move |has_header: bool| {
+            let names = if has_header {
+                &header_names
+            } else {
+                &numbered_names
+            };
+            let names = names.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+
+            // Splicing the models resets the selections, so restore them after.
+            let selections = [&source_row, &target_row, &label_row].map(|row| row.selected());
+
+            column_names.splice(0, column_names.n_items(), &names);
+            label_column_names.splice(1, label_column_names.n_items() - 1, &names);
+
+            for (row, selected) in [&source_row, &target_row, &label_row]
+                .into_iter()
+                .zip(selections)
+            {
+                row.set_selected(selected);
+            }
+        }
+    );
+    update_column_names(true);
+
+    source_row.set_selected(0);
+    target_row.set_selected(if table.n_columns() > 1 { 1 } else { 0 });
+    label_row.set_selected(if table.n_columns() > 2 { 3 } else { 0 });
+
+    header_row.connect_active_notify(move |row| update_column_names(row.is_active()));
+
+    let update_response = clone!(
+        #[weak]
+        dialog,
+        #[weak]
+        source_row,
+        #[weak]
+        target_row,
+        move || {
+            let is_valid = source_row.selected() != target_row.selected();
+            dialog.set_response_enabled(IMPORT_RESPONSE_ID, is_valid);
+        }
+    );
+    update_response();
+
+    source_row.connect_selected_notify(clone!(
+        #[strong]
+        update_response,
+        move |_| update_response()
+    ));
+    target_row.connect_selected_notify(move |_| update_response());
+
+    match dialog.choose_future(parent).await.as_str() {
+        CANCEL_RESPONSE_ID => None,
+        IMPORT_RESPONSE_ID => Some(Mapping {
+            has_header: header_row.is_active(),
+            source_column: source_row.selected() as usize,
+            target_column: target_row.selected() as usize,
+            label_column: label_row.selected().checked_sub(1).map(|c| c as usize),
+            is_directed: directed_row.is_active(),
+        }),
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt::Write;
+
+/// Number of unchanged lines shown around each change.
+const CONTEXT_LEN: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OpKind {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// A single line edit, along with the line positions in the old and new text
+/// where it happens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Op {
+    kind: OpKind,
+    old_index: usize,
+    new_index: usize,
+}
+
+/// Returns a unified diff of the lines of `old` and `new`, or an empty string
+/// if they have the same lines.
+pub fn unified(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    let mut ret = String::new();
+    for (start, end) in hunk_ranges(&ops) {
+        if ret.is_empty() {
+            writeln!(ret, "--- {}", old_label).unwrap();
+            writeln!(ret, "+++ {}", new_label).unwrap();
+        }
+
+        let hunk = &ops[start..end];
+        let old_len = hunk.iter().filter(|op| op.kind != OpKind::Insert).count();
+        let new_len = hunk.iter().filter(|op| op.kind != OpKind::Delete).count();
+
+        // Ranges with no lines use the line before them as the start.
+        let old_start = hunk[0].old_index + usize::from(old_len != 0);
+        let new_start = hunk[0].new_index + usize::from(new_len != 0);
+
+        writeln!(
+            ret,
+            "@@ -{},{} +{},{} @@",
+            old_start, old_len, new_start, new_len
+        )
+        .unwrap();
+
+        for op in hunk {
+            match op.kind {
+                OpKind::Equal => writeln!(ret, " {}", old_lines[op.old_index]),
+                OpKind::Delete => writeln!(ret, "-{}", old_lines[op.old_index]),
+                OpKind::Insert => writeln!(ret, "+{}", new_lines[op.new_index]),
+            }
+            .unwrap();
+        }
+    }
+
+    ret
+}
+
+/// Computes the shortest edit script between `old` and `new` using Myers'
+/// algorithm.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = n + m;
+    let offset = max;
+
+    let mut v = vec![0_isize; 2 * max as usize + 2];
+    // Snapshots of `v[-d..=d]` before each step `d`, used for backtracking.
+    let mut trace = Vec::new();
+
+    'outer: for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+
+        for k in (-d..=d).step_by(2) {
+            let idx = (offset + k) as usize;
+
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            v[idx] = x;
+
+            if x >= n && y >= m {
+                break 'outer;
+            }
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let get = |k: isize| v[(k + d) as usize];
+
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = if d == 0 { 0 } else { get(prev_k) };
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push(Op {
+                kind: OpKind::Equal,
+                old_index: x as usize,
+                new_index: y as usize,
+            });
+        }
+
+        if d > 0 {
+            if x == prev_x {
+                ops.push(Op {
+                    kind: OpKind::Insert,
+                    old_index: prev_x as usize,
+                    new_index: prev_y as usize,
+                });
+            } else {
+                ops.push(Op {
+                    kind: OpKind::Delete,
+                    old_index: prev_x as usize,
+                    new_index: prev_y as usize,
+                });
+            }
+        }
+
+        x = prev_x;
+        y = prev_y;
+    }
+
+    ops.reverse();
+    ops
+}
+
+/// Returns the ranges of `ops` that make up each hunk, including the
+/// surrounding context.
+fn hunk_ranges(ops: &[Op]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::<(usize, usize)>::new();
+
+    for (index, op) in ops.iter().enumerate() {
+        if op.kind == OpKind::Equal {
+            continue;
+        }
+
+        let start = index.saturating_sub(CONTEXT_LEN);
+        let end = (index + 1 + CONTEXT_LEN).min(ops.len());
+
+        match ranges.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unified_same() {
+        assert_eq!(unified("a\nb\n", "a\nb\n", "old", "new"), "");
+        assert_eq!(unified("", "", "old", "new"), "");
+    }
+
+    #[test]
+    fn unified_change() {
+        assert_eq!(
+            unified("a\nb\nc\n", "a\nB\nc\n", "old", "new"),
+            "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+    }
+
+    #[test]
+    fn unified_insert_into_empty() {
+        assert_eq!(
+            unified("", "a\nb\n", "old", "new"),
+            "--- old\n+++ new\n@@ -0,0 +1,2 @@\n+a\n+b\n"
+        );
+    }
+
+    #[test]
+    fn unified_delete_all() {
+        assert_eq!(
+            unified("a\nb\n", "", "old", "new"),
+            "--- old\n+++ new\n@@ -1,2 +0,0 @@\n-a\n-b\n"
+        );
+    }
+
+    #[test]
+    fn unified_separate_hunks() {
+        let old = (1..=20).map(|i| format!("{i}\n")).collect::<String>();
+        let new = old
+            .replace("\n2\n", "\ntwo\n")
+            .replace("19\n", "nineteen\n");
+
+        assert_eq!(
+            unified(&old, &new, "old", "new"),
+            "--- old\n+++ new\n\
+             @@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n\
+             @@ -16,5 +16,5 @@\n 16\n 17\n 18\n-19\n+nineteen\n 20\n"
+        );
+    }
+}
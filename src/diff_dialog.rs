@@ -0,0 +1,50 @@
+use adw::prelude::*;
+use gtk_source::prelude::*;
+
+/// Shows `diff`, a unified diff, in a read-only view.
+pub fn present(parent: &impl IsA<gtk::Widget>, title: &str, diff: &str) {
+    let buffer = gtk_source::Buffer::new(None);
+    buffer.set_text(diff);
+
+    let language_manager = gtk_source::LanguageManager::default();
+    if let Some(language) = language_manager.language("diff") {
+        buffer.set_language(Some(&language));
+        buffer.set_highlight_syntax(true);
+    }
+
+    let style_manager = adw::StyleManager::default();
+    let style_scheme_manager = gtk_source::StyleSchemeManager::default();
+    let style_scheme = if style_manager.is_dark() {
+        style_scheme_manager.scheme("Adwaita-dark")
+    } else {
+        style_scheme_manager.scheme("Adwaita")
+    };
+    buffer.set_style_scheme(style_scheme.as_ref());
+
+    let view = gtk_source::View::builder()
+        .buffer(&buffer)
+        .editable(false)
+        .monospace(true)
+        .top_margin(12)
+        .bottom_margin(12)
+        .left_margin(12)
+        .right_margin(12)
+        .build();
+
+    let toolbar_view = adw::ToolbarView::new();
+    toolbar_view.add_top_bar(&adw::HeaderBar::new());
+    toolbar_view.set_content(Some(
+        &gtk::ScrolledWindow::builder()
+            .child(&view)
+            .vexpand(true)
+            .build(),
+    ));
+
+    let dialog = adw::Dialog::builder()
+        .title(title)
+        .content_width(720)
+        .content_height(540)
+        .child(&toolbar_view)
+        .build();
+    dialog.present(Some(parent));
+}
@@ -1,16 +1,17 @@
 use std::{future::Future, pin::Pin};
 
-use anyhow::{ensure, Result};
+use anyhow::{ensure, Context, Result};
 use futures_util::{join, Stream, StreamExt};
 use gtk::{
     gio,
     glib::{self, clone},
+    pango,
     prelude::*,
     subclass::prelude::*,
 };
 use gtk_source::{prelude::*, subclass::prelude::*};
 
-use crate::utils;
+use crate::{colors, utils};
 
 /// Unmarks the document as busy on drop.
 struct MarkBusyGuard<'a> {
@@ -28,8 +29,17 @@ const FILE_SAVER_FLAGS: gtk_source::FileSaverFlags =
     gtk_source::FileSaverFlags::IGNORE_INVALID_CHARS
         .union(gtk_source::FileSaverFlags::IGNORE_MODIFICATION_TIME);
 
+const ERROR_TAG_NAME: &str = "error";
+
+/// Number of characters at the start of a draft where its title is looked for.
+const TITLE_SCAN_MAX_CHARS: i32 = 1024;
+
 mod imp {
-    use std::{cell::Cell, marker::PhantomData, sync::LazyLock};
+    use std::{
+        cell::{Cell, RefCell},
+        marker::PhantomData,
+        sync::LazyLock,
+    };
 
     use glib::subclass::Signal;
 
@@ -48,8 +58,30 @@ mod imp {
         pub(super) busy_progress: Cell<f64>,
         #[property(get)]
         pub(super) is_busy: Cell<bool>,
+        #[property(get)]
+        pub(super) is_externally_modified: Cell<bool>,
+        /// Whether the file was deleted or moved away by another program
+        /// since it was last loaded or saved
+        #[property(get)]
+        pub(super) is_deleted: Cell<bool>,
+        /// Whether the file, or the volume it is on, can't be written to.
+        /// This is only known once the document is loaded.
+        #[property(get)]
+        pub(super) is_read_only: Cell<bool>,
+        /// Whether to reload the document when the file is modified by another
+        /// program, as long as there are no unsaved changes
+        #[property(get, set = Self::set_auto_reload, explicit_notify)]
+        pub(super) auto_reload: Cell<bool>,
 
         pub(super) source_file: gtk_source::File,
+        pub(super) file_monitor: RefCell<Option<gio::FileMonitor>>,
+        /// Title of the draft, kept up to date as its start is edited.
+        pub(super) parsed_title: RefCell<String>,
+        /// Number of nested user actions, undos, and redos, during which
+        /// edits are only recorded to be handled once at the end.
+        pub(super) batch_depth: Cell<u32>,
+        pub(super) has_pending_text_change: Cell<bool>,
+        pub(super) has_pending_title_change: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -84,16 +116,31 @@ mod imp {
                 }
             ));
 
+            // Errors are also underlined, so they are not shown by color alone.
+            obj.create_tag(
+                Some(ERROR_TAG_NAME),
+                &[("underline", &pango::Underline::Error)],
+            );
+
             let style_manager = adw::StyleManager::default();
             style_manager.connect_dark_notify(clone!(
                 #[weak]
                 obj,
                 move |_| {
                     obj.update_style_scheme();
+                    obj.update_error_tag();
+                }
+            ));
+            style_manager.connect_high_contrast_notify(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.update_error_tag();
                 }
             ));
 
             obj.update_style_scheme();
+            obj.update_error_tag();
         }
 
         fn signals() -> &'static [Signal] {
@@ -112,31 +159,44 @@ mod imp {
         }
 
         fn insert_text(&self, iter: &mut gtk::TextIter, new_text: &str) {
-            self.parent_insert_text(iter, new_text);
+            let offset = iter.offset();
 
-            let obj = self.obj();
-
-            if obj.file().is_none() {
-                obj.notify_title();
-            }
+            self.parent_insert_text(iter, new_text);
 
-            if !obj.is_loading() {
-                obj.emit_text_changed();
-            }
+            self.obj().handle_edit(offset);
         }
 
         fn delete_range(&self, start: &mut gtk::TextIter, end: &mut gtk::TextIter) {
+            let offset = start.offset();
+
             self.parent_delete_range(start, end);
 
-            let obj = self.obj();
+            self.obj().handle_edit(offset);
+        }
 
-            if obj.file().is_none() {
-                obj.notify_title();
-            }
+        // Pastes and other multi-step edits are grouped in user actions.
+        fn begin_user_action(&self) {
+            self.parent_begin_user_action();
 
-            if !obj.is_loading() {
-                obj.emit_text_changed();
-            }
+            self.batch_depth.set(self.batch_depth.get() + 1);
+        }
+
+        fn end_user_action(&self) {
+            self.parent_end_user_action();
+
+            self.obj().end_batch();
+        }
+
+        fn undo(&self) {
+            self.batch_depth.set(self.batch_depth.get() + 1);
+            self.parent_undo();
+            self.obj().end_batch();
+        }
+
+        fn redo(&self) {
+            self.batch_depth.set(self.batch_depth.get() + 1);
+            self.parent_redo();
+            self.obj().end_batch();
         }
     }
 
@@ -153,6 +213,17 @@ mod imp {
 
             self.source_file.set_location(file);
             obj.notify_file();
+
+            if file.is_none() {
+                obj.update_parsed_title();
+            }
+
+            obj.update_file_monitor();
+            obj.set_is_deleted(false);
+
+            if file.is_none() {
+                obj.set_is_read_only(false);
+            }
         }
 
         fn title(&self) -> String {
@@ -161,7 +232,7 @@ mod imp {
             if let Some(file) = obj.file() {
                 utils::display_file_stem(&file)
             } else {
-                obj.parse_title()
+                self.parsed_title.borrow().clone()
             }
         }
 
@@ -171,6 +242,21 @@ mod imp {
             // This must not also be loading to be considered modified.
             gtk::TextBuffer::is_modified(obj.upcast_ref()) && !obj.is_loading()
         }
+
+        fn set_auto_reload(&self, auto_reload: bool) {
+            let obj = self.obj();
+
+            if auto_reload == obj.auto_reload() {
+                return;
+            }
+
+            self.auto_reload.set(auto_reload);
+            obj.notify_auto_reload();
+
+            if auto_reload && obj.is_externally_modified() && !obj.is_modified() {
+                obj.reload_externally_modified();
+            }
+        }
     }
 }
 
@@ -212,7 +298,13 @@ impl Document {
         self.handle_file_io(loader.load_future(FILE_IO_PRIORITY))
             .await?;
 
-        self.emit_text_changed();
+        self.set_is_externally_modified(false);
+        self.set_is_deleted(false);
+        self.update_is_read_only().await;
+
+        // The edits made while loading are handled all at once.
+        imp.has_pending_text_change.set(true);
+        self.flush_pending_changes();
 
         Ok(())
     }
@@ -233,6 +325,8 @@ impl Document {
         self.handle_file_io(saver.save_future(FILE_IO_PRIORITY))
             .await?;
 
+        self.set_is_externally_modified(false);
+        self.set_is_deleted(false);
         self.set_modified(false);
 
         Ok(())
@@ -255,10 +349,66 @@ impl Document {
         self.handle_file_io(saver.save_future(FILE_IO_PRIORITY))
             .await?;
 
+        utils::resolve_file_identity(file).await;
         self.notify_file();
         self.notify_title();
 
+        self.update_file_monitor();
+        self.set_is_externally_modified(false);
+        self.set_is_deleted(false);
         self.set_modified(false);
+        self.update_is_read_only().await;
+
+        Ok(())
+    }
+
+    /// Renames the file to `new_name`, or moves it if `new_name` is a path
+    /// relative to the file's folder.
+    pub async fn rename(&self, new_name: &str) -> Result<()> {
+        ensure!(!self.is_busy(), "Document must not be busy");
+
+        let file = self.file().context("Document has no file")?;
+
+        let _guard = self.mark_busy();
+
+        let new_file = if new_name.contains('/') {
+            let parent = file.parent().context("File has no parent")?;
+            let destination = parent.resolve_relative_path(new_name);
+
+            let (move_fut, _) =
+                file.move_future(&destination, gio::FileCopyFlags::NONE, FILE_IO_PRIORITY);
+            move_fut.await?;
+
+            destination
+        } else {
+            file.set_display_name_future(new_name, FILE_IO_PRIORITY)
+                .await?
+        };
+
+        tracing::debug!(from = %file.uri(), to = %new_file.uri(), "File renamed");
+
+        utils::resolve_file_identity(&new_file).await;
+        self.set_file(&new_file);
+        self.notify_title();
+        self.update_is_read_only().await;
+
+        Ok(())
+    }
+
+    /// Writes the contents to `file`, without changing the document's file.
+    pub async fn save_copy(&self, file: &gio::File) -> Result<()> {
+        ensure!(!self.is_busy(), "Document must not be busy");
+
+        let _guard = self.mark_busy();
+
+        file.replace_contents_future(
+            self.contents().as_bytes().to_vec(),
+            None,
+            false,
+            gio::FileCreateFlags::REPLACE_DESTINATION,
+        )
+        .await
+        .map_err(|(_, err)| err)?;
 
         Ok(())
     }
@@ -278,8 +428,85 @@ impl Document {
         Ok(())
     }
 
-    fn emit_text_changed(&self) {
-        self.emit_by_name::<()>("text-changed", &[]);
+    /// Replaces the contents with `contents`, keeping the previous contents in
+    /// the undo history.
+    pub fn recover_contents(&self, contents: &str) {
+        self.begin_user_action();
+        self.set_text(contents);
+        self.end_user_action();
+    }
+
+    /// Returns the contents of the file currently on disk.
+    pub async fn load_file_contents(&self) -> Result<String> {
+        let file = self.file().context("Document has no file")?;
+
+        let (bytes, _) = file.load_contents_future().await?;
+        let contents = String::from_utf8_lossy(&bytes).into_owned();
+
+        Ok(contents)
+    }
+
+    /// Keeps the changes in the buffer, ignoring that the file was modified by
+    /// another program until it is modified again.
+    /// Marks the zero-based line as the one with an error, or clears the
+    /// mark if `line` is `None`.
+    pub fn set_error_line(&self, line: Option<u32>) {
+        let tag = self.error_tag();
+
+        let (start, end) = self.bounds();
+        self.remove_tag(&tag, &start, &end);
+
+        let Some(start) = line.and_then(|line| self.iter_at_line(line as i32)) else {
+            return;
+        };
+        let mut end = start;
+        if !end.ends_line() {
+            end.forward_to_line_end();
+        }
+        self.apply_tag(&tag, &start, &end);
+    }
+
+    pub fn keep_changes(&self) {
+        self.set_is_externally_modified(false);
+    }
+
+    /// Records an edit at `offset`, handling it right away unless it is part
+    /// of a batch or of loading the file.
+    fn handle_edit(&self, offset: i32) {
+        let imp = self.imp();
+
+        imp.has_pending_text_change.set(true);
+
+        if offset < TITLE_SCAN_MAX_CHARS && self.file().is_none() {
+            imp.has_pending_title_change.set(true);
+        }
+
+        if imp.batch_depth.get() == 0 && !self.is_loading() {
+            self.flush_pending_changes();
+        }
+    }
+
+    fn end_batch(&self) {
+        let imp = self.imp();
+
+        let batch_depth = imp.batch_depth.get().saturating_sub(1);
+        imp.batch_depth.set(batch_depth);
+
+        if batch_depth == 0 && !self.is_loading() {
+            self.flush_pending_changes();
+        }
+    }
+
+    fn flush_pending_changes(&self) {
+        let imp = self.imp();
+
+        if imp.has_pending_title_change.replace(false) {
+            self.update_parsed_title();
+        }
+
+        if imp.has_pending_text_change.replace(false) {
+            self.emit_by_name::<()>("text-changed", &[]);
+        }
     }
 
     fn set_busy_progress(&self, busy_progress: f64) {
@@ -302,54 +529,184 @@ impl Document {
         self.notify_is_busy();
     }
 
-    fn mark_busy(&self) -> MarkBusyGuard<'_> {
-        self.set_busy_progress(0.0);
+    fn set_is_externally_modified(&self, is_externally_modified: bool) {
+        let imp = self.imp();
 
-        MarkBusyGuard { document: self }
+        if is_externally_modified == self.is_externally_modified() {
+            return;
+        }
+
+        imp.is_externally_modified.set(is_externally_modified);
+        self.notify_is_externally_modified();
     }
 
-    fn parse_title(&self) -> String {
-        let start = self.start_iter();
+    fn set_is_deleted(&self, is_deleted: bool) {
+        let imp = self.imp();
+
+        if is_deleted == self.is_deleted() {
+            return;
+        }
+
+        imp.is_deleted.set(is_deleted);
+        self.notify_is_deleted();
+    }
+
+    fn set_is_read_only(&self, is_read_only: bool) {
+        let imp = self.imp();
+
+        if is_read_only == self.is_read_only() {
+            return;
+        }
+
+        imp.is_read_only.set(is_read_only);
+        self.notify_is_read_only();
+    }
+
+    /// Checks whether the file can be written to, including whether it is on
+    /// a read-only mount, which the loader doesn't check.
+    async fn update_is_read_only(&self) {
+        let imp = self.imp();
+
+        let Some(file) = self.file() else {
+            self.set_is_read_only(false);
+            return;
+        };
+
+        let is_read_only = match query_is_read_only(&file).await {
+            Ok(is_read_only) => is_read_only,
+            Err(err) => {
+                tracing::debug!(uri = %file.uri(), "Failed to query whether file is read-only: {:?}", err);
+                false
+            }
+        };
+
+        // Files over `https://` and such are only known to be read-only by
+        // the loader.
+        self.set_is_read_only(is_read_only || imp.source_file.is_readonly());
+    }
+
+    fn update_file_monitor(&self) {
+        let imp = self.imp();
+
+        if let Some(prev_monitor) = imp.file_monitor.take() {
+            prev_monitor.cancel();
+        }
 
-        let mut second_word_end = start;
-        second_word_end.forward_word_end();
-        second_word_end.forward_word_end();
+        let Some(file) = self.file() else {
+            return;
+        };
 
-        let search_flags = gtk::TextSearchFlags::CASE_INSENSITIVE
-            | gtk::TextSearchFlags::TEXT_ONLY
-            | gtk::TextSearchFlags::VISIBLE_ONLY;
+        let monitor =
+            match file.monitor_file(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE) {
+                Ok(monitor) => monitor,
+                Err(err) => {
+                    tracing::warn!(uri = %file.uri(), "Failed to monitor file: {:?}", err);
+                    return;
+                }
+            };
+        monitor.connect_changed(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_, _, other_file, event| {
+                obj.handle_file_monitor_changed(other_file, event);
+            }
+        ));
+        imp.file_monitor.replace(Some(monitor));
+    }
 
-        // Second word is either the `digraph`/`graph` keyword or the title.
-        let search_match = start
-            .forward_search("digraph", search_flags, Some(&second_word_end))
-            .or_else(|| start.forward_search("graph", search_flags, Some(&second_word_end)));
+    fn handle_file_monitor_changed(
+        &self,
+        other_file: Option<&gio::File>,
+        event: gio::FileMonitorEvent,
+    ) {
+        let imp = self.imp();
 
-        let Some((match_start, match_end)) = search_match else {
-            return "".to_string();
+        let is_removal = match event {
+            gio::FileMonitorEvent::Deleted | gio::FileMonitorEvent::MovedOut => true,
+            // Other programs often save by renaming a new file onto the old
+            // one, which replaces it instead.
+            gio::FileMonitorEvent::Renamed => !other_file
+                .zip(self.file())
+                .is_some_and(|(other_file, file)| other_file.equal(&file)),
+            gio::FileMonitorEvent::ChangesDoneHint
+            | gio::FileMonitorEvent::Created
+            | gio::FileMonitorEvent::MovedIn => false,
+            _ => return,
         };
 
-        // `digraph` and `graph` must be a standalone word.
-        if !match_start.starts_word() || !match_end.ends_word() {
-            return "".to_string();
+        // Our own writes also trigger the monitor. Those that arrive after
+        // saving are ignored below since saving updates the file's last known
+        // modification time.
+        if self.is_busy() {
+            return;
         }
 
-        let mut title_end = match_end;
-        title_end.forward_word_end();
+        imp.source_file.check_file_on_disk();
+
+        // The buffer is kept as is, so it can still be saved elsewhere.
+        if imp.source_file.is_deleted() {
+            tracing::debug!(uri = ?self.file().map(|f| f.uri()), "File deleted externally");
 
-        // If we go forward a word and we go past `{`, title is empty.
-        if title_end.backward_search("{", search_flags, None).is_some() {
-            return "".to_string();
+            self.set_is_externally_modified(false);
+            self.set_is_deleted(true);
+            return;
         }
 
-        let mut title_start = title_end;
-        title_start.backward_word_start();
+        self.set_is_deleted(false);
 
-        // If we go back a word and it's `digraph`/`graph`, title is empty.
-        if title_start == match_start {
-            return "".to_string();
+        if is_removal {
+            return;
         }
 
-        title_start.visible_text(&title_end).to_string()
+        if imp.source_file.is_externally_modified() {
+            tracing::debug!(uri = ?self.file().map(|f| f.uri()), "File modified externally");
+
+            if self.auto_reload() && !self.is_modified() {
+                self.reload_externally_modified();
+            } else {
+                self.set_is_externally_modified(true);
+            }
+        }
+    }
+
+    fn reload_externally_modified(&self) {
+        utils::spawn(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                if obj.is_busy() {
+                    return;
+                }
+
+                if let Err(err) = obj.load().await {
+                    tracing::error!("Failed to auto reload document: {:?}", err);
+                    obj.set_is_externally_modified(true);
+                }
+            }
+        ));
+    }
+
+    fn mark_busy(&self) -> MarkBusyGuard<'_> {
+        self.set_busy_progress(0.0);
+
+        MarkBusyGuard { document: self }
+    }
+
+    /// Looks for the title in the start of the text, notifying only if it
+    /// changed, so edits don't rename the draft's tab and window every time.
+    fn update_parsed_title(&self) {
+        let imp = self.imp();
+
+        let start = self.start_iter();
+        let end = self.iter_at_offset(TITLE_SCAN_MAX_CHARS);
+        let title = utils::dot_graph_id(&self.text(&start, &end, false)).unwrap_or_default();
+
+        if title == *imp.parsed_title.borrow() {
+            return;
+        }
+
+        imp.parsed_title.replace(title);
+        self.notify_title();
     }
 
     #[allow(clippy::type_complexity)]
@@ -377,6 +734,16 @@ impl Document {
         Ok(())
     }
 
+    fn error_tag(&self) -> gtk::TextTag {
+        self.tag_table().lookup(ERROR_TAG_NAME).unwrap()
+    }
+
+    fn update_error_tag(&self) {
+        let tag = self.error_tag();
+        tag.set_underline_rgba(Some(&colors::error()));
+        tag.set_paragraph_background_rgba(Some(&colors::error_line_background()));
+    }
+
     fn update_style_scheme(&self) {
         let style_manager = adw::StyleManager::default();
         let style_scheme_manager = gtk_source::StyleSchemeManager::default();
@@ -394,3 +761,24 @@ impl Document {
         self.set_style_scheme(style_scheme.as_ref());
     }
 }
+
+/// Returns whether `file` can't be written to, or is on a read-only mount.
+async fn query_is_read_only(file: &gio::File) -> Result<bool> {
+    let info = file
+        .query_info_future(
+            gio::FILE_ATTRIBUTE_ACCESS_CAN_WRITE,
+            gio::FileQueryInfoFlags::NONE,
+            FILE_IO_PRIORITY,
+        )
+        .await?;
+    if info.has_attribute(gio::FILE_ATTRIBUTE_ACCESS_CAN_WRITE)
+        && !info.boolean(gio::FILE_ATTRIBUTE_ACCESS_CAN_WRITE)
+    {
+        return Ok(true);
+    }
+
+    let filesystem_info = file
+        .query_filesystem_info_future(gio::FILE_ATTRIBUTE_FILESYSTEM_READONLY, FILE_IO_PRIORITY)
+        .await?;
+    Ok(filesystem_info.boolean(gio::FILE_ATTRIBUTE_FILESYSTEM_READONLY))
+}
@@ -0,0 +1,202 @@
+//! Helpers for files that are shared with the app through the document
+//! portal, like when running in Flatpak, which show up under
+//! `$XDG_RUNTIME_DIR/doc/<id>/` instead of where they really are.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    os::{
+        fd::AsRawFd,
+        unix::{ffi::OsStringExt, fs::OpenOptionsExt},
+    },
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use gtk::{gio, glib, prelude::*};
+
+use crate::config::APP_ID;
+
+const DOCUMENTS_BUS_NAME: &str = "org.freedesktop.portal.Documents";
+const DOCUMENTS_OBJECT_PATH: &str = "/org/freedesktop/portal/documents";
+const DOCUMENTS_INTERFACE: &str = "org.freedesktop.portal.Documents";
+
+/// Extended attribute set by the document portal on its files.
+const HOST_PATH_ATTRIBUTE: &str = "xattr::document-portal.host-path";
+
+const ADD_FLAGS_REUSE_EXISTING: u32 = 1 << 0;
+const ADD_FLAGS_PERSISTENT: u32 = 1 << 1;
+
+thread_local! {
+    /// Host paths of files exported by the document portal, by URI, once
+    /// resolved with [`resolve_host_path`].
+    static HOST_PATHS: RefCell<HashMap<String, PathBuf>> = RefCell::default();
+}
+
+/// Returns whether the file is exported by the document portal.
+pub fn is_portal_file(file: &gio::File) -> bool {
+    file.path().is_some_and(|path| is_portal_path(&path))
+}
+
+fn is_portal_path(path: &Path) -> bool {
+    path.starts_with(glib::user_runtime_dir().join("doc"))
+}
+
+/// Returns the real path on the host of a file exported by the document
+/// portal, or `None` if it is not exported, or its host path is unknown or
+/// not resolved yet with [`resolve_host_path`].
+///
+/// The host path is only meant to be shown, as it is usually not accessible
+/// from within the sandbox.
+pub fn host_path(file: &gio::File) -> Option<PathBuf> {
+    if !is_portal_file(file) {
+        return None;
+    }
+
+    HOST_PATHS.with_borrow(|host_paths| host_paths.get(file.uri().as_str()).cloned())
+}
+
+/// Looks up the host path of a file exported by the document portal, so
+/// [`host_path`] returns it without querying the file every time it is shown.
+///
+/// Does nothing for files that are not exported by the document portal, or
+/// whose host path is already resolved.
+pub async fn resolve_host_path(file: &gio::File) {
+    if !is_portal_file(file)
+        || HOST_PATHS.with_borrow(|host_paths| host_paths.contains_key(file.uri().as_str()))
+    {
+        return;
+    }
+
+    let info = match file
+        .query_info_future(
+            HOST_PATH_ATTRIBUTE,
+            gio::FileQueryInfoFlags::NONE,
+            glib::Priority::DEFAULT,
+        )
+        .await
+    {
+        Ok(info) => info,
+        Err(err) => {
+            tracing::debug!(uri = %file.uri(), "Failed to query host path: {:?}", err);
+            return;
+        }
+    };
+
+    let Some(raw) = info.attribute_string(HOST_PATH_ATTRIBUTE) else {
+        return;
+    };
+    let mut bytes = unescape_xattr(&raw);
+    // The value includes the trailing NUL.
+    if bytes.last() == Some(&0) {
+        bytes.pop();
+    }
+
+    if !bytes.is_empty() {
+        let host_path = PathBuf::from(OsString::from_vec(bytes));
+        HOST_PATHS.with_borrow_mut(|host_paths| {
+            host_paths.insert(file.uri().to_string(), host_path);
+        });
+    }
+}
+
+/// Reverts GIO's escaping of extended attribute values, where bytes that are
+/// not printable ASCII are written as `\xNN`.
+fn unescape_xattr(escaped: &str) -> Vec<u8> {
+    let escaped = escaped.as_bytes();
+
+    let mut bytes = Vec::with_capacity(escaped.len());
+    let mut index = 0;
+    while index < escaped.len() {
+        let byte = escaped.get(index + 2..index + 4).and_then(|hex| {
+            if escaped[index] != b'\\' || escaped[index + 1] != b'x' {
+                return None;
+            }
+            u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+        });
+
+        if let Some(byte) = byte {
+            bytes.push(byte);
+            index += 4;
+        } else {
+            bytes.push(escaped[index]);
+            index += 1;
+        }
+    }
+
+    bytes
+}
+
+/// Asks the document portal to keep the file accessible to the app across
+/// restarts, so it can still be reopened from the recent documents.
+///
+/// Does nothing for files that are not exported by the document portal.
+pub async fn make_persistent(file: &gio::File) -> Result<()> {
+    let Some(path) = file.path().filter(|path| is_portal_path(path)) else {
+        return Ok(());
+    };
+
+    let o_path_file = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_PATH | libc::O_CLOEXEC)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let fd_list = gio::UnixFDList::new();
+    let fd_index = fd_list.append(o_path_file.as_raw_fd())?;
+
+    let connection = gio::bus_get_future(gio::BusType::Session).await?;
+    let parameters = (
+        vec![glib::variant::Handle(fd_index)],
+        ADD_FLAGS_REUSE_EXISTING | ADD_FLAGS_PERSISTENT,
+        APP_ID,
+        vec!["read", "write"],
+    )
+        .to_variant();
+    connection
+        .call_with_unix_fd_list_future(
+            Some(DOCUMENTS_BUS_NAME),
+            DOCUMENTS_OBJECT_PATH,
+            DOCUMENTS_INTERFACE,
+            "AddFull",
+            Some(&parameters),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+            Some(&fd_list),
+        )
+        .await?;
+
+    tracing::debug!(uri = %file.uri(), "Made document persistent");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape_xattr_plain() {
+        assert_eq!(unescape_xattr("/home/user/a.gv"), b"/home/user/a.gv");
+        assert_eq!(unescape_xattr(""), b"");
+    }
+
+    #[test]
+    fn unescape_xattr_escaped() {
+        assert_eq!(unescape_xattr("/home/a\\x20b.gv\\x00"), b"/home/a b.gv\0");
+        assert_eq!(
+            unescape_xattr("/caf\\xc3\\xa9.gv"),
+            "/caf\u{e9}.gv".as_bytes()
+        );
+    }
+
+    #[test]
+    fn unescape_xattr_incomplete() {
+        assert_eq!(unescape_xattr("a\\x2"), b"a\\x2");
+        assert_eq!(unescape_xattr("a\\xzz"), b"a\\xzz");
+        assert_eq!(unescape_xattr("a\\"), b"a\\");
+    }
+}
@@ -0,0 +1,308 @@
+use std::{cell::RefCell, rc::Rc};
+
+use adw::prelude::*;
+use futures_channel::oneshot;
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use gettextrs::gettext;
+use gtk::{
+    gdk, gio,
+    glib::{self, clone},
+};
+
+use crate::{page::Page, recent_item::RecentItem, session::Session, utils, window::Window};
+
+/// What the user picked in the switcher.
+pub enum Choice {
+    /// An already open page, and the window where it is.
+    Page(Window, Page),
+    /// A recent file that is not open yet.
+    File(gio::File),
+}
+
+struct Entry {
+    title: String,
+    subtitle: String,
+    is_recent: bool,
+    choice: Choice,
+}
+
+impl Entry {
+    fn fuzzy_match(&self, matcher: &SkimMatcherV2, pattern: &str) -> Option<i64> {
+        let title_score = matcher.fuzzy_match(&self.title, pattern);
+        let subtitle_score = matcher.fuzzy_match(&self.subtitle, pattern);
+        title_score.max(subtitle_score)
+    }
+}
+
+/// Lets the user jump to an open document, in any window, or to a recent
+/// file by typing part of its name.
+///
+/// Returns `None` if the user cancelled the dialog.
+pub async fn run(parent: &Window) -> Option<Choice> {
+    let entries = Rc::new(RefCell::new(collect_entries().await));
+
+    let dialog = adw::Dialog::builder()
+        .title(gettext("Go to Document"))
+        .content_width(480)
+        .content_height(420)
+        .build();
+
+    let search_entry = gtk::SearchEntry::builder()
+        .placeholder_text(gettext("Search documents"))
+        .hexpand(true)
+        .build();
+
+    let header_bar = adw::HeaderBar::builder()
+        .title_widget(&search_entry)
+        .show_end_title_buttons(false)
+        .build();
+
+    let list_box = gtk::ListBox::builder()
+        .selection_mode(gtk::SelectionMode::Browse)
+        .css_classes(["navigation-sidebar"])
+        .build();
+
+    let empty_page = adw::StatusPage::builder()
+        .icon_name("system-search-symbolic")
+        .title(gettext("No Documents Found"))
+        .build();
+    empty_page.add_css_class("compact");
+
+    let scrolled_window = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .child(&list_box)
+        .build();
+
+    let stack = gtk::Stack::new();
+    stack.add_named(&scrolled_window, Some("list"));
+    stack.add_named(&empty_page, Some("empty"));
+
+    let toolbar_view = adw::ToolbarView::new();
+    toolbar_view.add_top_bar(&header_bar);
+    toolbar_view.set_content(Some(&stack));
+    dialog.set_child(Some(&toolbar_view));
+    dialog.set_focus(Some(&search_entry));
+
+    // Indices of the entries in the order their rows are shown.
+    let shown = Rc::new(RefCell::new(Vec::new()));
+
+    let (tx, rx) = oneshot::channel();
+    let tx = Rc::new(RefCell::new(Some(tx)));
+
+    list_box.connect_row_activated(clone!(
+        #[weak]
+        dialog,
+        #[strong]
+        shown,
+        #[strong]
+        tx,
+        move |_, row| {
+            let index = shown.borrow()[row.index() as usize];
+            if let Some(tx) = tx.take() {
+                let _ = tx.send(Some(index));
+            }
+            dialog.close();
+        }
+    ));
+    dialog.connect_closed(clone!(
+        #[strong]
+        tx,
+        move |_| {
+            if let Some(tx) = tx.take() {
+                let _ = tx.send(None);
+            }
+        }
+    ));
+
+    search_entry.connect_search_changed(clone!(
+        #[weak]
+        list_box,
+        #[weak]
+        stack,
+        #[strong]
+        entries,
+        #[strong]
+        shown,
+        move |search_entry| {
+            populate(
+                &list_box,
+                &entries.borrow(),
+                &shown,
+                search_entry.text().trim(),
+            );
+            let visible_child_name = if shown.borrow().is_empty() {
+                "empty"
+            } else {
+                "list"
+            };
+            stack.set_visible_child_name(visible_child_name);
+        }
+    ));
+    search_entry.connect_activate(clone!(
+        #[weak]
+        list_box,
+        move |_| {
+            if let Some(row) = list_box.selected_row() {
+                row.activate();
+            }
+        }
+    ));
+    search_entry.connect_stop_search(clone!(
+        #[weak]
+        dialog,
+        move |_| {
+            dialog.close();
+        }
+    ));
+
+    let key_controller = gtk::EventControllerKey::new();
+    key_controller.connect_key_pressed(clone!(
+        #[weak]
+        list_box,
+        #[weak]
+        scrolled_window,
+        #[upgrade_or]
+        glib::Propagation::Proceed,
+        move |_, keyval, _, _| {
+            let offset = match keyval {
+                gdk::Key::Down | gdk::Key::KP_Down => 1,
+                gdk::Key::Up | gdk::Key::KP_Up => -1,
+                _ => return glib::Propagation::Proceed,
+            };
+
+            let index = list_box
+                .selected_row()
+                .map_or(0, |row| row.index() + offset);
+            if let Some(row) = list_box.row_at_index(index.max(0)) {
+                list_box.select_row(Some(&row));
+
+                if let Some(bounds) = row.compute_bounds(&list_box) {
+                    let vadjustment = scrolled_window.vadjustment();
+                    vadjustment
+                        .clamp_page(bounds.y() as f64, (bounds.y() + bounds.height()) as f64);
+                }
+            }
+
+            glib::Propagation::Stop
+        }
+    ));
+    search_entry.add_controller(key_controller);
+
+    populate(&list_box, &entries.borrow(), &shown, "");
+    if shown.borrow().is_empty() {
+        stack.set_visible_child_name("empty");
+    }
+
+    dialog.present(Some(parent));
+
+    let index = rx.await.ok().flatten()?;
+    let entry = entries.borrow_mut().swap_remove(index);
+    Some(entry.choice)
+}
+
+/// Returns the open pages of all windows, followed by the recent files that
+/// are not open.
+async fn collect_entries() -> Vec<Entry> {
+    let session = Session::instance();
+
+    let mut entries = Vec::new();
+    let mut open_files = Vec::new();
+    for window in session.windows() {
+        for page in window.pages() {
+            let file = page.document().file();
+            let subtitle = file
+                .as_ref()
+                .map_or_else(|| gettext("Draft"), utils::display_file);
+
+            if let Some(file) = file {
+                open_files.push(file);
+            }
+
+            entries.push(Entry {
+                title: page.title(),
+                subtitle,
+                is_recent: false,
+                choice: Choice::Page(window.clone(), page),
+            });
+        }
+    }
+
+    let mut recent_items = session
+        .recents()
+        .await
+        .iter::<RecentItem>()
+        .map_while(Result::ok)
+        .collect::<Vec<_>>();
+    recent_items.sort_by_key(|item| std::cmp::Reverse(item.added()));
+
+    for item in recent_items {
+        let file = item.file();
+
+        utils::resolve_file_identity(&file).await;
+        if open_files.iter().any(|f| utils::is_same_file(f, &file)) {
+            continue;
+        }
+
+        entries.push(Entry {
+            title: utils::display_file_stem(&file),
+            subtitle: utils::display_file(&file),
+            is_recent: true,
+            choice: Choice::File(file),
+        });
+    }
+
+    entries
+}
+
+/// Shows the rows of the entries matching the pattern, best matches first.
+fn populate(
+    list_box: &gtk::ListBox,
+    entries: &[Entry],
+    shown: &RefCell<Vec<usize>>,
+    pattern: &str,
+) {
+    let matcher = SkimMatcherV2::default();
+
+    let mut matches = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            if pattern.is_empty() {
+                Some((index, 0))
+            } else {
+                entry
+                    .fuzzy_match(&matcher, pattern)
+                    .map(|score| (index, score))
+            }
+        })
+        .collect::<Vec<_>>();
+    // Stable, so open pages stay before recents on ties.
+    matches.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+    list_box.remove_all();
+    for (index, _) in &matches {
+        let entry = &entries[*index];
+
+        let row = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(&entry.title))
+            .subtitle(glib::markup_escape_text(&entry.subtitle))
+            .activatable(true)
+            .build();
+
+        if entry.is_recent {
+            let label = gtk::Label::builder()
+                .label(gettext("Recent"))
+                .css_classes(["caption", "dim-label"])
+                .build();
+            row.add_suffix(&label);
+        }
+
+        list_box.append(&row);
+    }
+
+    shown.replace(matches.into_iter().map(|(index, _)| index).collect());
+
+    if let Some(row) = list_box.row_at_index(0) {
+        list_box.select_row(Some(&row));
+    }
+}
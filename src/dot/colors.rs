@@ -0,0 +1,91 @@
+use indexmap::IndexSet;
+
+use super::{EdgeOperand, Graph, Stmt, StmtKind};
+
+/// Returns the colors set in `graph`, in order of first appearance and
+/// without duplicates. Color lists, like `red:blue;0.3`, are split into their
+/// colors.
+pub fn used_colors(graph: &Graph) -> Vec<&str> {
+    let mut colors = IndexSet::new();
+    visit_stmts(&graph.stmts, &mut colors);
+    colors.into_iter().collect()
+}
+
+fn visit_stmts<'a>(stmts: &'a [Stmt], colors: &mut IndexSet<&'a str>) {
+    for stmt in stmts {
+        let attrs = match &stmt.kind {
+            StmtKind::Node(_, attrs) | StmtKind::Attr(_, attrs) => attrs.as_slice(),
+            StmtKind::Edge(operands, attrs) => {
+                for operand in operands {
+                    if let EdgeOperand::Subgraph(subgraph) = operand {
+                        visit_stmts(&subgraph.stmts, colors);
+                    }
+                }
+                attrs.as_slice()
+            }
+            StmtKind::Assign(attr) => std::slice::from_ref(attr),
+            StmtKind::Subgraph(subgraph) => {
+                visit_stmts(&subgraph.stmts, colors);
+                continue;
+            }
+        };
+
+        for attr in attrs {
+            // Like `color`, `fillcolor` and `fontcolor`. HTML strings are
+            // never colors.
+            if !attr.key.ends_with("color") || attr.value.starts_with('<') {
+                continue;
+            }
+
+            for item in attr.value.split(':') {
+                let color = item.split_once(';').map_or(item, |(color, _)| color).trim();
+                if !color.is_empty() {
+                    colors.insert(color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dot;
+
+    use super::*;
+
+    fn colors_in(source: &str) -> Vec<String> {
+        used_colors(&dot::parse(source).unwrap())
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn simple() {
+        assert_eq!(
+            colors_in(
+                "digraph { bgcolor=white; node [fillcolor=\"#ff0000\"]; a [color=blue fontcolor=white] a -> b [color=blue] }"
+            ),
+            ["white", "#ff0000", "blue"]
+        );
+        assert!(colors_in("digraph { a -> b [label=red] }").is_empty());
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(
+            colors_in(
+                "digraph { a -> b [color=\"red:green;0.3:blue\"] c [fillcolor=\"yellow;0.5:\"] }"
+            ),
+            ["red", "green", "blue", "yellow"]
+        );
+    }
+
+    #[test]
+    fn nested() {
+        assert_eq!(
+            colors_in("digraph { subgraph cluster_a { color=gray; a } b -> { c [color=navy] } }"),
+            ["gray", "navy"]
+        );
+    }
+}
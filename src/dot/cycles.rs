@@ -0,0 +1,179 @@
+use std::{ops::Range, slice};
+
+use super::{Graph, Stmt, StmtKind, Topology};
+
+/// Returns the spans of the edge statements with an edge that is part of a
+/// cycle, in order of appearance. Undirected graphs are never considered
+/// cyclic, as every edge could be followed back.
+pub fn cycle_edge_spans(graph: &Graph) -> Vec<Range<usize>> {
+    if !graph.is_directed {
+        return Vec::new();
+    }
+
+    let topology = graph.topology();
+    let components = strongly_connected_components(topology.nodes.len(), &topology.edges);
+
+    // Edges are in a cycle exactly when both ends are in the same component,
+    // which includes edges from a node to itself.
+    let is_in_cycle = |tail: &str, head: &str| {
+        let tail = topology.nodes.get_index_of(tail).unwrap();
+        let head = topology.nodes.get_index_of(head).unwrap();
+        components[tail] == components[head]
+    };
+
+    let mut ret = Vec::new();
+    visit_stmts(&graph.stmts, &is_in_cycle, &mut ret);
+    ret
+}
+
+fn visit_stmts(
+    stmts: &[Stmt],
+    is_in_cycle: &impl Fn(&str, &str) -> bool,
+    spans: &mut Vec<Range<usize>>,
+) {
+    for stmt in stmts {
+        match &stmt.kind {
+            StmtKind::Edge(..) => {
+                let mut topology = Topology::default();
+                topology.visit_stmts(slice::from_ref(stmt));
+
+                if topology
+                    .edges
+                    .iter()
+                    .any(|&(tail, head)| is_in_cycle(topology.nodes[tail], topology.nodes[head]))
+                {
+                    spans.push(stmt.span.clone());
+                }
+            }
+            StmtKind::Subgraph(subgraph) => visit_stmts(&subgraph.stmts, is_in_cycle, spans),
+            StmtKind::Node(..) | StmtKind::Attr(..) | StmtKind::Assign(_) => {}
+        }
+    }
+}
+
+/// Returns the index of the strongly connected component of each node, using
+/// Tarjan's algorithm without recursion, so long chains can't overflow the
+/// stack.
+fn strongly_connected_components(n_nodes: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    const UNVISITED: usize = usize::MAX;
+
+    let mut successors = vec![Vec::new(); n_nodes];
+    for &(tail, head) in edges {
+        successors[tail].push(head);
+    }
+
+    let mut index = vec![UNVISITED; n_nodes];
+    let mut low_link = vec![0; n_nodes];
+    let mut on_stack = vec![false; n_nodes];
+    let mut stack = Vec::new();
+    let mut components = vec![UNVISITED; n_nodes];
+    let mut next_index = 0;
+    let mut n_components = 0;
+
+    for root in 0..n_nodes {
+        if index[root] != UNVISITED {
+            continue;
+        }
+
+        index[root] = next_index;
+        low_link[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        // Nodes being visited, with the position of their next successor.
+        let mut call_stack = vec![(root, 0)];
+        while let Some((node, position)) = call_stack.last_mut() {
+            let node = *node;
+
+            if let Some(&next) = successors[node].get(*position) {
+                *position += 1;
+
+                if index[next] == UNVISITED {
+                    index[next] = next_index;
+                    low_link[next] = next_index;
+                    next_index += 1;
+                    stack.push(next);
+                    on_stack[next] = true;
+                    call_stack.push((next, 0));
+                } else if on_stack[next] {
+                    low_link[node] = low_link[node].min(index[next]);
+                }
+                continue;
+            }
+
+            call_stack.pop();
+            if let Some(&(parent, _)) = call_stack.last() {
+                low_link[parent] = low_link[parent].min(low_link[node]);
+            }
+
+            if low_link[node] == index[node] {
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    components[member] = n_components;
+                    if member == node {
+                        break;
+                    }
+                }
+                n_components += 1;
+            }
+        }
+    }
+
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dot;
+
+    use super::*;
+
+    fn cycle_stmts(source: &str) -> Vec<&str> {
+        let graph = dot::parse(source).unwrap();
+        cycle_edge_spans(&graph)
+            .into_iter()
+            .map(|span| &source[span])
+            .collect()
+    }
+
+    #[test]
+    fn acyclic() {
+        assert!(cycle_stmts("digraph { a -> b -> c; a -> c; d }").is_empty());
+        assert!(cycle_stmts("digraph { }").is_empty());
+    }
+
+    #[test]
+    fn cycles() {
+        assert_eq!(
+            cycle_stmts("digraph { a -> b; b -> c; c -> a; c -> d; d -> e; e -> d }"),
+            ["a -> b", "b -> c", "c -> a", "d -> e", "e -> d"]
+        );
+        assert_eq!(cycle_stmts("digraph { a -> b; b -> b }"), ["b -> b"]);
+        assert_eq!(
+            cycle_stmts("digraph { x -> a; subgraph s { a -> b -> a } b -> y }"),
+            ["a -> b -> a"]
+        );
+        assert_eq!(
+            cycle_stmts("digraph { a -> { b c }; c -> a }"),
+            ["a -> { b c }", "c -> a"]
+        );
+    }
+
+    #[test]
+    fn undirected() {
+        assert!(cycle_stmts("graph { a -- b -- c -- a }").is_empty());
+    }
+
+    #[test]
+    fn long_chain() {
+        let mut source = String::from("digraph {");
+        for i in 0..10_000 {
+            source.push_str(&format!(" n{} -> n{};", i, i + 1));
+        }
+        source.push_str(" n10000 -> n0 }");
+
+        assert_eq!(cycle_stmts(&source).len(), 10_001);
+    }
+}
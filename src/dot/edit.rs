@@ -0,0 +1,372 @@
+use std::{collections::HashMap, ops::Range};
+
+use super::{quote_id, Attr, EdgeOperand, Element, Graph, NodeId, Stmt, StmtKind};
+
+/// A replacement of a part of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    /// Byte range to be replaced with `text`.
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// Returns the edit that adds a node named `id` at the end of the graph.
+pub fn add_node(source: &str, graph: &Graph, id: &str) -> Edit {
+    append_stmt(source, graph, &quote_id(id))
+}
+
+/// Returns the edit that adds an edge from `tail` to `head` at the end of the
+/// graph.
+pub fn add_edge(source: &str, graph: &Graph, tail: &str, head: &str) -> Edit {
+    let edge_op = if graph.is_directed { "->" } else { "--" };
+    append_stmt(
+        source,
+        graph,
+        &format!("{} {} {}", quote_id(tail), edge_op, quote_id(head)),
+    )
+}
+
+/// Returns the first of `{prefix}1`, `{prefix}2`, and so on, that isn't the
+/// ID of a node in the graph.
+pub fn unused_node_id(graph: &Graph, prefix: &str) -> String {
+    let nodes = graph.topology().nodes;
+    (1..)
+        .map(|n| format!("{}{}", prefix, n))
+        .find(|id| !nodes.contains(id.as_str()))
+        .unwrap()
+}
+
+/// Returns the edits that remove `element` from the graph, or `None` if it
+/// can't be removed, like edges from or to a subgraph, as in `{ a b } -> c`.
+///
+/// Removing a node removes its node statements and the edges from and to
+/// it. Nodes that are only in the removed edges are kept as node statements,
+/// so removing something doesn't also remove its neighbors.
+pub fn remove_element(source: &str, graph: &Graph, element: &Element) -> Option<Vec<Edit>> {
+    let mut mentions = HashMap::new();
+    count_mentions(&graph.stmts, &mut mentions);
+
+    let remover = Remover {
+        source,
+        edge_op: if graph.is_directed { " -> " } else { " -- " },
+        mentions,
+    };
+
+    let edits = match element {
+        Element::Node(id) => {
+            let mut edits = Vec::new();
+            remover.remove_node(&graph.stmts, id, &mut edits);
+            edits
+        }
+        Element::Edge { tail, head } => {
+            let (stmt, index) = find_edge(&graph.stmts, tail, head, graph.is_directed)?;
+            vec![remover.remove_edge(stmt, index)]
+        }
+    };
+
+    (!edits.is_empty()).then_some(edits)
+}
+
+struct Remover<'a> {
+    source: &'a str,
+    edge_op: &'static str,
+    /// Number of statements each node is directly in.
+    mentions: HashMap<&'a str, usize>,
+}
+
+impl<'a> Remover<'a> {
+    fn remove_node(&self, stmts: &'a [Stmt], id: &str, edits: &mut Vec<Edit>) {
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::Node(node_id, _) if node_id.id == id => {
+                    edits.push(self.removal(stmt));
+                }
+                StmtKind::Edge(operands, attrs) => {
+                    let is_node = |operand: &EdgeOperand| matches!(operand, EdgeOperand::Node(node_id) if node_id.id == id);
+
+                    if operands.iter().any(is_node) {
+                        let chains = operands.split(is_node).collect::<Vec<_>>();
+                        edits.push(self.replacement(stmt, &chains, attrs));
+                    } else {
+                        for operand in operands {
+                            if let EdgeOperand::Subgraph(subgraph) = operand {
+                                self.remove_node(&subgraph.stmts, id, edits);
+                            }
+                        }
+                    }
+                }
+                StmtKind::Subgraph(subgraph) => self.remove_node(&subgraph.stmts, id, edits),
+                _ => {}
+            }
+        }
+    }
+
+    /// Removes the edge between the operands at `index` and `index + 1`.
+    fn remove_edge(&self, stmt: &'a Stmt, index: usize) -> Edit {
+        let StmtKind::Edge(operands, attrs) = &stmt.kind else {
+            unreachable!()
+        };
+
+        let (before, after) = operands.split_at(index + 1);
+        self.replacement(stmt, &[before, after], attrs)
+    }
+
+    /// Returns the edit that replaces the edge statement `stmt` with
+    /// `chains` of operands.
+    fn replacement(&self, stmt: &Stmt, chains: &[&[EdgeOperand]], attrs: &[Attr]) -> Edit {
+        let attrs_text = attrs.first().map(|first| {
+            let attrs_start = self.source[..first.span.start].rfind('[').unwrap();
+            &self.source[attrs_start..stmt.span.end]
+        });
+
+        let mut stmt_texts = Vec::new();
+        for chain in chains {
+            match chain {
+                [] => {}
+                [EdgeOperand::Node(node_id)] => {
+                    // Keep it only if it would be gone otherwise.
+                    if self.mentions.get(node_id.id.as_str()) == Some(&1) {
+                        stmt_texts.push(node_id_text(node_id));
+                    }
+                }
+                [EdgeOperand::Subgraph(subgraph)] => {
+                    stmt_texts.push(self.source[subgraph.span.clone()].to_string());
+                }
+                chain => {
+                    let mut text = chain
+                        .iter()
+                        .map(|operand| match operand {
+                            EdgeOperand::Node(node_id) => node_id_text(node_id),
+                            EdgeOperand::Subgraph(subgraph) => {
+                                self.source[subgraph.span.clone()].to_string()
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join(self.edge_op);
+                    if let Some(attrs_text) = attrs_text {
+                        text.push(' ');
+                        text.push_str(attrs_text);
+                    }
+                    stmt_texts.push(text);
+                }
+            }
+        }
+
+        if stmt_texts.is_empty() {
+            self.removal(stmt)
+        } else {
+            Edit {
+                range: stmt.span.clone(),
+                text: stmt_texts.join("; "),
+            }
+        }
+    }
+
+    /// Returns the edit that removes `stmt` with its semicolon, even if that
+    /// is on a later line, and its line if nothing else is on it.
+    fn removal(&self, stmt: &Stmt) -> Edit {
+        let source = self.source;
+
+        let mut end = stmt.span.end;
+        let rest = source[end..].trim_start();
+        if let Some(rest) = rest.strip_prefix(';') {
+            end = source.len() - rest.len();
+        }
+        end = source.len() - source[end..].trim_start_matches([' ', '\t']).len();
+
+        let line_start = source[..stmt.span.start].rfind('\n').map_or(0, |i| i + 1);
+        let is_own_line = source[line_start..stmt.span.start].trim().is_empty()
+            && (end == source.len() || source[end..].starts_with('\n'));
+
+        let range = if is_own_line {
+            line_start..(end + 1).min(source.len())
+        } else {
+            stmt.span.start..end
+        };
+        Edit {
+            range,
+            text: String::new(),
+        }
+    }
+}
+
+fn count_mentions<'a>(stmts: &'a [Stmt], mentions: &mut HashMap<&'a str, usize>) {
+    for stmt in stmts {
+        match &stmt.kind {
+            StmtKind::Node(node_id, _) => *mentions.entry(node_id.id.as_str()).or_default() += 1,
+            StmtKind::Edge(operands, _) => {
+                for operand in operands {
+                    match operand {
+                        EdgeOperand::Node(node_id) => {
+                            *mentions.entry(node_id.id.as_str()).or_default() += 1;
+                        }
+                        EdgeOperand::Subgraph(subgraph) => {
+                            count_mentions(&subgraph.stmts, mentions);
+                        }
+                    }
+                }
+            }
+            StmtKind::Subgraph(subgraph) => count_mentions(&subgraph.stmts, mentions),
+            StmtKind::Attr(..) | StmtKind::Assign(_) => {}
+        }
+    }
+}
+
+/// Returns the first edge statement with the edge from `tail` to `head`
+/// between two node operands, and the index of the first of them.
+fn find_edge<'a>(
+    stmts: &'a [Stmt],
+    tail: &str,
+    head: &str,
+    is_directed: bool,
+) -> Option<(&'a Stmt, usize)> {
+    stmts.iter().find_map(|stmt| match &stmt.kind {
+        StmtKind::Edge(operands, _) => {
+            let index = operands.windows(2).position(|pair| match pair {
+                [EdgeOperand::Node(a), EdgeOperand::Node(b)] => {
+                    (a.id == tail && b.id == head) || (!is_directed && a.id == head && b.id == tail)
+                }
+                _ => false,
+            });
+            index.map(|index| (stmt, index)).or_else(|| {
+                operands.iter().find_map(|operand| match operand {
+                    EdgeOperand::Subgraph(subgraph) => {
+                        find_edge(&subgraph.stmts, tail, head, is_directed)
+                    }
+                    EdgeOperand::Node(_) => None,
+                })
+            })
+        }
+        StmtKind::Subgraph(subgraph) => find_edge(&subgraph.stmts, tail, head, is_directed),
+        _ => None,
+    })
+}
+
+fn node_id_text(node_id: &NodeId) -> String {
+    let mut ret = quote_id(&node_id.id);
+    if let Some(port) = &node_id.port {
+        for part in port.split(':') {
+            ret.push(':');
+            ret.push_str(&quote_id(part));
+        }
+    }
+    ret
+}
+
+/// Returns the edit that adds `stmt_text` at the end of the graph body, on
+/// its own line if the closing brace is on its own line.
+pub(super) fn append_stmt(source: &str, graph: &Graph, stmt_text: &str) -> Edit {
+    let end = graph.body_span.end;
+    let line_start = source[..end].rfind('\n').map_or(0, |index| index + 1);
+
+    if line_start > graph.body_span.start && source[line_start..end].trim().is_empty() {
+        Edit {
+            range: line_start..line_start,
+            text: format!("    {}\n", stmt_text),
+        }
+    } else if source[..end].ends_with(char::is_whitespace) {
+        Edit {
+            range: end..end,
+            text: format!("{} ", stmt_text),
+        }
+    } else {
+        Edit {
+            range: end..end,
+            text: format!(" {} ", stmt_text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dot;
+
+    use super::*;
+
+    fn apply(source: &str, edit: &Edit) -> String {
+        let mut ret = source.to_string();
+        ret.replace_range(edit.range.clone(), &edit.text);
+        ret
+    }
+
+    #[test]
+    fn add() {
+        let source = "digraph {\n    a\n}";
+        let graph = dot::parse(source).unwrap();
+        assert_eq!(
+            apply(source, &add_node(source, &graph, "new node")),
+            "digraph {\n    a\n    \"new node\"\n}"
+        );
+        assert_eq!(
+            apply(source, &add_edge(source, &graph, "a", "b")),
+            "digraph {\n    a\n    a -> b\n}"
+        );
+
+        let source = "graph {}";
+        let graph = dot::parse(source).unwrap();
+        assert_eq!(
+            apply(source, &add_edge(source, &graph, "a", "b")),
+            "graph { a -- b }"
+        );
+    }
+
+    #[test]
+    fn remove() {
+        let remove = |source: &str, element: &Element| {
+            let graph = dot::parse(source).unwrap();
+            let mut ret = source.to_string();
+            for edit in remove_element(source, &graph, element)?.iter().rev() {
+                ret.replace_range(edit.range.clone(), &edit.text);
+            }
+            Some(ret)
+        };
+        let node = |id: &str| Element::Node(id.to_string());
+        let edge = |tail: &str, head: &str| Element::Edge {
+            tail: tail.to_string(),
+            head: head.to_string(),
+        };
+
+        let source =
+            "digraph {\n    a [shape=box];\n    a -> b -> c [color=red]\n    c -> a:p:n\n    c\n}";
+        assert_eq!(
+            remove(source, &node("a")).unwrap(),
+            "digraph {\n    b -> c [color=red]\n    c\n}"
+        );
+        assert_eq!(
+            remove(source, &node("b")).unwrap(),
+            "digraph {\n    a [shape=box];\n    c -> a:p:n\n    c\n}"
+        );
+        assert_eq!(
+            remove(source, &edge("a", "b")).unwrap(),
+            "digraph {\n    a [shape=box];\n    b -> c [color=red]\n    c -> a:p:n\n    c\n}"
+        );
+        assert_eq!(
+            remove(source, &edge("c", "a")).unwrap(),
+            "digraph {\n    a [shape=box];\n    a -> b -> c [color=red]\n    c\n}"
+        );
+
+        assert_eq!(
+            remove("graph { { a b } -- c; d -- e }", &edge("e", "d")).unwrap(),
+            "graph { { a b } -- c; d; e }"
+        );
+        assert_eq!(
+            remove("digraph { a\n ; b }", &node("a")).unwrap(),
+            "digraph { b }"
+        );
+        assert_eq!(
+            remove("digraph {\n    a\n    ;\n    b\n}", &node("a")).unwrap(),
+            "digraph {\n    b\n}"
+        );
+        assert_eq!(remove("graph { { a b } -- c }", &edge("a", "c")), None);
+        assert_eq!(remove("graph { a }", &node("b")), None);
+    }
+
+    #[test]
+    fn unused_id() {
+        let graph = dot::parse("digraph { node1 -> node3 }").unwrap();
+        assert_eq!(unused_node_id(&graph, "node"), "node2");
+
+        let graph = dot::parse("digraph { }").unwrap();
+        assert_eq!(unused_node_id(&graph, "node"), "node1");
+    }
+}
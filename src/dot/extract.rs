@@ -0,0 +1,193 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use super::{reindent, EdgeOperand, Stmt, StmtKind, Subgraph};
+
+/// Returns a standalone graph made from the innermost named subgraph that
+/// contains the byte `offset` of `source`, or `None` if there is none.
+///
+/// Attribute statements of the enclosing graphs that come before the
+/// subgraph are copied, as well as the statements of nodes declared outside
+/// the subgraph but used within it, so that it looks the same on its own.
+pub fn extract_subgraph(source: &str, offset: usize) -> Result<Option<String>> {
+    let graph = super::parse(source)?;
+
+    let mut ancestors = Vec::new();
+    let Some(subgraph) = find_subgraph(&graph.stmts, offset, &mut ancestors) else {
+        return Ok(None);
+    };
+
+    let used_nodes = subgraph.topology().nodes;
+
+    let mut attr_stmts = Vec::new();
+    let mut node_stmts = Vec::new();
+    for stmts in ancestors {
+        for stmt in stmts {
+            // Skip the statement that contains the subgraph itself.
+            if stmt.span.start <= subgraph.span.start && subgraph.span.end <= stmt.span.end {
+                continue;
+            }
+
+            match &stmt.kind {
+                StmtKind::Attr(..) | StmtKind::Assign(_)
+                    if stmt.span.end <= subgraph.span.start =>
+                {
+                    attr_stmts.push(&source[stmt.span.clone()]);
+                }
+                StmtKind::Node(node_id, attrs)
+                    if !attrs.is_empty() && used_nodes.contains(node_id.id.as_str()) =>
+                {
+                    node_stmts.push(&source[stmt.span.clone()]);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut ret = String::new();
+
+    if graph.is_strict {
+        ret.push_str("strict ");
+    }
+    ret.push_str(if graph.is_directed {
+        "digraph"
+    } else {
+        "graph"
+    });
+    if subgraph.id.is_some() {
+        write!(ret, " {}", source_id(source, subgraph)).unwrap();
+    }
+    ret.push_str(" {\n");
+
+    for section in [attr_stmts, node_stmts] {
+        if section.is_empty() {
+            continue;
+        }
+
+        for stmt in section {
+            writeln!(ret, "    {}", stmt).unwrap();
+        }
+        ret.push('\n');
+    }
+
+    ret.push_str(&reindent(&source[subgraph.body_span.clone()]));
+    ret.push_str("}\n");
+
+    Ok(Some(ret))
+}
+
+/// Returns the innermost named subgraph containing `offset`, pushing the
+/// statements of the graphs enclosing it to `ancestors`, outermost first.
+fn find_subgraph<'a>(
+    stmts: &'a [Stmt],
+    offset: usize,
+    ancestors: &mut Vec<&'a [Stmt]>,
+) -> Option<&'a Subgraph> {
+    ancestors.push(stmts);
+
+    for stmt in stmts.iter().filter(|stmt| stmt.span.contains(&offset)) {
+        let subgraphs = match &stmt.kind {
+            StmtKind::Subgraph(subgraph) => vec![subgraph],
+            StmtKind::Edge(operands, _) => operands
+                .iter()
+                .filter_map(|operand| match operand {
+                    EdgeOperand::Subgraph(subgraph) => Some(subgraph),
+                    EdgeOperand::Node(_) => None,
+                })
+                .collect(),
+            _ => continue,
+        };
+
+        for subgraph in subgraphs
+            .into_iter()
+            .filter(|subgraph| subgraph.span.contains(&offset))
+        {
+            let n_ancestors = ancestors.len();
+            if let Some(inner) = find_subgraph(&subgraph.stmts, offset, ancestors) {
+                return Some(inner);
+            }
+            ancestors.truncate(n_ancestors);
+
+            if subgraph.id.is_some() {
+                return Some(subgraph);
+            }
+        }
+    }
+
+    ancestors.pop();
+    None
+}
+
+/// Returns the ID of `subgraph` as written in the source, keeping its quotes.
+fn source_id<'a>(source: &'a str, subgraph: &Subgraph) -> &'a str {
+    // Named subgraphs always start with the `subgraph` keyword.
+    let header = &source[subgraph.span.start..subgraph.body_span.start - 1];
+    header["subgraph".len()..].trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cluster() {
+        let source = r#"digraph G {
+    rankdir=LR
+    node [shape=box]
+    a [label="A"]
+    b [label="B"]
+
+    subgraph "cluster a" {
+        label="Cluster"
+        a -> c
+        subgraph cluster_inner { d }
+    }
+
+    edge [color=red]
+}"#;
+
+        let offset = source.find("a -> c").unwrap();
+        assert_eq!(
+            extract_subgraph(source, offset).unwrap().unwrap(),
+            r#"digraph "cluster a" {
+    rankdir=LR
+    node [shape=box]
+
+    a [label="A"]
+
+    label="Cluster"
+    a -> c
+    subgraph cluster_inner { d }
+}
+"#
+        );
+
+        let offset = source.find("{ d }").unwrap() + 2;
+        assert_eq!(
+            extract_subgraph(source, offset).unwrap().unwrap(),
+            "digraph cluster_inner {\n    rankdir=LR\n    node [shape=box]\n    label=\"Cluster\"\n\n    d\n}\n"
+        );
+    }
+
+    #[test]
+    fn none() {
+        let source = "graph { a -- { b c } }";
+        assert_eq!(
+            extract_subgraph(source, source.find('b').unwrap()).unwrap(),
+            None
+        );
+        assert_eq!(extract_subgraph(source, 0).unwrap(), None);
+        assert!(extract_subgraph("graph {", 0).is_err());
+    }
+
+    #[test]
+    fn unicode_indent() {
+        let source = "digraph { subgraph cluster_a {\n\u{3000}a\n  b\n} }";
+        let offset = source.find('b').unwrap();
+        assert_eq!(
+            extract_subgraph(source, offset).unwrap().unwrap(),
+            "digraph cluster_a {\n    \u{3000}a\n      b\n}\n"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use std::{borrow::Cow, fmt::Write};
+
+use super::lexer::{self, TokenKind};
+
+/// Font size used by Graphviz when none is set.
+const DEFAULT_FONT_SIZE: f64 = 14.0;
+
+/// Returns `source` with the default font size of its first graph multiplied
+/// by `scale`, so text is bigger or smaller unless a font size is set.
+///
+/// The default attribute statements are inserted right after the opening
+/// brace, on the same line, so line numbers in errors stay the same. If the
+/// graph can't be found, like when the source is incomplete, it is returned
+/// as is.
+pub fn scale_default_font_size(source: &str, scale: f64) -> Cow<'_, str> {
+    if scale == 1.0 {
+        return Cow::Borrowed(source);
+    }
+
+    let Ok(tokens) = lexer::tokenize(source) else {
+        return Cow::Borrowed(source);
+    };
+
+    let mut tokens = tokens.iter().skip_while(|t| t.kind == TokenKind::Strict);
+    if !tokens
+        .next()
+        .is_some_and(|t| matches!(t.kind, TokenKind::Graph | TokenKind::Digraph))
+    {
+        return Cow::Borrowed(source);
+    }
+    let Some(lbrace) = tokens.take(2).find(|token| token.kind == TokenKind::LBrace) else {
+        return Cow::Borrowed(source);
+    };
+
+    let font_size = (DEFAULT_FONT_SIZE * scale * 100.0).round() / 100.0;
+
+    let mut ret = String::with_capacity(source.len() + 64);
+    ret.push_str(&source[..lbrace.span.end]);
+    for target in ["graph", "node", "edge"] {
+        write!(ret, "{target}[fontsize={font_size}];").unwrap();
+    }
+    ret.push_str(&source[lbrace.span.end..]);
+    Cow::Owned(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled() {
+        assert_eq!(
+            scale_default_font_size("digraph G {\n    a -> b\n}", 1.5),
+            "digraph G {graph[fontsize=21];node[fontsize=21];edge[fontsize=21];\n    a -> b\n}"
+        );
+        assert_eq!(
+            scale_default_font_size("strict graph { a -- b }", 1.2),
+            "strict graph {graph[fontsize=16.8];node[fontsize=16.8];edge[fontsize=16.8]; a -- b }"
+        );
+    }
+
+    #[test]
+    fn unscaled() {
+        let source = "digraph { a -> b }";
+        assert!(matches!(
+            scale_default_font_size(source, 1.0),
+            Cow::Borrowed(s) if s == source
+        ));
+    }
+
+    #[test]
+    fn not_a_graph() {
+        for source in [
+            "",
+            "digraph",
+            "a -> b",
+            "digraph { a -> \"b",
+            "digraph a b { }",
+        ] {
+            assert_eq!(scale_default_font_size(source, 2.0), source);
+        }
+    }
+}
@@ -0,0 +1,539 @@
+use std::{ops::Range, slice};
+
+use indexmap::{IndexMap, IndexSet};
+
+use super::{
+    edit::{append_stmt, Edit},
+    quote_id, Attr, AttrTarget, EdgeOperand, Graph, Stmt, StmtKind, Topology,
+};
+
+/// A node or an edge of a graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    Node(String),
+    Edge { tail: String, head: String },
+}
+
+/// An attribute of an element, as Graphviz would apply it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectiveAttr {
+    pub key: String,
+    pub value: String,
+    /// Whether the value comes from a `node [...]` or `edge [...]` statement
+    /// rather than from the statements of the element itself.
+    pub is_default: bool,
+}
+
+/// Returns the element of the statement that contains the byte `offset`, or
+/// `None` if it is not in a node or edge statement.
+///
+/// For edge statements with several edges, like `a -> b -> c`, the first
+/// edge is returned, as they all share the same attributes.
+pub fn element_at(graph: &Graph, offset: usize) -> Option<Element> {
+    element_in_stmts(&graph.stmts, offset)
+}
+
+/// Returns the element that Graphviz gave the title `title` in the SVG
+/// output, which for edges is their endpoints joined by the edge operator,
+/// possibly with ports.
+pub fn element_from_title(graph: &Graph, is_edge: bool, title: &str) -> Option<Element> {
+    let nodes = graph.topology().nodes;
+
+    if !is_edge {
+        return nodes
+            .contains(title)
+            .then(|| Element::Node(title.to_string()));
+    }
+
+    let edge_op = if graph.is_directed { "->" } else { "--" };
+    title.match_indices(edge_op).find_map(|(index, _)| {
+        let tail = resolve_node(&nodes, &title[..index])?;
+        let head = resolve_node(&nodes, &title[index + edge_op.len()..])?;
+        Some(Element::Edge {
+            tail: tail.to_string(),
+            head: head.to_string(),
+        })
+    })
+}
+
+/// Returns the attributes of `element`, including the defaults in effect
+/// where it was created, or `None` if it is not in the graph.
+pub fn inspect(graph: &Graph, element: &Element) -> Option<Vec<EffectiveAttr>> {
+    let lookup = Lookup::new(graph, element)?;
+
+    let mut attrs = IndexMap::new();
+    for attr in lookup.defaults {
+        attrs.insert(attr.key.as_str(), (attr.value.as_str(), true));
+    }
+    for stmt in &lookup.stmts {
+        for attr in stmt_attrs(stmt) {
+            attrs.insert(attr.key.as_str(), (attr.value.as_str(), false));
+        }
+    }
+
+    let ret = attrs
+        .into_iter()
+        .map(|(key, (value, is_default))| EffectiveAttr {
+            key: key.to_string(),
+            value: value.to_string(),
+            is_default,
+        })
+        .collect();
+    Some(ret)
+}
+
+/// Returns the edit that sets the attribute `key` of `element` to `value`,
+/// or `None` if the element is not in the graph.
+///
+/// The last attribute of the element named `key` is replaced if there is
+/// one. Otherwise, the attribute is added to the last statement of the
+/// element, or to a new node statement at the end of the graph if the node
+/// only appears in edges.
+pub fn set_attr(
+    source: &str,
+    graph: &Graph,
+    element: &Element,
+    key: &str,
+    value: &str,
+) -> Option<Edit> {
+    let lookup = Lookup::new(graph, element)?;
+
+    let attr_text = format!("{}={}", quote_id(key), quote_value(value));
+
+    let existing = lookup
+        .stmts
+        .iter()
+        .rev()
+        .flat_map(|stmt| stmt_attrs(stmt).iter().rev())
+        .find(|attr| attr.key == key);
+    if let Some(attr) = existing {
+        return Some(Edit {
+            range: attr.span.clone(),
+            text: attr_text,
+        });
+    }
+
+    if let Some(stmt) = lookup.stmts.last() {
+        let edit = match stmt_attrs(stmt).last() {
+            Some(last) => Edit {
+                range: last.span.end..last.span.end,
+                text: format!(", {}", attr_text),
+            },
+            None => Edit {
+                range: stmt.span.end..stmt.span.end,
+                text: format!(" [{}]", attr_text),
+            },
+        };
+        return Some(edit);
+    }
+
+    let Element::Node(id) = element else {
+        unreachable!("edges are always created by a statement");
+    };
+    Some(append_stmt(
+        source,
+        graph,
+        &format!("{} [{}]", quote_id(id), attr_text),
+    ))
+}
+
+/// Returns the edits that remove the attributes of `element` named `key`,
+/// ordered by position, so defaults apply again.
+pub fn remove_attr(source: &str, graph: &Graph, element: &Element, key: &str) -> Vec<Edit> {
+    let Some(lookup) = Lookup::new(graph, element) else {
+        return Vec::new();
+    };
+
+    let mut ranges = lookup
+        .stmts
+        .iter()
+        .flat_map(|stmt| stmt_attrs(stmt))
+        .filter(|attr| attr.key == key)
+        .map(|attr| removal_range(source, &attr.span))
+        .collect::<Vec<_>>();
+    ranges.sort_by_key(|range| range.start);
+
+    // Removing adjacent attributes may take the same separator.
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|range| Edit {
+            range,
+            text: String::new(),
+        })
+        .collect()
+}
+
+#[derive(Default, Clone)]
+struct Scope<'a> {
+    node_defaults: Vec<&'a Attr>,
+    edge_defaults: Vec<&'a Attr>,
+}
+
+/// Where the attributes of an element come from.
+struct Lookup<'a> {
+    element: &'a Element,
+    is_directed: bool,
+    /// Defaults in effect where the element was created.
+    defaults: Vec<&'a Attr>,
+    /// Statements whose attributes apply to the element, in order.
+    stmts: Vec<&'a Stmt>,
+    is_found: bool,
+}
+
+impl<'a> Lookup<'a> {
+    fn new(graph: &'a Graph, element: &'a Element) -> Option<Self> {
+        let mut lookup = Self {
+            element,
+            is_directed: graph.is_directed,
+            defaults: Vec::new(),
+            stmts: Vec::new(),
+            is_found: false,
+        };
+        lookup.visit_stmts(&graph.stmts, Scope::default());
+        lookup.is_found.then_some(lookup)
+    }
+
+    fn visit_stmts(&mut self, stmts: &'a [Stmt], mut scope: Scope<'a>) {
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::Attr(AttrTarget::Node, attrs) => scope.node_defaults.extend(attrs),
+                StmtKind::Attr(AttrTarget::Edge, attrs) => scope.edge_defaults.extend(attrs),
+                StmtKind::Node(node_id, _) => {
+                    if self.is_node(&node_id.id) {
+                        self.found(&scope.node_defaults);
+                        self.stmts.push(stmt);
+                    }
+                }
+                StmtKind::Edge(operands, _) => {
+                    for operand in operands {
+                        match operand {
+                            EdgeOperand::Node(node_id) => {
+                                if self.is_node(&node_id.id) {
+                                    self.found(&scope.node_defaults);
+                                }
+                            }
+                            EdgeOperand::Subgraph(subgraph) => {
+                                self.visit_stmts(&subgraph.stmts, scope.clone());
+                            }
+                        }
+                    }
+
+                    // Only the first matching edge is considered, as that is
+                    // what the other lookups return too.
+                    if self.stmts.is_empty() && self.is_edge_in(stmt) {
+                        self.found(&scope.edge_defaults);
+                        self.stmts.push(stmt);
+                    }
+                }
+                StmtKind::Subgraph(subgraph) => {
+                    self.visit_stmts(&subgraph.stmts, scope.clone());
+                }
+                StmtKind::Attr(AttrTarget::Graph, _) | StmtKind::Assign(_) => {}
+            }
+        }
+    }
+
+    fn found(&mut self, defaults: &[&'a Attr]) {
+        if !self.is_found {
+            self.is_found = true;
+            self.defaults = defaults.to_vec();
+        }
+    }
+
+    fn is_node(&self, id: &str) -> bool {
+        matches!(self.element, Element::Node(node) if node == id)
+    }
+
+    fn is_edge_in(&self, stmt: &'a Stmt) -> bool {
+        let Element::Edge { tail, head } = self.element else {
+            return false;
+        };
+
+        let mut topology = Topology::default();
+        topology.visit_stmts(slice::from_ref(stmt));
+
+        topology.edges.iter().any(|&(source, target)| {
+            let (source, target) = (topology.nodes[source], topology.nodes[target]);
+            (source == tail && target == head)
+                || (!self.is_directed && source == head && target == tail)
+        })
+    }
+}
+
+fn element_in_stmts(stmts: &[Stmt], offset: usize) -> Option<Element> {
+    let stmt = stmts
+        .iter()
+        .find(|stmt| stmt.span.start <= offset && offset <= stmt.span.end)?;
+
+    match &stmt.kind {
+        StmtKind::Node(node_id, _) => Some(Element::Node(node_id.id.clone())),
+        StmtKind::Edge(operands, _) => {
+            for operand in operands {
+                if let EdgeOperand::Subgraph(subgraph) = operand {
+                    if subgraph.body_span.contains(&offset) {
+                        return element_in_stmts(&subgraph.stmts, offset);
+                    }
+                }
+            }
+
+            let first_node = |operand: &EdgeOperand| match operand {
+                EdgeOperand::Node(node_id) => Some(node_id.id.clone()),
+                EdgeOperand::Subgraph(subgraph) => {
+                    subgraph.topology().nodes.first().map(|id| id.to_string())
+                }
+            };
+            Some(Element::Edge {
+                tail: first_node(operands.first()?)?,
+                head: first_node(operands.get(1)?)?,
+            })
+        }
+        StmtKind::Subgraph(subgraph) => element_in_stmts(&subgraph.stmts, offset),
+        StmtKind::Attr(..) | StmtKind::Assign(_) => None,
+    }
+}
+
+/// Returns the node in `nodes` that `text` refers to, ignoring its port.
+fn resolve_node<'a>(nodes: &IndexSet<&'a str>, mut text: &str) -> Option<&'a str> {
+    loop {
+        if let Some(node) = nodes.get(text) {
+            return Some(node);
+        }
+        text = text.rsplit_once(':')?.0;
+    }
+}
+
+fn stmt_attrs(stmt: &Stmt) -> &[Attr] {
+    match &stmt.kind {
+        StmtKind::Node(_, attrs) | StmtKind::Edge(_, attrs) => attrs,
+        _ => &[],
+    }
+}
+
+/// Returns `value` quoted if needed, keeping HTML strings as is.
+fn quote_value(value: &str) -> String {
+    if value.starts_with('<') && value.ends_with('>') {
+        value.to_string()
+    } else {
+        quote_id(value)
+    }
+}
+
+/// Returns the range of the attribute at `span` together with its separator,
+/// so the rest of the attribute list stays well formatted.
+fn removal_range(source: &str, span: &Range<usize>) -> Range<usize> {
+    let after = &source[span.end..];
+    let after_trimmed = after.trim_start();
+    if let Some(rest) = after_trimmed.strip_prefix([',', ';']) {
+        let rest_trimmed = rest.trim_start_matches([' ', '\t']);
+        return span.start..source.len() - rest_trimmed.len();
+    }
+
+    let before = source[..span.start].trim_end();
+    match before.strip_suffix([',', ';']) {
+        Some(before) => before.len()..span.end,
+        None => span.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dot;
+
+    use super::*;
+
+    fn node(id: &str) -> Element {
+        Element::Node(id.to_string())
+    }
+
+    fn edge(tail: &str, head: &str) -> Element {
+        Element::Edge {
+            tail: tail.to_string(),
+            head: head.to_string(),
+        }
+    }
+
+    fn apply(source: &str, edits: &[Edit]) -> String {
+        let mut ret = source.to_string();
+        for edit in edits.iter().rev() {
+            ret.replace_range(edit.range.clone(), &edit.text);
+        }
+        ret
+    }
+
+    fn attrs(source: &str, element: &Element) -> Vec<(String, String, bool)> {
+        let graph = dot::parse(source).unwrap();
+        inspect(&graph, element)
+            .unwrap()
+            .into_iter()
+            .map(|attr| (attr.key, attr.value, attr.is_default))
+            .collect()
+    }
+
+    #[test]
+    fn effective_attrs() {
+        let source = "digraph {
+            node [shape=box color=red]
+            a
+            subgraph { node [color=blue]; b -> c [style=dashed] }
+            node [shape=circle]
+            edge [arrowhead=dot]
+            a [color=green]
+            a -> b
+        }";
+
+        assert_eq!(
+            attrs(source, &node("a")),
+            [
+                ("shape".into(), "box".into(), true),
+                ("color".into(), "green".into(), false),
+            ]
+        );
+        assert_eq!(
+            attrs(source, &node("c")),
+            [
+                ("shape".into(), "box".into(), true),
+                ("color".into(), "blue".into(), true),
+            ]
+        );
+        assert_eq!(
+            attrs(source, &edge("b", "c")),
+            [("style".into(), "dashed".into(), false)]
+        );
+        assert_eq!(
+            attrs(source, &edge("a", "b")),
+            [("arrowhead".into(), "dot".into(), true)]
+        );
+
+        let graph = dot::parse(source).unwrap();
+        assert_eq!(inspect(&graph, &node("d")), None);
+        assert_eq!(inspect(&graph, &edge("c", "b")), None);
+    }
+
+    #[test]
+    fn undirected_edge() {
+        let source = "graph { a -- b [color=red] }";
+        assert_eq!(
+            attrs(source, &edge("b", "a")),
+            [("color".into(), "red".into(), false)]
+        );
+    }
+
+    #[test]
+    fn set() {
+        let set = |source: &str, element: &Element, key: &str, value: &str| {
+            let graph = dot::parse(source).unwrap();
+            let edit = set_attr(source, &graph, element, key, value).unwrap();
+            apply(source, &[edit])
+        };
+
+        assert_eq!(
+            set(
+                "digraph { a [color=red, shape=box] }",
+                &node("a"),
+                "color",
+                "light blue"
+            ),
+            "digraph { a [color=\"light blue\", shape=box] }"
+        );
+        assert_eq!(
+            set(
+                "digraph { a [shape=box] }",
+                &node("a"),
+                "label",
+                "<<b>A</b>>"
+            ),
+            "digraph { a [shape=box, label=<<b>A</b>>] }"
+        );
+        assert_eq!(
+            set(
+                "digraph { a -> b; b -> c }",
+                &edge("b", "c"),
+                "color",
+                "red"
+            ),
+            "digraph { a -> b; b -> c [color=red] }"
+        );
+        assert_eq!(
+            set("digraph {\n    a -> b\n}", &node("b"), "shape", "box"),
+            "digraph {\n    a -> b\n    b [shape=box]\n}"
+        );
+        assert_eq!(
+            set("digraph { a -> b }", &node("b"), "shape", "box"),
+            "digraph { a -> b b [shape=box] }"
+        );
+
+        let graph = dot::parse("digraph { a }").unwrap();
+        assert_eq!(
+            set_attr("digraph { a }", &graph, &node("b"), "x", "y"),
+            None
+        );
+    }
+
+    #[test]
+    fn remove() {
+        let remove = |source: &str, element: &Element, key: &str| {
+            let graph = dot::parse(source).unwrap();
+            apply(source, &remove_attr(source, &graph, element, key))
+        };
+
+        assert_eq!(
+            remove("digraph { a [color=red, shape=box] }", &node("a"), "color"),
+            "digraph { a [shape=box] }"
+        );
+        assert_eq!(
+            remove("digraph { a [shape=box; color=red] }", &node("a"), "color"),
+            "digraph { a [shape=box] }"
+        );
+        assert_eq!(
+            remove(
+                "digraph { a [color=red, color=blue]; a [color=green] }",
+                &node("a"),
+                "color"
+            ),
+            "digraph { a []; a [] }"
+        );
+        assert_eq!(
+            remove("digraph { a [shape=box] }", &node("a"), "color"),
+            "digraph { a [shape=box] }"
+        );
+    }
+
+    #[test]
+    fn at_offset() {
+        let source = "digraph { node [shape=box]; a; b -> { c d } -> e; subgraph { f -> g } }";
+        let graph = dot::parse(source).unwrap();
+        let at = |text: &str| element_at(&graph, source.find(text).unwrap());
+
+        assert_eq!(at("node"), None);
+        assert_eq!(at("a;"), Some(node("a")));
+        assert_eq!(at("b ->"), Some(edge("b", "c")));
+        assert_eq!(at("d }"), Some(node("d")));
+        assert_eq!(at("-> e"), Some(edge("b", "c")));
+        assert_eq!(at("g }"), Some(edge("f", "g")));
+    }
+
+    #[test]
+    fn from_title() {
+        let graph = dot::parse("digraph { \"a:b\" -> c:p:n; \"x->y\" -> z }").unwrap();
+
+        assert_eq!(element_from_title(&graph, false, "c"), Some(node("c")));
+        assert_eq!(element_from_title(&graph, false, "p"), None);
+        assert_eq!(
+            element_from_title(&graph, true, "a:b->c:p:n"),
+            Some(edge("a:b", "c"))
+        );
+        assert_eq!(
+            element_from_title(&graph, true, "x->y->z"),
+            Some(edge("x->y", "z"))
+        );
+        assert_eq!(element_from_title(&graph, true, "c--a:b"), None);
+    }
+}
@@ -0,0 +1,295 @@
+use std::ops::Range;
+
+use anyhow::{bail, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    Strict,
+    Graph,
+    Digraph,
+    Node,
+    Edge,
+    Subgraph,
+    /// An identifier, numeral, or quoted string, with quotes and escaped
+    /// quotes already removed.
+    Id(String),
+    /// An HTML string, including the outer `<` and `>`.
+    HtmlId(String),
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Equal,
+    Semicolon,
+    Comma,
+    Colon,
+    /// Concatenates quoted strings.
+    Plus,
+    /// Either `->` or `--`.
+    EdgeOp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+/// Splits DOT source into tokens, skipping whitespace and comments.
+pub fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let start = offset;
+        let c = bytes[offset];
+
+        let kind = match c {
+            c if c.is_ascii_whitespace() => {
+                offset += 1;
+                continue;
+            }
+            b'/' if bytes.get(offset + 1) == Some(&b'/') => {
+                offset = line_end(source, offset);
+                continue;
+            }
+            b'/' if bytes.get(offset + 1) == Some(&b'*') => {
+                let Some(end) = source[offset + 2..].find("*/") else {
+                    bail!("Unterminated comment at {}", location(source, start));
+                };
+                offset += end + 4;
+                continue;
+            }
+            // Lines starting with `#` are C preprocessor output and ignored.
+            b'#' if is_line_start(source, offset) => {
+                offset = line_end(source, offset);
+                continue;
+            }
+            b'{' => single(&mut offset, TokenKind::LBrace),
+            b'}' => single(&mut offset, TokenKind::RBrace),
+            b'[' => single(&mut offset, TokenKind::LBracket),
+            b']' => single(&mut offset, TokenKind::RBracket),
+            b'=' => single(&mut offset, TokenKind::Equal),
+            b';' => single(&mut offset, TokenKind::Semicolon),
+            b',' => single(&mut offset, TokenKind::Comma),
+            b':' => single(&mut offset, TokenKind::Colon),
+            b'+' => single(&mut offset, TokenKind::Plus),
+            b'-' if matches!(bytes.get(offset + 1), Some(b'>' | b'-')) => {
+                offset += 2;
+                TokenKind::EdgeOp
+            }
+            b'"' => {
+                let (value, end) = quoted_string(source, offset)?;
+                offset = end;
+                TokenKind::Id(value)
+            }
+            b'<' => {
+                let end = html_string_end(source, offset)?;
+                let value = source[offset..end].to_string();
+                offset = end;
+                TokenKind::HtmlId(value)
+            }
+            b'-' | b'.' | b'0'..=b'9' => {
+                let end = source[offset + 1..]
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                    .map_or(source.len(), |end| offset + 1 + end);
+                let value = source[offset..end].to_string();
+                offset = end;
+                TokenKind::Id(value)
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' || !c.is_ascii() => {
+                let end = source[offset..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || !c.is_ascii()))
+                    .map_or(source.len(), |end| offset + end);
+                let value = &source[offset..end];
+                offset = end;
+                keyword(value).unwrap_or_else(|| TokenKind::Id(value.to_string()))
+            }
+            _ => {
+                let c = source[offset..].chars().next().unwrap();
+                bail!("Unexpected `{}` at {}", c, location(source, start));
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            span: start..offset,
+        });
+    }
+
+    Ok(tokens)
+}
+
+/// Returns the 1-based `line:column` of `offset` in `source`.
+pub fn location(source: &str, offset: usize) -> String {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap().chars().count() + 1;
+    format!("{}:{}", line, column)
+}
+
+fn single(offset: &mut usize, kind: TokenKind) -> TokenKind {
+    *offset += 1;
+    kind
+}
+
+fn keyword(value: &str) -> Option<TokenKind> {
+    let kind = match value.to_ascii_lowercase().as_str() {
+        "strict" => TokenKind::Strict,
+        "graph" => TokenKind::Graph,
+        "digraph" => TokenKind::Digraph,
+        "node" => TokenKind::Node,
+        "edge" => TokenKind::Edge,
+        "subgraph" => TokenKind::Subgraph,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+fn line_end(source: &str, offset: usize) -> usize {
+    source[offset..]
+        .find('\n')
+        .map_or(source.len(), |end| offset + end)
+}
+
+fn is_line_start(source: &str, offset: usize) -> bool {
+    source[..offset]
+        .rsplit('\n')
+        .next()
+        .unwrap()
+        .trim()
+        .is_empty()
+}
+
+/// Returns the unescaped contents of the quoted string starting at `start`,
+/// and the offset just after its closing quote.
+fn quoted_string(source: &str, start: usize) -> Result<(String, usize)> {
+    let mut value = String::new();
+
+    let mut chars = source[start + 1..].char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, start + 1 + index + 1)),
+            '\\' => match chars.next() {
+                Some((_, '"')) => value.push('"'),
+                // Backslash-newline continues the string on the next line.
+                Some((_, '\n')) => {}
+                Some((_, '\r')) => {
+                    chars.next();
+                }
+                Some((_, c)) => {
+                    value.push('\\');
+                    value.push(c);
+                }
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+
+    bail!("Unterminated string at {}", location(source, start))
+}
+
+/// Returns the offset just after the `>` that closes the HTML string starting
+/// at `start`.
+fn html_string_end(source: &str, start: usize) -> Result<usize> {
+    let mut depth = 0;
+
+    for (index, c) in source[start..].char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(start + index + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    bail!("Unterminated HTML string at {}", location(source, start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    fn id(value: &str) -> TokenKind {
+        TokenKind::Id(value.to_string())
+    }
+
+    #[test]
+    fn simple() {
+        assert_eq!(
+            kinds("Digraph G { a -> b [label=\"x\"]; }"),
+            [
+                TokenKind::Digraph,
+                id("G"),
+                TokenKind::LBrace,
+                id("a"),
+                TokenKind::EdgeOp,
+                id("b"),
+                TokenKind::LBracket,
+                id("label"),
+                TokenKind::Equal,
+                id("x"),
+                TokenKind::RBracket,
+                TokenKind::Semicolon,
+                TokenKind::RBrace,
+            ]
+        );
+    }
+
+    #[test]
+    fn ids() {
+        assert_eq!(
+            kinds(r#"-1.5 .5 "say \"hi\"\n" a_1 é <<b>x</b>>"#),
+            [
+                id("-1.5"),
+                id(".5"),
+                id("say \"hi\"\\n"),
+                id("a_1"),
+                id("é"),
+                TokenKind::HtmlId("<<b>x</b>>".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            kinds("# line\na // comment\n/* block\n */ b"),
+            [id("a"), id("b")]
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let tokens = tokenize("a --\n\"b\"").unwrap();
+        assert_eq!(
+            tokens.iter().map(|t| t.span.clone()).collect::<Vec<_>>(),
+            [0..1, 2..4, 5..8]
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            tokenize("a\n  \"b").unwrap_err().to_string(),
+            "Unterminated string at 2:3"
+        );
+        assert!(tokenize("/* a").is_err());
+        assert!(tokenize("<a").is_err());
+        assert!(tokenize("a ? b").is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use super::{
+    lexer::{self, TokenKind},
+    quote_id, reindent, Attr, AttrTarget, Graph, StmtKind,
+};
+
+/// Text to be inserted into a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Insertion {
+    /// Byte offset where `text` should be inserted.
+    pub offset: usize,
+    pub text: String,
+}
+
+/// Returns the statements of `other` to be inserted at the end of the graph in
+/// `source`, introduced by a `// {comment}` line.
+///
+/// Node statements that wouldn't change anything, graph attributes that are
+/// already set in `source`, and edge operators that don't match the graph in
+/// `source` are left out or rewritten.
+pub fn merge(source: &str, other: &str, comment: &str) -> Result<Insertion> {
+    let graph = super::parse(source)?;
+    let other_graph = super::parse(other)?;
+
+    let existing_nodes = graph.topology().nodes;
+    let mut declared_node_stmts = HashSet::new();
+    collect_node_stmts(&graph, source, &mut declared_node_stmts);
+
+    let mut stmts = Vec::new();
+    for stmt in &other_graph.stmts {
+        let stmt_source = &other[stmt.span.clone()];
+
+        let stmt_text = match &stmt.kind {
+            StmtKind::Node(node_id, attrs) => {
+                let is_redundant = if attrs.is_empty() {
+                    existing_nodes.contains(node_id.id.as_str())
+                } else {
+                    !declared_node_stmts.insert(normalize(stmt_source))
+                };
+                if is_redundant {
+                    continue;
+                }
+                stmt_source.to_string()
+            }
+            StmtKind::Assign(attr) => {
+                if graph.attr(&attr.key).is_some() {
+                    continue;
+                }
+                stmt_source.to_string()
+            }
+            StmtKind::Attr(AttrTarget::Graph, attrs) => {
+                let unset_attrs = attrs
+                    .iter()
+                    .filter(|attr| graph.attr(&attr.key).is_none())
+                    .collect::<Vec<_>>();
+                if unset_attrs.is_empty() {
+                    continue;
+                }
+                if unset_attrs.len() == attrs.len() {
+                    stmt_source.to_string()
+                } else {
+                    format!("graph [{}]", format_attrs(&unset_attrs))
+                }
+            }
+            StmtKind::Edge(..) | StmtKind::Subgraph(_) | StmtKind::Attr(..) => {
+                convert_edge_ops(stmt_source, graph.is_directed)?
+            }
+        };
+
+        // Include the indentation of the first line so that the following
+        // lines stay aligned relative to it.
+        let line_start = other[..stmt.span.start].rfind('\n').map_or(0, |i| i + 1);
+        let indent = &other[line_start..stmt.span.start];
+        if indent.trim().is_empty() {
+            stmts.push(reindent(&format!("{}{}", indent, stmt_text)));
+        } else {
+            stmts.push(reindent(&stmt_text));
+        }
+    }
+
+    let mut body = format!("    // {}\n", comment);
+    for stmt in stmts {
+        body.push_str(&stmt);
+    }
+
+    // Insert on its own line just before the closing brace.
+    let close_offset = graph.body_span.end;
+    let line_start = source[..close_offset].rfind('\n').map_or(0, |i| i + 1);
+    let insertion = if source[line_start..close_offset].trim().is_empty() {
+        Insertion {
+            offset: line_start,
+            text: format!("\n{}", body),
+        }
+    } else {
+        Insertion {
+            offset: close_offset,
+            text: format!("\n\n{}", body),
+        }
+    };
+
+    Ok(insertion)
+}
+
+/// Adds the normalized text of all node statements with attributes in
+/// `graph` to `stmts`.
+fn collect_node_stmts(graph: &Graph, source: &str, stmts: &mut HashSet<String>) {
+    let mut stack = vec![&graph.stmts];
+
+    while let Some(current) = stack.pop() {
+        for stmt in current {
+            match &stmt.kind {
+                StmtKind::Node(_, attrs) if !attrs.is_empty() => {
+                    stmts.insert(normalize(&source[stmt.span.clone()]));
+                }
+                StmtKind::Subgraph(subgraph) => stack.push(&subgraph.stmts),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Removes whitespace so that statements can be compared regardless of
+/// formatting.
+fn normalize(stmt: &str) -> String {
+    stmt.split_whitespace().collect()
+}
+
+/// Replaces the edge operators in `stmt` with the one of a directed or
+/// undirected graph.
+fn convert_edge_ops(stmt: &str, is_directed: bool) -> Result<String> {
+    let edge_op = if is_directed { "->" } else { "--" };
+
+    let mut ret = stmt.to_string();
+    for token in lexer::tokenize(stmt)?.into_iter().rev() {
+        if token.kind == TokenKind::EdgeOp {
+            ret.replace_range(token.span, edge_op);
+        }
+    }
+    Ok(ret)
+}
+
+fn format_attrs(attrs: &[&Attr]) -> String {
+    attrs
+        .iter()
+        .map(|attr| {
+            let is_html = attr.value.starts_with('<') && attr.value.ends_with('>');
+            let value = if is_html {
+                attr.value.clone()
+            } else {
+                quote_id(&attr.value)
+            };
+            format!("{}={}", quote_id(&attr.key), value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merged(source: &str, other: &str) -> String {
+        let insertion = merge(source, other, "From other.gv").unwrap();
+
+        let mut ret = source.to_string();
+        ret.insert_str(insertion.offset, &insertion.text);
+        ret
+    }
+
+    #[test]
+    fn simple() {
+        assert_eq!(
+            merged(
+                "digraph {\n    rankdir=LR\n    a [label=A]\n    a -> b\n}\n",
+                "graph {\n  rankdir=TB; graph [bgcolor=red, rankdir=TB]\n  a [ label=A ]\n  b\n  c\n  a -- c\n  subgraph cluster_x {\n    d -- e\n  }\n}"
+            ),
+            "digraph {\n    rankdir=LR\n    a [label=A]\n    a -> b\n\n    // From other.gv\n    graph [bgcolor=red]\n    c\n    a -> c\n    subgraph cluster_x {\n      d -> e\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn same_line_brace() {
+        assert_eq!(
+            merged("graph { a }", "graph { b -- c }"),
+            "graph { a \n\n    // From other.gv\n    b -- c\n}"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(merge("graph {", "graph {}", "").is_err());
+        assert!(merge("graph {}", "graph {", "").is_err());
+    }
+}
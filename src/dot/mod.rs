@@ -0,0 +1,366 @@
+//! A parser for the DOT language that keeps track of where each statement is
+//! in the source, so that the results can be mapped back to the document.
+
+mod colors;
+mod cycles;
+mod edit;
+mod extract;
+mod font_scale;
+mod inspect;
+mod lexer;
+mod merge;
+mod parser;
+mod positions;
+mod simplify;
+
+use std::{fmt::Write, ops::Range};
+
+use anyhow::Result;
+use indexmap::IndexSet;
+
+pub use self::{
+    colors::used_colors,
+    cycles::cycle_edge_spans,
+    edit::{add_edge, add_node, remove_element, unused_node_id, Edit},
+    extract::extract_subgraph,
+    font_scale::scale_default_font_size,
+    inspect::{
+        element_at, element_from_title, inspect, remove_attr, set_attr, EffectiveAttr, Element,
+    },
+    merge::merge,
+    positions::with_initial_positions,
+    simplify::simplified_source,
+};
+
+/// Keywords that must be quoted to be used as IDs.
+const KEYWORDS: &[&str] = &["node", "edge", "graph", "digraph", "subgraph", "strict"];
+
+/// Parses the first graph in `source`.
+pub fn parse(source: &str) -> Result<Graph> {
+    let tokens = lexer::tokenize(source)?;
+    parser::Parser::new(source, &tokens).parse_graph()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Graph {
+    pub is_strict: bool,
+    pub is_directed: bool,
+    pub id: Option<String>,
+    pub stmts: Vec<Stmt>,
+    /// The span between the braces.
+    pub body_span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StmtKind {
+    /// `a [color=red]`
+    Node(NodeId, Vec<Attr>),
+    /// `a -> b -> { c d } [color=red]`
+    Edge(Vec<EdgeOperand>, Vec<Attr>),
+    /// `node [shape=box]`
+    Attr(AttrTarget, Vec<Attr>),
+    /// `rankdir=LR`
+    Assign(Attr),
+    /// `subgraph cluster_a { ... }`
+    Subgraph(Subgraph),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeId {
+    pub id: String,
+    pub port: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdgeOperand {
+    Node(NodeId),
+    Subgraph(Subgraph),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttrTarget {
+    Graph,
+    Node,
+    Edge,
+}
+
+/// An attribute, where HTML string values keep their outer `<` and `>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attr {
+    pub key: String,
+    pub value: String,
+    /// The span of `key=value`, or only `key` if the value is implied.
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subgraph {
+    pub id: Option<String>,
+    pub stmts: Vec<Stmt>,
+    pub span: Range<usize>,
+    /// The span between the braces.
+    pub body_span: Range<usize>,
+}
+
+/// The nodes and edges of a graph, with edge chains and subgraph operands
+/// expanded into individual edges.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Topology<'a> {
+    /// Node IDs, in order of first appearance.
+    pub nodes: IndexSet<&'a str>,
+    /// Pairs of indices into `nodes`.
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl Graph {
+    /// Returns the value of the graph attribute `key` set at the top level.
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        attr_in_stmts(&self.stmts, key)
+    }
+
+    pub fn topology(&self) -> Topology<'_> {
+        let mut topology = Topology::default();
+        topology.visit_stmts(&self.stmts);
+        topology
+    }
+}
+
+impl<'a> Topology<'a> {
+    fn visit_stmts(&mut self, stmts: &'a [Stmt]) {
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::Node(node_id, _) => {
+                    self.nodes.insert(&node_id.id);
+                }
+                StmtKind::Edge(operands, _) => {
+                    let operand_nodes = operands
+                        .iter()
+                        .map(|operand| self.visit_operand(operand))
+                        .collect::<Vec<_>>();
+
+                    for pair in operand_nodes.windows(2) {
+                        for &source in &pair[0] {
+                            for &target in &pair[1] {
+                                self.edges.push((source, target));
+                            }
+                        }
+                    }
+                }
+                StmtKind::Subgraph(subgraph) => {
+                    self.visit_stmts(&subgraph.stmts);
+                }
+                StmtKind::Attr(..) | StmtKind::Assign(_) => {}
+            }
+        }
+    }
+
+    /// Returns the indices of the nodes that `operand` refers to.
+    fn visit_operand(&mut self, operand: &'a EdgeOperand) -> Vec<usize> {
+        match operand {
+            EdgeOperand::Node(node_id) => vec![self.nodes.insert_full(&node_id.id).0],
+            EdgeOperand::Subgraph(subgraph) => {
+                let subgraph_topology = subgraph.topology();
+
+                self.edges
+                    .extend(subgraph_topology.edges.iter().map(|(s, t)| {
+                        (
+                            self.nodes.insert_full(subgraph_topology.nodes[*s]).0,
+                            self.nodes.insert_full(subgraph_topology.nodes[*t]).0,
+                        )
+                    }));
+                subgraph_topology
+                    .nodes
+                    .iter()
+                    .map(|id| self.nodes.insert_full(id).0)
+                    .collect()
+            }
+        }
+    }
+}
+
+impl Subgraph {
+    /// Whether Graphviz draws this subgraph as a box around its nodes.
+    pub fn is_cluster(&self) -> bool {
+        self.id
+            .as_ref()
+            .is_some_and(|id| id.starts_with("cluster") || self.attr("cluster") == Some("true"))
+    }
+
+    /// Returns the value of the graph attribute `key` set within the
+    /// subgraph.
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        attr_in_stmts(&self.stmts, key)
+    }
+
+    pub fn topology(&self) -> Topology<'_> {
+        let mut topology = Topology::default();
+        topology.visit_stmts(&self.stmts);
+        topology
+    }
+}
+
+/// Returns the value of the last attribute named `key` in `attrs`.
+pub fn find_attr<'a>(attrs: &'a [Attr], key: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .rev()
+        .find(|attr| attr.key == key)
+        .map(|attr| attr.value.as_str())
+}
+
+/// Returns `id` as is if it is a valid unquoted DOT ID, otherwise quoted.
+///
+/// Backslashes are kept as is, as in DOT they only escape quotes, except
+/// before quotes, line breaks, and the end, where they are doubled so they
+/// don't escape those. Like Graphviz, the parser then keeps both, which are
+/// shown as a single backslash in labels.
+pub fn quote_id(id: &str) -> String {
+    if is_plain_id(id) || is_numeral(id) {
+        return id.to_string();
+    }
+
+    let mut ret = String::with_capacity(id.len() + 2);
+    ret.push('"');
+    let mut chars = id.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' if matches!(chars.peek(), None | Some('"' | '\n' | '\r')) => {
+                ret.push_str("\\\\");
+            }
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+fn is_plain_id(id: &str) -> bool {
+    let mut chars = id.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+
+    (first.is_ascii_alphabetic() || first == '_' || !first.is_ascii())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || !c.is_ascii())
+        && !KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(id))
+}
+
+fn is_numeral(id: &str) -> bool {
+    let digits = id.strip_prefix('-').unwrap_or(id);
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+
+    !(int.is_empty() && frac.is_empty())
+        && int.chars().all(|c| c.is_ascii_digit())
+        && frac.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Indents the lines of `body` by four spaces, replacing their common
+/// indentation.
+fn reindent(body: &str) -> String {
+    let lines = body
+        .trim_end()
+        .trim_start_matches(['\n', '\r'])
+        .lines()
+        .map(|line| line.trim_end())
+        .collect::<Vec<_>>();
+
+    // Only spaces and tabs are counted, so the indentation is always at a
+    // char boundary, even with other whitespace like U+3000.
+    let common_indent = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+
+    let mut ret = String::new();
+    for line in lines {
+        if line.is_empty() {
+            ret.push('\n');
+        } else {
+            writeln!(ret, "    {}", &line[common_indent..]).unwrap();
+        }
+    }
+    ret
+}
+
+fn attr_in_stmts<'a>(stmts: &'a [Stmt], key: &str) -> Option<&'a str> {
+    stmts.iter().rev().find_map(|stmt| match &stmt.kind {
+        StmtKind::Assign(attr) if attr.key == key => Some(attr.value.as_str()),
+        StmtKind::Attr(AttrTarget::Graph, attrs) => find_attr(attrs, key),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote() {
+        assert_eq!(quote_id("a_1"), "a_1");
+        assert_eq!(quote_id("-1.5"), "-1.5");
+        assert_eq!(quote_id(".5"), ".5");
+        assert_eq!(quote_id("node"), "\"node\"");
+        assert_eq!(quote_id("a b"), "\"a b\"");
+        assert_eq!(quote_id("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(quote_id(""), "\"\"");
+        assert_eq!(quote_id("1a"), "\"1a\"");
+        assert_eq!(quote_id("a\\"), "\"a\\\\\"");
+        assert_eq!(quote_id("a\nb"), "\"a\nb\"");
+    }
+
+    #[test]
+    fn quote_round_trip() {
+        let parsed_id = |id: &str| {
+            let source = format!("digraph {{ {} }}", quote_id(id));
+            let graph = parse(&source).unwrap();
+            graph
+                .topology()
+                .nodes
+                .into_iter()
+                .next()
+                .unwrap()
+                .to_string()
+        };
+
+        for id in [
+            "a b",
+            "say \"hi\"",
+            "line\nbreak",
+            "crlf\r\nbreak",
+            "C:\\Users\\me",
+            "\\n",
+            "node",
+        ] {
+            assert_eq!(parsed_id(id), id);
+        }
+
+        // Graphviz also keeps both backslashes, which are shown as one.
+        assert_eq!(parsed_id("C:\\dir\\"), "C:\\dir\\\\");
+        assert_eq!(parsed_id("a\\\"b"), "a\\\\\"b");
+        assert_eq!(parsed_id("a\\\nb"), "a\\\\\nb");
+    }
+
+    #[test]
+    fn topology() {
+        let graph = parse("digraph { a; b -> { c -> d } -> e; subgraph { f -> a } }").unwrap();
+        let topology = graph.topology();
+
+        assert_eq!(
+            topology.nodes.into_iter().collect::<Vec<_>>(),
+            ["a", "b", "c", "d", "e", "f"]
+        );
+        assert_eq!(
+            topology.edges,
+            [(2, 3), (1, 2), (1, 3), (2, 4), (3, 4), (5, 0)]
+        );
+    }
+}
@@ -0,0 +1,429 @@
+use anyhow::{bail, Result};
+
+use super::{
+    lexer::{location, Token, TokenKind},
+    Attr, AttrTarget, EdgeOperand, Graph, NodeId, Stmt, StmtKind, Subgraph,
+};
+
+pub struct Parser<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(source: &'a str, tokens: &'a [Token]) -> Self {
+        Self {
+            source,
+            tokens,
+            index: 0,
+        }
+    }
+
+    pub fn parse_graph(&mut self) -> Result<Graph> {
+        let is_strict = self.eat(&TokenKind::Strict);
+
+        let is_directed = match self.next_kind() {
+            Some(TokenKind::Graph) => false,
+            Some(TokenKind::Digraph) => true,
+            _ => return self.error("Expected `graph` or `digraph`"),
+        };
+
+        let id = self.eat_id();
+
+        self.expect(&TokenKind::LBrace, "`{`")?;
+        let body_start = self.prev_end();
+        let stmts = self.parse_stmts()?;
+        let body_end = self.offset();
+        self.expect(&TokenKind::RBrace, "`}`")?;
+
+        Ok(Graph {
+            is_strict,
+            is_directed,
+            id,
+            stmts,
+            body_span: body_start..body_end,
+        })
+    }
+
+    fn parse_stmts(&mut self) -> Result<Vec<Stmt>> {
+        let mut stmts = Vec::new();
+
+        while !matches!(self.peek_kind(), Some(TokenKind::RBrace) | None) {
+            stmts.push(self.parse_stmt()?);
+            self.eat(&TokenKind::Semicolon);
+        }
+
+        Ok(stmts)
+    }
+
+    fn parse_stmt(&mut self) -> Result<Stmt> {
+        let start = self.offset();
+
+        let kind = match self.peek_kind() {
+            Some(TokenKind::Graph) => self.parse_attr_stmt(AttrTarget::Graph)?,
+            Some(TokenKind::Node) => self.parse_attr_stmt(AttrTarget::Node)?,
+            Some(TokenKind::Edge) => self.parse_attr_stmt(AttrTarget::Edge)?,
+            Some(TokenKind::Subgraph | TokenKind::LBrace) => {
+                let subgraph = self.parse_subgraph()?;
+                if self.peek_kind() == Some(&TokenKind::EdgeOp) {
+                    self.parse_edge_stmt(EdgeOperand::Subgraph(subgraph))?
+                } else {
+                    StmtKind::Subgraph(subgraph)
+                }
+            }
+            Some(TokenKind::Id(_) | TokenKind::HtmlId(_)) => {
+                if self.peek_kind_at(1) == Some(&TokenKind::Equal) {
+                    let key = self.eat_id().unwrap();
+                    self.index += 1;
+                    let value = self.expect_id()?;
+                    StmtKind::Assign(Attr {
+                        key,
+                        value,
+                        span: start..self.prev_end(),
+                    })
+                } else {
+                    let node_id = self.parse_node_id()?;
+                    if self.peek_kind() == Some(&TokenKind::EdgeOp) {
+                        self.parse_edge_stmt(EdgeOperand::Node(node_id))?
+                    } else {
+                        StmtKind::Node(node_id, self.parse_attr_lists()?)
+                    }
+                }
+            }
+            _ => return self.error("Expected a statement"),
+        };
+
+        Ok(Stmt {
+            kind,
+            span: start..self.prev_end(),
+        })
+    }
+
+    fn parse_attr_stmt(&mut self, target: AttrTarget) -> Result<StmtKind> {
+        self.index += 1;
+
+        if self.peek_kind() != Some(&TokenKind::LBracket) {
+            return self.error("Expected `[`");
+        }
+
+        Ok(StmtKind::Attr(target, self.parse_attr_lists()?))
+    }
+
+    fn parse_edge_stmt(&mut self, first: EdgeOperand) -> Result<StmtKind> {
+        let mut operands = vec![first];
+
+        while self.eat(&TokenKind::EdgeOp) {
+            let operand = match self.peek_kind() {
+                Some(TokenKind::Subgraph | TokenKind::LBrace) => {
+                    EdgeOperand::Subgraph(self.parse_subgraph()?)
+                }
+                _ => EdgeOperand::Node(self.parse_node_id()?),
+            };
+            operands.push(operand);
+        }
+
+        Ok(StmtKind::Edge(operands, self.parse_attr_lists()?))
+    }
+
+    fn parse_subgraph(&mut self) -> Result<Subgraph> {
+        let start = self.offset();
+
+        let id = if self.eat(&TokenKind::Subgraph) {
+            self.eat_id()
+        } else {
+            None
+        };
+
+        self.expect(&TokenKind::LBrace, "`{`")?;
+        let body_start = self.prev_end();
+        let stmts = self.parse_stmts()?;
+        let body_end = self.offset();
+        self.expect(&TokenKind::RBrace, "`}`")?;
+
+        Ok(Subgraph {
+            id,
+            stmts,
+            span: start..self.prev_end(),
+            body_span: body_start..body_end,
+        })
+    }
+
+    fn parse_node_id(&mut self) -> Result<NodeId> {
+        let id = self.expect_id()?;
+
+        // Ports may also have a compass point, like `a:port:n`, which is kept
+        // as part of the port.
+        let port = if self.eat(&TokenKind::Colon) {
+            let mut port = self.expect_id()?;
+            if self.eat(&TokenKind::Colon) {
+                port.push(':');
+                port.push_str(&self.expect_id()?);
+            }
+            Some(port)
+        } else {
+            None
+        };
+
+        Ok(NodeId { id, port })
+    }
+
+    fn parse_attr_lists(&mut self) -> Result<Vec<Attr>> {
+        let mut attrs = Vec::new();
+
+        while self.eat(&TokenKind::LBracket) {
+            while !self.eat(&TokenKind::RBracket) {
+                let start = self.offset();
+                let key = self.expect_id()?;
+                let value = if self.eat(&TokenKind::Equal) {
+                    self.expect_id()?
+                } else {
+                    // Graphviz treats `[a]` as `[a=true]`.
+                    "true".to_string()
+                };
+                attrs.push(Attr {
+                    key,
+                    value,
+                    span: start..self.prev_end(),
+                });
+
+                if !self.eat(&TokenKind::Comma) {
+                    self.eat(&TokenKind::Semicolon);
+                }
+            }
+        }
+
+        Ok(attrs)
+    }
+
+    /// Consumes an ID, concatenating quoted strings joined by `+`.
+    fn eat_id(&mut self) -> Option<String> {
+        match self.peek_kind()? {
+            TokenKind::Id(_) | TokenKind::HtmlId(_) => {}
+            _ => return None,
+        }
+
+        let (TokenKind::Id(id) | TokenKind::HtmlId(id)) = self.next_kind().unwrap() else {
+            unreachable!()
+        };
+        let mut id = id.clone();
+
+        while self.is_concatenation() {
+            self.index += 1;
+            let Some(TokenKind::Id(next)) = self.next_kind() else {
+                unreachable!()
+            };
+            id.push_str(next);
+        }
+
+        Some(id)
+    }
+
+    fn is_concatenation(&self) -> bool {
+        let (Some(plus), Some(next)) =
+            (self.tokens.get(self.index), self.tokens.get(self.index + 1))
+        else {
+            return false;
+        };
+
+        plus.kind == TokenKind::Plus
+            && matches!(next.kind, TokenKind::Id(_))
+            && self.source[next.span.clone()].starts_with('"')
+    }
+
+    fn expect_id(&mut self) -> Result<String> {
+        match self.eat_id() {
+            Some(id) => Ok(id),
+            None => self.error("Expected an ID"),
+        }
+    }
+
+    fn expect(&mut self, kind: &TokenKind, description: &str) -> Result<()> {
+        if self.eat(kind) {
+            Ok(())
+        } else {
+            self.error(&format!("Expected {}", description))
+        }
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.peek_kind() == Some(kind) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek_kind(&self) -> Option<&'a TokenKind> {
+        self.peek_kind_at(0)
+    }
+
+    fn peek_kind_at(&self, n: usize) -> Option<&'a TokenKind> {
+        self.tokens.get(self.index + n).map(|token| &token.kind)
+    }
+
+    fn next_kind(&mut self) -> Option<&'a TokenKind> {
+        let kind = self.peek_kind();
+        self.index += 1;
+        kind
+    }
+
+    /// Returns the start offset of the next token.
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map_or(self.source.len(), |token| token.span.start)
+    }
+
+    /// Returns the end offset of the previous token.
+    fn prev_end(&self) -> usize {
+        self.index
+            .checked_sub(1)
+            .and_then(|index| self.tokens.get(index))
+            .map_or(0, |token| token.span.end)
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T> {
+        match self.tokens.get(self.index) {
+            Some(token) => bail!(
+                "{}, found `{}` at {}",
+                message,
+                &self.source[token.span.clone()],
+                location(self.source, token.span.start)
+            ),
+            None => bail!("{}, found end of file", message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use crate::dot::{self, find_attr};
+
+    use super::*;
+
+    fn node(id: &str) -> EdgeOperand {
+        EdgeOperand::Node(NodeId {
+            id: id.to_string(),
+            port: None,
+        })
+    }
+
+    fn attr(key: &str, value: &str, span: Range<usize>) -> Attr {
+        Attr {
+            key: key.to_string(),
+            value: value.to_string(),
+            span,
+        }
+    }
+
+    #[test]
+    fn simple() {
+        let source = "strict digraph G {\n  rankdir=LR\n  node [shape=box]\n  a:p:n -> b -> c [color=red, style=dashed]\n  d [label=<<b>D</b>>]\n}";
+        let graph = dot::parse(source).unwrap();
+
+        assert!(graph.is_strict);
+        assert!(graph.is_directed);
+        assert_eq!(graph.id.as_deref(), Some("G"));
+        assert_eq!(graph.attr("rankdir"), Some("LR"));
+
+        let kinds = graph
+            .stmts
+            .iter()
+            .map(|stmt| stmt.kind.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                StmtKind::Assign(attr("rankdir", "LR", 21..31)),
+                StmtKind::Attr(AttrTarget::Node, vec![attr("shape", "box", 40..49)]),
+                StmtKind::Edge(
+                    vec![
+                        EdgeOperand::Node(NodeId {
+                            id: "a".to_string(),
+                            port: Some("p:n".to_string()),
+                        }),
+                        node("b"),
+                        node("c"),
+                    ],
+                    vec![
+                        attr("color", "red", 70..79),
+                        attr("style", "dashed", 81..93)
+                    ],
+                ),
+                StmtKind::Node(
+                    NodeId {
+                        id: "d".to_string(),
+                        port: None,
+                    },
+                    vec![attr("label", "<<b>D</b>>", 100..116)],
+                ),
+            ]
+        );
+
+        assert_eq!(
+            &source[graph.stmts[2].span.clone()],
+            "a:p:n -> b -> c [color=red, style=dashed]"
+        );
+    }
+
+    #[test]
+    fn subgraphs() {
+        let graph =
+            dot::parse("graph { subgraph cluster_a { label=\"A\"; x } { rank=same; y z } -- w; }")
+                .unwrap();
+
+        assert!(!graph.is_directed);
+        assert_eq!(graph.id, None);
+
+        let StmtKind::Subgraph(subgraph) = &graph.stmts[0].kind else {
+            panic!()
+        };
+        assert!(subgraph.is_cluster());
+        assert_eq!(subgraph.attr("label"), Some("A"));
+
+        let StmtKind::Edge(operands, _) = &graph.stmts[1].kind else {
+            panic!()
+        };
+        let EdgeOperand::Subgraph(anonymous) = &operands[0] else {
+            panic!()
+        };
+        assert_eq!(anonymous.id, None);
+        assert!(!anonymous.is_cluster());
+        assert_eq!(anonymous.stmts.len(), 3);
+        assert_eq!(operands[1], node("w"));
+    }
+
+    #[test]
+    fn attrs() {
+        let graph = dot::parse("digraph { a [x; y=\"1\" + \"2\"][z=3] }").unwrap();
+
+        let StmtKind::Node(_, attrs) = &graph.stmts[0].kind else {
+            panic!()
+        };
+        assert_eq!(
+            attrs,
+            &[
+                attr("x", "true", 13..14),
+                attr("y", "12", 16..27),
+                attr("z", "3", 29..32)
+            ]
+        );
+        assert_eq!(find_attr(attrs, "y"), Some("12"));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            dot::parse("digraph {\n  a -> }").unwrap_err().to_string(),
+            "Expected an ID, found `}` at 2:8"
+        );
+        assert!(dot::parse("").is_err());
+        assert!(dot::parse("digraph { a").is_err());
+        assert!(dot::parse("node { }").is_err());
+        assert!(dot::parse("digraph { node }").is_err());
+    }
+}
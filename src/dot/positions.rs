@@ -0,0 +1,124 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use super::{find_attr, quote_id, EdgeOperand, Graph, Stmt, StmtKind};
+
+const POINTS_PER_INCH: f64 = 72.0;
+
+/// Returns `source` with `positions`, in points, as the initial positions of
+/// the nodes of its first graph, so engines that start from them, like
+/// `neato` and `fdp`, mostly keep the previous layout. Nodes with a position
+/// set in the source are left as is.
+///
+/// The node statements are inserted right before the closing brace, on the
+/// same line, so line numbers in errors stay the same.
+pub fn with_initial_positions<'a>(
+    source: &'a str,
+    graph: &Graph,
+    positions: &HashMap<String, (f64, f64)>,
+) -> Cow<'a, str> {
+    let mut positioned = HashSet::new();
+    collect_positioned_nodes(&graph.stmts, &mut positioned);
+
+    let mut stmts = String::new();
+    for id in graph.topology().nodes {
+        if positioned.contains(id) {
+            continue;
+        }
+
+        let Some((x, y)) = positions.get(id) else {
+            continue;
+        };
+
+        // Unlike output positions, `pos` is in inches by default.
+        write!(
+            stmts,
+            "{} [pos=\"{:.3},{:.3}\"]; ",
+            quote_id(id),
+            x / POINTS_PER_INCH,
+            y / POINTS_PER_INCH
+        )
+        .unwrap();
+    }
+
+    if stmts.is_empty() {
+        return Cow::Borrowed(source);
+    }
+
+    let end = graph.body_span.end;
+    let mut ret = String::with_capacity(source.len() + stmts.len());
+    ret.push_str(&source[..end]);
+    ret.push_str(&stmts);
+    ret.push_str(&source[end..]);
+    Cow::Owned(ret)
+}
+
+fn collect_positioned_nodes<'a>(stmts: &'a [Stmt], positioned: &mut HashSet<&'a str>) {
+    for stmt in stmts {
+        match &stmt.kind {
+            StmtKind::Node(node_id, attrs) => {
+                if find_attr(attrs, "pos").is_some() {
+                    positioned.insert(&node_id.id);
+                }
+            }
+            StmtKind::Edge(operands, _) => {
+                for operand in operands {
+                    if let EdgeOperand::Subgraph(subgraph) = operand {
+                        collect_positioned_nodes(&subgraph.stmts, positioned);
+                    }
+                }
+            }
+            StmtKind::Subgraph(subgraph) => collect_positioned_nodes(&subgraph.stmts, positioned),
+            StmtKind::Attr(..) | StmtKind::Assign(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dot;
+
+    use super::*;
+
+    fn positioned(source: &str, positions: &[(&str, (f64, f64))]) -> String {
+        let positions = positions
+            .iter()
+            .map(|(id, position)| (id.to_string(), *position))
+            .collect();
+        with_initial_positions(source, &dot::parse(source).unwrap(), &positions).into_owned()
+    }
+
+    #[test]
+    fn inserted() {
+        assert_eq!(
+            positioned(
+                "graph {\n    a -- \"b c\"\n}",
+                &[
+                    ("a", (72.0, 36.0)),
+                    ("b c", (0.0, -144.0)),
+                    ("d", (1.0, 1.0))
+                ]
+            ),
+            "graph {\n    a -- \"b c\"\na [pos=\"1.000,0.500\"]; \"b c\" [pos=\"0.000,-2.000\"]; }"
+        );
+    }
+
+    #[test]
+    fn explicit_positions_kept() {
+        assert_eq!(
+            positioned(
+                "graph { subgraph s { a [pos=\"3,3!\"] } a -- b }",
+                &[("a", (72.0, 72.0)), ("b", (144.0, 0.0))]
+            ),
+            "graph { subgraph s { a [pos=\"3,3!\"] } a -- b b [pos=\"2.000,0.000\"]; }"
+        );
+    }
+
+    #[test]
+    fn unchanged() {
+        assert_eq!(positioned("graph { a -- b }", &[]), "graph { a -- b }");
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use indexmap::IndexSet;
+
+use super::{quote_id, Graph, Stmt, StmtKind};
+
+/// Returns a quick-to-lay-out version of `graph`, with only its structure:
+/// the nodes of each outermost cluster are collapsed into a single node, and
+/// attributes, including labels, are left out. Edges between the same nodes
+/// are drawn once.
+pub fn simplified_source(graph: &Graph) -> String {
+    let mut node_clusters = HashMap::new();
+    collect_node_clusters(&graph.stmts, &mut node_clusters);
+    let clusters = node_clusters.values().copied().collect::<HashSet<_>>();
+
+    let topology = graph.topology();
+    let collapsed_id = |index: usize| {
+        let id = topology.nodes[index];
+        node_clusters.get(id).copied().unwrap_or(id)
+    };
+
+    let mut nodes = IndexSet::new();
+    for index in 0..topology.nodes.len() {
+        nodes.insert(collapsed_id(index));
+    }
+
+    let mut edges = IndexSet::new();
+    for &(tail, head) in &topology.edges {
+        let (tail, head) = (collapsed_id(tail), collapsed_id(head));
+        // Edges within a cluster are hidden in it.
+        if tail != head || !clusters.contains(tail) {
+            edges.insert((tail, head));
+        }
+    }
+
+    let (keyword, edge_op) = if graph.is_directed {
+        ("digraph", "->")
+    } else {
+        ("graph", "--")
+    };
+
+    let mut ret = String::new();
+    writeln!(ret, "{keyword} {{").unwrap();
+    writeln!(
+        ret,
+        "    node [label=\"\" shape=box width=0.3 height=0.2]; edge [arrowsize=0.5]"
+    )
+    .unwrap();
+    for id in &nodes {
+        if clusters.contains(id) {
+            writeln!(
+                ret,
+                "    {} [shape=box3d width=0.6 height=0.4]",
+                quote_id(id)
+            )
+            .unwrap();
+        } else {
+            writeln!(ret, "    {}", quote_id(id)).unwrap();
+        }
+    }
+    for (tail, head) in &edges {
+        writeln!(ret, "    {} {edge_op} {}", quote_id(tail), quote_id(head)).unwrap();
+    }
+    ret.push('}');
+    ret
+}
+
+/// Maps the nodes in each outermost cluster in `stmts` to the cluster's ID.
+fn collect_node_clusters<'a>(stmts: &'a [Stmt], node_clusters: &mut HashMap<&'a str, &'a str>) {
+    for stmt in stmts {
+        let StmtKind::Subgraph(subgraph) = &stmt.kind else {
+            continue;
+        };
+
+        match subgraph.id.as_deref() {
+            Some(cluster_id) if subgraph.is_cluster() => {
+                for node in subgraph.topology().nodes {
+                    // Nodes belong to the first cluster they appear in.
+                    node_clusters.entry(node).or_insert(cluster_id);
+                }
+            }
+            _ => collect_node_clusters(&subgraph.stmts, node_clusters),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dot;
+
+    use super::*;
+
+    fn simplified(source: &str) -> String {
+        simplified_source(&dot::parse(source).unwrap())
+    }
+
+    #[test]
+    fn attributes_removed() {
+        assert_eq!(
+            simplified("digraph { a [label=\"A\" color=red]; a -> b [label=x]; a -> b; \"c d\" }"),
+            "digraph {\n    node [label=\"\" shape=box width=0.3 height=0.2]; edge [arrowsize=0.5]\n    a\n    b\n    \"c d\"\n    a -> b\n}"
+        );
+        assert_eq!(
+            simplified("graph { a -- b }"),
+            "graph {\n    node [label=\"\" shape=box width=0.3 height=0.2]; edge [arrowsize=0.5]\n    a\n    b\n    a -- b\n}"
+        );
+    }
+
+    #[test]
+    fn clusters_collapsed() {
+        assert_eq!(
+            simplified(
+                "digraph { subgraph cluster_a { x -> y; subgraph cluster_b { z } } subgraph s { w } y -> w; z -> x; w -> w }"
+            ),
+            "digraph {\n    node [label=\"\" shape=box width=0.3 height=0.2]; edge [arrowsize=0.5]\n    cluster_a [shape=box3d width=0.6 height=0.4]\n    w\n    cluster_a -> w\n    w -> w\n}"
+        );
+    }
+}
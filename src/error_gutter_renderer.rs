@@ -7,8 +7,7 @@ use gtk::{
 };
 use gtk_source::{prelude::*, subclass::prelude::*};
 
-const ERROR_LIGHT_MODE_COLOR: gdk::RGBA = gdk::RGBA::new(0.753, 0.11, 0.157, 1.0);
-const ERROR_DARK_MODE_COLOR: gdk::RGBA = gdk::RGBA::new(1.0, 0.482, 0.388, 1.0);
+use crate::colors;
 
 const SIZE_SP: f64 = 12.0;
 
@@ -62,6 +61,22 @@ mod imp {
                 }
             ));
 
+            let style_manager = adw::StyleManager::default();
+            style_manager.connect_dark_notify(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.queue_draw();
+                }
+            ));
+            style_manager.connect_high_contrast_notify(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.queue_draw();
+                }
+            ));
+
             obj.cache_paintable();
         }
     }
@@ -136,18 +151,11 @@ mod imp {
                 snapshot.save();
                 snapshot.translate(&Point::new(x, y));
 
-                let style_manager = adw::StyleManager::default();
-                let color = if style_manager.is_dark() {
-                    ERROR_DARK_MODE_COLOR
-                } else {
-                    ERROR_LIGHT_MODE_COLOR
-                };
-
                 self.paintable.borrow().as_ref().unwrap().snapshot_symbolic(
                     snapshot,
                     size,
                     size,
-                    &[color],
+                    &[colors::error()],
                 );
 
                 snapshot.restore();
@@ -174,6 +182,11 @@ impl ErrorGutterRenderer {
         self.queue_draw();
     }
 
+    pub fn remove_error(&self, line: u32) {
+        self.imp().error_lines.borrow_mut().remove(&line);
+        self.queue_draw();
+    }
+
     pub fn clear_errors(&self) {
         self.imp().error_lines.borrow_mut().clear();
         self.queue_draw();
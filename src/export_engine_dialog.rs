@@ -0,0 +1,78 @@
+use adw::prelude::*;
+use gettextrs::gettext;
+use gtk::glib::{self, closure};
+
+use crate::{export_format::ExportFormat, graph_view::LayoutEngine};
+
+const CANCEL_RESPONSE_ID: &str = "cancel";
+const CONTINUE_RESPONSE_ID: &str = "continue";
+
+/// Lets the user pick a layout engine and a format to export the graph
+/// with, without changing the engine of the preview.
+///
+/// Returns `None` if the user cancelled the dialog.
+pub async fn run(
+    parent: &impl IsA<gtk::Widget>,
+    current_engine: LayoutEngine,
+) -> Option<(LayoutEngine, ExportFormat)> {
+    let dialog = adw::AlertDialog::builder()
+        .heading(gettext("Export With Layout Engine"))
+        .body(gettext(
+            "The graph will be laid out again with the selected engine. The preview is left as is.",
+        ))
+        .close_response(CANCEL_RESPONSE_ID)
+        .default_response(CONTINUE_RESPONSE_ID)
+        .build();
+
+    dialog.add_response(CANCEL_RESPONSE_ID, &gettext("Cancel"));
+    dialog.add_response(CONTINUE_RESPONSE_ID, &gettext("_Choose File…"));
+
+    dialog.set_response_appearance(CONTINUE_RESPONSE_ID, adw::ResponseAppearance::Suggested);
+
+    let engine_model = LayoutEngine::supported_model();
+    let engine_row = adw::ComboRow::builder()
+        .title(gettext("Layout Engine"))
+        .expression(gtk::ClosureExpression::new::<glib::GString>(
+            &[] as &[gtk::Expression],
+            closure!(|list_item: adw::EnumListItem| list_item.name()),
+        ))
+        .model(&engine_model)
+        .build();
+    if let Some(position) = current_engine.position_in(&engine_model) {
+        engine_row.set_selected(position);
+    }
+
+    let formats = ExportFormat::ALL
+        .into_iter()
+        .filter(ExportFormat::is_supported)
+        .collect::<Vec<_>>();
+    let format_names = formats
+        .iter()
+        .map(|format| format.name())
+        .collect::<Vec<_>>();
+    let format_row = adw::ComboRow::builder()
+        .title(gettext("Format"))
+        .model(&gtk::StringList::new(
+            &format_names.iter().map(String::as_str).collect::<Vec<_>>(),
+        ))
+        .build();
+
+    let list_box = gtk::ListBox::new();
+    list_box.add_css_class("boxed-list");
+    list_box.append(&engine_row);
+    list_box.append(&format_row);
+    dialog.set_extra_child(Some(&list_box));
+
+    match dialog.choose_future(parent).await.as_str() {
+        CANCEL_RESPONSE_ID => None,
+        CONTINUE_RESPONSE_ID => {
+            let item = engine_row
+                .selected_item()
+                .and_downcast::<adw::EnumListItem>()?;
+            let engine = LayoutEngine::try_from(item.value()).unwrap();
+            let format = *formats.get(format_row.selected() as usize)?;
+            Some((engine, format))
+        }
+        _ => unreachable!(),
+    }
+}
@@ -1,17 +1,32 @@
 use gettextrs::gettext;
+use gtk::gdk_pixbuf;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExportFormat {
     Svg,
     Png,
+    /// PNG rendered at twice the graph's natural size
+    Png2x,
     Jpeg,
 }
 
 impl ExportFormat {
+    pub const ALL: [Self; 4] = [Self::Svg, Self::Png, Self::Png2x, Self::Jpeg];
+
+    pub fn from_raw(raw: &str) -> Option<Self> {
+        match raw {
+            "svg" => Some(Self::Svg),
+            "png" => Some(Self::Png),
+            "png-2x" => Some(Self::Png2x),
+            "jpeg" => Some(Self::Jpeg),
+            _ => None,
+        }
+    }
+
     pub fn extension(&self) -> &'static str {
         match self {
             Self::Svg => "svg",
-            Self::Png => "png",
+            Self::Png | Self::Png2x => "png",
             Self::Jpeg => "jpg",
         }
     }
@@ -19,7 +34,7 @@ impl ExportFormat {
     pub fn mime_type(&self) -> &'static str {
         match self {
             Self::Svg => "image/svg+xml",
-            Self::Png => "image/png",
+            Self::Png | Self::Png2x => "image/png",
             Self::Jpeg => "image/jpeg",
         }
     }
@@ -28,7 +43,45 @@ impl ExportFormat {
         match self {
             Self::Svg => gettext("SVG"),
             Self::Png => gettext("PNG"),
+            Self::Png2x => gettext("PNG (2×)"),
             Self::Jpeg => gettext("JPEG"),
         }
     }
+
+    /// Returns the file name to use when exporting a graph named `stem`.
+    pub fn file_name(&self, stem: &str) -> String {
+        match self {
+            Self::Png2x => format!("{}@2x.{}", stem, self.extension()),
+            _ => format!("{}.{}", stem, self.extension()),
+        }
+    }
+
+    /// Returns the gdk-pixbuf type to save as, or `None` for vector formats.
+    pub fn pixbuf_type(&self) -> Option<&'static str> {
+        match self {
+            Self::Svg => None,
+            Self::Png | Self::Png2x => Some("png"),
+            Self::Jpeg => Some("jpeg"),
+        }
+    }
+
+    /// Whether the graph can be exported to this format, which for raster
+    /// formats depends on the gdk-pixbuf savers available at runtime.
+    pub fn is_supported(&self) -> bool {
+        let Some(pixbuf_type) = self.pixbuf_type() else {
+            return true;
+        };
+
+        gdk_pixbuf::Pixbuf::formats().iter().any(|format| {
+            format.name().is_some_and(|name| name == pixbuf_type) && format.is_writable()
+        })
+    }
+
+    /// Returns how much the graph's natural size is scaled when rasterized.
+    pub fn scale(&self) -> i32 {
+        match self {
+            Self::Png2x => 2,
+            _ => 1,
+        }
+    }
 }
@@ -0,0 +1,78 @@
+use std::{cell::RefCell, rc::Rc};
+
+use adw::prelude::*;
+use gettextrs::gettext;
+use gtk::glib::{self, clone};
+
+use crate::export_format::ExportFormat;
+
+const CANCEL_RESPONSE_ID: &str = "cancel";
+const CONTINUE_RESPONSE_ID: &str = "continue";
+
+/// Lets the user pick which formats to export the graph to.
+///
+/// Returns `None` if the user cancelled the dialog.
+pub async fn run(parent: &impl IsA<gtk::Widget>) -> Option<Vec<ExportFormat>> {
+    let dialog = adw::AlertDialog::builder()
+        .heading(gettext("Export Graph"))
+        .body(gettext(
+            "Select the formats to export. All of them will be saved in the same folder.",
+        ))
+        .close_response(CANCEL_RESPONSE_ID)
+        .default_response(CONTINUE_RESPONSE_ID)
+        .build();
+
+    dialog.add_response(CANCEL_RESPONSE_ID, &gettext("Cancel"));
+    dialog.add_response(CONTINUE_RESPONSE_ID, &gettext("_Choose Folder…"));
+
+    dialog.set_response_appearance(CONTINUE_RESPONSE_ID, adw::ResponseAppearance::Suggested);
+
+    let list_box = gtk::ListBox::new();
+    list_box.add_css_class("boxed-list");
+    dialog.set_extra_child(Some(&list_box));
+
+    let items = Rc::new(RefCell::new(Vec::new()));
+    for format in ExportFormat::ALL
+        .into_iter()
+        .filter(ExportFormat::is_supported)
+    {
+        let row = adw::ActionRow::builder().title(format.name()).build();
+        list_box.append(&row);
+
+        let check_button = gtk::CheckButton::builder()
+            .valign(gtk::Align::Center)
+            .active(matches!(format, ExportFormat::Svg | ExportFormat::Png))
+            .build();
+        row.add_prefix(&check_button);
+        row.set_activatable_widget(Some(&check_button));
+
+        items.borrow_mut().push((format, check_button));
+    }
+
+    for (_, button) in items.borrow().iter() {
+        button.connect_active_notify(clone!(
+            #[weak]
+            dialog,
+            #[weak]
+            items,
+            move |_| {
+                let has_active = items.borrow().iter().any(|(_, b)| b.is_active());
+                dialog.set_response_enabled(CONTINUE_RESPONSE_ID, has_active);
+            }
+        ));
+    }
+
+    match dialog.choose_future(parent).await.as_str() {
+        CANCEL_RESPONSE_ID => None,
+        CONTINUE_RESPONSE_ID => {
+            let formats = items
+                .borrow()
+                .iter()
+                .filter(|(_, b)| b.is_active())
+                .map(|(format, _)| *format)
+                .collect::<Vec<_>>();
+            Some(formats)
+        }
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,119 @@
+const HINT_PREFIX: &str = "position:";
+
+/// A 1-based line and column in a file, as given on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilePosition {
+    pub line: u32,
+    pub column: u32,
+}
+
+impl FilePosition {
+    /// Parses the `+LINE` argument that precedes a file, like in `vim +120 file.gv`.
+    pub fn from_plus_arg(arg: &str) -> Option<Self> {
+        let line = parse_number(arg.strip_prefix('+')?)?;
+        Some(Self { line, column: 1 })
+    }
+
+    /// Splits `path:LINE` or `path:LINE:COLUMN` into the path and position.
+    pub fn split_from_path(arg: &str) -> Option<(&str, Self)> {
+        let (rest, last) = arg.rsplit_once(':')?;
+        let last = parse_number(last)?;
+
+        if let Some((path, line)) = rest
+            .rsplit_once(':')
+            .and_then(|(path, line)| Some((path, parse_number(line)?)))
+            .filter(|(path, _)| !path.is_empty())
+        {
+            return Some((path, Self { line, column: last }));
+        }
+
+        if rest.is_empty() {
+            return None;
+        }
+
+        Some((
+            rest,
+            Self {
+                line: last,
+                column: 1,
+            },
+        ))
+    }
+
+    /// Parses the hint passed to `gio::Application::open`.
+    pub fn from_hint(hint: &str) -> Option<Self> {
+        let (line, column) = hint.strip_prefix(HINT_PREFIX)?.split_once(':')?;
+        Some(Self {
+            line: parse_number(line)?,
+            column: parse_number(column)?,
+        })
+    }
+
+    /// Returns the hint to pass to `gio::Application::open`.
+    pub fn to_hint(self) -> String {
+        format!("{}{}:{}", HINT_PREFIX, self.line, self.column)
+    }
+}
+
+fn parse_number(text: &str) -> Option<u32> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    text.parse().ok().filter(|n| *n > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(line: u32, column: u32) -> FilePosition {
+        FilePosition { line, column }
+    }
+
+    #[test]
+    fn plus_arg() {
+        assert_eq!(FilePosition::from_plus_arg("+120"), Some(pos(120, 1)));
+        assert_eq!(FilePosition::from_plus_arg("+"), None);
+        assert_eq!(FilePosition::from_plus_arg("+0"), None);
+        assert_eq!(FilePosition::from_plus_arg("+-1"), None);
+        assert_eq!(FilePosition::from_plus_arg("120"), None);
+        assert_eq!(FilePosition::from_plus_arg("file.gv"), None);
+    }
+
+    #[test]
+    fn split_from_path() {
+        assert_eq!(
+            FilePosition::split_from_path("file.gv:120"),
+            Some(("file.gv", pos(120, 1)))
+        );
+        assert_eq!(
+            FilePosition::split_from_path("file.gv:120:5"),
+            Some(("file.gv", pos(120, 5)))
+        );
+        assert_eq!(
+            FilePosition::split_from_path("dir:1/file.gv:3"),
+            Some(("dir:1/file.gv", pos(3, 1)))
+        );
+        assert_eq!(
+            FilePosition::split_from_path("file:///tmp/a.gv:2:4"),
+            Some(("file:///tmp/a.gv", pos(2, 4)))
+        );
+        assert_eq!(FilePosition::split_from_path("file.gv"), None);
+        assert_eq!(FilePosition::split_from_path("file.gv:"), None);
+        assert_eq!(FilePosition::split_from_path("file.gv:x"), None);
+        assert_eq!(FilePosition::split_from_path(":12"), None);
+        assert_eq!(FilePosition::split_from_path("file.gv:0"), None);
+    }
+
+    #[test]
+    fn hint() {
+        assert_eq!(
+            FilePosition::from_hint(&pos(7, 3).to_hint()),
+            Some(pos(7, 3))
+        );
+        assert_eq!(FilePosition::from_hint(""), None);
+        assert_eq!(FilePosition::from_hint("position:7"), None);
+        assert_eq!(FilePosition::from_hint("other:7:3"), None);
+    }
+}
@@ -0,0 +1,477 @@
+use std::{cell::RefCell, rc::Rc};
+
+use adw::prelude::*;
+use futures_channel::oneshot;
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, clone},
+};
+
+use crate::{
+    file_position::FilePosition,
+    i18n::{format_number, ngettext_f},
+    page::Page,
+    recent_item::RecentItem,
+    session::Session,
+    utils,
+    window::Window,
+};
+
+/// Number of matching lines listed per document, so a common pattern doesn't
+/// create thousands of rows.
+const MAX_MATCHES_PER_DOCUMENT: usize = 50;
+
+/// Number of characters of a matching line that are shown.
+const MAX_LINE_CHARS: usize = 200;
+
+/// Where the user picked a match.
+pub enum Target {
+    /// An already open page, and the window where it is.
+    Page(Window, Page),
+    /// A recent file that is not open yet.
+    File(gio::File),
+}
+
+pub struct Choice {
+    pub target: Target,
+    pub position: FilePosition,
+}
+
+/// A document whose contents are searched.
+struct Source {
+    title: String,
+    subtitle: String,
+    contents: String,
+    target: Target,
+}
+
+/// A line containing the pattern.
+#[derive(Debug, PartialEq, Eq)]
+struct LineMatch<'a> {
+    position: FilePosition,
+    line: &'a str,
+}
+
+/// Lets the user search the contents of all open documents, in any window,
+/// and optionally of recent files, and jump to a match.
+///
+/// Returns `None` if the user cancelled the dialog.
+pub async fn run(parent: &Window) -> Option<Choice> {
+    let sources = Rc::new(RefCell::new(collect_page_sources()));
+    // Indices of the source and the match of the rows, in order.
+    let shown = Rc::new(RefCell::new(Vec::<(usize, FilePosition)>::new()));
+
+    let dialog = adw::Dialog::builder()
+        .title(gettext("Find in Open Documents"))
+        .content_width(560)
+        .content_height(520)
+        .build();
+
+    let search_entry = gtk::SearchEntry::builder()
+        .placeholder_text(gettext("Search in documents"))
+        .hexpand(true)
+        .build();
+
+    let recents_button = gtk::ToggleButton::builder()
+        .icon_name("document-open-recent-symbolic")
+        .tooltip_text(gettext("Include Recent Files"))
+        .build();
+
+    let header_bar = adw::HeaderBar::builder()
+        .title_widget(&search_entry)
+        .show_end_title_buttons(false)
+        .build();
+    header_bar.pack_end(&recents_button);
+
+    let initial_page = adw::StatusPage::builder()
+        .icon_name("system-search-symbolic")
+        .title(gettext("Search Open Documents"))
+        .description(gettext("Matching lines are listed by document"))
+        .build();
+    initial_page.add_css_class("compact");
+
+    let empty_page = adw::StatusPage::builder()
+        .icon_name("system-search-symbolic")
+        .title(gettext("No Matches Found"))
+        .build();
+    empty_page.add_css_class("compact");
+
+    let results_bin = adw::Bin::new();
+
+    let stack = gtk::Stack::new();
+    stack.add_named(&initial_page, Some("initial"));
+    stack.add_named(&empty_page, Some("empty"));
+    stack.add_named(&results_bin, Some("results"));
+
+    let toolbar_view = adw::ToolbarView::new();
+    toolbar_view.add_top_bar(&header_bar);
+    toolbar_view.set_content(Some(&stack));
+    dialog.set_child(Some(&toolbar_view));
+    dialog.set_focus(Some(&search_entry));
+
+    let (tx, rx) = oneshot::channel();
+    let tx = Rc::new(RefCell::new(Some(tx)));
+
+    let choose = Rc::new(clone!(
+        #[weak]
+        dialog,
+        #[strong]
+        tx,
+        move |index: usize| {
+            if let Some(tx) = tx.take() {
+                let _ = tx.send(Some(index));
+            }
+            dialog.close();
+        }
+    ));
+    dialog.connect_closed(clone!(
+        #[strong]
+        tx,
+        move |_| {
+            if let Some(tx) = tx.take() {
+                let _ = tx.send(None);
+            }
+        }
+    ));
+
+    let search = Rc::new(clone!(
+        #[weak]
+        search_entry,
+        #[weak]
+        stack,
+        #[weak]
+        results_bin,
+        #[strong]
+        sources,
+        #[strong]
+        shown,
+        #[strong]
+        choose,
+        move || {
+            let pattern = search_entry.text();
+            if pattern.is_empty() {
+                shown.borrow_mut().clear();
+                stack.set_visible_child_name("initial");
+                return;
+            }
+
+            let results = populate(&sources.borrow(), &shown, &pattern, &choose);
+            results_bin.set_child(Some(&results));
+
+            let visible_child_name = if shown.borrow().is_empty() {
+                "empty"
+            } else {
+                "results"
+            };
+            stack.set_visible_child_name(visible_child_name);
+        }
+    ));
+
+    search_entry.connect_search_changed(clone!(
+        #[strong]
+        search,
+        move |_| {
+            search();
+        }
+    ));
+    search_entry.connect_activate(clone!(
+        #[strong]
+        shown,
+        #[strong]
+        choose,
+        move |_| {
+            if !shown.borrow().is_empty() {
+                choose(0);
+            }
+        }
+    ));
+    search_entry.connect_stop_search(clone!(
+        #[weak]
+        dialog,
+        move |_| {
+            dialog.close();
+        }
+    ));
+
+    recents_button.connect_toggled(clone!(
+        #[strong]
+        sources,
+        #[strong]
+        search,
+        move |button| {
+            if button.is_active() {
+                utils::spawn(clone!(
+                    #[weak]
+                    button,
+                    #[strong]
+                    sources,
+                    #[strong]
+                    search,
+                    async move {
+                        button.set_sensitive(false);
+                        let open_files = sources
+                            .borrow()
+                            .iter()
+                            .filter_map(|source| match &source.target {
+                                Target::Page(_, page) => page.document().file(),
+                                Target::File(_) => None,
+                            })
+                            .collect::<Vec<_>>();
+                        let recent_sources = collect_recent_sources(&open_files).await;
+                        button.set_sensitive(true);
+
+                        // It may have been untoggled while loading.
+                        if button.is_active() {
+                            sources.borrow_mut().extend(recent_sources);
+                            search();
+                        }
+                    }
+                ));
+            } else {
+                sources
+                    .borrow_mut()
+                    .retain(|source| !matches!(source.target, Target::File(_)));
+                search();
+            }
+        }
+    ));
+
+    dialog.present(Some(parent));
+
+    let index = rx.await.ok().flatten()?;
+    let (source_index, position) = shown.borrow()[index];
+    let source = sources.borrow_mut().swap_remove(source_index);
+    Some(Choice {
+        target: source.target,
+        position,
+    })
+}
+
+/// Returns the open pages of all windows.
+fn collect_page_sources() -> Vec<Source> {
+    let session = Session::instance();
+
+    let mut sources = Vec::new();
+    for window in session.windows() {
+        for page in window.pages() {
+            let document = page.document();
+            let subtitle = document
+                .file()
+                .as_ref()
+                .map_or_else(|| gettext("Draft"), utils::display_file);
+
+            sources.push(Source {
+                title: page.title(),
+                subtitle,
+                contents: document.contents().to_string(),
+                target: Target::Page(window.clone(), page),
+            });
+        }
+    }
+    sources
+}
+
+/// Loads the recent files that are not in `open_files`.
+async fn collect_recent_sources(open_files: &[gio::File]) -> Vec<Source> {
+    let session = Session::instance();
+
+    let mut recent_items = session
+        .recents()
+        .await
+        .iter::<RecentItem>()
+        .map_while(Result::ok)
+        .collect::<Vec<_>>();
+    recent_items.sort_by_key(|item| std::cmp::Reverse(item.added()));
+
+    let mut ret = Vec::new();
+    for item in recent_items {
+        let file = item.file();
+
+        utils::resolve_file_identity(&file).await;
+        if open_files.iter().any(|f| utils::is_same_file(f, &file)) {
+            continue;
+        }
+
+        let contents = match file.load_contents_future().await {
+            Ok((bytes, _)) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(err) => {
+                tracing::debug!(uri = %file.uri(), "Failed to load recent file: {:?}", err);
+                continue;
+            }
+        };
+
+        ret.push(Source {
+            title: utils::display_file_stem(&file),
+            subtitle: utils::display_file(&file),
+            contents,
+            target: Target::File(file),
+        });
+    }
+    ret
+}
+
+/// Returns the group of matches of each source that has some, and updates
+/// `shown` with the matches of the rows, in order.
+fn populate(
+    sources: &[Source],
+    shown: &RefCell<Vec<(usize, FilePosition)>>,
+    pattern: &str,
+    choose: &Rc<impl Fn(usize) + 'static>,
+) -> adw::PreferencesPage {
+    let page = adw::PreferencesPage::new();
+
+    let mut new_shown = Vec::new();
+    for (source_index, source) in sources.iter().enumerate() {
+        let matches = find_lines(&source.contents, pattern);
+        if matches.is_empty() {
+            continue;
+        }
+
+        let group = adw::PreferencesGroup::builder()
+            .title(glib::markup_escape_text(&source.title))
+            .description(glib::markup_escape_text(&source.subtitle))
+            .build();
+
+        let n_matches = matches.len();
+        let count_label = gtk::Label::builder()
+            .label(ngettext_f(
+                // Translators: Do NOT translate the contents between '{' and '}', this is a variable name.
+                "{n_matches} match",
+                "{n_matches} matches",
+                n_matches as u32,
+                &[("n_matches", &format_number(n_matches as i64))],
+            ))
+            .valign(gtk::Align::Start)
+            .css_classes(["caption", "dim-label"])
+            .build();
+        group.set_header_suffix(Some(&count_label));
+
+        for line_match in matches.into_iter().take(MAX_MATCHES_PER_DOCUMENT) {
+            let index = new_shown.len();
+            new_shown.push((source_index, line_match.position));
+
+            let line = line_match.line.trim();
+            let line = match line.char_indices().nth(MAX_LINE_CHARS) {
+                Some((end, _)) => &line[..end],
+                None => line,
+            };
+
+            let line_number_label = gtk::Label::builder()
+                .label(format_number(line_match.position.line as i64))
+                .width_chars(4)
+                .xalign(1.0)
+                .css_classes(["dim-label", "numeric"])
+                .build();
+
+            let row = adw::ActionRow::builder()
+                .title(glib::markup_escape_text(line))
+                .title_lines(1)
+                .activatable(true)
+                .build();
+            row.add_css_class("monospace");
+            row.add_prefix(&line_number_label);
+            row.connect_activated(clone!(
+                #[strong]
+                choose,
+                move |_| {
+                    choose(index);
+                }
+            ));
+            group.add(&row);
+        }
+
+        page.add(&group);
+    }
+
+    shown.replace(new_shown);
+
+    page
+}
+
+/// Returns the lines of `text` that contain `pattern`, with the position of
+/// the first match in each. Letters are matched ignoring their case, unless
+/// `pattern` has uppercase letters.
+fn find_lines<'a>(text: &'a str, pattern: &str) -> Vec<LineMatch<'a>> {
+    let is_case_sensitive = pattern.chars().any(char::is_uppercase);
+
+    text.lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let byte_offset = if is_case_sensitive {
+                line.find(pattern)?
+            } else {
+                find_ignoring_case(line, pattern)?
+            };
+            let column = line[..byte_offset].chars().count() + 1;
+
+            Some(LineMatch {
+                position: FilePosition {
+                    line: index as u32 + 1,
+                    column: column as u32,
+                },
+                line,
+            })
+        })
+        .collect()
+}
+
+/// Like `str::find`, but with letters matched ignoring their case.
+fn find_ignoring_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .char_indices()
+        .map(|(index, _)| index)
+        .find(|&index| {
+            let mut rest = haystack[index..].chars().flat_map(char::to_lowercase);
+            needle
+                .chars()
+                .flat_map(char::to_lowercase)
+                .all(|c| rest.next() == Some(c))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(line: u32, column: u32) -> FilePosition {
+        FilePosition { line, column }
+    }
+
+    #[test]
+    fn lines() {
+        let text = "digraph {\n    a -> b\n    B -> c [label=\"ab\"]\n}";
+        assert_eq!(
+            find_lines(text, "b"),
+            [
+                LineMatch {
+                    position: position(2, 10),
+                    line: "    a -> b",
+                },
+                LineMatch {
+                    position: position(3, 5),
+                    line: "    B -> c [label=\"ab\"]",
+                },
+            ]
+        );
+        assert_eq!(
+            find_lines(text, "B"),
+            [LineMatch {
+                position: position(3, 5),
+                line: "    B -> c [label=\"ab\"]",
+            }]
+        );
+        assert!(find_lines(text, "x").is_empty());
+    }
+
+    #[test]
+    fn unicode() {
+        assert_eq!(
+            find_lines("a [label=\"Ünïcode\"]", "ünï"),
+            [LineMatch {
+                position: position(1, 11),
+                line: "a [label=\"Ünïcode\"]",
+            }]
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use std::cmp::Ordering;
+
+use adw::{prelude::*, subclass::prelude::*};
+use gtk::{
+    gio,
+    glib::{self, clone, closure_local},
+};
+
+use crate::utils;
+
+const FILE_ATTRIBUTES: &str =
+    "standard::name,standard::display-name,standard::type,standard::is-hidden";
+
+/// Suffixes of the files listed, matching those of the file dialog filter.
+const GRAPH_FILE_SUFFIXES: [&str; 3] = [".gv", ".dot", ".gv.txt"];
+
+mod imp {
+    use std::{cell::OnceCell, sync::LazyLock};
+
+    use glib::subclass::Signal;
+
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Delineate/ui/folder_sidebar.ui")]
+    pub struct FolderSidebar {
+        #[template_child]
+        pub(super) title_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub(super) stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) list_page: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub(super) list_view: TemplateChild<gtk::ListView>,
+        #[template_child]
+        pub(super) empty_page: TemplateChild<adw::StatusPage>,
+
+        pub(super) directory_list: OnceCell<gtk::DirectoryList>,
+        pub(super) model: OnceCell<gtk::SortListModel>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for FolderSidebar {
+        const NAME: &'static str = "DelineateFolderSidebar";
+        type Type = super::FolderSidebar;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for FolderSidebar {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+
+            // The directory list monitors the folder, so files are added and
+            // removed as they appear and disappear.
+            let directory_list = gtk::DirectoryList::new(Some(FILE_ATTRIBUTES), gio::File::NONE);
+            directory_list.set_monitored(true);
+            directory_list.connect_loading_notify(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.update_stack();
+                }
+            ));
+            directory_list.connect_error_notify(|directory_list| {
+                if let Some(err) = directory_list.error() {
+                    tracing::warn!("Failed to list folder: {:?}", err);
+                }
+            });
+
+            let filter = gtk::CustomFilter::new(|item| {
+                let info = item.downcast_ref::<gio::FileInfo>().unwrap();
+                is_graph_file(info)
+            });
+            let filter_model =
+                gtk::FilterListModel::new(Some(directory_list.clone()), Some(filter));
+
+            let sorter = gtk::CustomSorter::new(|a, b| {
+                let a = a.downcast_ref::<gio::FileInfo>().unwrap();
+                let b = b.downcast_ref::<gio::FileInfo>().unwrap();
+                compare_names(a, b).into()
+            });
+            let model = gtk::SortListModel::new(Some(filter_model), Some(sorter));
+            model.connect_items_changed(clone!(
+                #[weak]
+                obj,
+                move |_, _, _, _| {
+                    obj.update_stack();
+                }
+            ));
+
+            let factory = gtk::SignalListItemFactory::new();
+            factory.connect_setup(|_, list_item| {
+                let list_item = list_item.downcast_ref::<gtk::ListItem>().unwrap();
+                let label = gtk::Label::builder()
+                    .xalign(0.0)
+                    .ellipsize(gtk::pango::EllipsizeMode::Middle)
+                    .build();
+                list_item.set_child(Some(&label));
+            });
+            factory.connect_bind(|_, list_item| {
+                let list_item = list_item.downcast_ref::<gtk::ListItem>().unwrap();
+                let info = list_item.item().and_downcast::<gio::FileInfo>().unwrap();
+                let label = list_item.child().and_downcast::<gtk::Label>().unwrap();
+                label.set_label(&info.display_name());
+                label.set_tooltip_text(Some(&utils::display_file(&file_of(&info))));
+            });
+            self.list_view.set_factory(Some(&factory));
+            self.list_view
+                .set_model(Some(&gtk::NoSelection::new(Some(model.clone()))));
+
+            self.list_view.connect_activate(clone!(
+                #[weak]
+                obj,
+                move |_, position| {
+                    let imp = obj.imp();
+                    let info = imp
+                        .model
+                        .get()
+                        .unwrap()
+                        .item(position)
+                        .and_downcast::<gio::FileInfo>()
+                        .unwrap();
+                    obj.emit_by_name::<()>("file-activated", &[&file_of(&info)]);
+                }
+            ));
+
+            self.directory_list.set(directory_list).unwrap();
+            self.model.set(model).unwrap();
+
+            obj.update_stack();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: LazyLock<Vec<Signal>> = LazyLock::new(|| {
+                vec![Signal::builder("file-activated")
+                    .param_types([gio::File::static_type()])
+                    .build()]
+            });
+
+            SIGNALS.as_ref()
+        }
+    }
+
+    impl WidgetImpl for FolderSidebar {}
+    impl BinImpl for FolderSidebar {}
+}
+
+glib::wrapper! {
+    /// Lists the graph files in a folder, refreshing as they are added or
+    /// removed, like when a tool writes many graphs at once.
+    pub struct FolderSidebar(ObjectSubclass<imp::FolderSidebar>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+impl FolderSidebar {
+    pub fn connect_file_activated<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &gio::File) + 'static,
+    {
+        self.connect_closure(
+            "file-activated",
+            false,
+            closure_local!(|obj: &Self, file: &gio::File| {
+                f(obj, file);
+            }),
+        )
+    }
+
+    pub fn folder(&self) -> Option<gio::File> {
+        self.directory_list().file()
+    }
+
+    /// Lists the graph files in `folder`, or nothing if it is `None`.
+    pub fn set_folder(&self, folder: Option<&gio::File>) {
+        let imp = self.imp();
+
+        let title = folder
+            .and_then(|folder| folder.basename())
+            .map(|basename| basename.display().to_string())
+            .unwrap_or_default();
+        imp.title_label.set_label(&title);
+        imp.title_label
+            .set_tooltip_text(folder.map(utils::display_file).as_deref());
+
+        self.directory_list().set_file(folder);
+    }
+
+    fn directory_list(&self) -> &gtk::DirectoryList {
+        self.imp().directory_list.get().unwrap()
+    }
+
+    fn update_stack(&self) {
+        let imp = self.imp();
+
+        let is_empty = imp.model.get().unwrap().n_items() == 0;
+        if is_empty && !self.directory_list().is_loading() {
+            imp.stack.set_visible_child(&*imp.empty_page);
+        } else {
+            imp.stack.set_visible_child(&*imp.list_page);
+        }
+    }
+}
+
+fn file_of(info: &gio::FileInfo) -> gio::File {
+    info.attribute_object("standard::file")
+        .and_downcast::<gio::File>()
+        .unwrap()
+}
+
+fn is_graph_file(info: &gio::FileInfo) -> bool {
+    if info.file_type() != gio::FileType::Regular || info.is_hidden() {
+        return false;
+    }
+
+    let name = info.name();
+    let name = name.to_string_lossy();
+    GRAPH_FILE_SUFFIXES
+        .iter()
+        .any(|suffix| name.len() > suffix.len() && name.ends_with(suffix))
+}
+
+fn compare_names(a: &gio::FileInfo, b: &gio::FileInfo) -> Ordering {
+    let a = glib::FilenameCollationKey::from(a.display_name());
+    let b = glib::FilenameCollationKey::from(b.display_name());
+    a.cmp(&b)
+}
@@ -0,0 +1,87 @@
+//! The user's style sheet for graphs, at `APP_DATA_DIR/graph.css`, which is
+//! applied to the preview, and optionally to exported graphs, so styling like
+//! fonts and colors doesn't have to be repeated in every document.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use gtk::{gio, glib, prelude::*};
+
+use crate::APP_DATA_DIR;
+
+pub fn path() -> PathBuf {
+    APP_DATA_DIR.join("graph.css")
+}
+
+/// Loads the style sheet, returning `None` if there is none.
+pub async fn load() -> Result<Option<String>> {
+    let file = gio::File::for_path(path());
+    match file.load_bytes_future().await {
+        Ok((bytes, _)) => {
+            let css = String::from_utf8_lossy(&bytes).into_owned();
+            Ok(Some(css).filter(|css| !css.trim().is_empty()))
+        }
+        Err(err) if err.matches(gio::IOErrorEnum::NotFound) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Returns a monitor that reports when the style sheet is changed, created,
+/// or deleted.
+pub fn monitor() -> Result<gio::FileMonitor, glib::Error> {
+    gio::File::for_path(path()).monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+}
+
+/// Returns `svg` with `css` in a `<style>` element at the start of the root
+/// element, or `None` if there is no root element.
+pub fn embed_in_svg(svg: &[u8], css: &str) -> Option<Vec<u8>> {
+    let svg_start = find(svg, b"<svg")?;
+    let insert_at = svg_start + svg[svg_start..].iter().position(|b| *b == b'>')? + 1;
+
+    let style = format!(
+        "\n<style type=\"text/css\"><![CDATA[\n{}\n]]></style>",
+        css.replace("]]>", "]]]]><![CDATA[>")
+    );
+
+    let mut ret = Vec::with_capacity(svg.len() + style.len());
+    ret.extend_from_slice(&svg[..insert_at]);
+    ret.extend_from_slice(style.as_bytes());
+    ret.extend_from_slice(&svg[insert_at..]);
+    Some(ret)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embed() {
+        let svg = b"<?xml version=\"1.0\"?>\n<svg width=\"8pt\">\n<g/>\n</svg>\n";
+        assert_eq!(
+            String::from_utf8(embed_in_svg(svg, "text { fill: red; }").unwrap()).unwrap(),
+            "<?xml version=\"1.0\"?>\n<svg width=\"8pt\">\n<style type=\"text/css\"><![CDATA[\ntext { fill: red; }\n]]></style>\n<g/>\n</svg>\n"
+        );
+    }
+
+    #[test]
+    fn embed_cdata_end() {
+        let styled = embed_in_svg(b"<svg></svg>", "/* ]]> */").unwrap();
+        assert_eq!(
+            String::from_utf8(styled).unwrap(),
+            "<svg>\n<style type=\"text/css\"><![CDATA[\n/* ]]]]><![CDATA[> */\n]]></style></svg>"
+        );
+    }
+
+    #[test]
+    fn embed_no_root() {
+        assert_eq!(embed_in_svg(b"", "text {}"), None);
+        assert_eq!(embed_in_svg(b"<html></html>", "text {}"), None);
+        assert_eq!(embed_in_svg(b"<svg", "text {}"), None);
+    }
+}
@@ -1,4 +1,11 @@
-use std::cell::RefCell;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
 
 use anyhow::{ensure, Context, Result};
 use futures_channel::oneshot;
@@ -9,21 +16,155 @@ use gtk::{
     subclass::prelude::*,
 };
 use serde::{Deserialize, Serialize};
-use webkit::{javascriptcore::Value, prelude::*, ContextMenuAction};
+use tracing::Instrument;
+use webkit::{
+    javascriptcore::Value, prelude::*, ContextMenuAction, NavigationPolicyDecision,
+    PolicyDecisionType, WebProcessTerminationReason,
+};
 
-use crate::{config::GRAPHVIEWSRCDIR, utils};
+use crate::{config::GRAPHVIEWSRCDIR, graph_style, utils, STARTED_AT};
 
 const INIT_END_MESSAGE_ID: &str = "initEnd";
 const ERROR_MESSAGE_ID: &str = "error";
 const IS_GRAPH_LOADED_CHANGED_MESSAGE_ID: &str = "isGraphLoadedChanged";
 const IS_RENDERING_CHANGED_MESSAGE_ID: &str = "isRenderingChanged";
 const ZOOM_LEVEL_CHANGED_MESSAGE_ID: &str = "zoomLevelChanged";
+const ELEMENT_CLICKED_MESSAGE_ID: &str = "elementClicked";
+const ADD_NODE_REQUESTED_MESSAGE_ID: &str = "addNodeRequested";
+const ADD_EDGE_REQUESTED_MESSAGE_ID: &str = "addEdgeRequested";
+const DELETE_REQUESTED_MESSAGE_ID: &str = "deleteRequested";
+
+/// Scheme the bundled files of the view are served from, so it can't load
+/// anything else.
+const URI_SCHEME: &str = "delineate-graph-view";
+const INDEX_URI: &str = "delineate-graph-view:///index.html";
+/// Path where views post their SVG, followed by the ID of the transfer.
+const SVG_TRANSFER_PATH_PREFIX: &str = "/svg-transfer/";
+
+/// Memory the web process of each view may use before it is terminated,
+/// so a pathological graph can't use up all memory.
+const WEB_PROCESS_MEMORY_LIMIT_MB: u32 = 2048;
 
 const ZOOM_FACTOR: f64 = 1.5;
 const MIN_ZOOM_LEVEL: f64 = 0.1;
 const MAX_ZOOM_LEVEL: f64 = 100.0;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, glib::Enum)]
+/// Longest time views wait for the first window to be painted before they
+/// are initialized anyway, like when no window is shown.
+const MAX_INIT_DELAY: Duration = Duration::from_secs(3);
+
+/// Layout engines that the bundled Graphviz can run, known once the first
+/// view is initialized.
+static SUPPORTED_LAYOUT_ENGINES: OnceLock<Vec<LayoutEngine>> = OnceLock::new();
+
+thread_local! {
+    /// Views waiting for the first window to be painted to be initialized,
+    /// or `None` once they may be.
+    static PENDING_INITS: RefCell<Option<Vec<oneshot::Sender<()>>>> =
+        const { RefCell::new(Some(Vec::new())) };
+
+    /// Receivers of the SVGs that views were asked to post, by transfer ID.
+    static PENDING_SVG_TRANSFERS: RefCell<HashMap<String, oneshot::Sender<Result<glib::Bytes>>>> =
+        RefCell::default();
+}
+
+/// Lets views be initialized once `window` has painted its first frame, so
+/// starting a web process for every restored document doesn't delay showing
+/// them.
+pub fn init_after_first_frame(window: &impl IsA<gtk::Widget>) {
+    if PENDING_INITS.with_borrow(Option::is_none) {
+        return;
+    }
+
+    let Some(frame_clock) = window.frame_clock() else {
+        allow_init();
+        return;
+    };
+
+    let handler_id = Rc::new(RefCell::new(None));
+    let id = frame_clock.connect_after_paint(clone!(
+        #[strong]
+        handler_id,
+        move |frame_clock| {
+            if let Some(handler_id) = handler_id.take() {
+                frame_clock.disconnect(handler_id);
+            }
+
+            tracing::debug!(since_start = ?STARTED_AT.elapsed(), "First frame painted");
+
+            // Let the frame be presented before starting the web processes.
+            glib::idle_add_local_once(allow_init);
+        }
+    ));
+    handler_id.replace(Some(id));
+}
+
+fn allow_init() {
+    for tx in PENDING_INITS.take().into_iter().flatten() {
+        let _ = tx.send(());
+    }
+}
+
+/// Waits until views may be initialized, see `init_after_first_frame`.
+async fn wait_for_init_allowed() {
+    let rx = PENDING_INITS.with_borrow_mut(|pending| {
+        let pending = pending.as_mut()?;
+
+        if pending.is_empty() {
+            glib::timeout_add_local_once(MAX_INIT_DELAY, allow_init);
+        }
+
+        let (tx, rx) = oneshot::channel();
+        pending.push(tx);
+        Some(rx)
+    });
+
+    if let Some(rx) = rx {
+        let _ = rx.await;
+    }
+}
+
+/// What clicking in the graph does.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EditTool {
+    /// Clicking only selects nodes and edges.
+    #[default]
+    None,
+    /// Same as `None`, but the selected node or edge can also be deleted.
+    Select,
+    /// Clicking the background requests adding a node.
+    AddNode,
+    /// Clicking two nodes requests adding an edge between them.
+    AddEdge,
+}
+
+impl EditTool {
+    fn as_raw(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Select => "select",
+            Self::AddNode => "addNode",
+            Self::AddEdge => "addEdge",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AddEdgeRequest {
+    tail: String,
+    head: String,
+}
+
+/// A node or an edge clicked in the graph.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClickedElement {
+    pub is_edge: bool,
+    /// The title Graphviz gave the element in the SVG output.
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, glib::Enum)]
 #[repr(i32)]
 #[enum_type(name = "DelineateGraphViewEngine")]
 pub enum LayoutEngine {
@@ -46,7 +187,68 @@ impl TryFrom<i32> for LayoutEngine {
 }
 
 impl LayoutEngine {
-    fn as_raw(&self) -> &'static str {
+    pub const ALL: [Self; 8] = [
+        Self::Dot,
+        Self::Circo,
+        Self::Fdp,
+        Self::Sfdp,
+        Self::Neato,
+        Self::Osage,
+        Self::Patchwork,
+        Self::Twopi,
+    ];
+
+    /// Returns a model of the supported engines, as `adw::EnumListItem`s, for
+    /// drop-downs and combo rows.
+    pub fn supported_model() -> gtk::FilterListModel {
+        let filter = gtk::CustomFilter::new(|item| {
+            let item = item.downcast_ref::<adw::EnumListItem>().unwrap();
+            Self::try_from(item.value()).unwrap().is_supported()
+        });
+        gtk::FilterListModel::new(
+            Some(adw::EnumListModel::new(Self::static_type())),
+            Some(filter),
+        )
+    }
+
+    /// Whether the bundled Graphviz can run this engine. All engines are
+    /// assumed to be supported until a view is initialized.
+    pub fn is_supported(self) -> bool {
+        SUPPORTED_LAYOUT_ENGINES
+            .get()
+            .is_none_or(|engines| engines.contains(&self))
+    }
+
+    /// Returns the position of this engine in a model from `supported_model`.
+    pub fn position_in(self, model: &impl IsA<gio::ListModel>) -> Option<u32> {
+        model
+            .iter::<adw::EnumListItem>()
+            .map_while(Result::ok)
+            .position(|item| item.value() == self as i32)
+            .map(|position| position as u32)
+    }
+
+    /// Whether the engine starts from the positions in the `pos` attribute,
+    /// if any.
+    pub fn uses_initial_positions(self) -> bool {
+        matches!(self, Self::Neato | Self::Fdp | Self::Sfdp)
+    }
+
+    pub fn from_raw(raw: &str) -> Option<Self> {
+        match raw {
+            "dot" => Some(Self::Dot),
+            "circo" => Some(Self::Circo),
+            "fdp" => Some(Self::Fdp),
+            "sfdp" => Some(Self::Sfdp),
+            "neato" => Some(Self::Neato),
+            "osage" => Some(Self::Osage),
+            "patchwork" => Some(Self::Patchwork),
+            "twopi" => Some(Self::Twopi),
+            _ => None,
+        }
+    }
+
+    pub fn as_raw(&self) -> &'static str {
         match self {
             Self::Dot => "dot",
             Self::Circo => "circo",
@@ -77,15 +279,24 @@ mod imp {
         pub(super) is_rendering: Cell<bool>,
         #[property(get)]
         pub(super) zoom_level: Cell<f64>,
+        /// Whether the web process was terminated for using too much memory,
+        /// until the view is restarted
+        #[property(get)]
+        pub(super) exceeded_memory_limit: Cell<bool>,
         #[property(get = Self::can_zoom_in)]
         pub(super) can_zoom_in: PhantomData<bool>,
         #[property(get = Self::can_zoom_out)]
         pub(super) can_zoom_out: PhantomData<bool>,
         #[property(get = Self::can_reset_zoom)]
         pub(super) can_reset_zoom: PhantomData<bool>,
+        /// Whether the web view was loaded, which only happens once it is
+        /// first used
+        #[property(get)]
+        pub(super) is_initialized: Cell<bool>,
 
         pub(super) view: webkit::WebView,
         pub(super) index_loaded: OnceCell<()>,
+        pub(super) style_monitor: RefCell<Option<gio::FileMonitor>>,
     }
 
     #[glib::object_subclass]
@@ -97,27 +308,61 @@ mod imp {
         fn new() -> Self {
             let settings = webkit::Settings::new();
 
+            // Graphs are untrusted, as DOT files can point to any URL, so
+            // disable everything that is not needed to show them.
+            settings.set_allow_file_access_from_file_urls(false);
+            settings.set_allow_universal_access_from_file_urls(false);
+            settings.set_allow_top_navigation_to_data_urls(false);
+            settings.set_auto_load_images(false);
+            settings.set_enable_back_forward_navigation_gestures(false);
+            settings.set_enable_dns_prefetching(false);
+            settings.set_enable_html5_database(false);
+            settings.set_enable_html5_local_storage(false);
+            settings.set_enable_hyperlink_auditing(false);
+            settings.set_enable_media(false);
+            settings.set_enable_page_cache(false);
+            settings.set_enable_webaudio(false);
+            settings.set_enable_webgl(false);
+            settings.set_enable_webrtc(false);
+            settings.set_javascript_can_access_clipboard(false);
+            settings.set_javascript_can_open_windows_automatically(false);
+
             if utils::is_devel_profile() {
                 settings.set_enable_developer_extras(true);
                 settings.set_enable_write_console_messages_to_stdout(true);
             }
 
-            let context = webkit::WebContext::new();
+            let mut memory_pressure_settings = webkit::MemoryPressureSettings::new();
+            memory_pressure_settings.set_memory_limit(WEB_PROCESS_MEMORY_LIMIT_MB);
+            memory_pressure_settings.set_conservative_threshold(0.5);
+            memory_pressure_settings.set_strict_threshold(0.75);
+            memory_pressure_settings.set_kill_threshold(1.0);
+
+            let context = webkit::WebContext::builder()
+                .memory_pressure_settings(&memory_pressure_settings)
+                .build();
             context.set_cache_model(webkit::CacheModel::DocumentViewer);
+            context.register_uri_scheme(URI_SCHEME, handle_uri_scheme_request);
+            if let Some(security_manager) = context.security_manager() {
+                security_manager.register_uri_scheme_as_secure(URI_SCHEME);
+            }
 
             Self {
                 is_graph_loaded: Cell::new(false),
                 is_rendering: Cell::new(false),
                 zoom_level: Cell::new(1.0),
+                exceeded_memory_limit: Cell::new(false),
                 can_zoom_in: PhantomData,
                 can_zoom_out: PhantomData,
                 can_reset_zoom: PhantomData,
+                is_initialized: Cell::new(false),
                 view: glib::Object::builder()
                     .property("visible", false)
                     .property("settings", settings)
                     .property("web-context", context)
                     .build(),
                 index_loaded: OnceCell::new(),
+                style_monitor: RefCell::default(),
             }
         }
 
@@ -135,14 +380,38 @@ mod imp {
 
             self.view.set_parent(&*obj);
 
-            self.view.connect_web_process_terminated(|_, reason| {
-                tracing::error!("Web process terminated: {:?}", reason);
-            });
+            self.view.connect_web_process_terminated(clone!(
+                #[weak]
+                obj,
+                move |_, reason| {
+                    obj.handle_web_process_terminated(reason);
+                }
+            ));
             self.view.connect_is_web_process_responsive_notify(|view| {
                 if !view.is_web_process_responsive() {
                     tracing::warn!("Web process is unresponsive");
                 }
             });
+            self.view
+                .connect_decide_policy(|_, decision, decision_type| match decision_type {
+                    PolicyDecisionType::NavigationAction | PolicyDecisionType::NewWindowAction => {
+                        let uri = decision
+                            .downcast_ref::<NavigationPolicyDecision>()
+                            .and_then(|decision| decision.navigation_action())
+                            .and_then(|mut action| action.request())
+                            .and_then(|request| request.uri());
+
+                        // Links in graphs must not replace the view.
+                        if uri.as_deref() == Some(INDEX_URI) {
+                            false
+                        } else {
+                            tracing::warn!(?uri, "Blocked navigation");
+                            decision.ignore();
+                            true
+                        }
+                    }
+                    _ => false,
+                });
             self.view.connect_context_menu(move |_, ctx_menu, _| {
                 for item in ctx_menu.items() {
                     if !matches!(item.stock_action(), ContextMenuAction::InspectElement) {
@@ -201,16 +470,68 @@ mod imp {
                     }
                 ),
             );
-
-            utils::spawn(clone!(
-                #[weak]
-                obj,
-                async move {
-                    if let Err(err) = obj.ensure_view_initialized().await {
-                        tracing::error!("Failed to initialize view: {:?}", err);
+            obj.connect_script_message_received(
+                ELEMENT_CLICKED_MESSAGE_ID,
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_, value| {
+                        obj.handle_element_clicked(value);
+                    }
+                ),
+            );
+            obj.connect_script_message_received(
+                ADD_NODE_REQUESTED_MESSAGE_ID,
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_, _| {
+                        obj.emit_by_name::<()>("add-node-request", &[]);
+                    }
+                ),
+            );
+            obj.connect_script_message_received(
+                ADD_EDGE_REQUESTED_MESSAGE_ID,
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_, value| {
+                        obj.handle_add_edge_requested(value);
+                    }
+                ),
+            );
+            obj.connect_script_message_received(
+                DELETE_REQUESTED_MESSAGE_ID,
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_, value| {
+                        obj.handle_delete_requested(value);
                     }
+                ),
+            );
+
+            match graph_style::monitor() {
+                Ok(monitor) => {
+                    monitor.connect_changed(clone!(
+                        #[weak]
+                        obj,
+                        move |_, _, _, event| {
+                            if matches!(
+                                event,
+                                gio::FileMonitorEvent::ChangesDoneHint
+                                    | gio::FileMonitorEvent::Deleted
+                            ) {
+                                utils::spawn(async move {
+                                    obj.load_user_style_sheet().await;
+                                });
+                            }
+                        }
+                    ));
+                    self.style_monitor.replace(Some(monitor));
                 }
-            ));
+                Err(err) => tracing::warn!("Failed to monitor graph style sheet: {:?}", err),
+            }
         }
 
         fn dispose(&self) {
@@ -219,16 +540,43 @@ mod imp {
 
         fn signals() -> &'static [Signal] {
             static SIGNALS: LazyLock<Vec<Signal>> = LazyLock::new(|| {
-                vec![Signal::builder("error")
-                    .param_types([String::static_type()])
-                    .build()]
+                vec![
+                    Signal::builder("error")
+                        .param_types([String::static_type()])
+                        .build(),
+                    Signal::builder("element-clicked")
+                        .param_types([bool::static_type(), Option::<String>::static_type()])
+                        .build(),
+                    Signal::builder("add-node-request").build(),
+                    Signal::builder("add-edge-request")
+                        .param_types([String::static_type(), String::static_type()])
+                        .build(),
+                    Signal::builder("delete-request")
+                        .param_types([bool::static_type(), String::static_type()])
+                        .build(),
+                ]
             });
 
             SIGNALS.as_ref()
         }
     }
 
-    impl WidgetImpl for GraphView {}
+    impl WidgetImpl for GraphView {
+        fn map(&self) {
+            self.parent_map();
+
+            // Views of pages that are never shown don't need a web process.
+            utils::spawn(clone!(
+                #[weak(rename_to = obj)]
+                self.obj(),
+                async move {
+                    if let Err(err) = obj.ensure_view_initialized().await {
+                        tracing::error!("Failed to initialize view: {:?}", err);
+                    }
+                }
+            ));
+        }
+    }
 
     impl GraphView {
         fn can_zoom_in(&self) -> bool {
@@ -275,6 +623,78 @@ impl GraphView {
         )
     }
 
+    /// Connects to clicks on nodes and edges, or on the background, in which
+    /// case `None` is passed.
+    pub fn connect_element_clicked<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, Option<ClickedElement>) + 'static,
+    {
+        self.connect_closure(
+            "element-clicked",
+            false,
+            closure_local!(|obj: &Self, is_edge: bool, title: Option<String>| {
+                f(obj, title.map(|title| ClickedElement { is_edge, title }));
+            }),
+        )
+    }
+
+    pub fn connect_add_node_request<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_closure(
+            "add-node-request",
+            false,
+            closure_local!(|obj: &Self| {
+                f(obj);
+            }),
+        )
+    }
+
+    /// Connects to requests to add an edge between the nodes with the given
+    /// titles.
+    pub fn connect_add_edge_request<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &str, &str) + 'static,
+    {
+        self.connect_closure(
+            "add-edge-request",
+            false,
+            closure_local!(|obj: &Self, tail: &str, head: &str| {
+                f(obj, tail, head);
+            }),
+        )
+    }
+
+    /// Connects to requests to delete the selected node or edge, with the
+    /// `Select` tool.
+    pub fn connect_delete_request<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &ClickedElement) + 'static,
+    {
+        self.connect_closure(
+            "delete-request",
+            false,
+            closure_local!(|obj: &Self, is_edge: bool, title: String| {
+                f(obj, &ClickedElement { is_edge, title });
+            }),
+        )
+    }
+
+    pub async fn set_edit_tool(&self, tool: EditTool) -> Result<()> {
+        self.call_js_method("setEditTool", &[&tool.as_raw()])
+            .await?;
+        Ok(())
+    }
+
+    /// Sets whether a grid, with the graph coordinates under the pointer, is
+    /// shown over the graph.
+    pub async fn set_grid_visible(&self, is_visible: bool) -> Result<()> {
+        self.call_js_method("setGridVisible", &[&is_visible])
+            .await?;
+        Ok(())
+    }
+
     pub async fn set_data(&self, dot_src: &str, layout_engine: LayoutEngine) -> Result<()> {
         self.call_js_method("setData", &[&dot_src, &layout_engine.as_raw()])
             .await?;
@@ -297,14 +717,84 @@ impl GraphView {
     }
 
     pub async fn get_svg(&self) -> Result<glib::Bytes> {
-        let value = self.call_js_method("getSvgString", &[]).await?;
+        self.receive_svg_from("sendSvg", &[]).await
+    }
+
+    /// Returns the SVG of `dot_src` laid out with `layout_engine`, without
+    /// showing it. Unlike `get_svg`, this doesn't need the graph to be
+    /// rendered.
+    pub async fn layout_svg(
+        &self,
+        dot_src: &str,
+        layout_engine: LayoutEngine,
+    ) -> Result<glib::Bytes> {
+        self.receive_svg_from("sendLayout", &[&dot_src, &layout_engine.as_raw()])
+            .await
+    }
+
+    /// Returns the center of each node of the rendered graph by title, in
+    /// points, with the y-axis pointing up like in Graphviz.
+    pub async fn node_positions(&self) -> Result<HashMap<String, (f64, f64)>> {
+        let value = self.call_js_method("getNodePositions", &[]).await?;
+
+        ensure!(!value.is_null(), "Graph is not loaded");
+
+        let json = value.to_json(0).context("Failed to get value as JSON")?;
+        let positions = serde_json::from_str::<HashMap<String, [f64; 2]>>(&json)?
+            .into_iter()
+            .map(|(title, [x, y])| (title, (x, y)))
+            .collect();
+        Ok(positions)
+    }
 
-        ensure!(!value.is_null(), "SVG is null");
+    /// Loads the view again after its web process was terminated.
+    pub async fn restart(&self) -> Result<()> {
+        let imp = self.imp();
+
+        self.set_exceeded_memory_limit(false);
+
+        imp.view.set_visible(false);
+        self.init_view().await?;
+
+        Ok(())
+    }
+
+    /// Returns the layout engines that the bundled Graphviz can run.
+    pub async fn supported_layout_engines(&self) -> Result<&'static [LayoutEngine]> {
+        self.ensure_view_initialized().await?;
+
+        let engines = SUPPORTED_LAYOUT_ENGINES
+            .get()
+            .context("Supported layout engines are unknown")?;
+        Ok(engines)
+    }
+
+    /// Calls the JS method that posts an SVG to the URI given before `args`,
+    /// and returns the SVG.
+    async fn receive_svg_from(
+        &self,
+        method_name: &str,
+        args: &[&dyn ToVariant],
+    ) -> Result<glib::Bytes> {
+        let id = glib::uuid_string_random().to_string();
+        let uri = format!("{}://{}{}", URI_SCHEME, SVG_TRANSFER_PATH_PREFIX, id);
+
+        let (tx, rx) = oneshot::channel();
+        PENDING_SVG_TRANSFERS.with_borrow_mut(|pending| pending.insert(id.clone(), tx));
+
+        let mut all_args = vec![&uri as &dyn ToVariant];
+        all_args.extend_from_slice(args);
 
-        let bytes = value
-            .to_string_as_bytes()
-            .context("Failed to get value as bytes")?;
-        Ok(bytes)
+        let is_sent = self
+            .call_js_method(method_name, &all_args)
+            .await
+            .map(|value| value.to_boolean());
+        if !matches!(is_sent, Ok(true)) {
+            PENDING_SVG_TRANSFERS.with_borrow_mut(|pending| pending.remove(&id));
+        }
+        ensure!(is_sent?, "SVG is null");
+
+        rx.await.context("SVG transfer was cancelled")?
     }
 
     async fn set_zoom_level_by(&self, factor: f64) -> Result<()> {
@@ -313,6 +803,11 @@ impl GraphView {
     }
 
     async fn call_js_method(&self, method_name: &str, args: &[&dyn ToVariant]) -> Result<Value> {
+        ensure!(
+            !self.exceeded_memory_limit(),
+            "Web process exceeded its memory limit"
+        );
+
         self.ensure_view_initialized().await?;
         self.call_js_method_inner(method_name, args).await
     }
@@ -394,6 +889,99 @@ impl GraphView {
         self.notify_is_rendering();
     }
 
+    /// Applies the user's graph style sheet, replacing the previous one.
+    async fn load_user_style_sheet(&self) {
+        let imp = self.imp();
+
+        let user_content_manager = imp.view.user_content_manager().unwrap();
+        user_content_manager.remove_all_style_sheets();
+
+        match graph_style::load().await {
+            Ok(Some(css)) => {
+                let style_sheet = webkit::UserStyleSheet::new(
+                    &css,
+                    webkit::UserContentInjectedFrames::AllFrames,
+                    webkit::UserStyleLevel::User,
+                    &[],
+                    &[],
+                );
+                user_content_manager.add_style_sheet(&style_sheet);
+
+                tracing::debug!("Loaded graph style sheet");
+            }
+            Ok(None) => {}
+            Err(err) => tracing::warn!("Failed to load graph style sheet: {:?}", err),
+        }
+    }
+
+    fn handle_element_clicked(&self, value: &Value) {
+        let element = value
+            .to_json(0)
+            .context("Failed to get value as JSON")
+            .and_then(|json| Ok(serde_json::from_str::<Option<ClickedElement>>(&json)?));
+
+        match element {
+            Ok(Some(element)) => {
+                self.emit_by_name::<()>(
+                    "element-clicked",
+                    &[&element.is_edge, &Some(element.title)],
+                );
+            }
+            Ok(None) => {
+                self.emit_by_name::<()>("element-clicked", &[&false, &None::<String>]);
+            }
+            Err(err) => tracing::warn!("Failed to parse clicked element: {:?}", err),
+        }
+    }
+
+    fn handle_delete_requested(&self, value: &Value) {
+        let element = value
+            .to_json(0)
+            .context("Failed to get value as JSON")
+            .and_then(|json| Ok(serde_json::from_str::<ClickedElement>(&json)?));
+
+        match element {
+            Ok(element) => {
+                self.emit_by_name::<()>("delete-request", &[&element.is_edge, &element.title]);
+            }
+            Err(err) => tracing::warn!("Failed to parse delete request: {:?}", err),
+        }
+    }
+
+    fn handle_add_edge_requested(&self, value: &Value) {
+        let request = value
+            .to_json(0)
+            .context("Failed to get value as JSON")
+            .and_then(|json| Ok(serde_json::from_str::<AddEdgeRequest>(&json)?));
+
+        match request {
+            Ok(request) => {
+                self.emit_by_name::<()>("add-edge-request", &[&request.tail, &request.head]);
+            }
+            Err(err) => tracing::warn!("Failed to parse add edge request: {:?}", err),
+        }
+    }
+
+    fn set_exceeded_memory_limit(&self, exceeded_memory_limit: bool) {
+        if exceeded_memory_limit == self.exceeded_memory_limit() {
+            return;
+        }
+
+        self.imp().exceeded_memory_limit.set(exceeded_memory_limit);
+        self.notify_exceeded_memory_limit();
+    }
+
+    fn handle_web_process_terminated(&self, reason: WebProcessTerminationReason) {
+        tracing::error!("Web process terminated: {:?}", reason);
+
+        self.set_rendering(false);
+        self.set_graph_loaded(false);
+
+        if reason == WebProcessTerminationReason::ExceededMemoryLimit {
+            self.set_exceeded_memory_limit(true);
+        }
+    }
+
     fn set_zoom_level(&self, zoom_level: f64) {
         if zoom_level == self.zoom_level() {
             return;
@@ -410,19 +998,51 @@ impl GraphView {
 
         // FIXME Use a proper async Once
         imp.index_loaded
-            .get_or_try_init(|| self.init_view())
+            .get_or_try_init(|| {
+                let fut = async {
+                    wait_for_init_allowed().await;
+                    self.init_view().await?;
+
+                    imp.is_initialized.set(true);
+                    self.notify_is_initialized();
+
+                    Ok::<_, anyhow::Error>(())
+                };
+                fut.instrument(tracing::debug_span!("init_graph_view"))
+            })
             .await?;
 
         Ok(())
     }
 
+    async fn query_supported_layout_engines(&self) -> Result<Vec<LayoutEngine>> {
+        let raw_engines = LayoutEngine::ALL
+            .iter()
+            .map(|engine| engine.as_raw())
+            .collect::<Vec<_>>();
+        let value = self
+            .call_js_method_inner("supportedEngines", &[&raw_engines])
+            .await?;
+
+        // Graphviz can't be queried, so just assume everything works.
+        if value.is_null() {
+            return Ok(LayoutEngine::ALL.to_vec());
+        }
+
+        let json = value.to_json(0).context("Failed to get value as JSON")?;
+        let engines = serde_json::from_str::<Vec<String>>(&json)?
+            .iter()
+            .filter_map(|raw| LayoutEngine::from_raw(raw))
+            .collect();
+        Ok(engines)
+    }
+
     async fn init_view(&self) -> Result<()> {
         let imp = self.imp();
 
-        let graph_view_src_dir = gio::File::for_path(GRAPHVIEWSRCDIR);
-        let index_file = graph_view_src_dir.child("index.html");
+        let now = Instant::now();
 
-        let (index_bytes, _) = index_file.load_bytes_future().await?;
+        self.load_user_style_sheet().await;
 
         let (load_tx, load_rx) = oneshot::channel();
         let load_tx = RefCell::new(Some(load_tx));
@@ -445,15 +1065,12 @@ impl GraphView {
                 }
             });
 
-        // Needs to add trailing slash to base_uri
-        let base_uri = format!("{}/", graph_view_src_dir.uri());
-        imp.view
-            .load_bytes(&index_bytes, None, None, Some(&base_uri));
+        imp.view.load_uri(INDEX_URI);
 
         load_rx.await.unwrap();
         imp.view.disconnect(load_handler_id);
 
-        tracing::debug!("Loaded index.html from {}", index_file.uri());
+        tracing::debug!("Loaded index.html from {}", GRAPHVIEWSRCDIR);
 
         init_rx.await.unwrap();
         let user_content_manager = imp.view.user_content_manager().unwrap();
@@ -471,10 +1088,136 @@ impl GraphView {
             .to_str();
         tracing::debug!(%version, "Initialized Graphviz");
 
+        if SUPPORTED_LAYOUT_ENGINES.get().is_none() {
+            let engines = self
+                .query_supported_layout_engines()
+                .await
+                .context("Failed to query supported layout engines")?;
+            tracing::debug!(?engines, "Queried supported layout engines");
+
+            let _ = SUPPORTED_LAYOUT_ENGINES.set(engines);
+        }
+
         // Hide view while it's loading to prevent flickering from the delayed
         // style sheet loading.
         imp.view.set_visible(true);
 
+        tracing::debug!(
+            elapsed = ?now.elapsed(),
+            since_start = ?STARTED_AT.elapsed(),
+            "Graph view initialized"
+        );
+
         Ok(())
     }
 }
+
+/// Serves the bundled files of the view, from within its directory only.
+fn handle_uri_scheme_request(request: &webkit::URISchemeRequest) {
+    if let Some(id) = request.path().and_then(|path| {
+        path.strip_prefix(SVG_TRANSFER_PATH_PREFIX)
+            .map(str::to_string)
+    }) {
+        let request = request.clone();
+        utils::spawn(async move {
+            receive_svg(&request, &id).await;
+        });
+        return;
+    }
+
+    let res = request
+        .path()
+        .context("Request has no path")
+        .and_then(|path| bundled_file_path(&path))
+        .and_then(|path| {
+            let stream = gio::File::for_path(&path).read(gio::Cancellable::NONE)?;
+            Ok((path, stream))
+        });
+
+    match res {
+        Ok((path, stream)) => {
+            request.finish(&stream, -1, content_type(&path));
+        }
+        Err(err) => {
+            tracing::warn!(uri = ?request.uri(), "Failed to serve request: {:?}", err);
+
+            let mut error = glib::Error::new(gio::IOErrorEnum::NotFound, &format!("{:#}", err));
+            request.finish_error(&mut error);
+        }
+    }
+}
+
+/// Reads the SVG posted with `request` and passes it to whoever is waiting
+/// for the transfer with `id`.
+async fn receive_svg(request: &webkit::URISchemeRequest, id: &str) {
+    let Some(tx) = PENDING_SVG_TRANSFERS.with_borrow_mut(|pending| pending.remove(id)) else {
+        tracing::warn!(id, "Received unexpected SVG transfer");
+
+        let mut error = glib::Error::new(gio::IOErrorEnum::NotFound, "Unknown transfer");
+        request.finish_error(&mut error);
+        return;
+    };
+
+    let res = read_request_body(request).await;
+
+    match &res {
+        Ok(bytes) => {
+            tracing::debug!(n_bytes = bytes.len(), "Received SVG");
+
+            let stream = gio::MemoryInputStream::new();
+            request.finish(&stream, 0, Some("text/plain"));
+        }
+        Err(err) => {
+            let mut error = glib::Error::new(gio::IOErrorEnum::Failed, &format!("{:#}", err));
+            request.finish_error(&mut error);
+        }
+    }
+
+    let _ = tx.send(res);
+}
+
+async fn read_request_body(request: &webkit::URISchemeRequest) -> Result<glib::Bytes> {
+    ensure!(
+        request.http_method().as_deref() == Some("POST"),
+        "Request is not a POST"
+    );
+
+    let body = request.http_body().context("Request has no body")?;
+
+    // Spliced in chunks, so the main loop isn't blocked by large graphs.
+    let output = gio::MemoryOutputStream::new_resizable();
+    output
+        .splice_future(
+            &body,
+            gio::OutputStreamSpliceFlags::CLOSE_SOURCE | gio::OutputStreamSpliceFlags::CLOSE_TARGET,
+            glib::Priority::DEFAULT_IDLE,
+        )
+        .await?;
+    Ok(output.steal_as_bytes())
+}
+
+/// Returns the path of the bundled file at `uri_path`, refusing paths that
+/// lead outside of the bundle.
+fn bundled_file_path(uri_path: &str) -> Result<PathBuf> {
+    let relative_path = Path::new(uri_path.trim_start_matches('/'));
+    ensure!(
+        relative_path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_))),
+        "Invalid path `{}`",
+        uri_path
+    );
+
+    Ok(Path::new(GRAPHVIEWSRCDIR).join(relative_path))
+}
+
+fn content_type(path: &Path) -> Option<&'static str> {
+    let content_type = match path.extension()?.to_str()? {
+        "html" => "text/html",
+        "js" => "text/javascript",
+        "css" => "text/css",
+        "wasm" => "application/wasm",
+        _ => return None,
+    };
+    Some(content_type)
+}
@@ -21,6 +21,28 @@ pub fn ngettext_f(msgid: &str, msgid_plural: &str, n: u32, args: &[(&str, &str)]
     freplace(s, args)
 }
 
+/// Formats `n` with the digits and digit grouping of the current locale,
+/// falling back to plain ASCII digits if that fails.
+pub fn format_number(n: i64) -> String {
+    let mut buf = [0_u8; 64];
+
+    // SAFETY: The format takes a single `long long`, and `snprintf` writes at
+    // most `buf.len()` bytes, including the terminating NUL.
+    let len = unsafe {
+        libc::snprintf(
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+            c"%'Illd".as_ptr(),
+            libc::c_longlong::from(n),
+        )
+    };
+
+    match usize::try_from(len) {
+        Ok(len) if len < buf.len() => String::from_utf8_lossy(&buf[..len]).into_owned(),
+        _ => n.to_string(),
+    }
+}
+
 /// Replace variables in the given string using the given key-value tuples.
 ///
 /// The expected format to replace is `{name}`, where `name` is the first string
@@ -65,6 +87,13 @@ mod tests {
         gettext_f("missing {one}", &[("two", "2")]);
     }
 
+    #[test]
+    fn format_number_c_locale() {
+        assert_eq!(format_number(0), "0");
+        assert_eq!(format_number(1_234_567), "1234567");
+        assert_eq!(format_number(-42), "-42");
+    }
+
     #[test]
     fn gettext_f_simple() {
         assert_eq!(gettext_f("no replace", &[("one", "1")]), "no replace");
@@ -0,0 +1,199 @@
+use anyhow::{ensure, Result};
+
+use super::graph::{Edge, Graph};
+
+/// Delimiters that are tried when guessing how a file is separated.
+const DELIMITERS: [char; 3] = [',', ';', '\t'];
+
+/// How the columns of a [`Table`] are converted into edges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    pub has_header: bool,
+    pub source_column: usize,
+    pub target_column: usize,
+    pub label_column: Option<usize>,
+    pub is_directed: bool,
+}
+
+/// Rows of a delimiter-separated file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Parses comma, semicolon, or tab-separated values, guessing the
+    /// delimiter from the first line.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let contents = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+
+        let delimiter = guess_delimiter(contents);
+        let rows = parse_rows(contents, delimiter)?;
+        ensure!(!rows.is_empty(), "No rows");
+
+        Ok(Self { rows })
+    }
+
+    pub fn n_columns(&self) -> usize {
+        self.rows.iter().map(|row| row.len()).max().unwrap_or(0)
+    }
+
+    pub fn first_row(&self) -> &[String] {
+        &self.rows[0]
+    }
+
+    /// Creates a graph with an edge for each row that has both a source and
+    /// a target.
+    pub fn to_graph(&self, mapping: &Mapping) -> Graph {
+        let rows = if mapping.has_header {
+            &self.rows[1..]
+        } else {
+            &self.rows[..]
+        };
+
+        let cell = |row: &[String], column: usize| {
+            row.get(column)
+                .map(|cell| cell.trim())
+                .filter(|cell| !cell.is_empty())
+                .map(|cell| cell.to_string())
+        };
+
+        let edges = rows
+            .iter()
+            .filter_map(|row| {
+                let source = cell(row, mapping.source_column)?;
+                let target = cell(row, mapping.target_column)?;
+                let attrs = mapping
+                    .label_column
+                    .and_then(|column| cell(row, column))
+                    .map(|label| vec![("label".to_string(), label)])
+                    .unwrap_or_default();
+                Some(Edge {
+                    source,
+                    target,
+                    attrs,
+                })
+            })
+            .collect();
+
+        Graph {
+            is_directed: mapping.is_directed,
+            edges,
+            ..Default::default()
+        }
+    }
+}
+
+fn guess_delimiter(contents: &str) -> char {
+    let first_line = contents.lines().next().unwrap_or_default();
+
+    DELIMITERS
+        .into_iter()
+        .max_by_key(|delimiter| first_line.matches(*delimiter).count())
+        .filter(|delimiter| first_line.contains(*delimiter))
+        .unwrap_or(',')
+}
+
+fn parse_rows(contents: &str, delimiter: char) -> Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut is_quoted = false;
+
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        if is_quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => is_quoted = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => is_quoted = true,
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                push_row(&mut rows, std::mem::take(&mut row));
+            }
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    ensure!(!is_quoted, "Unterminated quoted field");
+
+    row.push(field);
+    push_row(&mut rows, row);
+
+    Ok(rows)
+}
+
+/// Pushes `row` to `rows`, skipping blank lines.
+fn push_row(rows: &mut Vec<Vec<String>>, row: Vec<String>) {
+    if !(row.len() == 1 && row[0].is_empty()) {
+        rows.push(row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let table = Table::parse("a,b,c\r\n1,\"2, \"\"two\"\"\",3\n\n\"multi\nline\",x\n").unwrap();
+        assert_eq!(
+            table.rows,
+            [
+                vec!["a", "b", "c"],
+                vec!["1", "2, \"two\"", "3"],
+                vec!["multi\nline", "x"],
+            ]
+        );
+        assert_eq!(table.n_columns(), 3);
+
+        let table = Table::parse("a;b\n1;2,5").unwrap();
+        assert_eq!(table.rows, [vec!["a", "b"], vec!["1", "2,5"]]);
+
+        let table = Table::parse("a\tb").unwrap();
+        assert_eq!(table.rows, [vec!["a", "b"]]);
+
+        assert!(Table::parse("").is_err());
+        assert!(Table::parse("\"a,b").is_err());
+    }
+
+    #[test]
+    fn to_graph() {
+        let table = Table::parse("from,to,weight\nA,B,1\nB,,2\nB,C\n").unwrap();
+
+        let graph = table.to_graph(&Mapping {
+            has_header: true,
+            source_column: 0,
+            target_column: 1,
+            label_column: Some(2),
+            is_directed: false,
+        });
+        assert_eq!(
+            graph.to_dot(),
+            "graph {\n    A -- B [label=1]\n    B -- C\n}\n"
+        );
+
+        let graph = table.to_graph(&Mapping {
+            has_header: false,
+            source_column: 1,
+            target_column: 0,
+            label_column: None,
+            is_directed: true,
+        });
+        assert_eq!(
+            graph.to_dot(),
+            "digraph {\n    to -> from\n    B -> A\n    C -> B\n}\n"
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+
+use super::{
+    graph::{Edge, Graph, Node},
+    graphml::NodeExt,
+};
+
+/// Parses a `GEXF` document, as written by Gephi.
+///
+/// Node colors are kept as fill colors, while positions, sizes, and
+/// dynamic attributes are ignored.
+pub fn parse(contents: &str) -> Result<Graph> {
+    let document = roxmltree::Document::parse(contents).context("Invalid XML")?;
+
+    let root = document.root_element();
+    if root.tag_name().name() != "gexf" {
+        bail!("Root element is not `gexf`");
+    }
+
+    let graph_node = root
+        .children()
+        .find(|n| n.has_tag_name_local("graph"))
+        .context("No `graph` element")?;
+
+    // Maps attribute IDs to their titles, for each class of element.
+    let mut attribute_titles = HashMap::new();
+    for attributes in graph_node
+        .children()
+        .filter(|n| n.has_tag_name_local("attributes"))
+    {
+        let class = attributes.attribute("class").unwrap_or("node");
+        for attribute in attributes
+            .children()
+            .filter(|n| n.has_tag_name_local("attribute"))
+        {
+            if let Some(id) = attribute.attribute("id") {
+                let title = attribute.attribute("title").unwrap_or(id);
+                attribute_titles.insert((class, id), title);
+            }
+        }
+    }
+
+    let default_edge_type = graph_node
+        .attribute("defaultedgetype")
+        .unwrap_or("undirected");
+    let is_directed = default_edge_type == "directed";
+
+    let mut graph = Graph {
+        is_directed,
+        ..Default::default()
+    };
+
+    for node in graph_node
+        .descendants()
+        .filter(|n| n.has_tag_name_local("node"))
+    {
+        let id = node.attribute("id").context("Node has no `id`")?;
+
+        let mut attrs = Vec::new();
+        if let Some(label) = node.attribute("label") {
+            attrs.push(("label".to_string(), label.to_string()));
+        }
+        attrs.extend(attvalues(node, "node", &attribute_titles));
+
+        if let Some(color) = node.children().find(|n| n.has_tag_name_local("color")) {
+            let component = |name| color.attribute(name).and_then(|c| c.parse::<u8>().ok());
+            if let (Some(r), Some(g), Some(b)) = (component("r"), component("g"), component("b")) {
+                attrs.push(("style".to_string(), "filled".to_string()));
+                attrs.push((
+                    "fillcolor".to_string(),
+                    format!("#{:02x}{:02x}{:02x}", r, g, b),
+                ));
+            }
+        }
+
+        graph.nodes.push(Node {
+            id: id.to_string(),
+            attrs,
+        });
+    }
+
+    for edge in graph_node
+        .descendants()
+        .filter(|n| n.has_tag_name_local("edge"))
+    {
+        let source = edge.attribute("source").context("Edge has no `source`")?;
+        let target = edge.attribute("target").context("Edge has no `target`")?;
+
+        let mut attrs = Vec::new();
+        if let Some(label) = edge.attribute("label") {
+            attrs.push(("label".to_string(), label.to_string()));
+        }
+        if let Some(weight) = edge.attribute("weight") {
+            attrs.push(("weight".to_string(), weight.to_string()));
+        }
+        attrs.extend(attvalues(edge, "edge", &attribute_titles));
+
+        // Mixed graphs may have edges whose type differs from the default.
+        match edge.attribute("type") {
+            Some("undirected") if is_directed => {
+                attrs.push(("dir".to_string(), "none".to_string()));
+            }
+            Some("mutual") if is_directed => {
+                attrs.push(("dir".to_string(), "both".to_string()));
+            }
+            _ => {}
+        }
+
+        graph.edges.push(Edge {
+            source: source.to_string(),
+            target: target.to_string(),
+            attrs,
+        });
+    }
+
+    Ok(graph)
+}
+
+/// Converts the `attvalue` children of `node` into attributes.
+fn attvalues(
+    node: roxmltree::Node<'_, '_>,
+    class: &str,
+    attribute_titles: &HashMap<(&str, &str), &str>,
+) -> Vec<(String, String)> {
+    node.children()
+        .filter(|n| n.has_tag_name_local("attvalues"))
+        .flat_map(|n| n.children())
+        .filter(|n| n.has_tag_name_local("attvalue"))
+        .filter_map(|attvalue| {
+            let id = attvalue
+                .attribute("for")
+                .or_else(|| attvalue.attribute("id"))?;
+            let value = attvalue.attribute("value")?;
+            let title = attribute_titles.get(&(class, id)).copied().unwrap_or(id);
+            Some((title.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple() {
+        let graph = parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <gexf xmlns="http://gexf.net/1.3" xmlns:viz="http://gexf.net/1.3/viz" version="1.3">
+              <graph defaultedgetype="directed">
+                <attributes class="node">
+                  <attribute id="0" title="group" type="string"/>
+                </attributes>
+                <nodes>
+                  <node id="0" label="Hello">
+                    <attvalues><attvalue for="0" value="a"/></attvalues>
+                    <viz:color r="255" g="0" b="16"/>
+                  </node>
+                  <node id="1" label="World"/>
+                </nodes>
+                <edges>
+                  <edge id="0" source="0" target="1" weight="2.0"/>
+                  <edge id="1" source="1" target="0" type="undirected"/>
+                </edges>
+              </graph>
+            </gexf>"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            graph.to_dot(),
+            "digraph {\n    0 [label=Hello, group=a, style=filled, fillcolor=\"#ff0010\"]\n    1 [label=World]\n\n    0 -> 1 [weight=2.0]\n    1 -> 0 [dir=none]\n}\n"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse("<gexf>").is_err());
+        assert!(parse("<graphml/>").is_err());
+        assert!(parse("<gexf/>").is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::{bail, Context, Result};
+
+use super::graph::{Edge, Graph, Node};
+
+#[derive(Debug)]
+enum Value {
+    Scalar(String),
+    List(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn as_scalar(&self) -> Option<&str> {
+        match self {
+            Self::Scalar(s) => Some(s),
+            Self::List(_) => None,
+        }
+    }
+
+    fn as_list(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Self::Scalar(_) => None,
+            Self::List(list) => Some(list),
+        }
+    }
+}
+
+/// Parses a Graph Modelling Language document.
+///
+/// Only scalar node and edge attributes are kept, so nested lists, like
+/// `graphics`, are ignored.
+pub fn parse(contents: &str) -> Result<Graph> {
+    let mut tokens = Tokenizer::new(contents);
+    let root = parse_list(&mut tokens, false)?;
+
+    let graph_list = root
+        .iter()
+        .find(|(key, _)| key == "graph")
+        .and_then(|(_, value)| value.as_list())
+        .context("No `graph` list")?;
+
+    let mut graph = Graph::default();
+    for (key, value) in graph_list {
+        match (key.as_str(), value) {
+            ("directed", value) => graph.is_directed = value.as_scalar() == Some("1"),
+            ("node", Value::List(list)) => {
+                let id = scalar(list, "id").context("Node has no `id`")?;
+                graph.nodes.push(Node {
+                    id: id.to_string(),
+                    attrs: scalar_attrs(list, &["id"]),
+                });
+            }
+            ("edge", Value::List(list)) => {
+                let source = scalar(list, "source").context("Edge has no `source`")?;
+                let target = scalar(list, "target").context("Edge has no `target`")?;
+                graph.edges.push(Edge {
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    attrs: scalar_attrs(list, &["source", "target"]),
+                });
+            }
+            ("label" | "name", Value::Scalar(label)) => graph.id = Some(label.clone()),
+            _ => {}
+        }
+    }
+
+    Ok(graph)
+}
+
+fn scalar<'a>(list: &'a [(String, Value)], key: &str) -> Option<&'a str> {
+    list.iter()
+        .find(|(k, _)| k == key)
+        .and_then(|(_, value)| value.as_scalar())
+}
+
+fn scalar_attrs(list: &[(String, Value)], excluded_keys: &[&str]) -> Vec<(String, String)> {
+    list.iter()
+        .filter(|(key, _)| !excluded_keys.contains(&key.as_str()))
+        .filter_map(|(key, value)| Some((key.clone(), value.as_scalar()?.to_string())))
+        .collect()
+}
+
+fn parse_list(tokens: &mut Tokenizer<'_>, is_nested: bool) -> Result<Vec<(String, Value)>> {
+    let mut list = Vec::new();
+
+    loop {
+        let key = match tokens.next()? {
+            Some(Token::Word(key)) => key,
+            Some(Token::CloseBracket) if is_nested => return Ok(list),
+            None if !is_nested => return Ok(list),
+            Some(token) => bail!("Expected a key, found {:?}", token),
+            None => bail!("Unclosed list"),
+        };
+
+        let value = match tokens.next()? {
+            Some(Token::Word(value) | Token::String(value)) => Value::Scalar(value),
+            Some(Token::OpenBracket) => Value::List(parse_list(tokens, true)?),
+            Some(token) => bail!("Expected a value for `{}`, found {:?}", key, token),
+            None => bail!("Missing value for `{}`", key),
+        };
+
+        list.push((key, value));
+    }
+}
+
+#[derive(Debug)]
+enum Token {
+    Word(String),
+    String(String),
+    OpenBracket,
+    CloseBracket,
+}
+
+struct Tokenizer<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(contents: &'a str) -> Self {
+        Self { rest: contents }
+    }
+
+    fn next(&mut self) -> Result<Option<Token>> {
+        loop {
+            self.rest = self.rest.trim_start();
+
+            // Lines starting with `#` are comments.
+            if self.rest.starts_with('#') {
+                self.rest = self.rest.split_once('\n').map_or("", |(_, rest)| rest);
+            } else {
+                break;
+            }
+        }
+
+        let Some(first) = self.rest.chars().next() else {
+            return Ok(None);
+        };
+
+        let token = match first {
+            '[' => {
+                self.rest = &self.rest[1..];
+                Token::OpenBracket
+            }
+            ']' => {
+                self.rest = &self.rest[1..];
+                Token::CloseBracket
+            }
+            '"' => {
+                let (string, rest) = self.rest[1..]
+                    .split_once('"')
+                    .context("Unterminated string")?;
+                self.rest = rest;
+                Token::String(unescape_entities(string))
+            }
+            _ => {
+                let end = self
+                    .rest
+                    .find(|c: char| c.is_whitespace() || c == '[' || c == ']')
+                    .unwrap_or(self.rest.len());
+                let (word, rest) = self.rest.split_at(end);
+                self.rest = rest;
+                Token::Word(word.to_string())
+            }
+        };
+
+        Ok(Some(token))
+    }
+}
+
+/// Strings can't contain `"`, so they are written as HTML entities instead.
+fn unescape_entities(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple() {
+        let graph = parse(
+            r##"# Created by hand
+            graph [
+              directed 1
+              label "G"
+              node [ id 1 label "A &quot;node&quot;" graphics [ fill "#FF0000" ] ]
+              node [ id 2 ]
+              edge [ source 1 target 2 weight 0.5 ]
+            ]"##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            graph.to_dot(),
+            "digraph G {\n    1 [label=\"A \\\"node\\\"\"]\n    2\n\n    1 -> 2 [weight=0.5]\n}\n"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse("graph [ node [ id 1 ]").is_err());
+        assert!(parse("graph [ node [ label \"A\" ] ]").is_err());
+        assert!(parse("node [ id 1 ]").is_err());
+    }
+}
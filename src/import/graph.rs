@@ -0,0 +1,118 @@
+use std::fmt::Write;
+
+use crate::dot::quote_id;
+
+/// A format-independent graph that imported files are converted into before
+/// being written as DOT.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Graph {
+    pub id: Option<String>,
+    pub is_directed: bool,
+    pub attrs: Vec<(String, String)>,
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub id: String,
+    pub attrs: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub source: String,
+    pub target: String,
+    pub attrs: Vec<(String, String)>,
+}
+
+impl Graph {
+    /// Writes the graph as DOT.
+    pub fn to_dot(&self) -> String {
+        let mut ret = String::new();
+
+        let (keyword, edge_op) = if self.is_directed {
+            ("digraph", "->")
+        } else {
+            ("graph", "--")
+        };
+
+        match &self.id {
+            Some(id) => writeln!(ret, "{} {} {{", keyword, quote_id(id)),
+            None => writeln!(ret, "{} {{", keyword),
+        }
+        .unwrap();
+
+        for (key, value) in &self.attrs {
+            writeln!(ret, "    {}={}", quote_id(key), quote_id(value)).unwrap();
+        }
+
+        if !self.attrs.is_empty() && !self.nodes.is_empty() {
+            ret.push('\n');
+        }
+
+        for node in &self.nodes {
+            writeln!(ret, "    {}{}", quote_id(&node.id), attr_list(&node.attrs)).unwrap();
+        }
+
+        if !self.nodes.is_empty() && !self.edges.is_empty() {
+            ret.push('\n');
+        }
+
+        for edge in &self.edges {
+            writeln!(
+                ret,
+                "    {} {} {}{}",
+                quote_id(&edge.source),
+                edge_op,
+                quote_id(&edge.target),
+                attr_list(&edge.attrs)
+            )
+            .unwrap();
+        }
+
+        ret.push_str("}\n");
+
+        ret
+    }
+}
+
+fn attr_list(attrs: &[(String, String)]) -> String {
+    if attrs.is_empty() {
+        return String::new();
+    }
+
+    let attrs = attrs
+        .iter()
+        .map(|(key, value)| format!("{}={}", quote_id(key), quote_id(value)))
+        .collect::<Vec<_>>();
+    format!(" [{}]", attrs.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_dot() {
+        let graph = Graph {
+            id: Some("G".to_string()),
+            is_directed: true,
+            attrs: vec![("rankdir".to_string(), "LR".to_string())],
+            nodes: vec![Node {
+                id: "a".to_string(),
+                attrs: vec![("label".to_string(), "A node".to_string())],
+            }],
+            edges: vec![Edge {
+                source: "a".to_string(),
+                target: "b".to_string(),
+                attrs: Vec::new(),
+            }],
+        };
+
+        assert_eq!(
+            graph.to_dot(),
+            "digraph G {\n    rankdir=LR\n\n    a [label=\"A node\"]\n\n    a -> b\n}\n"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+
+use super::graph::{Edge, Graph, Node};
+
+/// Parses a `GraphML` document, as written by yEd, `NetworkX`, and others.
+///
+/// Nested graphs are flattened, and hyperedges and ports are ignored.
+pub fn parse(contents: &str) -> Result<Graph> {
+    let document = roxmltree::Document::parse(contents).context("Invalid XML")?;
+
+    let root = document.root_element();
+    if root.tag_name().name() != "graphml" {
+        bail!("Root element is not `graphml`");
+    }
+
+    // Maps key IDs to their attribute names.
+    let keys = root
+        .children()
+        .filter(|n| n.has_tag_name_local("key"))
+        .filter_map(|n| {
+            let id = n.attribute("id")?;
+            let name = n
+                .attribute("attr.name")
+                .or_else(|| n.attribute("yfiles.type"))
+                .unwrap_or(id);
+            Some((id, name))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let graph_node = root
+        .children()
+        .find(|n| n.has_tag_name_local("graph"))
+        .context("No `graph` element")?;
+
+    let mut graph = Graph {
+        id: graph_node.attribute("id").map(|id| id.to_string()),
+        is_directed: graph_node.attribute("edgedefault") != Some("undirected"),
+        attrs: data_attrs(graph_node, &keys),
+        ..Default::default()
+    };
+    collect_elements(graph_node, &keys, &mut graph)?;
+
+    Ok(graph)
+}
+
+fn collect_elements(
+    graph_node: roxmltree::Node<'_, '_>,
+    keys: &HashMap<&str, &str>,
+    graph: &mut Graph,
+) -> Result<()> {
+    for child in graph_node.children().filter(|n| n.is_element()) {
+        match child.tag_name().name() {
+            "node" => {
+                let id = child.attribute("id").context("Node has no `id`")?;
+                graph.nodes.push(Node {
+                    id: id.to_string(),
+                    attrs: data_attrs(child, keys),
+                });
+
+                for nested_graph in child.children().filter(|n| n.has_tag_name_local("graph")) {
+                    collect_elements(nested_graph, keys, graph)?;
+                }
+            }
+            "edge" => {
+                let source = child.attribute("source").context("Edge has no `source`")?;
+                let target = child.attribute("target").context("Edge has no `target`")?;
+                graph.edges.push(Edge {
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    attrs: data_attrs(child, keys),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts the `data` children of `node` into attributes.
+fn data_attrs(node: roxmltree::Node<'_, '_>, keys: &HashMap<&str, &str>) -> Vec<(String, String)> {
+    node.children()
+        .filter(|n| n.has_tag_name_local("data"))
+        .filter_map(|data| {
+            let key = data.attribute("key")?;
+
+            // yEd stores labels in nested graphics elements, like
+            // `<y:ShapeNode><y:NodeLabel>label</y:NodeLabel></y:ShapeNode>`.
+            if data.children().any(|n| n.is_element()) {
+                let label = data
+                    .descendants()
+                    .find(|n| matches!(n.tag_name().name(), "NodeLabel" | "EdgeLabel"))
+                    .and_then(|n| n.text())?;
+                return Some(("label".to_string(), label.trim().to_string()));
+            }
+
+            let name = keys.get(key).copied().unwrap_or(key);
+            Some((
+                name.to_string(),
+                data.text().unwrap_or_default().to_string(),
+            ))
+        })
+        .collect()
+}
+
+pub(super) trait NodeExt {
+    fn has_tag_name_local(&self, name: &str) -> bool;
+}
+
+impl NodeExt for roxmltree::Node<'_, '_> {
+    /// Like `has_tag_name`, but ignores the namespace.
+    fn has_tag_name_local(&self, name: &str) -> bool {
+        self.is_element() && self.tag_name().name() == name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple() {
+        let graph = parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+              <key id="d0" for="node" attr.name="color" attr.type="string"/>
+              <key id="d1" for="edge" attr.name="weight" attr.type="double"/>
+              <graph id="G" edgedefault="undirected">
+                <node id="n0"><data key="d0">green</data></node>
+                <node id="n1"/>
+                <edge source="n0" target="n1"><data key="d1">1.0</data></edge>
+              </graph>
+            </graphml>"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            graph.to_dot(),
+            "graph G {\n    n0 [color=green]\n    n1\n\n    n0 -- n1 [weight=1.0]\n}\n"
+        );
+    }
+
+    #[test]
+    fn yed_labels() {
+        let graph = parse(
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns"
+                xmlns:y="http://www.yworks.com/xml/graphml">
+              <key id="d6" for="node" yfiles.type="nodegraphics"/>
+              <graph id="G" edgedefault="directed">
+                <node id="n0">
+                  <data key="d6"><y:ShapeNode><y:NodeLabel>Start</y:NodeLabel></y:ShapeNode></data>
+                </node>
+              </graph>
+            </graphml>"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            graph.nodes[0].attrs,
+            [("label".to_string(), "Start".to_string())]
+        );
+        assert!(graph.is_directed);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse("<graphml>").is_err());
+        assert!(parse("<foo/>").is_err());
+        assert!(parse("<graphml/>").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+pub mod csv;
+mod gexf;
+mod gml;
+mod graph;
+mod graphml;
+mod tgf;
+
+use anyhow::Result;
+use gettextrs::gettext;
+use gtk::{gio, prelude::*};
+
+pub use self::graph::Graph;
+
+/// Formats that can be imported and converted to DOT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Gexf,
+    Gml,
+    GraphMl,
+    Tgf,
+}
+
+impl ImportFormat {
+    pub const ALL: [Self; 4] = [Self::GraphMl, Self::Gexf, Self::Gml, Self::Tgf];
+
+    /// Returns the format of `file`, guessed from its extension.
+    pub fn for_file(file: &gio::File) -> Option<Self> {
+        let extension = file
+            .basename()?
+            .extension()?
+            .to_string_lossy()
+            .to_lowercase();
+
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Gexf => gettext("GEXF"),
+            Self::Gml => gettext("GML"),
+            Self::GraphMl => gettext("GraphML"),
+            Self::Tgf => gettext("Trivial Graph Format"),
+        }
+    }
+
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::Gexf => &["gexf"],
+            Self::Gml => &["gml"],
+            Self::GraphMl => &["graphml"],
+            Self::Tgf => &["tgf"],
+        }
+    }
+
+    pub fn parse(&self, contents: &str) -> Result<Graph> {
+        match self {
+            Self::Gexf => gexf::parse(contents),
+            Self::Gml => gml::parse(contents),
+            Self::GraphMl => graphml::parse(contents),
+            Self::Tgf => tgf::parse(contents),
+        }
+    }
+}
+
+pub fn file_filters() -> gio::ListStore {
+    let filters = gio::ListStore::new::<gtk::FileFilter>();
+
+    let all_filter = gtk::FileFilter::new();
+    all_filter.set_name(Some(&gettext("All Supported Files")));
+    filters.append(&all_filter);
+
+    for format in ImportFormat::ALL {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some(&format.name()));
+
+        for extension in format.extensions() {
+            filter.add_suffix(extension);
+            all_filter.add_suffix(extension);
+        }
+
+        filters.append(&filter);
+    }
+
+    filters
+}
+
+pub fn csv_file_filters() -> gio::ListStore {
+    let filter = gtk::FileFilter::new();
+    filter.set_name(Some(&gettext("CSV Files")));
+    filter.add_mime_type("text/csv");
+    filter.add_mime_type("text/tab-separated-values");
+    filter.add_suffix("csv");
+    filter.add_suffix("tsv");
+
+    let filters = gio::ListStore::new::<gtk::FileFilter>();
+    filters.append(&filter);
+    filters
+}
@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+
+use super::graph::{Edge, Graph, Node};
+
+/// Parses a Trivial Graph Format document, which lists nodes as `id label`
+/// lines, then a `#` line, then edges as `source target label` lines.
+///
+/// TGF has no notion of direction, so the graph is assumed to be directed.
+pub fn parse(contents: &str) -> Result<Graph> {
+    let mut graph = Graph {
+        is_directed: true,
+        ..Default::default()
+    };
+
+    let mut is_in_edges = false;
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('#') {
+            is_in_edges = true;
+            continue;
+        }
+
+        let mut parts = line.splitn(if is_in_edges { 3 } else { 2 }, char::is_whitespace);
+        let label = |part: Option<&str>| {
+            part.map(|label| label.trim())
+                .filter(|label| !label.is_empty())
+                .map(|label| vec![("label".to_string(), label.to_string())])
+                .unwrap_or_default()
+        };
+
+        if is_in_edges {
+            let source = parts.next().unwrap();
+            let target = parts
+                .next()
+                .with_context(|| format!("Edge has no target at line {}", index + 1))?;
+            graph.edges.push(Edge {
+                source: source.to_string(),
+                target: target.to_string(),
+                attrs: label(parts.next()),
+            });
+        } else {
+            let id = parts.next().unwrap();
+            graph.nodes.push(Node {
+                id: id.to_string(),
+                attrs: label(parts.next()),
+            });
+        }
+    }
+
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple() {
+        let graph = parse("1 First node\n2\n#\n1 2 Edge label\n2 1\n").unwrap();
+
+        assert_eq!(
+            graph.to_dot(),
+            "digraph {\n    1 [label=\"First node\"]\n    2\n\n    1 -> 2 [label=\"Edge label\"]\n    2 -> 1\n}\n"
+        );
+    }
+
+    #[test]
+    fn missing_target() {
+        assert!(parse("1\n#\n1\n").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{dot, graph_view::LayoutEngine};
+
+/// Number of nodes from which a graph is large enough for `sfdp`, which is
+/// built for big graphs, to be much faster than `dot`.
+const LARGE_GRAPH_MIN_N_NODES: usize = 1000;
+const LARGE_GRAPH_MIN_N_EDGES: usize = 3000;
+
+/// Number of nodes from which a highly connected graph is likely a tangle
+/// with `dot`'s layered layout.
+const DENSE_GRAPH_MIN_N_NODES: usize = 50;
+/// Average number of edges per node from which a graph is highly connected.
+const DENSE_GRAPH_MIN_AVERAGE_DEGREE: f64 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Large,
+    Dense,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Suggestion {
+    pub engine: LayoutEngine,
+    pub reason: Reason,
+}
+
+/// Returns a layout engine that suits `graph` better than `dot`, or `None`
+/// if `dot` is fine or the graph picks its engine with the `layout`
+/// attribute.
+pub fn suggest(graph: &dot::Graph) -> Option<Suggestion> {
+    if graph.attr("layout").is_some() {
+        return None;
+    }
+
+    let topology = graph.topology();
+    let n_nodes = topology.nodes.len();
+    let n_edges = topology.edges.len();
+
+    if n_nodes >= LARGE_GRAPH_MIN_N_NODES || n_edges >= LARGE_GRAPH_MIN_N_EDGES {
+        return Some(Suggestion {
+            engine: LayoutEngine::Sfdp,
+            reason: Reason::Large,
+        });
+    }
+
+    let average_degree = 2.0 * n_edges as f64 / n_nodes.max(1) as f64;
+    if n_nodes >= DENSE_GRAPH_MIN_N_NODES && average_degree >= DENSE_GRAPH_MIN_AVERAGE_DEGREE {
+        return Some(Suggestion {
+            engine: LayoutEngine::Neato,
+            reason: Reason::Dense,
+        });
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use super::*;
+
+    fn suggest_for(source: &str) -> Option<Suggestion> {
+        suggest(&dot::parse(source).unwrap())
+    }
+
+    /// Returns a graph with `n_nodes` nodes, each with edges to the next
+    /// `n_successors` nodes.
+    fn graph_source(n_nodes: usize, n_successors: usize) -> String {
+        let mut ret = String::from("digraph {");
+        for i in 0..n_nodes {
+            write!(ret, " n{i};").unwrap();
+            for j in 1..=n_successors {
+                write!(ret, " n{i} -> n{};", (i + j) % n_nodes).unwrap();
+            }
+        }
+        ret.push('}');
+        ret
+    }
+
+    #[test]
+    fn small() {
+        assert_eq!(suggest_for("digraph { a -> b -> c }"), None);
+        assert_eq!(suggest_for("digraph { }"), None);
+        assert_eq!(suggest_for(&graph_source(40, 10)), None);
+        assert_eq!(suggest_for(&graph_source(500, 1)), None);
+    }
+
+    #[test]
+    fn large() {
+        assert_eq!(
+            suggest_for(&graph_source(1000, 1)),
+            Some(Suggestion {
+                engine: LayoutEngine::Sfdp,
+                reason: Reason::Large,
+            })
+        );
+        assert_eq!(
+            suggest_for(&graph_source(300, 10)),
+            Some(Suggestion {
+                engine: LayoutEngine::Sfdp,
+                reason: Reason::Large,
+            })
+        );
+    }
+
+    #[test]
+    fn dense() {
+        assert_eq!(
+            suggest_for(&graph_source(60, 3)),
+            Some(Suggestion {
+                engine: LayoutEngine::Neato,
+                reason: Reason::Dense,
+            })
+        );
+        assert_eq!(suggest_for(&graph_source(60, 2)), None);
+    }
+
+    #[test]
+    fn explicit_layout() {
+        let mut source = graph_source(1000, 1);
+        source.insert_str("digraph {".len(), " layout=dot;");
+        assert_eq!(suggest_for(&source), None);
+    }
+}
@@ -24,27 +24,54 @@
 )]
 
 mod about;
+mod adjacency_matrix;
 mod application;
+mod attribute_inspector;
+mod attribute_palette;
+mod colors;
 mod config;
+mod crash_report;
+mod csv_import_dialog;
+mod diff;
+mod diff_dialog;
 mod document;
+mod document_portal;
+mod document_switcher;
+mod dot;
 mod drag_overlay;
 mod error_gutter_renderer;
+mod export_engine_dialog;
 mod export_format;
+mod export_formats_dialog;
+mod file_position;
+mod find_in_documents;
+mod folder_sidebar;
+mod graph_style;
 mod graph_view;
 mod i18n;
+mod import;
+mod layout_suggestion;
+mod mermaid;
+mod node_name_dialog;
 mod page;
+mod preferences_dialog;
 mod recent_filter;
 mod recent_item;
 mod recent_list;
 mod recent_popover;
 mod recent_row;
 mod recent_sorter;
+mod remote_control;
+mod rename_dialog;
 mod save_changes_dialog;
 mod session;
+mod settings;
+mod thumbnail;
 mod utils;
+mod view_settings;
 mod window;
 
-use std::{fs, path::PathBuf, sync::LazyLock};
+use std::{fs, path::PathBuf, sync::LazyLock, time::Instant};
 
 use gettextrs::LocaleCategory;
 use gtk::{gio, glib};
@@ -60,7 +87,18 @@ static APP_DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
     path
 });
 
+static APP_CACHE_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    let mut path = glib::user_cache_dir();
+    path.push(APP_ID);
+    path
+});
+
+/// When the app was started, to measure how long starting up takes.
+static STARTED_AT: LazyLock<Instant> = LazyLock::new(Instant::now);
+
 fn main() -> glib::ExitCode {
+    LazyLock::force(&STARTED_AT);
+
     tracing_subscriber::fmt::init();
 
     gtk::init().unwrap();
@@ -76,6 +114,9 @@ fn main() -> glib::ExitCode {
     gio::resources_register(&res);
 
     fs::create_dir_all(APP_DATA_DIR.as_path()).unwrap();
+    fs::create_dir_all(APP_CACHE_DIR.as_path()).unwrap();
+
+    crash_report::install_panic_hook();
 
     let app = Application::new();
     app.run()
@@ -0,0 +1,397 @@
+use std::{collections::HashMap, fmt::Write};
+
+use anyhow::Result;
+
+use crate::dot::{self, find_attr, Attr, AttrTarget, EdgeOperand, StmtKind, Subgraph};
+
+/// Words that can't be used as Mermaid node IDs.
+const RESERVED_IDS: &[&str] = &["end", "graph", "subgraph", "flowchart", "style", "class"];
+
+/// Converts a DOT graph into a Mermaid flowchart.
+///
+/// Only what Mermaid can express is kept: node labels and common shapes, edge
+/// labels and styles, clusters, and the rank direction. Everything else,
+/// including ports and colors, is dropped.
+pub fn from_dot(source: &str) -> Result<String> {
+    let graph = dot::parse(source)?;
+
+    let mut converter = Converter {
+        is_directed: graph.is_directed,
+        ..Default::default()
+    };
+    let mut root = Cluster::default();
+    converter.visit_stmts(&graph.stmts, &mut root, &mut Scope::default());
+
+    let direction = match graph.attr("rankdir").map(|d| d.to_ascii_uppercase()) {
+        Some(d) if d == "LR" => "LR",
+        Some(d) if d == "BT" => "BT",
+        Some(d) if d == "RL" => "RL",
+        _ => "TD",
+    };
+
+    let mut ret = String::new();
+    writeln!(ret, "graph {}", direction).unwrap();
+    converter.write_cluster(&mut ret, &root, 1);
+    for edge in &converter.edges {
+        converter.write_edge(&mut ret, edge);
+    }
+
+    Ok(ret)
+}
+
+#[derive(Default)]
+struct Converter {
+    is_directed: bool,
+    nodes: Vec<Node>,
+    node_indices: HashMap<String, usize>,
+    edges: Vec<Edge>,
+    n_clusters: usize,
+}
+
+struct Node {
+    dot_id: String,
+    attrs: Vec<Attr>,
+}
+
+struct Edge {
+    source: usize,
+    target: usize,
+    attrs: Vec<Attr>,
+}
+
+#[derive(Default)]
+struct Cluster {
+    label: Option<String>,
+    nodes: Vec<usize>,
+    children: Vec<Cluster>,
+}
+
+/// Default attributes set by `node [...]` and `edge [...]`, which are
+/// inherited by subgraphs.
+#[derive(Default, Clone)]
+struct Scope {
+    node_attrs: Vec<Attr>,
+    edge_attrs: Vec<Attr>,
+}
+
+impl Converter {
+    fn visit_stmts(&mut self, stmts: &[dot::Stmt], cluster: &mut Cluster, scope: &mut Scope) {
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::Node(node_id, attrs) => {
+                    let index = self.node(&node_id.id, cluster, scope);
+                    self.nodes[index].attrs.extend(attrs.iter().cloned());
+                }
+                StmtKind::Edge(operands, attrs) => {
+                    let operand_nodes = operands
+                        .iter()
+                        .map(|operand| self.operand_nodes(operand, cluster, scope))
+                        .collect::<Vec<_>>();
+
+                    let mut edge_attrs = scope.edge_attrs.clone();
+                    edge_attrs.extend(attrs.iter().cloned());
+
+                    for pair in operand_nodes.windows(2) {
+                        for &source in &pair[0] {
+                            for &target in &pair[1] {
+                                self.edges.push(Edge {
+                                    source,
+                                    target,
+                                    attrs: edge_attrs.clone(),
+                                });
+                            }
+                        }
+                    }
+                }
+                StmtKind::Attr(AttrTarget::Node, attrs) => {
+                    scope.node_attrs.extend(attrs.iter().cloned());
+                }
+                StmtKind::Attr(AttrTarget::Edge, attrs) => {
+                    scope.edge_attrs.extend(attrs.iter().cloned());
+                }
+                StmtKind::Attr(AttrTarget::Graph, _) | StmtKind::Assign(_) => {}
+                StmtKind::Subgraph(subgraph) => {
+                    self.visit_subgraph(subgraph, cluster, scope);
+                }
+            }
+        }
+    }
+
+    /// Visits `subgraph`, returning the indices of the nodes in it.
+    fn visit_subgraph(
+        &mut self,
+        subgraph: &Subgraph,
+        cluster: &mut Cluster,
+        scope: &Scope,
+    ) -> Vec<usize> {
+        let n_nodes_before = self.nodes.len();
+        let mut scope = scope.clone();
+
+        let mut referenced = Vec::new();
+        if subgraph.is_cluster() {
+            let mut child = Cluster {
+                label: subgraph.attr("label").map(|label| label.to_string()),
+                ..Default::default()
+            };
+            self.visit_stmts(&subgraph.stmts, &mut child, &mut scope);
+            referenced.extend(child.all_nodes());
+            cluster.children.push(child);
+        } else {
+            self.visit_stmts(&subgraph.stmts, cluster, &mut scope);
+        }
+
+        // Nodes that were already declared before the subgraph are not added
+        // to it, but they are still endpoints of edges to the subgraph.
+        referenced.extend(n_nodes_before..self.nodes.len());
+        referenced.extend(subgraph_node_ids(subgraph).filter_map(|id| self.node_indices.get(id)));
+        referenced.sort_unstable();
+        referenced.dedup();
+        referenced
+    }
+
+    fn operand_nodes(
+        &mut self,
+        operand: &EdgeOperand,
+        cluster: &mut Cluster,
+        scope: &Scope,
+    ) -> Vec<usize> {
+        match operand {
+            EdgeOperand::Node(node_id) => vec![self.node(&node_id.id, cluster, scope)],
+            EdgeOperand::Subgraph(subgraph) => self.visit_subgraph(subgraph, cluster, scope),
+        }
+    }
+
+    /// Returns the index of the node `dot_id`, creating it in `cluster` if
+    /// it doesn't exist yet.
+    fn node(&mut self, dot_id: &str, cluster: &mut Cluster, scope: &Scope) -> usize {
+        if let Some(index) = self.node_indices.get(dot_id) {
+            return *index;
+        }
+
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            dot_id: dot_id.to_string(),
+            attrs: scope.node_attrs.clone(),
+        });
+        self.node_indices.insert(dot_id.to_string(), index);
+        cluster.nodes.push(index);
+        index
+    }
+
+    /// Returns the Mermaid ID of the node at `index`, which is the DOT ID if
+    /// it is valid in Mermaid.
+    fn mermaid_id(&self, index: usize) -> String {
+        let dot_id = &self.nodes[index].dot_id;
+
+        let is_valid = dot_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !RESERVED_IDS.contains(&dot_id.to_ascii_lowercase().as_str());
+        if is_valid {
+            dot_id.clone()
+        } else {
+            format!("node{}", index)
+        }
+    }
+
+    fn write_cluster(&mut self, ret: &mut String, cluster: &Cluster, depth: usize) {
+        let indent = "    ".repeat(depth);
+
+        for &index in &cluster.nodes {
+            let node = &self.nodes[index];
+            let label = find_attr(&node.attrs, "label").unwrap_or(&node.dot_id);
+            let label = escape_label(&label.replace("\\N", &node.dot_id));
+            let (open, close) = shape_brackets(find_attr(&node.attrs, "shape"));
+            writeln!(
+                ret,
+                "{}{}{}\"{}\"{}",
+                indent,
+                self.mermaid_id(index),
+                open,
+                label,
+                close
+            )
+            .unwrap();
+        }
+
+        for child in &cluster.children {
+            self.n_clusters += 1;
+            let id = format!("cluster{}", self.n_clusters);
+            match &child.label {
+                Some(label) => {
+                    writeln!(
+                        ret,
+                        "{}subgraph {}[\"{}\"]",
+                        indent,
+                        id,
+                        escape_label(label)
+                    )
+                }
+                None => writeln!(ret, "{}subgraph {}[\" \"]", indent, id),
+            }
+            .unwrap();
+            self.write_cluster(ret, child, depth + 1);
+            writeln!(ret, "{}end", indent).unwrap();
+        }
+    }
+
+    fn write_edge(&self, ret: &mut String, edge: &Edge) {
+        let style = find_attr(&edge.attrs, "style").unwrap_or_default();
+        let is_directed = self.is_directed && find_attr(&edge.attrs, "dir") != Some("none");
+
+        let arrow = match (style, is_directed) {
+            (s, true) if s.contains("dashed") || s.contains("dotted") => "-.->",
+            (s, false) if s.contains("dashed") || s.contains("dotted") => "-.-",
+            (s, true) if s.contains("bold") => "==>",
+            (s, false) if s.contains("bold") => "===",
+            (_, true) => "-->",
+            (_, false) => "---",
+        };
+
+        let source = self.mermaid_id(edge.source);
+        let target = self.mermaid_id(edge.target);
+        match find_attr(&edge.attrs, "label").filter(|label| !label.is_empty()) {
+            Some(label) => writeln!(
+                ret,
+                "    {} {}|\"{}\"| {}",
+                source,
+                arrow,
+                escape_label(label),
+                target
+            ),
+            None => writeln!(ret, "    {} {} {}", source, arrow, target),
+        }
+        .unwrap();
+    }
+}
+
+impl Cluster {
+    fn all_nodes(&self) -> Vec<usize> {
+        let mut nodes = self.nodes.clone();
+        for child in &self.children {
+            nodes.extend(child.all_nodes());
+        }
+        nodes
+    }
+}
+
+/// Returns the IDs of all nodes referenced in `subgraph`.
+fn subgraph_node_ids(subgraph: &Subgraph) -> impl Iterator<Item = &str> {
+    let mut ids = Vec::new();
+    let mut stack = vec![&subgraph.stmts];
+
+    while let Some(stmts) = stack.pop() {
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::Node(node_id, _) => ids.push(node_id.id.as_str()),
+                StmtKind::Edge(operands, _) => {
+                    for operand in operands {
+                        match operand {
+                            EdgeOperand::Node(node_id) => ids.push(node_id.id.as_str()),
+                            EdgeOperand::Subgraph(subgraph) => stack.push(&subgraph.stmts),
+                        }
+                    }
+                }
+                StmtKind::Subgraph(subgraph) => stack.push(&subgraph.stmts),
+                StmtKind::Attr(..) | StmtKind::Assign(_) => {}
+            }
+        }
+    }
+
+    ids.into_iter()
+}
+
+/// Returns the brackets around a label for the closest Mermaid equivalent of
+/// the DOT `shape`.
+fn shape_brackets(shape: Option<&str>) -> (&'static str, &'static str) {
+    match shape.unwrap_or("ellipse") {
+        "box" | "rect" | "rectangle" | "square" | "plain" | "plaintext" | "none" => ("[", "]"),
+        "circle" | "point" => ("((", "))"),
+        "doublecircle" => ("(((", ")))"),
+        "diamond" => ("{", "}"),
+        "hexagon" => ("{{", "}}"),
+        "cylinder" => ("[(", ")]"),
+        "parallelogram" => ("[/", "/]"),
+        "trapezium" => ("[/", "\\]"),
+        "invtrapezium" => ("[\\", "/]"),
+        "cds" | "rarrow" => (">", "]"),
+        _ => ("(", ")"),
+    }
+}
+
+/// Escapes `label` to be put within double quotes, converting DOT line breaks
+/// and stripping the tags of HTML labels.
+fn escape_label(label: &str) -> String {
+    let label = match label.strip_prefix('<').and_then(|l| l.strip_suffix('>')) {
+        Some(html) => strip_html_tags(html),
+        None => label
+            .replace("\\n", "<br>")
+            .replace("\\l", "<br>")
+            .replace("\\r", "<br>"),
+    };
+
+    let label = label.trim_end_matches("<br>");
+    label.replace('"', "#quot;")
+}
+
+fn strip_html_tags(html: &str) -> String {
+    let mut ret = String::new();
+    let mut is_in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => is_in_tag = true,
+            '>' => is_in_tag = false,
+            c if !is_in_tag => ret.push(c),
+            _ => {}
+        }
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple() {
+        assert_eq!(
+            from_dot(
+                r#"digraph {
+                    rankdir=LR
+                    node [shape=box]
+                    a [label="Say \"hi\"\n"]
+                    a -> "b c" [label=yes]
+                    "b c" -> end [style=dashed]
+                }"#
+            )
+            .unwrap(),
+            "graph LR\n    a[\"Say #quot;hi#quot;\"]\n    node1[\"b c\"]\n    node2[\"end\"]\n    a -->|\"yes\"| node1\n    node1 -.-> node2\n"
+        );
+    }
+
+    #[test]
+    fn undirected_chain() {
+        assert_eq!(
+            from_dot("graph { a -- b -- { c d } [dir=none] }").unwrap(),
+            "graph TD\n    a(\"a\")\n    b(\"b\")\n    c(\"c\")\n    d(\"d\")\n    a --- b\n    b --- c\n    b --- d\n"
+        );
+    }
+
+    #[test]
+    fn clusters() {
+        assert_eq!(
+            from_dot(
+                "digraph { a; subgraph cluster_x { label=<<b>X</b>>; a; b [shape=diamond] } a -> b }"
+            )
+            .unwrap(),
+            "graph TD\n    a(\"a\")\n    subgraph cluster1[\"X\"]\n        b{\"b\"}\n    end\n    a --> b\n"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(from_dot("digraph {").is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::HashSet;
+
+use adw::prelude::*;
+use gettextrs::gettext;
+use gtk::glib::{self, clone};
+
+const CANCEL_RESPONSE_ID: &str = "cancel";
+const ADD_RESPONSE_ID: &str = "add";
+
+/// Asks the user for the name of a new node, suggesting `suggested_name`.
+///
+/// Names in `existing_names` can't be used. Returns `None` if the user
+/// cancelled the dialog.
+pub async fn run(
+    parent: &impl IsA<gtk::Widget>,
+    suggested_name: &str,
+    existing_names: HashSet<String>,
+) -> Option<String> {
+    let dialog = adw::AlertDialog::builder()
+        .heading(gettext("Add Node"))
+        .close_response(CANCEL_RESPONSE_ID)
+        .default_response(ADD_RESPONSE_ID)
+        .build();
+
+    dialog.add_response(CANCEL_RESPONSE_ID, &gettext("Cancel"));
+    dialog.add_response(ADD_RESPONSE_ID, &gettext("_Add"));
+
+    dialog.set_response_appearance(ADD_RESPONSE_ID, adw::ResponseAppearance::Suggested);
+
+    let entry = gtk::Entry::builder()
+        .text(suggested_name)
+        .activates_default(true)
+        .build();
+    entry.connect_changed(clone!(
+        #[weak]
+        dialog,
+        move |entry| {
+            let text = entry.text();
+            let name = text.trim();
+            let is_valid = !name.is_empty() && !existing_names.contains(name);
+            dialog.set_response_enabled(ADD_RESPONSE_ID, is_valid);
+        }
+    ));
+    entry.connect_map(|entry| {
+        entry.select_region(0, -1);
+    });
+    dialog.set_extra_child(Some(&entry));
+
+    match dialog.choose_future(parent).await.as_str() {
+        CANCEL_RESPONSE_ID => None,
+        ADD_RESPONSE_ID => Some(entry.text().trim().to_string()),
+        _ => unreachable!(),
+    }
+}
@@ -1,24 +1,60 @@
-use std::{sync::LazyLock, time::Duration};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fs,
+    rc::Rc,
+    slice,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
 
 use adw::prelude::*;
-use anyhow::{Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use futures_channel::oneshot;
+use futures_util::future::{self, Either};
 use gettextrs::gettext;
 use gtk::{
     gdk, gdk_pixbuf, gio,
-    glib::{self, clone, closure},
+    glib::{self, clone, closure, closure_local},
     subclass::prelude::*,
 };
 use gtk_source::prelude::*;
 use regex::Regex;
 
 use crate::{
-    document::Document, export_format::ExportFormat, graph_view::LayoutEngine, utils,
+    adjacency_matrix, diff, diff_dialog,
+    document::Document,
+    document_portal, dot, export_engine_dialog,
+    export_format::ExportFormat,
+    export_formats_dialog, graph_style,
+    graph_view::{ClickedElement, EditTool, LayoutEngine},
+    i18n::{format_number, gettext_f, ngettext_f},
+    layout_suggestion, mermaid, node_name_dialog, rename_dialog,
+    session::Session,
+    thumbnail, utils, view_settings,
     window::Window,
+    Application, APP_CACHE_DIR,
 };
 
 const DRAW_GRAPH_PRIORITY: glib::Priority = glib::Priority::DEFAULT_IDLE;
 const DRAW_GRAPH_INTERVAL: Duration = Duration::from_secs(1);
 
+/// Number of nodes and edges from which a graph takes long enough to lay out
+/// that a simplified version is shown first.
+const PROGRESSIVE_DRAW_MIN_N_ELEMENTS: usize = 2000;
+
+const WAIT_FOR_GRAPH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What to do with exports whose file already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConflictResolution {
+    Overwrite,
+    /// Exports next to the existing file, under a numbered name.
+    KeepBoth,
+    /// Only exports the files that don't exist yet.
+    Skip,
+}
+
 static SYNTAX_ERROR_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"syntax error in line (\d+)").expect("Failed to compile regex"));
 
@@ -28,7 +64,12 @@ mod imp {
         marker::PhantomData,
     };
 
-    use crate::{error_gutter_renderer::ErrorGutterRenderer, graph_view::GraphView};
+    use glib::subclass::Signal;
+
+    use crate::{
+        attribute_inspector::AttributeInspector, attribute_palette::AttributePalette,
+        error_gutter_renderer::ErrorGutterRenderer, graph_view::GraphView,
+    };
 
     use super::*;
 
@@ -38,10 +79,20 @@ mod imp {
     pub struct Page {
         #[property(get = Self::title)]
         pub(super) title: PhantomData<String>,
+        /// Markup describing where the document is saved and whether it
+        /// has unsaved changes.
+        #[property(get = Self::tooltip)]
+        pub(super) tooltip: PhantomData<String>,
+        /// Extra text, other than the title, to match when searching for
+        /// the page.
+        #[property(get = Self::keyword)]
+        pub(super) keyword: PhantomData<String>,
         #[property(get = Self::is_busy)]
         pub(super) is_busy: PhantomData<bool>,
         #[property(get = Self::is_modified)]
         pub(super) is_modified: PhantomData<bool>,
+        #[property(get = Self::is_read_only)]
+        pub(super) is_read_only: PhantomData<bool>,
         #[property(get = Self::can_save)]
         pub(super) can_save: PhantomData<bool>,
         #[property(get = Self::can_discard_changes)]
@@ -50,7 +101,45 @@ mod imp {
         pub(super) can_export_graph: PhantomData<bool>,
         #[property(get = Self::can_open_containing_folder)]
         pub(super) can_open_containing_folder: PhantomData<bool>,
+        #[property(get = Self::can_rename)]
+        pub(super) can_rename: PhantomData<bool>,
+        #[property(get = Self::can_trash)]
+        pub(super) can_trash: PhantomData<bool>,
+        #[property(get = Self::auto_reload, set = Self::set_auto_reload, explicit_notify)]
+        pub(super) auto_reload: PhantomData<bool>,
+        /// Whether edges that are part of a cycle are marked as errors, for
+        /// graphs that must be acyclic, like dependency graphs.
+        #[property(get, set = Self::set_expect_acyclic, explicit_notify)]
+        pub(super) expect_acyclic: Cell<bool>,
+        /// Whether the preview settings are saved next to the document's
+        /// file, so others opening it get the same preview.
+        #[property(get, set = Self::set_shares_view_settings, explicit_notify)]
+        pub(super) shares_view_settings: Cell<bool>,
+        /// Whether a file is being loaded, including mounting it and
+        /// checking that it is text, before the document is replaced
+        #[property(get)]
+        pub(super) is_loading_file: Cell<bool>,
+        /// Whether a grid and the graph coordinates under the pointer are
+        /// shown, to help with setting positions and sizes by hand.
+        #[property(get, set = Self::set_shows_graph_grid, explicit_notify)]
+        pub(super) shows_graph_grid: Cell<bool>,
 
+        #[template_child]
+        pub(super) deleted_banner: TemplateChild<adw::Banner>,
+        #[template_child]
+        pub(super) not_dot_banner: TemplateChild<adw::Banner>,
+        #[template_child]
+        pub(super) memory_limit_banner: TemplateChild<adw::Banner>,
+        #[template_child]
+        pub(super) layout_engine_suggestion_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub(super) layout_engine_suggestion_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub(super) layout_engine_suggestion_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) file_changed_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub(super) split_view: TemplateChild<adw::OverlaySplitView>,
         #[template_child]
         pub(super) paned: TemplateChild<gtk::Paned>,
         #[template_child]
@@ -60,6 +149,12 @@ mod imp {
         #[template_child]
         pub(super) view: TemplateChild<gtk_source::View>,
         #[template_child]
+        pub(super) graph_toolbar_view: TemplateChild<adw::ToolbarView>,
+        #[template_child]
+        pub(super) add_node_tool_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub(super) add_edge_tool_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
         pub(super) graph_view: TemplateChild<GraphView>,
         #[template_child]
         pub(super) layout_engine_drop_down: TemplateChild<gtk::DropDown>,
@@ -67,15 +162,33 @@ mod imp {
         pub(super) zoom_level_button: TemplateChild<gtk::Button>,
         #[template_child]
         pub(super) spinner_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub(super) attribute_inspector: TemplateChild<AttributeInspector>,
 
         pub(super) error_gutter_renderer: ErrorGutterRenderer,
+        pub(super) attribute_palette: AttributePalette,
         pub(super) line_with_error: Cell<Option<u32>>,
+        pub(super) lines_with_cycle: RefCell<Vec<u32>>,
+        pub(super) suggested_layout_engine: Cell<Option<LayoutEngine>>,
+        pub(super) is_layout_engine_suggestion_dismissed: Cell<bool>,
 
         pub(super) document_bindings: glib::BindingGroup,
         pub(super) document_signals: OnceCell<glib::SignalGroup>,
 
         pub(super) queued_draw_graph: Cell<bool>,
+        /// Woken up whenever the document stops or starts being busy, or the
+        /// graph is drawn, so waiting for the graph doesn't poll.
+        pub(super) graph_state_waiters: RefCell<Vec<oneshot::Sender<()>>>,
         pub(super) draw_graph_timeout_cancellable: RefCell<Option<gio::Cancellable>>,
+        pub(super) graph_text_scale_handler_id: RefCell<Option<glib::SignalHandlerId>>,
+
+        /// Element clicked in the graph, which is inspected instead of the
+        /// one at the cursor until the cursor is moved.
+        pub(super) clicked_element: RefCell<Option<dot::Element>>,
+        pub(super) inspected_element: RefCell<Option<dot::Element>>,
+        pub(super) queued_update_attribute_inspector: Cell<bool>,
+        /// Element whose attributes are shown in the palette.
+        pub(super) palette_element: RefCell<Option<dot::Element>>,
     }
 
     #[glib::object_subclass]
@@ -95,6 +208,51 @@ mod imp {
                 imp.view.scroll_to_iter(&mut iter, 0.0, true, 0.0, 0.5);
             });
 
+            klass.install_action_async("page.reload-document", None, |obj, _, _| async move {
+                if let Err(err) = obj.document().load().await {
+                    tracing::error!("Failed to reload document: {:?}", err);
+                    obj.add_message_toast(&gettext("Failed to reload document"));
+                }
+            });
+
+            klass.install_action("page.keep-document-changes", None, |obj, _, _| {
+                obj.document().keep_changes();
+            });
+
+            klass.install_action_async("page.show-document-diff", None, |obj, _, _| async move {
+                if let Err(err) = obj.show_document_diff().await {
+                    tracing::error!("Failed to show changes: {:?}", err);
+                    obj.add_message_toast(&gettext("Failed to show changes"));
+                }
+            });
+
+            klass.install_property_action("page.toggle-auto-reload", "auto-reload");
+
+            klass.install_action("page.apply-layout-engine-suggestion", None, |obj, _, _| {
+                let imp = obj.imp();
+
+                if let Some(engine) = imp.suggested_layout_engine.get() {
+                    obj.set_layout_engine(engine);
+                }
+            });
+
+            klass.install_action(
+                "page.dismiss-layout-engine-suggestion",
+                None,
+                |obj, _, _| {
+                    let imp = obj.imp();
+
+                    imp.is_layout_engine_suggestion_dismissed.set(true);
+                    obj.update_layout_engine_suggestion(None);
+                },
+            );
+            klass.install_property_action("page.toggle-expect-acyclic", "expect-acyclic");
+            klass.install_property_action("page.toggle-graph-grid", "shows-graph-grid");
+            klass.install_property_action(
+                "page.toggle-shares-view-settings",
+                "shares-view-settings",
+            );
+
             klass.install_action_async("page.zoom-graph-in", None, |obj, _, _| async move {
                 if let Err(err) = obj.imp().graph_view.zoom_in().await {
                     tracing::error!("Failed to zoom in: {:?}", err);
@@ -113,6 +271,32 @@ mod imp {
                 }
             });
 
+            klass.install_action_async("page.restart-graph-view", None, |obj, _, _| async move {
+                if let Err(err) = obj.imp().graph_view.restart().await {
+                    tracing::error!("Failed to restart preview: {:?}", err);
+                    obj.add_message_toast(&gettext("Failed to restart preview"));
+                    return;
+                }
+
+                obj.queue_draw_graph();
+                obj.update_graph_edit_tool();
+                obj.update_graph_grid();
+            });
+
+            klass.install_action("page.toggle-attribute-inspector", None, |obj, _, _| {
+                let split_view = &obj.imp().split_view;
+                split_view.set_show_sidebar(!split_view.shows_sidebar());
+            });
+
+            klass.install_action("page.show-attribute-palette", None, |obj, _, _| {
+                obj.show_attribute_palette();
+            });
+
+            klass.add_binding_action(
+                gdk::Key::F9,
+                gdk::ModifierType::empty(),
+                "page.toggle-attribute-inspector",
+            );
             klass.add_binding_action(
                 gdk::Key::plus,
                 gdk::ModifierType::CONTROL_MASK,
@@ -175,6 +359,18 @@ mod imp {
                 .bind("is-busy", &*self.progress_bar, "visible")
                 .sync_create()
                 .build();
+            self.document_bindings
+                .bind("is-deleted", &*self.deleted_banner, "revealed")
+                .sync_create()
+                .build();
+            self.document_bindings
+                .bind(
+                    "is-externally-modified",
+                    &*self.file_changed_revealer,
+                    "reveal-child",
+                )
+                .sync_create()
+                .build();
 
             let document_signals = glib::SignalGroup::new::<Document>();
             document_signals.connect_local(
@@ -196,7 +392,60 @@ mod imp {
                     #[weak]
                     obj,
                     move |_, _| {
+                        obj.notify_tooltip();
+                        obj.notify_keyword();
                         obj.notify_can_open_containing_folder();
+                        obj.notify_can_rename();
+                        obj.notify_can_trash();
+                        obj.update_toggle_auto_reload_action();
+                        obj.update_toggle_shares_view_settings_action();
+                        // Carry over the layout engine on save as and rename.
+                        obj.remember_layout_engine();
+                        if obj.shares_view_settings() {
+                            obj.update_shared_view_settings();
+                        }
+                        obj.emit_state_changed();
+                    }
+                ),
+            );
+            document_signals.connect_notify_local(
+                Some("is-read-only"),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_, _| {
+                        obj.notify_is_read_only();
+                        obj.notify_tooltip();
+                    }
+                ),
+            );
+            document_signals.connect_notify_local(
+                Some("auto-reload"),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_, _| {
+                        obj.notify_auto_reload();
+                        obj.emit_state_changed();
+                    }
+                ),
+            );
+            document_signals.connect_local(
+                "mark-set",
+                false,
+                clone!(
+                    #[weak]
+                    obj,
+                    #[upgrade_or_panic]
+                    move |values| {
+                        let mark = values[2].get::<gtk::TextMark>().unwrap();
+                        if mark.name().as_deref() == Some("insert") {
+                            obj.imp().clicked_element.replace(None);
+                            obj.queue_update_attribute_inspector();
+                        }
+
+                        obj.emit_state_changed();
+                        None
                     }
                 ),
             );
@@ -217,7 +466,9 @@ mod imp {
                     obj,
                     move |_, _| {
                         obj.notify_is_modified();
+                        obj.notify_tooltip();
                         obj.notify_can_discard_changes();
+                        obj.update_thumbnail();
                     }
                 ),
             );
@@ -229,26 +480,70 @@ mod imp {
                     move |_, _| {
                         obj.notify_is_busy();
                         obj.notify_can_save();
+                        obj.notify_can_rename();
+                        obj.notify_can_trash();
+                        obj.wake_graph_state_waiters();
                     }
                 ),
             );
             self.document_signals.set(document_signals).unwrap();
 
+            self.not_dot_banner.connect_button_clicked(|banner| {
+                banner.set_revealed(false);
+            });
+
             self.layout_engine_drop_down
                 .set_expression(Some(&gtk::ClosureExpression::new::<glib::GString>(
                     &[] as &[gtk::Expression],
                     closure!(|list_item: adw::EnumListItem| list_item.name()),
                 )));
             self.layout_engine_drop_down
-                .set_model(Some(&adw::EnumListModel::new(LayoutEngine::static_type())));
+                .set_model(Some(&LayoutEngine::supported_model()));
+            let app = Application::get();
+            let settings = app.settings();
+            obj.set_layout_engine(settings.default_layout_engine());
+            let handler_id = settings.connect_graph_text_scale_changed(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.queue_draw_graph();
+                }
+            ));
+            self.graph_text_scale_handler_id.replace(Some(handler_id));
             self.layout_engine_drop_down.connect_selected_notify(clone!(
                 #[weak]
                 obj,
                 move |_| {
                     obj.queue_draw_graph();
+                    obj.remember_layout_engine();
+                    if obj.shares_view_settings() {
+                        obj.update_shared_view_settings();
+                    }
+                    obj.emit_state_changed();
                 }
             ));
 
+            self.paned.connect_position_notify(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.emit_state_changed();
+                }
+            ));
+            if let Some(vadjustment) = self.view.vadjustment() {
+                vadjustment.connect_value_changed(clone!(
+                    #[weak]
+                    obj,
+                    move |_| {
+                        obj.emit_state_changed();
+                    }
+                ));
+            }
+
+            // DOT is written left to right even in right-to-left locales,
+            // where only the paned is mirrored, putting the editor on the right.
+            self.view.set_direction(gtk::TextDirection::Ltr);
+
             let gutter = ViewExt::gutter(&*self.view, gtk::TextWindowType::Left);
             let was_inserted = gutter.insert(&self.error_gutter_renderer, 0);
             debug_assert!(was_inserted);
@@ -270,6 +565,14 @@ mod imp {
                     }
                 ));
 
+            self.graph_view
+                .bind_property(
+                    "exceeded-memory-limit",
+                    &*self.memory_limit_banner,
+                    "revealed",
+                )
+                .sync_create()
+                .build();
             self.graph_view.connect_is_graph_loaded_notify(clone!(
                 #[weak]
                 obj,
@@ -277,6 +580,45 @@ mod imp {
                     obj.notify_can_export_graph();
                 }
             ));
+            self.graph_view.connect_element_clicked(clone!(
+                #[weak]
+                obj,
+                move |_, element| {
+                    obj.handle_graph_element_clicked(element);
+                }
+            ));
+            self.graph_view.connect_add_node_request(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    utils::spawn(async move {
+                        if let Err(err) = obj.add_node_from_graph().await {
+                            tracing::error!("Failed to add node: {:?}", err);
+                            obj.add_message_toast(&gettext("Failed to add node"));
+                        }
+                    });
+                }
+            ));
+            self.graph_view.connect_add_edge_request(clone!(
+                #[weak]
+                obj,
+                move |_, tail, head| {
+                    if let Err(err) = obj.add_edge_from_graph(tail, head) {
+                        tracing::error!("Failed to add edge: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to add edge"));
+                    }
+                }
+            ));
+            self.graph_view.connect_delete_request(clone!(
+                #[weak]
+                obj,
+                move |_, element| {
+                    if let Err(err) = obj.delete_from_graph(element) {
+                        tracing::error!("Failed to delete from graph: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to delete"));
+                    }
+                }
+            ));
             self.graph_view.connect_error(clone!(
                 #[weak]
                 obj,
@@ -290,7 +632,10 @@ mod imp {
                 move |graph_view| {
                     if !graph_view.is_rendering() {
                         obj.imp().spinner_revealer.set_reveal_child(false);
+                        obj.update_thumbnail();
                     }
+
+                    obj.wake_graph_state_waiters();
                 }
             ));
             self.graph_view.connect_zoom_level_notify(clone!(
@@ -321,6 +666,104 @@ mod imp {
                     obj.update_reset_zoom_action();
                 }
             ));
+            self.graph_view.connect_is_initialized_notify(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    utils::spawn(clone!(
+                        #[weak]
+                        obj,
+                        async move {
+                            obj.hide_unsupported_layout_engines().await;
+                        }
+                    ));
+
+                    obj.update_graph_edit_tool();
+                    obj.update_graph_grid();
+                }
+            ));
+
+            self.graph_toolbar_view
+                .connect_reveal_top_bars_notify(clone!(
+                    #[weak]
+                    obj,
+                    move |_| {
+                        obj.update_graph_edit_tool();
+                    }
+                ));
+            for button in [&*self.add_node_tool_button, &*self.add_edge_tool_button] {
+                button.connect_active_notify(clone!(
+                    #[weak]
+                    obj,
+                    move |_| {
+                        obj.update_graph_edit_tool();
+                    }
+                ));
+            }
+
+            self.split_view.connect_show_sidebar_notify(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.queue_update_attribute_inspector();
+                }
+            ));
+            self.attribute_inspector.connect_attr_set_request(clone!(
+                #[weak]
+                obj,
+                move |_, key, value| {
+                    if let Err(err) = obj.set_inspected_attr(key, value) {
+                        tracing::error!("Failed to set attribute: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to set attribute"));
+                    }
+                }
+            ));
+            self.attribute_inspector.connect_attr_remove_request(clone!(
+                #[weak]
+                obj,
+                move |_, key| {
+                    if let Err(err) = obj.remove_inspected_attr(key) {
+                        tracing::error!("Failed to remove attribute: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to remove attribute"));
+                    }
+                }
+            ));
+
+            self.attribute_palette.set_parent(&*self.view);
+            self.attribute_palette.connect_attr_set_request(clone!(
+                #[weak]
+                obj,
+                move |_, key, value| {
+                    if key == "color" {
+                        Application::get().settings().add_recent_color(value);
+                    }
+
+                    if let Err(err) = obj.set_palette_attr(key, value) {
+                        tracing::error!("Failed to set attribute: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to set attribute"));
+                    }
+                }
+            ));
+            self.attribute_palette.connect_attr_remove_request(clone!(
+                #[weak]
+                obj,
+                move |_, key| {
+                    if let Err(err) = obj.remove_palette_attr(key) {
+                        tracing::error!("Failed to remove attribute: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to remove attribute"));
+                    }
+                }
+            ));
+
+            // The text view uses Ctrl+. to insert emojis, so the shortcut must
+            // be handled before it gets the key.
+            let shortcut_controller = gtk::ShortcutController::new();
+            shortcut_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+            shortcut_controller.add_shortcut(gtk::Shortcut::new(
+                gtk::ShortcutTrigger::parse_string("<Control>period"),
+                Some(gtk::NamedAction::new("page.show-attribute-palette")),
+            ));
+            obj.add_controller(shortcut_controller);
 
             utils::spawn_with_priority(
                 DRAW_GRAPH_PRIORITY,
@@ -344,8 +787,21 @@ mod imp {
         }
 
         fn dispose(&self) {
+            if let Some(handler_id) = self.graph_text_scale_handler_id.take() {
+                Application::get().settings().disconnect(handler_id);
+            }
+
+            self.attribute_palette.unparent();
+
             self.dispose_template();
         }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: LazyLock<Vec<Signal>> =
+                LazyLock::new(|| vec![Signal::builder("state-changed").build()]);
+
+            SIGNALS.as_ref()
+        }
     }
 
     impl WidgetImpl for Page {}
@@ -360,6 +816,35 @@ mod imp {
             }
         }
 
+        fn tooltip(&self) -> String {
+            let document = self.obj().document();
+
+            let Some(file) = document.file() else {
+                return glib::markup_escape_text(&gettext("Draft")).to_string();
+            };
+
+            let status = if document.is_read_only() {
+                gettext("Read-Only")
+            } else if document.is_modified() {
+                gettext("Modified")
+            } else {
+                gettext("Saved")
+            };
+            format!(
+                "{}\n<small>{}</small>",
+                glib::markup_escape_text(&utils::display_file(&file)),
+                glib::markup_escape_text(&status)
+            )
+        }
+
+        fn keyword(&self) -> String {
+            self.obj()
+                .document()
+                .file()
+                .map(|file| utils::display_file(&file))
+                .unwrap_or_default()
+        }
+
         fn is_busy(&self) -> bool {
             self.obj().document().is_busy()
         }
@@ -368,6 +853,10 @@ mod imp {
             self.obj().document().is_modified()
         }
 
+        fn is_read_only(&self) -> bool {
+            self.obj().document().is_read_only()
+        }
+
         fn can_save(&self) -> bool {
             !self.obj().document().is_busy()
         }
@@ -383,6 +872,66 @@ mod imp {
         fn can_open_containing_folder(&self) -> bool {
             self.obj().document().file().is_some()
         }
+
+        fn can_rename(&self) -> bool {
+            let document = self.obj().document();
+            document.file().is_some() && !document.is_busy()
+        }
+
+        fn can_trash(&self) -> bool {
+            let document = self.obj().document();
+            document.file().is_some() && !document.is_busy()
+        }
+
+        fn auto_reload(&self) -> bool {
+            self.obj().document().auto_reload()
+        }
+
+        fn set_auto_reload(&self, auto_reload: bool) {
+            self.obj().document().set_auto_reload(auto_reload);
+        }
+
+        fn set_expect_acyclic(&self, expect_acyclic: bool) {
+            let obj = self.obj();
+
+            if expect_acyclic == obj.expect_acyclic() {
+                return;
+            }
+
+            self.expect_acyclic.set(expect_acyclic);
+            obj.notify_expect_acyclic();
+
+            let contents = obj.document().contents();
+            obj.update_cycle_errors(&contents, dot::parse(&contents).ok().as_ref());
+            obj.emit_state_changed();
+        }
+
+        fn set_shares_view_settings(&self, shares_view_settings: bool) {
+            let obj = self.obj();
+
+            if shares_view_settings == obj.shares_view_settings() {
+                return;
+            }
+
+            self.shares_view_settings.set(shares_view_settings);
+            obj.notify_shares_view_settings();
+
+            obj.update_shared_view_settings();
+        }
+
+        fn set_shows_graph_grid(&self, shows_graph_grid: bool) {
+            let obj = self.obj();
+
+            if shows_graph_grid == obj.shows_graph_grid() {
+                return;
+            }
+
+            self.shows_graph_grid.set(shows_graph_grid);
+            obj.notify_shows_graph_grid();
+
+            obj.update_graph_grid();
+            obj.emit_state_changed();
+        }
     }
 }
 
@@ -396,10 +945,56 @@ impl Page {
         glib::Object::new()
     }
 
+    /// Emitted when state stored in the session, like the layout engine or
+    /// the selection, changes.
+    pub fn connect_state_changed<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_closure(
+            "state-changed",
+            false,
+            closure_local!(|obj: &Self| {
+                f(obj);
+            }),
+        )
+    }
+
     pub async fn load_file(&self, file: gio::File) -> Result<()> {
+        let imp = self.imp();
+
+        imp.is_loading_file.set(true);
+        self.notify_is_loading_file();
+
+        let res = self.load_file_inner(file).await;
+
+        imp.is_loading_file.set(false);
+        self.notify_is_loading_file();
+
+        res
+    }
+
+    async fn load_file_inner(&self, file: gio::File) -> Result<()> {
+        utils::ensure_file_mounted(&file, self.window().as_ref()).await?;
+        utils::ensure_text_file(&file).await?;
+        utils::resolve_file_identity(&file).await;
+
         let document = Document::for_file(file);
+        document.set_auto_reload(self.auto_reload());
         self.set_document(&document);
+
+        // The settings of the previous file must not be shared with this one.
+        self.imp().shares_view_settings.set(false);
+        self.notify_shares_view_settings();
+
         document.load().await?;
+
+        // Files from the "All Text Files" filter may not be DOT at all.
+        let contents = document.contents();
+        self.imp()
+            .not_dot_banner
+            .set_revealed(!contents.trim().is_empty() && !utils::looks_like_dot(&contents));
+
         Ok(())
     }
 
@@ -408,17 +1003,31 @@ impl Page {
 
         let document = self.document();
 
-        if document.is_draft() {
+        // Files that can't be written to, like those over `https://` or on
+        // read-only mounts, must be saved somewhere else. So must deleted
+        // files, rather than silently bringing them back.
+        if document.is_draft() || document.is_read_only() || document.is_deleted() {
             let dialog = gtk::FileDialog::builder()
                 .title(gettext("Save Document"))
-                .filters(&utils::graphviz_file_filters())
+                .filters(&utils::graphviz_save_file_filters())
                 .modal(true)
                 .initial_name(format!("{}.gv", document.title()))
                 .build();
+            if document.is_read_only() {
+                dialog.set_initial_folder(Some(&utils::default_save_folder()));
+            }
             let file = dialog.save_future(Some(&self.window().unwrap())).await?;
 
             document.save_as(&file).await?;
         } else {
+            if document.is_externally_modified()
+                && !confirm_overwrite_external_changes(&self.window().unwrap()).await
+            {
+                return Err(
+                    glib::Error::new(gtk::DialogError::Dismissed, "Overwrite cancelled").into(),
+                );
+            }
+
             document.save().await?;
         }
 
@@ -432,7 +1041,7 @@ impl Page {
 
         let dialog = gtk::FileDialog::builder()
             .title(gettext("Save Document As"))
-            .filters(&utils::graphviz_file_filters())
+            .filters(&utils::graphviz_save_file_filters())
             .modal(true)
             .initial_name(format!("{}.gv", document.title()))
             .build();
@@ -443,33 +1052,184 @@ impl Page {
         Ok(())
     }
 
-    pub async fn discard_changes(&self) -> Result<()> {
-        debug_assert!(self.can_discard_changes());
+    pub async fn rename_document(&self) -> Result<()> {
+        debug_assert!(self.can_rename());
 
         let document = self.document();
-        document.discard_changes().await?;
+        let file = document.file().context("No file")?;
+
+        let current_name = file
+            .basename()
+            .context("File has no basename")?
+            .to_string_lossy()
+            .to_string();
+        let Some(new_name) = rename_dialog::run(&self.window().unwrap(), &current_name).await
+        else {
+            return Err(glib::Error::new(gtk::DialogError::Dismissed, "Rename cancelled").into());
+        };
+
+        document.rename(&new_name).await?;
 
         Ok(())
     }
 
-    pub async fn open_containing_folder(&self) -> Result<()> {
-        debug_assert!(self.can_open_containing_folder());
+    pub async fn save_document_copy(&self) -> Result<()> {
+        debug_assert!(self.can_save());
 
-        let file = self.document().file().context("No file")?;
+        let document = self.document();
 
-        let file_launcher = gtk::FileLauncher::new(Some(&file));
-        file_launcher
-            .open_containing_folder_future(Some(&self.window().unwrap()))
-            .await?;
+        let dialog = gtk::FileDialog::builder()
+            .title(gettext("Save a Copy"))
+            .filters(&utils::graphviz_save_file_filters())
+            .modal(true)
+            .initial_name(format!("{}.gv", document.title()))
+            .build();
+        let file = dialog.save_future(Some(&self.window().unwrap())).await?;
+
+        document.save_copy(&file).await?;
+
+        tracing::debug!(uri = %file.uri(), "Document copy saved");
 
         Ok(())
     }
 
-    pub async fn export_graph(&self, format: ExportFormat) -> Result<()> {
-        debug_assert!(self.can_export_graph());
+    pub async fn discard_changes(&self) -> Result<()> {
+        debug_assert!(self.can_discard_changes());
+
+        let document = self.document();
+        document.discard_changes().await?;
+
+        Ok(())
+    }
+
+    pub async fn open_containing_folder(&self) -> Result<()> {
+        debug_assert!(self.can_open_containing_folder());
+
+        let file = self.document().file().context("No file")?;
+        let window = self.window().unwrap();
+
+        let file_launcher = gtk::FileLauncher::new(Some(&file));
+        match file_launcher
+            .open_containing_folder_future(Some(&window))
+            .await
+        {
+            Ok(()) => Ok(()),
+            Err(err) if !err.matches(gtk::DialogError::Dismissed) => {
+                // Older portals can't show where files exported by the
+                // document portal are, so open their folder on the host instead.
+                let Some(folder) = document_portal::host_path(&file)
+                    .and_then(|host_path| host_path.parent().map(gio::File::for_path))
+                else {
+                    return Err(err.into());
+                };
+
+                tracing::debug!(
+                    "Failed to open containing folder, opening host folder instead: {:?}",
+                    err
+                );
+
+                let uri_launcher = gtk::UriLauncher::new(&folder.uri());
+                uri_launcher.launch_future(Some(&window)).await?;
+
+                Ok(())
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
 
+    /// Moves the keyboard focus to the editor, so typing goes to the document.
+    pub fn focus_editor(&self) {
         let imp = self.imp();
 
+        if !imp.view.grab_focus() {
+            tracing::debug!("Failed to focus editor");
+        }
+    }
+
+    /// Moves the cursor to the 1-based `line` and `column`, clamped to the
+    /// document's contents, and scrolls it to the center of the editor.
+    pub fn place_cursor_at(&self, line: u32, column: u32) {
+        let imp = self.imp();
+        let document = self.document();
+
+        let line = line.saturating_sub(1) as i32;
+        let mut iter = document
+            .iter_at_line(line)
+            .unwrap_or_else(|| document.end_iter());
+
+        let mut line_end = iter;
+        if !line_end.ends_line() {
+            line_end.forward_to_line_end();
+        }
+        let column = (column.saturating_sub(1) as i32).min(line_end.line_offset());
+        iter.set_line_offset(column);
+
+        document.place_cursor(&iter);
+        imp.view
+            .scroll_to_mark(&document.get_insert(), 0.0, true, 0.0, 0.5);
+    }
+
+    pub fn copy_file_path(&self) -> Result<()> {
+        debug_assert!(self.can_open_containing_folder());
+
+        let file = self.document().file().context("No file")?;
+        let path = file
+            .path()
+            .map_or_else(|| file.uri().to_string(), |path| path.display().to_string());
+        self.clipboard().set_text(&path);
+
+        self.add_message_toast(&gettext("Copied file path"));
+
+        Ok(())
+    }
+
+    pub async fn export_graph(&self, format: ExportFormat) -> Result<()> {
+        debug_assert!(self.can_export_graph());
+
+        let file = self.choose_export_file(format).await?;
+
+        self.write_graph(format, &file).await?;
+
+        self.add_exported_toast(&gettext("Graph exported"), &file);
+
+        tracing::debug!(uri = %file.uri(), "Graph exported");
+
+        Ok(())
+    }
+
+    /// Exports the graph laid out with another engine than the preview's,
+    /// like a slower one that suits the final graph better.
+    pub async fn export_graph_with_engine(&self) -> Result<()> {
+        debug_assert!(self.can_export_graph());
+
+        let Some((layout_engine, format)) =
+            export_engine_dialog::run(&self.window().unwrap(), self.layout_engine()).await
+        else {
+            return Ok(());
+        };
+
+        let file = self.choose_export_file(format).await?;
+
+        let svg_bytes = self.layout_svg_bytes(layout_engine).await?;
+        let svg_bytes = Self::style_export_svg_bytes(svg_bytes).await?;
+        let bytes = Self::convert_svg_bytes(svg_bytes, format)?;
+        file.replace_contents_future(
+            bytes,
+            None,
+            false,
+            gio::FileCreateFlags::REPLACE_DESTINATION,
+        )
+        .await
+        .map_err(|(_, err)| err)?;
+
+        self.add_exported_toast(&gettext("Graph exported"), &file);
+
+        tracing::debug!(uri = %file.uri(), ?layout_engine, "Graph exported");
+
+        Ok(())
+    }
+
+    async fn choose_export_file(&self, format: ExportFormat) -> Result<gio::File> {
         let filter = gtk::FileFilter::new();
         filter.set_name(Some(&format.name()));
         filter.add_mime_type(format.mime_type());
@@ -483,37 +1243,125 @@ impl Page {
         let dialog = gtk::FileDialog::builder()
             .title(gettext("Export Graph"))
             .accept_label(gettext("_Export"))
-            .initial_name(format!("{}.{}", document.title(), format.extension()))
+            .initial_name(format.file_name(&document.title()))
             .filters(&filters)
             .modal(true)
             .build();
         let file = dialog.save_future(Some(&self.window().unwrap())).await?;
 
-        let svg_bytes = imp.graph_view.get_svg().await?;
+        Ok(file)
+    }
 
-        let bytes = match format {
-            ExportFormat::Svg => svg_bytes,
-            ExportFormat::Png | ExportFormat::Jpeg => {
-                // TODO improve resolution
+    /// Exports the graph to `file` without asking, once the graph shows the
+    /// document's current contents.
+    pub async fn export_graph_to(&self, format: ExportFormat, file: &gio::File) -> Result<()> {
+        self.wait_for_graph().await?;
+        self.write_graph(format, file).await?;
 
-                let loader = gdk_pixbuf::PixbufLoader::new();
-                loader
-                    .write_bytes(&svg_bytes)
-                    .context("Failed to write SVG bytes")?;
-                loader.close().context("Failed to close loader")?;
-                let pixbuf = loader.pixbuf().context("Loader has no pixbuf")?;
+        tracing::debug!(uri = %file.uri(), "Graph exported");
 
-                let pixbuf_type = match format {
-                    ExportFormat::Png => "png",
-                    ExportFormat::Jpeg => "jpeg",
-                    ExportFormat::Svg => unreachable!(),
-                };
-                let buffer = pixbuf.save_to_bufferv(pixbuf_type, &[])?;
+        Ok(())
+    }
 
-                glib::Bytes::from_owned(buffer)
-            }
+    /// Exports the graph to several formats at once, all in a single folder.
+    pub async fn export_graph_multiple(&self) -> Result<()> {
+        debug_assert!(self.can_export_graph());
+
+        let window = self.window().unwrap();
+
+        let Some(formats) = export_formats_dialog::run(&window).await else {
+            return Ok(());
         };
+        debug_assert!(!formats.is_empty());
+
+        let dialog = gtk::FileDialog::builder()
+            .title(gettext("Export Graph"))
+            .accept_label(gettext("_Export"))
+            .modal(true)
+            .build();
+        let folder = dialog.select_folder_future(Some(&window)).await?;
+
+        let stem = self.document().title().replace('/', "-");
+        let mut targets = formats
+            .into_iter()
+            .map(|format| (format, folder.child(format.file_name(&stem))))
+            .collect::<Vec<_>>();
+
+        let mut is_existing = Vec::with_capacity(targets.len());
+        for (_, file) in &targets {
+            is_existing.push(utils::file_exists(file).await);
+        }
+        let n_existing = is_existing
+            .iter()
+            .filter(|is_existing| **is_existing)
+            .count();
+        if n_existing > 0 {
+            let Some(resolution) = choose_conflict_resolution(&window, n_existing).await else {
+                return Ok(());
+            };
+            match resolution {
+                ConflictResolution::Overwrite => {}
+                ConflictResolution::KeepBoth => {
+                    for ((_, file), is_existing) in targets.iter_mut().zip(&is_existing) {
+                        if *is_existing {
+                            *file = utils::unique_file(file).await;
+                        }
+                    }
+                }
+                ConflictResolution::Skip => {
+                    let mut is_existing = is_existing.iter();
+                    targets.retain(|_| !is_existing.next().unwrap());
+                }
+            }
+        }
+
+        if targets.is_empty() {
+            self.add_message_toast(&gettext("All files already exist, nothing exported"));
+            return Ok(());
+        }
+
+        let svg_bytes = self.export_svg_bytes().await?;
+
+        for (format, file) in &targets {
+            let bytes = Self::convert_svg_bytes(svg_bytes.clone(), *format)?;
+
+            file.replace_contents_future(
+                bytes,
+                None,
+                false,
+                gio::FileCreateFlags::REPLACE_DESTINATION,
+            )
+            .await
+            .map_err(|(_, err)| err)?;
+
+            tracing::debug!(uri = %file.uri(), "Graph exported");
+        }
+
+        let (_, first_file) = targets.first().unwrap();
+        self.add_exported_many_toast(
+            &ngettext_f(
+                "Graph exported in {n_formats} format",
+                "Graph exported in {n_formats} formats",
+                targets.len() as u32,
+                &[("n_formats", &targets.len().to_string())],
+            ),
+            first_file,
+        );
+
+        Ok(())
+    }
+
+    /// Exports the graph to a temporary file and lets the user pick an app
+    /// to send it to through the `OpenURI` portal.
+    pub async fn share_graph(&self, format: ExportFormat) -> Result<()> {
+        debug_assert!(self.can_export_graph());
+
+        let bytes = self.graph_bytes(format).await?;
+
+        let dir = APP_CACHE_DIR.join("shared");
+        fs::create_dir_all(&dir)?;
 
+        let file = gio::File::for_path(dir.join(format.file_name(&self.title().replace('/', "-"))));
         file.replace_contents_future(
             bytes,
             None,
@@ -523,35 +1371,164 @@ impl Page {
         .await
         .map_err(|(_, err)| err)?;
 
-        let toast = adw::Toast::builder()
-            .title(gettext("Graph exported"))
-            .button_label(gettext("Show in Files"))
+        let file_launcher = gtk::FileLauncher::new(Some(&file));
+        file_launcher.set_always_ask(true);
+        file_launcher
+            .launch_future(Some(&self.window().unwrap()))
+            .await?;
+
+        tracing::debug!(uri = %file.uri(), "Graph shared");
+
+        Ok(())
+    }
+
+    pub async fn export_adjacency_matrix(&self) -> Result<()> {
+        let document = self.document();
+
+        let matrix = adjacency_matrix::from_dot(&document.contents())?;
+
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some(&gettext("CSV")));
+        filter.add_mime_type("text/csv");
+        filter.add_suffix("csv");
+
+        let filters = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+
+        let dialog = gtk::FileDialog::builder()
+            .title(gettext("Export Adjacency Matrix"))
+            .accept_label(gettext("_Export"))
+            .initial_name(format!("{}.csv", document.title()))
+            .filters(&filters)
+            .modal(true)
             .build();
-        toast.connect_button_clicked(clone!(
-            #[weak(rename_to = obj)]
-            self,
-            #[strong]
-            file,
-            move |_| {
-                let file_launcher = gtk::FileLauncher::new(Some(&file));
-                utils::spawn(async move {
-                    if let Err(err) = file_launcher
-                        .open_containing_folder_future(Some(&obj.window().unwrap()))
-                        .await
-                    {
-                        tracing::error!("Failed to show in Files: {:?}", err);
-                        obj.add_message_toast(&gettext("Failed to show in Files"));
-                    }
-                });
-            }
-        ));
-        self.add_toast(toast);
+        let file = dialog.save_future(Some(&self.window().unwrap())).await?;
 
-        tracing::debug!(uri = %file.uri(), "Graph exported");
+        file.replace_contents_future(
+            matrix.into_bytes(),
+            None,
+            false,
+            gio::FileCreateFlags::REPLACE_DESTINATION,
+        )
+        .await
+        .map_err(|(_, err)| err)?;
+
+        self.add_exported_toast(&gettext("Adjacency matrix exported"), &file);
+
+        tracing::debug!(uri = %file.uri(), "Adjacency matrix exported");
 
         Ok(())
     }
 
+    /// Merges the statements of another DOT file at the end of the graph.
+    pub async fn insert_file(&self) -> Result<()> {
+        let dialog = gtk::FileDialog::builder()
+            .title(gettext("Insert File"))
+            .accept_label(gettext("_Insert"))
+            .filters(&utils::graphviz_open_file_filters())
+            .modal(true)
+            .build();
+        let file = dialog.open_future(Some(&self.window().unwrap())).await?;
+
+        let (bytes, _) = file.load_contents_future().await?;
+        let other = String::from_utf8(bytes.to_vec()).context("File is not valid UTF-8")?;
+
+        let document = self.document();
+        let contents = document.contents();
+
+        let insertion = dot::merge(
+            &contents,
+            &other,
+            &gettext_f(
+                "Inserted from {file}",
+                &[("file", &utils::display_file_stem(&file))],
+            ),
+        )?;
+
+        let char_offset = contents[..insertion.offset].chars().count();
+        let mut iter = document.iter_at_offset(char_offset as i32);
+        document.begin_user_action();
+        document.insert(&mut iter, &insertion.text);
+        document.end_user_action();
+        document.place_cursor(&iter);
+
+        self.imp().view.scroll_mark_onscreen(&document.get_insert());
+
+        tracing::debug!(uri = %file.uri(), "File inserted");
+
+        Ok(())
+    }
+
+    /// Returns the innermost named subgraph at the cursor as a standalone
+    /// graph, or `None` if the cursor is not in one.
+    pub fn extract_subgraph_at_cursor(&self) -> Result<Option<String>> {
+        let contents = self.document().contents();
+        dot::extract_subgraph(&contents, self.cursor_byte_offset(&contents))
+    }
+
+    /// Converts the document to a Mermaid flowchart and copies it to the
+    /// clipboard.
+    pub fn copy_graph_as_mermaid(&self) -> Result<()> {
+        let mermaid = mermaid::from_dot(&self.document().contents())?;
+        self.clipboard().set_text(&mermaid);
+
+        self.add_message_toast(&gettext("Copied as Mermaid"));
+
+        Ok(())
+    }
+
+    async fn show_document_diff(&self) -> Result<()> {
+        let document = self.document();
+
+        let on_disk = document.load_file_contents().await?;
+        let diff = diff::unified(
+            &on_disk,
+            &document.contents(),
+            &gettext("On Disk"),
+            &gettext("Current"),
+        );
+
+        if diff.is_empty() {
+            self.add_message_toast(&gettext("Document is the same as the file on disk"));
+            return Ok(());
+        }
+
+        diff_dialog::present(self, &gettext("Changes"), &diff);
+
+        Ok(())
+    }
+
+    /// Replaces the document's contents with `contents` recovered from a
+    /// previous session, once the file is done loading.
+    pub async fn recover_contents(&self, contents: &str) {
+        // Until the file is mounted and checked, the document is yet to be
+        // replaced by the file's, so the contents would be lost with it.
+        while self.is_loading_file() || self.is_busy() {
+            let (tx, rx) = oneshot::channel();
+            let tx = Rc::new(RefCell::new(Some(tx)));
+            let handler_ids = ["is-loading-file", "is-busy"].map(|name| {
+                self.connect_notify_local(
+                    Some(name),
+                    clone!(
+                        #[strong]
+                        tx,
+                        move |_, _| {
+                            if let Some(tx) = tx.take() {
+                                let _ = tx.send(());
+                            }
+                        }
+                    ),
+                )
+            });
+            let _ = rx.await;
+            for handler_id in handler_ids {
+                self.disconnect(handler_id);
+            }
+        }
+
+        self.document().recover_contents(contents);
+    }
+
     pub fn document(&self) -> Document {
         self.imp().view.buffer().downcast().unwrap()
     }
@@ -564,9 +1541,105 @@ impl Page {
         self.imp().paned.position()
     }
 
+    /// Returns the vertical scroll offset of the editor.
+    pub fn scroll_offset(&self) -> f64 {
+        self.imp().view.vadjustment().map_or(0.0, |a| a.value())
+    }
+
+    /// Whether the cursor is within the visible area of the editor.
+    pub fn is_cursor_onscreen(&self) -> bool {
+        let imp = self.imp();
+
+        let document = self.document();
+        let iter = document.iter_at_mark(&document.get_insert());
+        let location = imp.view.iter_location(&iter);
+        imp.view.visible_rect().intersect(&location).is_some()
+    }
+
+    /// Scrolls the editor to `offset` once the document is laid out enough for
+    /// it, optionally scrolling further to keep the cursor onscreen.
+    ///
+    /// This is given up once the document is changed.
+    pub fn restore_scroll_offset(&self, offset: f64, keep_cursor_onscreen: bool) {
+        let imp = self.imp();
+
+        let Some(vadjustment) = imp.view.vadjustment() else {
+            return;
+        };
+
+        let try_restore = clone!(
+            #[weak(rename_to = obj)]
+            self,
+            #[upgrade_or]
+            true,
+            move |vadjustment: &gtk::Adjustment| {
+                if vadjustment.page_size() <= 0.0
+                    || vadjustment.upper() - vadjustment.page_size() < offset
+                {
+                    return false;
+                }
+
+                vadjustment.set_value(offset);
+
+                if keep_cursor_onscreen {
+                    let document = obj.document();
+                    obj.imp().view.scroll_mark_onscreen(&document.get_insert());
+                }
+
+                true
+            }
+        );
+
+        if try_restore(&vadjustment) {
+            return;
+        }
+
+        let handler_ids = Rc::new(RefCell::new(None));
+        let changed_handler_id = vadjustment.connect_changed(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            #[strong]
+            handler_ids,
+            move |vadjustment| {
+                if try_restore(vadjustment) {
+                    obj.disconnect_scroll_offset_handlers(vadjustment, &handler_ids);
+                }
+            }
+        ));
+        let document_changed_handler_id = self.document().connect_changed(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            #[weak]
+            vadjustment,
+            #[strong]
+            handler_ids,
+            move |_| {
+                obj.disconnect_scroll_offset_handlers(&vadjustment, &handler_ids);
+            }
+        ));
+        handler_ids.replace(Some((changed_handler_id, document_changed_handler_id)));
+    }
+
+    fn disconnect_scroll_offset_handlers(
+        &self,
+        vadjustment: &gtk::Adjustment,
+        handler_ids: &RefCell<Option<(glib::SignalHandlerId, glib::SignalHandlerId)>>,
+    ) {
+        if let Some((changed_handler_id, document_changed_handler_id)) = handler_ids.take() {
+            vadjustment.disconnect(changed_handler_id);
+            self.document().disconnect(document_changed_handler_id);
+        }
+    }
+
     pub fn set_layout_engine(&self, engine: LayoutEngine) {
         let imp = self.imp();
-        imp.layout_engine_drop_down.set_selected(engine as u32);
+
+        let model = imp.layout_engine_drop_down.model().unwrap();
+        if let Some(position) = engine.position_in(&model) {
+            imp.layout_engine_drop_down.set_selected(position);
+        } else {
+            tracing::warn!(?engine, "Layout engine is not supported");
+        }
     }
 
     pub fn layout_engine(&self) -> LayoutEngine {
@@ -603,14 +1676,243 @@ impl Page {
         }
     }
 
-    fn window(&self) -> Option<Window> {
-        self.root().map(|r| r.downcast().unwrap())
+    fn window(&self) -> Option<Window> {
+        self.root().map(|r| r.downcast().unwrap())
+    }
+
+    /// Tells that `file` was not opened as it is not text, offering to open
+    /// it in the default app if it is an image.
+    pub fn add_not_text_toast(&self, file: &gio::File, is_image: bool) {
+        let title = gettext_f(
+            // Translators: Do NOT translate the contents between '{' and '}', this is a variable name.
+            "“{name}” is not a text file",
+            &[("name", &utils::display_file_basename(file))],
+        );
+
+        if !is_image {
+            self.add_message_toast(&title);
+            return;
+        }
+
+        let toast = adw::Toast::builder()
+            .title(title)
+            .button_label(gettext("Open in Image Viewer"))
+            .build();
+        toast.connect_button_clicked(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            #[strong]
+            file,
+            move |_| {
+                let file_launcher = gtk::FileLauncher::new(Some(&file));
+                utils::spawn(async move {
+                    if let Err(err) = file_launcher
+                        .launch_future(Some(&obj.window().unwrap()))
+                        .await
+                    {
+                        tracing::error!("Failed to open image: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to open image"));
+                    }
+                });
+            }
+        ));
+        self.add_toast(toast);
+    }
+
+    /// Tells that `file` was exported, offering to open it in the default
+    /// app.
+    fn add_exported_toast(&self, title: &str, file: &gio::File) {
+        let toast = adw::Toast::builder()
+            .title(title)
+            .button_label(gettext("Open"))
+            .build();
+        toast.connect_button_clicked(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            #[strong]
+            file,
+            move |_| {
+                let file_launcher = gtk::FileLauncher::new(Some(&file));
+                utils::spawn(async move {
+                    if let Err(err) = file_launcher
+                        .launch_future(Some(&obj.window().unwrap()))
+                        .await
+                    {
+                        if err.matches(gtk::DialogError::Dismissed) {
+                            return;
+                        }
+
+                        tracing::error!("Failed to open exported file: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to open exported file"));
+                    }
+                });
+            }
+        ));
+        self.add_toast(toast);
+    }
+
+    /// Like `add_exported_toast`, but for several files, which are shown in
+    /// their folder instead.
+    fn add_exported_many_toast(&self, title: &str, file: &gio::File) {
+        let toast = adw::Toast::builder()
+            .title(title)
+            .button_label(gettext("Show in Files"))
+            .build();
+        toast.connect_button_clicked(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            #[strong]
+            file,
+            move |_| {
+                let file_launcher = gtk::FileLauncher::new(Some(&file));
+                utils::spawn(async move {
+                    if let Err(err) = file_launcher
+                        .open_containing_folder_future(Some(&obj.window().unwrap()))
+                        .await
+                    {
+                        tracing::error!("Failed to show in Files: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to show in Files"));
+                    }
+                });
+            }
+        ));
+        self.add_toast(toast);
+    }
+
+    async fn write_graph(&self, format: ExportFormat, file: &gio::File) -> Result<()> {
+        let bytes = self.graph_bytes(format).await?;
+
+        file.replace_contents_future(
+            bytes,
+            None,
+            false,
+            gio::FileCreateFlags::REPLACE_DESTINATION,
+        )
+        .await
+        .map_err(|(_, err)| err)?;
+
+        Ok(())
+    }
+
+    /// Waits until the document is loaded and the graph is drawn from its
+    /// latest contents.
+    async fn wait_for_graph(&self) -> Result<()> {
+        let imp = self.imp();
+
+        // Graphs of pages that were never shown are not drawn, and are laid
+        // out separately when exported.
+        let is_initialized = imp.graph_view.is_initialized();
+
+        let deadline = Instant::now() + WAIT_FOR_GRAPH_TIMEOUT;
+        while self.document().is_busy()
+            || (is_initialized && (imp.queued_draw_graph.get() || imp.graph_view.is_rendering()))
+        {
+            ensure!(
+                self.wait_for_graph_state_change(deadline).await,
+                "Timed out waiting for the graph to be drawn"
+            );
+        }
+
+        ensure!(
+            !is_initialized || imp.graph_view.is_graph_loaded(),
+            "Graph has errors"
+        );
+
+        Ok(())
+    }
+
+    async fn graph_bytes(&self, format: ExportFormat) -> Result<glib::Bytes> {
+        let svg_bytes = self.export_svg_bytes().await?;
+        Self::convert_svg_bytes(svg_bytes, format)
+    }
+
+    /// Returns the SVG of the graph, with the user's graph style sheet if it
+    /// shall be applied to exports.
+    ///
+    /// If the preview was never shown, the graph is laid out by the shared
+    /// renderer, so exporting many pages doesn't load a view for each.
+    async fn export_svg_bytes(&self) -> Result<glib::Bytes> {
+        let imp = self.imp();
+
+        let svg_bytes = if imp.graph_view.is_initialized() {
+            imp.graph_view.get_svg().await?
+        } else {
+            self.layout_svg_bytes(self.layout_engine()).await?
+        };
+
+        Self::style_export_svg_bytes(svg_bytes).await
+    }
+
+    /// Returns the SVG of the document's contents laid out with
+    /// `layout_engine`, regardless of what the preview shows.
+    async fn layout_svg_bytes(&self, layout_engine: LayoutEngine) -> Result<glib::Bytes> {
+        let imp = self.imp();
+
+        let app = Application::get();
+        let settings = app.settings();
+
+        let contents = self.document().contents();
+        let dot_src = dot::scale_default_font_size(&contents, settings.graph_text_scale());
+
+        // The preview's view is used if loaded, so another isn't loaded.
+        let graph_view = if imp.graph_view.is_initialized() {
+            imp.graph_view.get()
+        } else {
+            app.shared_graph_renderer()
+        };
+        graph_view.layout_svg(&dot_src, layout_engine).await
+    }
+
+    /// Adds the user's graph style sheet to `svg_bytes` if it shall be
+    /// applied to exports.
+    async fn style_export_svg_bytes(svg_bytes: glib::Bytes) -> Result<glib::Bytes> {
+        if !Application::get().settings().apply_graph_style_to_exports() {
+            return Ok(svg_bytes);
+        }
+
+        let Some(css) = graph_style::load().await? else {
+            return Ok(svg_bytes);
+        };
+
+        let styled_bytes =
+            graph_style::embed_in_svg(&svg_bytes, &css).context("SVG has no root element")?;
+        Ok(glib::Bytes::from_owned(styled_bytes))
+    }
+
+    fn convert_svg_bytes(svg_bytes: glib::Bytes, format: ExportFormat) -> Result<glib::Bytes> {
+        let bytes = match format.pixbuf_type() {
+            None => svg_bytes,
+            Some(pixbuf_type) => {
+                // TODO improve resolution
+
+                let scale = format.scale();
+
+                let loader = gdk_pixbuf::PixbufLoader::new();
+                if scale != 1 {
+                    loader.connect_size_prepared(move |loader, width, height| {
+                        loader.set_size(width * scale, height * scale);
+                    });
+                }
+                loader
+                    .write_bytes(&svg_bytes)
+                    .context("Failed to write SVG bytes")?;
+                loader.close().context("Failed to close loader")?;
+                let pixbuf = loader.pixbuf().context("Loader has no pixbuf")?;
+
+                let buffer = pixbuf.save_to_bufferv(pixbuf_type, &[])?;
+
+                glib::Bytes::from_owned(buffer)
+            }
+        };
+
+        Ok(bytes)
     }
 
     fn set_document(&self, document: &Document) {
         let imp = self.imp();
 
         imp.view.set_buffer(Some(document));
+        imp.not_dot_banner.set_revealed(false);
 
         imp.document_bindings.set_source(Some(document));
 
@@ -618,11 +1920,149 @@ impl Page {
         document_signals.set_target(Some(document));
 
         self.notify_title();
+        self.notify_tooltip();
+        self.notify_keyword();
         self.notify_is_busy();
         self.notify_is_modified();
+        self.notify_is_read_only();
         self.notify_can_save();
         self.notify_can_discard_changes();
         self.notify_can_open_containing_folder();
+        self.notify_can_rename();
+        self.notify_can_trash();
+        self.notify_auto_reload();
+
+        self.update_toggle_auto_reload_action();
+        self.update_toggle_shares_view_settings_action();
+    }
+
+    /// Updates the thumbnail of the document's file if the graph shows what
+    /// is saved in it.
+    fn update_thumbnail(&self) {
+        let imp = self.imp();
+
+        let document = self.document();
+        let Some(file) = document.file() else {
+            return;
+        };
+
+        if document.is_modified()
+            || document.is_busy()
+            || imp.queued_draw_graph.get()
+            || imp.graph_view.is_rendering()
+            || !imp.graph_view.is_graph_loaded()
+        {
+            return;
+        }
+
+        utils::spawn(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let uri = file.uri();
+
+                // Thumbnails are only shown for recents, and are deleted along
+                // with them.
+                let session = Session::instance();
+                let recents = session.recents().await;
+                if !recents.contains(&uri) {
+                    return;
+                }
+
+                let res = async {
+                    let svg_bytes = obj.imp().graph_view.get_svg().await?;
+                    thumbnail::save(&uri, &svg_bytes).await
+                };
+                if let Err(err) = res.await {
+                    tracing::warn!("Failed to update thumbnail: {:?}", err);
+                    return;
+                }
+
+                recents.update(&uri);
+            }
+        ));
+    }
+
+    /// Removes the engines that the bundled Graphviz can't run from the
+    /// drop-down, once it is known which ones those are.
+    async fn hide_unsupported_layout_engines(&self) {
+        let imp = self.imp();
+
+        if let Err(err) = imp.graph_view.supported_layout_engines().await {
+            tracing::warn!("Failed to get supported layout engines: {:?}", err);
+            return;
+        }
+
+        let model = imp
+            .layout_engine_drop_down
+            .model()
+            .and_downcast::<gtk::FilterListModel>()
+            .unwrap();
+        if let Some(filter) = model.filter() {
+            filter.changed(gtk::FilterChange::MoreStrict);
+        }
+    }
+
+    /// Applies the settings shared next to the document's file, if any,
+    /// over the remembered ones.
+    pub async fn load_shared_view_settings(&self) {
+        let Some(file) = self.document().file() else {
+            return;
+        };
+
+        match view_settings::load(&file).await {
+            Ok(Some(settings)) => {
+                if let Some(layout_engine) = settings.layout_engine() {
+                    self.set_layout_engine(layout_engine);
+                }
+
+                // Set after applying them, so they are not saved again.
+                self.imp().shares_view_settings.set(true);
+                self.notify_shares_view_settings();
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::warn!("Failed to load shared view settings: {:?}", err);
+            }
+        }
+    }
+
+    /// Saves the view settings next to the document's file, or deletes them
+    /// if they are no longer shared.
+    fn update_shared_view_settings(&self) {
+        let Some(file) = self.document().file() else {
+            return;
+        };
+
+        let shares_view_settings = self.shares_view_settings();
+        let layout_engine = self.layout_engine();
+        utils::spawn(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                let res = if shares_view_settings {
+                    view_settings::save_layout_engine(&file, layout_engine).await
+                } else {
+                    view_settings::delete(&file).await
+                };
+
+                if let Err(err) = res {
+                    tracing::error!("Failed to update shared view settings: {:?}", err);
+                    obj.add_message_toast(&gettext("Failed to update shared view settings"));
+                }
+            }
+        ));
+    }
+
+    fn remember_layout_engine(&self) {
+        if let Some(file) = self.document().file() {
+            let session = Session::instance();
+            session.remember_layout_engine(file.uri().into(), self.layout_engine());
+        }
+    }
+
+    fn emit_state_changed(&self) {
+        self.emit_by_name::<()>("state-changed", &[]);
     }
 
     fn queue_draw_graph(&self) {
@@ -658,15 +2098,99 @@ impl Page {
                 continue;
             }
 
+            // Previews of pages restored in the background only start once
+            // they are shown.
+            if !imp.graph_view.is_initialized() && !self.is_mapped() {
+                continue;
+            }
+
             imp.queued_draw_graph.set(false);
 
+            let contents = self.document().contents();
+            let graph = dot::parse(&contents).ok();
+            self.update_cycle_errors(&contents, graph.as_ref());
+            self.update_layout_engine_suggestion(graph.as_ref());
+
+            // Until the full graph is laid out, the structure of enormous ones
+            // is shown, instead of nothing. Later changes keep showing the
+            // previous graph in the meantime, so they don't need it.
+            if !imp.graph_view.is_graph_loaded() {
+                if let Some(graph) = graph.as_ref().filter(|graph| {
+                    let topology = graph.topology();
+                    topology.nodes.len() + topology.edges.len() >= PROGRESSIVE_DRAW_MIN_N_ELEMENTS
+                }) {
+                    if let Err(err) = imp
+                        .graph_view
+                        .set_data(&dot::simplified_source(graph), self.layout_engine())
+                        .await
+                    {
+                        tracing::error!("Failed to render simplified graph: {:?}", err);
+                    }
+
+                    // Otherwise, it would be abandoned for the full graph
+                    // before being shown.
+                    let deadline = Instant::now() + WAIT_FOR_GRAPH_TIMEOUT;
+                    while imp.graph_view.is_rendering() {
+                        if !self.wait_for_graph_state_change(deadline).await {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            let app = Application::get();
+            let settings = app.settings();
+            let layout_engine = self.layout_engine();
+
+            // The shown graph is the previous layout, so its positions are
+            // where the nodes start.
+            let mut positions = None;
+            if settings.incremental_layout()
+                && layout_engine.uses_initial_positions()
+                && imp.graph_view.is_graph_loaded()
+                && graph.is_some()
+            {
+                match imp.graph_view.node_positions().await {
+                    Ok(node_positions) => positions = Some(node_positions),
+                    Err(err) => tracing::warn!("Failed to get node positions: {:?}", err),
+                }
+            }
+            let contents = match (&graph, &positions) {
+                (Some(graph), Some(positions)) => {
+                    dot::with_initial_positions(&contents, graph, positions)
+                }
+                _ => Cow::Borrowed(contents.as_str()),
+            };
+
             if let Err(err) = imp
                 .graph_view
-                .set_data(&self.document().contents(), self.layout_engine())
+                .set_data(
+                    &dot::scale_default_font_size(&contents, settings.graph_text_scale()),
+                    layout_engine,
+                )
                 .await
             {
                 tracing::error!("Failed to render: {:?}", err);
             }
+
+            self.wake_graph_state_waiters();
+        }
+    }
+
+    /// Waits until the document stops or starts being busy, or the graph is
+    /// rendered or drawn, returning `false` if `deadline` passes first.
+    async fn wait_for_graph_state_change(&self, deadline: Instant) -> bool {
+        let (tx, rx) = oneshot::channel();
+        self.imp().graph_state_waiters.borrow_mut().push(tx);
+
+        let timeout = glib::timeout_future(deadline.saturating_duration_since(Instant::now()));
+        matches!(future::select(rx, timeout).await, Either::Left(_))
+    }
+
+    fn wake_graph_state_waiters(&self) {
+        let waiters = self.imp().graph_state_waiters.take();
+        for tx in waiters {
+            let _ = tx.send(());
         }
     }
 
@@ -674,11 +2198,432 @@ impl Page {
         let imp = self.imp();
 
         imp.error_gutter_renderer.clear_errors();
+        self.document().set_error_line(None);
 
         imp.line_with_error.set(None);
+        imp.lines_with_cycle.borrow_mut().clear();
         self.update_go_to_error_revealer_reveal_child();
 
         self.queue_draw_graph();
+        self.queue_update_attribute_inspector();
+
+        // Keep the unsaved contents stored in the session up to date.
+        self.emit_state_changed();
+    }
+
+    /// Returns the byte offset of the cursor in `contents`, the contents of
+    /// the document.
+    fn cursor_byte_offset(&self, contents: &str) -> usize {
+        contents
+            .char_indices()
+            .nth(self.document().cursor_position() as usize)
+            .map_or(contents.len(), |(index, _)| index)
+    }
+
+    /// Asks for the name of a node to add, after the background of the graph
+    /// was clicked while adding nodes.
+    async fn add_node_from_graph(&self) -> Result<()> {
+        let contents = self.document().contents();
+        let graph = dot::parse(&contents)?;
+
+        let existing_names = graph
+            .topology()
+            .nodes
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        let Some(id) = node_name_dialog::run(
+            &self.window().unwrap(),
+            &dot::unused_node_id(&graph, "node"),
+            existing_names,
+        )
+        .await
+        else {
+            return Ok(());
+        };
+
+        // The document may have changed while the dialog was open.
+        let contents = self.document().contents();
+        let graph = dot::parse(&contents)?;
+        let edit = dot::add_node(&contents, &graph, &id);
+        self.apply_edits(&contents, slice::from_ref(&edit));
+        self.reveal_edit(&contents, &edit);
+
+        Ok(())
+    }
+
+    /// Adds an edge between the nodes titled `tail_title` and `head_title`,
+    /// after they were clicked while adding edges.
+    fn add_edge_from_graph(&self, tail_title: &str, head_title: &str) -> Result<()> {
+        let contents = self.document().contents();
+        let graph = dot::parse(&contents)?;
+
+        let Some(dot::Element::Node(tail)) = dot::element_from_title(&graph, false, tail_title)
+        else {
+            bail!("Unknown tail node `{}`", tail_title);
+        };
+        let Some(dot::Element::Node(head)) = dot::element_from_title(&graph, false, head_title)
+        else {
+            bail!("Unknown head node `{}`", head_title);
+        };
+
+        let edit = dot::add_edge(&contents, &graph, &tail, &head);
+        self.apply_edits(&contents, slice::from_ref(&edit));
+        self.reveal_edit(&contents, &edit);
+
+        Ok(())
+    }
+
+    /// Removes the statements of the node or edge selected in the graph.
+    fn delete_from_graph(&self, element: &ClickedElement) -> Result<()> {
+        let contents = self.document().contents();
+        let graph = dot::parse(&contents)?;
+
+        let element = dot::element_from_title(&graph, element.is_edge, &element.title)
+            .with_context(|| format!("Unknown element `{}`", element.title))?;
+        let edits =
+            dot::remove_element(&contents, &graph, &element).context("Element can't be removed")?;
+        self.apply_edits(&contents, &edits);
+        self.reveal_edit(&contents, &edits[0]);
+
+        Ok(())
+    }
+
+    fn graph_edit_tool(&self) -> EditTool {
+        let imp = self.imp();
+
+        if !imp.graph_toolbar_view.reveals_top_bars() {
+            EditTool::None
+        } else if imp.add_node_tool_button.is_active() {
+            EditTool::AddNode
+        } else if imp.add_edge_tool_button.is_active() {
+            EditTool::AddEdge
+        } else {
+            EditTool::Select
+        }
+    }
+
+    fn update_graph_edit_tool(&self) {
+        // Applied once it is initialized.
+        if !self.imp().graph_view.is_initialized() {
+            return;
+        }
+
+        let tool = self.graph_edit_tool();
+        utils::spawn(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                if let Err(err) = obj.imp().graph_view.set_edit_tool(tool).await {
+                    tracing::error!("Failed to set edit tool: {:?}", err);
+                }
+            }
+        ));
+    }
+
+    fn update_graph_grid(&self) {
+        // Applied once it is initialized.
+        if !self.imp().graph_view.is_initialized() {
+            return;
+        }
+
+        let shows_graph_grid = self.shows_graph_grid();
+        utils::spawn(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                if let Err(err) = obj
+                    .imp()
+                    .graph_view
+                    .set_grid_visible(shows_graph_grid)
+                    .await
+                {
+                    tracing::error!("Failed to set grid visibility: {:?}", err);
+                }
+            }
+        ));
+    }
+
+    fn handle_graph_element_clicked(&self, element: Option<ClickedElement>) {
+        let imp = self.imp();
+
+        let element = element.and_then(|element| {
+            let graph = dot::parse(&self.document().contents()).ok()?;
+            dot::element_from_title(&graph, element.is_edge, &element.title)
+        });
+        imp.clicked_element.replace(element);
+
+        self.queue_update_attribute_inspector();
+    }
+
+    fn queue_update_attribute_inspector(&self) {
+        let imp = self.imp();
+
+        if imp.queued_update_attribute_inspector.replace(true) {
+            return;
+        }
+
+        glib::idle_add_local_once(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move || {
+                obj.imp().queued_update_attribute_inspector.set(false);
+                obj.update_attribute_inspector();
+            }
+        ));
+    }
+
+    fn update_attribute_inspector(&self) {
+        let imp = self.imp();
+
+        if !imp.split_view.shows_sidebar() {
+            return;
+        }
+
+        let contents = self.document().contents();
+
+        // Keep showing the last element while the document can't be parsed,
+        // like while typing.
+        let Ok(graph) = dot::parse(&contents) else {
+            imp.attribute_inspector.set_sensitive(false);
+            return;
+        };
+        imp.attribute_inspector.set_sensitive(true);
+
+        let element = imp
+            .clicked_element
+            .borrow()
+            .clone()
+            .or_else(|| dot::element_at(&graph, self.cursor_byte_offset(&contents)));
+        let inspection = element.and_then(|element| {
+            let attrs = dot::inspect(&graph, &element)?;
+            Some((element, attrs))
+        });
+
+        match inspection {
+            Some((element, attrs)) => {
+                let title = element_title(&graph, &element);
+                imp.attribute_inspector.set_element(Some((title, attrs)));
+                imp.inspected_element.replace(Some(element));
+            }
+            None => {
+                imp.attribute_inspector.set_element(None);
+                imp.inspected_element.replace(None);
+            }
+        }
+    }
+
+    fn set_inspected_attr(&self, key: &str, value: &str) -> Result<()> {
+        let element = self
+            .imp()
+            .inspected_element
+            .borrow()
+            .clone()
+            .context("No element is inspected")?;
+        self.set_element_attr(&element, key, value)
+    }
+
+    fn remove_inspected_attr(&self, key: &str) -> Result<()> {
+        let element = self
+            .imp()
+            .inspected_element
+            .borrow()
+            .clone()
+            .context("No element is inspected")?;
+        self.remove_element_attr(&element, key)
+    }
+
+    /// Shows the attribute palette for the element at the cursor, next to
+    /// the cursor.
+    fn show_attribute_palette(&self) {
+        let imp = self.imp();
+
+        let contents = self.document().contents();
+        let Ok(graph) = dot::parse(&contents) else {
+            self.error_bell();
+            return;
+        };
+        let inspection =
+            dot::element_at(&graph, self.cursor_byte_offset(&contents)).and_then(|element| {
+                let attrs = dot::inspect(&graph, &element)?;
+                Some((element, attrs))
+            });
+        let Some((element, attrs)) = inspection else {
+            self.error_bell();
+            return;
+        };
+
+        imp.attribute_palette.set_element(
+            &element_title(&graph, &element),
+            matches!(element, dot::Element::Edge { .. }),
+            &attrs,
+        );
+        let recent_colors = Application::get().settings().recent_colors();
+        imp.attribute_palette.set_colors(
+            dot::used_colors(&graph)
+                .into_iter()
+                .chain(recent_colors.iter().map(|color| color.as_str())),
+        );
+        imp.palette_element.replace(Some(element));
+
+        let document = self.document();
+        let cursor_iter = document.iter_at_mark(&document.get_insert());
+        let location = imp.view.iter_location(&cursor_iter);
+        let (x, y) = imp.view.buffer_to_window_coords(
+            gtk::TextWindowType::Widget,
+            location.x(),
+            location.y(),
+        );
+        imp.attribute_palette
+            .set_pointing_to(Some(&gdk::Rectangle::new(x, y, 1, location.height())));
+        imp.attribute_palette.popup();
+    }
+
+    fn set_palette_attr(&self, key: &str, value: &str) -> Result<()> {
+        let element = self
+            .imp()
+            .palette_element
+            .borrow()
+            .clone()
+            .context("No element in palette")?;
+        self.set_element_attr(&element, key, value)
+    }
+
+    fn remove_palette_attr(&self, key: &str) -> Result<()> {
+        let element = self
+            .imp()
+            .palette_element
+            .borrow()
+            .clone()
+            .context("No element in palette")?;
+        self.remove_element_attr(&element, key)
+    }
+
+    fn set_element_attr(&self, element: &dot::Element, key: &str, value: &str) -> Result<()> {
+        let contents = self.document().contents();
+        let graph = dot::parse(&contents)?;
+        let edit = dot::set_attr(&contents, &graph, element, key, value)
+            .context("Element is no longer in the graph")?;
+        self.apply_edits(&contents, &[edit]);
+
+        Ok(())
+    }
+
+    fn remove_element_attr(&self, element: &dot::Element, key: &str) -> Result<()> {
+        let contents = self.document().contents();
+        let graph = dot::parse(&contents)?;
+        let edits = dot::remove_attr(&contents, &graph, element, key);
+        self.apply_edits(&contents, &edits);
+
+        Ok(())
+    }
+
+    /// Applies `edits`, which must be ordered and made against `contents`,
+    /// as a single undoable action.
+    fn apply_edits(&self, contents: &str, edits: &[dot::Edit]) {
+        let document = self.document();
+
+        document.begin_user_action();
+        // Apply from the end, so the offsets of the remaining edits stay valid.
+        for edit in edits.iter().rev() {
+            let start = contents[..edit.range.start].chars().count();
+            let end = start + contents[edit.range.clone()].chars().count();
+
+            let mut start_iter = document.iter_at_offset(start as i32);
+            let mut end_iter = document.iter_at_offset(end as i32);
+            document.delete(&mut start_iter, &mut end_iter);
+            document.insert(&mut start_iter, &edit.text);
+        }
+        document.end_user_action();
+    }
+
+    /// Places the cursor after the text inserted by `edit`, which was made
+    /// against `contents`, and scrolls to it.
+    fn reveal_edit(&self, contents: &str, edit: &dot::Edit) {
+        let document = self.document();
+
+        let offset =
+            contents[..edit.range.start].chars().count() + edit.text.trim_end().chars().count();
+        let iter = document.iter_at_offset(offset as i32);
+        document.place_cursor(&iter);
+
+        self.imp().view.scroll_mark_onscreen(&document.get_insert());
+    }
+
+    /// Marks the lines of edges that are part of a cycle, or clears the marks
+    /// if the graph is not expected to be acyclic.
+    fn update_cycle_errors(&self, contents: &str, graph: Option<&dot::Graph>) {
+        let imp = self.imp();
+
+        for line in imp.lines_with_cycle.take() {
+            if imp.line_with_error.get() != Some(line) {
+                imp.error_gutter_renderer.remove_error(line);
+            }
+        }
+
+        if !self.expect_acyclic() {
+            return;
+        }
+
+        // Syntax errors are shown once the graph is rendered instead.
+        let Some(graph) = graph else {
+            return;
+        };
+
+        let message = gettext("Edge is part of a cycle, but the graph is expected to be acyclic");
+
+        let mut lines = Vec::new();
+        for span in dot::cycle_edge_spans(graph) {
+            let line = contents[..span.start].matches('\n').count() as u32;
+            if lines.last() != Some(&line) {
+                imp.error_gutter_renderer.set_error(line, &message);
+                lines.push(line);
+            }
+        }
+        imp.lines_with_cycle.replace(lines);
+    }
+
+    /// Suggests a layout engine that suits `graph` better, if `dot` is used
+    /// and the suggestion wasn't dismissed, or hides the suggestion.
+    fn update_layout_engine_suggestion(&self, graph: Option<&dot::Graph>) {
+        let imp = self.imp();
+
+        let suggestion = graph
+            .filter(|_| {
+                self.layout_engine() == LayoutEngine::Dot
+                    && !imp.is_layout_engine_suggestion_dismissed.get()
+            })
+            .and_then(layout_suggestion::suggest)
+            .filter(|suggestion| suggestion.engine.is_supported());
+
+        let Some(suggestion) = suggestion else {
+            imp.layout_engine_suggestion_revealer
+                .set_reveal_child(false);
+            return;
+        };
+
+        let engine = suggestion.engine.as_raw();
+        let message = match suggestion.reason {
+            layout_suggestion::Reason::Large => gettext_f(
+                // Translators: Do NOT translate the contents between '{' and '}', this is a variable name.
+                "This graph is large, so the {engine} layout engine may lay it out much faster",
+                &[("engine", engine)],
+            ),
+            layout_suggestion::Reason::Dense => gettext_f(
+                // Translators: Do NOT translate the contents between '{' and '}', this is a variable name.
+                "This graph is highly connected, so the {engine} layout engine may lay it out more clearly",
+                &[("engine", engine)],
+            ),
+        };
+        imp.layout_engine_suggestion_label.set_label(&message);
+        imp.layout_engine_suggestion_button.set_label(&gettext_f(
+            // Translators: This is a button to switch to a layout engine. Do NOT translate the contents between '{' and '}', this is a variable name.
+            "_Use {engine}",
+            &[("engine", engine)],
+        ));
+
+        imp.suggested_layout_engine.set(Some(suggestion.engine));
+        imp.layout_engine_suggestion_revealer.set_reveal_child(true);
     }
 
     fn handle_graph_view_error(&self, message: &str) {
@@ -693,6 +2638,7 @@ impl Page {
             // Subtract 1 since line numbers from the error starts at 1.
             let line_number = raw_line_number - 1;
             imp.error_gutter_renderer.set_error(line_number, message);
+            self.document().set_error_line(Some(line_number));
 
             imp.line_with_error.set(Some(line_number));
             self.update_go_to_error_revealer_reveal_child();
@@ -722,8 +2668,23 @@ impl Page {
         let imp = self.imp();
 
         let zoom_level = imp.graph_view.zoom_level();
-        imp.zoom_level_button
-            .set_label(&format!("{:.0}%", zoom_level * 100.0));
+        let percent = format_number((zoom_level * 100.0).round() as i64);
+        imp.zoom_level_button.set_label(&gettext_f(
+            // Translators: This is the zoom level, like "100%". Do NOT translate the contents between '{' and '}', this is a variable name.
+            "{percent}%",
+            &[("percent", &percent)],
+        ));
+    }
+
+    fn update_toggle_auto_reload_action(&self) {
+        self.action_set_enabled("page.toggle-auto-reload", self.document().file().is_some());
+    }
+
+    fn update_toggle_shares_view_settings_action(&self) {
+        self.action_set_enabled(
+            "page.toggle-shares-view-settings",
+            self.document().file().is_some(),
+        );
     }
 
     fn update_zoom_in_action(&self) {
@@ -744,3 +2705,76 @@ impl Page {
         self.action_set_enabled("page.reset-graph-zoom", imp.graph_view.can_reset_zoom());
     }
 }
+
+/// Returns `true` if the user agreed to overwrite the changes made by another
+/// program to the file.
+async fn confirm_overwrite_external_changes(parent: &impl IsA<gtk::Widget>) -> bool {
+    const CANCEL_RESPONSE_ID: &str = "cancel";
+    const OVERWRITE_RESPONSE_ID: &str = "overwrite";
+
+    let dialog = adw::AlertDialog::builder()
+        .heading(gettext("Overwrite File?"))
+        .body(gettext(
+            "The file has been changed by another program. Saving will overwrite those changes.",
+        ))
+        .close_response(CANCEL_RESPONSE_ID)
+        .default_response(CANCEL_RESPONSE_ID)
+        .build();
+
+    dialog.add_response(CANCEL_RESPONSE_ID, &gettext("Cancel"));
+    dialog.add_response(OVERWRITE_RESPONSE_ID, &gettext("_Overwrite"));
+
+    dialog.set_response_appearance(OVERWRITE_RESPONSE_ID, adw::ResponseAppearance::Destructive);
+
+    dialog.choose_future(parent).await == OVERWRITE_RESPONSE_ID
+}
+
+/// Asks what to do with the exports whose file already exists, returning
+/// `None` if the export is cancelled.
+async fn choose_conflict_resolution(
+    parent: &impl IsA<gtk::Widget>,
+    n_existing: usize,
+) -> Option<ConflictResolution> {
+    const CANCEL_RESPONSE_ID: &str = "cancel";
+    const SKIP_RESPONSE_ID: &str = "skip";
+    const KEEP_BOTH_RESPONSE_ID: &str = "keep-both";
+    const OVERWRITE_RESPONSE_ID: &str = "overwrite";
+
+    let dialog = adw::AlertDialog::builder()
+        .heading(gettext("Files Already Exist"))
+        .body(ngettext_f(
+            "{n_files} file already exists in the folder. Overwriting it will replace its contents.",
+            "{n_files} files already exist in the folder. Overwriting them will replace their contents.",
+            n_existing as u32,
+            &[("n_files", &n_existing.to_string())],
+        ))
+        .close_response(CANCEL_RESPONSE_ID)
+        .default_response(KEEP_BOTH_RESPONSE_ID)
+        .build();
+
+    dialog.add_response(CANCEL_RESPONSE_ID, &gettext("Cancel"));
+    dialog.add_response(SKIP_RESPONSE_ID, &gettext("_Skip"));
+    dialog.add_response(KEEP_BOTH_RESPONSE_ID, &gettext("_Keep Both"));
+    dialog.add_response(OVERWRITE_RESPONSE_ID, &gettext("_Overwrite"));
+
+    dialog.set_response_appearance(KEEP_BOTH_RESPONSE_ID, adw::ResponseAppearance::Suggested);
+    dialog.set_response_appearance(OVERWRITE_RESPONSE_ID, adw::ResponseAppearance::Destructive);
+
+    match dialog.choose_future(parent).await.as_str() {
+        SKIP_RESPONSE_ID => Some(ConflictResolution::Skip),
+        KEEP_BOTH_RESPONSE_ID => Some(ConflictResolution::KeepBoth),
+        OVERWRITE_RESPONSE_ID => Some(ConflictResolution::Overwrite),
+        _ => None,
+    }
+}
+
+/// Returns how `element` is written in DOT, like `a -> b` for edges.
+fn element_title(graph: &dot::Graph, element: &dot::Element) -> String {
+    match element {
+        dot::Element::Node(id) => id.clone(),
+        dot::Element::Edge { tail, head } => {
+            let edge_op = if graph.is_directed { "->" } else { "--" };
+            format!("{} {} {}", tail, edge_op, head)
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gtk::glib::{self, closure};
+
+use crate::{graph_view::LayoutEngine, Application};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Delineate/ui/preferences_dialog.ui")]
+    pub struct PreferencesDialog {
+        #[template_child]
+        pub(super) restore_session_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub(super) session_save_delay_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) open_in_new_window_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub(super) move_open_files_to_active_window_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub(super) run_in_background_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub(super) default_layout_engine_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) graph_text_scale_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) apply_graph_style_to_exports_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub(super) incremental_layout_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub(super) recents_max_count_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) recents_max_age_days_row: TemplateChild<adw::SpinRow>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PreferencesDialog {
+        const NAME: &'static str = "DelineatePreferencesDialog";
+        type Type = super::PreferencesDialog;
+        type ParentType = adw::PreferencesDialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for PreferencesDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let app = Application::get();
+            let settings = app.settings();
+            settings
+                .bind_restore_session(&*self.restore_session_row, "active")
+                .build();
+            settings
+                .bind_session_save_delay_secs(&*self.session_save_delay_row, "value")
+                .build();
+            settings
+                .bind_open_in_new_window(&*self.open_in_new_window_row, "active")
+                .build();
+            settings
+                .bind_move_open_files_to_active_window(
+                    &*self.move_open_files_to_active_window_row,
+                    "active",
+                )
+                .build();
+            settings
+                .bind_run_in_background(&*self.run_in_background_row, "active")
+                .build();
+
+            self.default_layout_engine_row
+                .set_expression(Some(&gtk::ClosureExpression::new::<glib::GString>(
+                    &[] as &[gtk::Expression],
+                    closure!(|list_item: adw::EnumListItem| list_item.name()),
+                )));
+            let model = LayoutEngine::supported_model();
+            self.default_layout_engine_row.set_model(Some(&model));
+            if let Some(position) = settings.default_layout_engine().position_in(&model) {
+                self.default_layout_engine_row.set_selected(position);
+            }
+            self.default_layout_engine_row
+                .connect_selected_item_notify(|row| {
+                    let Some(item) = row.selected_item().and_downcast::<adw::EnumListItem>() else {
+                        return;
+                    };
+                    let engine = LayoutEngine::try_from(item.value()).unwrap();
+                    Application::get()
+                        .settings()
+                        .set_default_layout_engine(engine);
+                });
+            settings
+                .bind_graph_text_scale(&*self.graph_text_scale_row, "value")
+                .build();
+            settings
+                .bind_apply_graph_style_to_exports(
+                    &*self.apply_graph_style_to_exports_row,
+                    "active",
+                )
+                .build();
+            settings
+                .bind_incremental_layout(&*self.incremental_layout_row, "active")
+                .build();
+
+            settings
+                .bind_recents_max_count(&*self.recents_max_count_row, "value")
+                .build();
+            settings
+                .bind_recents_max_age_days(&*self.recents_max_age_days_row, "value")
+                .build();
+        }
+
+        fn dispose(&self) {
+            self.dispose_template();
+        }
+    }
+
+    impl WidgetImpl for PreferencesDialog {}
+    impl AdwDialogImpl for PreferencesDialog {}
+    impl PreferencesDialogImpl for PreferencesDialog {}
+}
+
+glib::wrapper! {
+    pub struct PreferencesDialog(ObjectSubclass<imp::PreferencesDialog>)
+        @extends gtk::Widget, adw::Dialog, adw::PreferencesDialog;
+}
+
+impl PreferencesDialog {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+}
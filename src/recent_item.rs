@@ -1,12 +1,41 @@
 use std::sync::LazyLock;
 
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
-use gtk::{gio, glib, prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, TimeSpan},
+    prelude::*,
+    subclass::prelude::*,
+};
 
 static FUZZY_MATCHER: LazyLock<SkimMatcherV2> = LazyLock::new(SkimMatcherV2::default);
 
+/// Section where a recent item is shown in the list, in the order of the
+/// sections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RecentGroup {
+    Pinned,
+    Today,
+    Yesterday,
+    ThisWeek,
+    Older,
+}
+
+impl RecentGroup {
+    pub fn title(self) -> String {
+        match self {
+            Self::Pinned => gettext("Pinned"),
+            Self::Today => gettext("Today"),
+            Self::Yesterday => gettext("Yesterday"),
+            Self::ThisWeek => gettext("This Week"),
+            Self::Older => gettext("Older"),
+        }
+    }
+}
+
 mod imp {
-    use std::cell::{OnceCell, RefCell};
+    use std::cell::{Cell, OnceCell, RefCell};
 
     use super::*;
 
@@ -17,6 +46,11 @@ mod imp {
         pub(super) file: OnceCell<gio::File>,
         #[property(get, set = Self::set_added, explicit_notify, construct)]
         pub(super) added: RefCell<glib::DateTime>,
+        #[property(get, set = Self::set_is_pinned, explicit_notify)]
+        pub(super) is_pinned: Cell<bool>,
+        /// Whether the file existed the last time it was checked.
+        #[property(get, set = Self::set_is_available, explicit_notify)]
+        pub(super) is_available: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -30,6 +64,8 @@ mod imp {
                 // It would panic if RecentItem is constructed without `added`, so this
                 // is never actually accessed.
                 added: RefCell::new(glib::DateTime::from_unix_utc(0).unwrap()),
+                is_pinned: Cell::new(false),
+                is_available: Cell::new(true),
             }
         }
     }
@@ -48,6 +84,28 @@ mod imp {
             self.added.replace(added);
             obj.notify_added();
         }
+
+        fn set_is_pinned(&self, is_pinned: bool) {
+            let obj = self.obj();
+
+            if is_pinned == obj.is_pinned() {
+                return;
+            }
+
+            self.is_pinned.set(is_pinned);
+            obj.notify_is_pinned();
+        }
+
+        fn set_is_available(&self, is_available: bool) {
+            let obj = self.obj();
+
+            if is_available == obj.is_available() {
+                return;
+            }
+
+            self.is_available.set(is_available);
+            obj.notify_is_available();
+        }
     }
 }
 
@@ -63,8 +121,44 @@ impl RecentItem {
             .build()
     }
 
+    /// Returns how long ago the item was added.
+    pub fn age(&self) -> TimeSpan {
+        let now = glib::DateTime::now_utc().unwrap();
+        now.difference(&self.added())
+    }
+
+    pub fn group(&self) -> RecentGroup {
+        if self.is_pinned() {
+            return RecentGroup::Pinned;
+        }
+
+        let now = glib::DateTime::now_local().unwrap();
+        let today_start = glib::DateTime::new(
+            &glib::TimeZone::local(),
+            now.year(),
+            now.month(),
+            now.day_of_month(),
+            0,
+            0,
+            0.0,
+        )
+        .unwrap();
+        let yesterday_start = today_start.add_days(-1).unwrap();
+
+        let added = self.added();
+        if added >= today_start {
+            RecentGroup::Today
+        } else if added >= yesterday_start {
+            RecentGroup::Yesterday
+        } else if self.age() < TimeSpan::from_days(7) {
+            RecentGroup::ThisWeek
+        } else {
+            RecentGroup::Older
+        }
+    }
+
     pub fn fuzzy_match(&self, pattern: &str) -> Option<i64> {
-        let choice = self.file().path().unwrap();
-        FUZZY_MATCHER.fuzzy_match(choice.to_string_lossy().trim_end_matches(".gv"), pattern)
+        let choice = self.file().parse_name();
+        FUZZY_MATCHER.fuzzy_match(choice.trim_end_matches(".gv"), pattern)
     }
 }
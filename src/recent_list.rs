@@ -1,16 +1,27 @@
 use std::time::Instant;
 
 use anyhow::Result;
-use gtk::{gio, glib, prelude::*, subclass::prelude::*};
+use gtk::{
+    gio,
+    glib::{self, clone, TimeSpan},
+    prelude::*,
+    subclass::prelude::*,
+};
 use indexmap::{map::Entry, IndexMap};
 use serde::{Deserialize, Serialize};
 
-use crate::{recent_item::RecentItem, APP_DATA_DIR};
+use crate::{
+    document_portal, recent_item::RecentItem, thumbnail, utils, Application, APP_DATA_DIR,
+};
+
+const MIME_TYPE: &str = "text/vnd.graphviz";
 
 #[derive(Debug, Serialize, Deserialize)]
 struct RecentItemState {
     uri: String,
     added: String,
+    #[serde(default)]
+    is_pinned: bool,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -26,6 +37,8 @@ mod imp {
     pub struct RecentList {
         pub(super) state_file: gio::File,
         pub(super) list: RefCell<IndexMap<String, RecentItem>>,
+        /// What was last loaded or saved, to skip saving unchanged recents.
+        pub(super) saved_bytes: RefCell<Vec<u8>>,
     }
 
     #[glib::object_subclass]
@@ -38,6 +51,7 @@ mod imp {
             Self {
                 state_file: gio::File::for_path(APP_DATA_DIR.join("recents.json")),
                 list: RefCell::new(IndexMap::new()),
+                saved_bytes: RefCell::default(),
             }
         }
     }
@@ -80,7 +94,11 @@ impl RecentList {
         let now = Instant::now();
 
         let state = match imp.state_file.load_bytes_future().await {
-            Ok((bytes, _)) => serde_json::from_slice::<State>(&bytes)?,
+            Ok((bytes, _)) => {
+                let state = serde_json::from_slice::<State>(&bytes)?;
+                imp.saved_bytes.replace(bytes.to_vec());
+                state
+            }
             Err(err) => {
                 if !err.matches(gio::IOErrorEnum::NotFound) {
                     return Err(err.into());
@@ -95,21 +113,23 @@ impl RecentList {
         for recent_state in &state.recents {
             let uri = &recent_state.uri;
             let file = gio::File::for_uri(uri);
-
-            if !file.query_exists(gio::Cancellable::NONE) {
-                tracing::debug!(?uri, "Recent file ignored as it does not exist");
-                continue;
-            }
+            document_portal::resolve_host_path(&file).await;
 
             let added = glib::DateTime::from_iso8601(&recent_state.added, None)?;
             let item = RecentItem::new(&file, &added);
+            item.set_is_pinned(recent_state.is_pinned);
 
             list.insert(uri.to_owned(), item);
         }
         imp.list.replace(list);
 
+        this.prune();
+
         tracing::debug!(elapsed = ?now.elapsed(), "Recents loaded");
 
+        this.check_availability();
+        this.prune_thumbnails();
+
         Ok(this)
     }
 
@@ -118,6 +138,8 @@ impl RecentList {
 
         let now = Instant::now();
 
+        self.prune();
+
         let recent_states = imp
             .list
             .borrow()
@@ -128,6 +150,7 @@ impl RecentList {
                 RecentItemState {
                     uri: uri.clone(),
                     added: item.added().format_iso8601().unwrap().to_string(),
+                    is_pinned: item.is_pinned(),
                 }
             })
             .collect::<Vec<_>>();
@@ -137,7 +160,13 @@ impl RecentList {
         tracing::trace!(?state, "State stored");
 
         let bytes = serde_json::to_vec(&state)?;
-        imp.state_file
+        if bytes == *imp.saved_bytes.borrow() {
+            tracing::debug!("Recents unchanged, not saved");
+            return Ok(());
+        }
+
+        let (bytes, _) = imp
+            .state_file
             .replace_contents_future(
                 bytes,
                 None,
@@ -146,6 +175,7 @@ impl RecentList {
             )
             .await
             .map_err(|(_, err)| err)?;
+        imp.saved_bytes.replace(bytes);
 
         tracing::debug!(elapsed = ?now.elapsed(), "Recents saved");
 
@@ -175,12 +205,156 @@ impl RecentList {
 
                 let item = entry.get();
                 item.set_added(glib::DateTime::now_utc().unwrap());
+                item.set_is_available(true);
 
                 (index, 1, 1)
             }
         };
 
         self.items_changed(index as u32, n_removed, n_added);
+
+        // Also make it show up in file choosers and other apps.
+        let recent_data = gtk::RecentData::new(
+            None,
+            None,
+            MIME_TYPE,
+            &glib::application_name().unwrap_or_default(),
+            &format!("{} %u", glib::prgname().unwrap_or_default()),
+            &[],
+            false,
+        );
+        let uri = imp.list.borrow().get_index(index).unwrap().0.clone();
+        if !gtk::RecentManager::default().add_full(&uri, &recent_data) {
+            tracing::warn!(uri, "Failed to add to recent manager");
+        }
+    }
+
+    /// Pins or unpins the item with the uri, so it is kept on top of the
+    /// other items.
+    pub fn set_pinned(&self, uri: &str, is_pinned: bool) {
+        let imp = self.imp();
+
+        let item = imp
+            .list
+            .borrow()
+            .get_full(uri)
+            .map(|(index, _, item)| (index, item.clone()));
+        if let Some((index, item)) = item {
+            item.set_is_pinned(is_pinned);
+            self.items_changed(index as u32, 1, 1);
+        }
+    }
+
+    /// Checks in the background whether the files of the items still exist,
+    /// updating them as the results arrive.
+    ///
+    /// Missing files are kept, as they may only be on a drive that is not
+    /// mounted right now.
+    fn check_availability(&self) {
+        let imp = self.imp();
+
+        for item in imp.list.borrow().values() {
+            utils::spawn_with_priority(
+                glib::Priority::LOW,
+                clone!(
+                    #[weak]
+                    item,
+                    async move {
+                        let file = item.file();
+                        let is_available = match file
+                            .query_info_future(
+                                gio::FILE_ATTRIBUTE_STANDARD_TYPE,
+                                gio::FileQueryInfoFlags::NONE,
+                                glib::Priority::LOW,
+                            )
+                            .await
+                        {
+                            Ok(_) => true,
+                            Err(err) => {
+                                if !err.matches(gio::IOErrorEnum::NotFound)
+                                    && !err.matches(gio::IOErrorEnum::NotMounted)
+                                {
+                                    tracing::warn!(
+                                        uri = %file.uri(),
+                                        "Failed to check recent file: {:?}",
+                                        err
+                                    );
+                                }
+                                false
+                            }
+                        };
+
+                        if !is_available {
+                            tracing::debug!(uri = %file.uri(), "Recent file is unavailable");
+                        }
+
+                        item.set_is_available(is_available);
+                    }
+                ),
+            );
+        }
+    }
+
+    /// Deletes in the background the thumbnails of files that are no longer
+    /// recent, like ones removed while the app was not running.
+    fn prune_thumbnails(&self) {
+        let uris = self.imp().list.borrow().keys().cloned().collect::<Vec<_>>();
+        utils::spawn_with_priority(glib::Priority::LOW, async move {
+            if let Err(err) = thumbnail::prune(uris.iter().map(String::as_str)).await {
+                tracing::warn!("Failed to prune thumbnails: {:?}", err);
+            }
+        });
+    }
+
+    /// Removes the items that are older or more than allowed by the
+    /// settings, keeping the pinned ones.
+    fn prune(&self) {
+        let imp = self.imp();
+
+        let app = Application::get();
+        let settings = app.settings();
+        let max_count = settings.recents_max_count() as usize;
+        let max_age = match settings.recents_max_age_days() {
+            0 => None,
+            days => Some(TimeSpan::from_days(days as i64)),
+        };
+
+        let mut unpinned = imp
+            .list
+            .borrow()
+            .iter()
+            .filter(|(_, item)| !item.is_pinned())
+            .map(|(uri, item)| (uri.clone(), item.added()))
+            .collect::<Vec<_>>();
+        let n_pinned = imp.list.borrow().len() - unpinned.len();
+
+        // Newest first, so the oldest are the ones to go once there are too many.
+        unpinned.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+        let now = glib::DateTime::now_utc().unwrap();
+        for (index, (uri, added)) in unpinned.into_iter().enumerate() {
+            let is_too_many = n_pinned + index >= max_count;
+            let is_too_old = max_age.is_some_and(|max_age| now.difference(&added) > max_age);
+            if is_too_many || is_too_old {
+                tracing::debug!(?uri, is_too_many, is_too_old, "Recent item pruned");
+                self.remove(&uri);
+            }
+        }
+    }
+
+    /// Notifies that what is shown for the item with the uri, like its
+    /// thumbnail, has changed.
+    pub fn update(&self, uri: &str) {
+        let imp = self.imp();
+
+        let index = imp.list.borrow().get_index_of(uri);
+        if let Some(index) = index {
+            self.items_changed(index as u32, 1, 1);
+        }
+    }
+
+    pub fn contains(&self, uri: &str) -> bool {
+        self.imp().list.borrow().contains_key(uri)
     }
 
     pub fn remove(&self, uri: &str) {
@@ -189,6 +363,13 @@ impl RecentList {
         let item = imp.list.borrow_mut().shift_remove_full(uri);
         if let Some((position, _, _)) = item {
             self.items_changed(position as u32, 1, 0);
+
+            let uri = uri.to_string();
+            utils::spawn(async move {
+                if let Err(err) = thumbnail::delete(&uri).await {
+                    tracing::warn!("Failed to delete thumbnail: {:?}", err);
+                }
+            });
         }
     }
 }
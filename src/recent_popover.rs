@@ -1,4 +1,5 @@
 use gtk::{
+    gdk,
     glib::{self, clone, closure_local},
     prelude::*,
     subclass::prelude::*,
@@ -6,7 +7,7 @@ use gtk::{
 
 use crate::{
     recent_filter::RecentFilter, recent_item::RecentItem, recent_list::RecentList,
-    recent_row::RecentRow, recent_sorter::RecentSorter, session::Session,
+    recent_row::RecentRow, recent_sorter::RecentSorter,
 };
 
 mod imp {
@@ -32,11 +33,12 @@ mod imp {
         #[template_child]
         pub(super) list_page: TemplateChild<gtk::ScrolledWindow>,
         #[template_child]
-        pub(super) list_box: TemplateChild<gtk::ListBox>,
+        pub(super) list_view: TemplateChild<gtk::ListView>,
 
         pub(super) model: OnceCell<RecentList>,
         pub(super) filter_model: OnceCell<gtk::FilterListModel>,
-        pub(super) sort_model: OnceCell<gtk::SortListModel>,
+        pub(super) selection: OnceCell<gtk::SingleSelection>,
+        pub(super) header_factory: OnceCell<gtk::SignalListItemFactory>,
     }
 
     #[glib::object_subclass]
@@ -60,13 +62,14 @@ mod imp {
 
             let obj = self.obj();
 
-            self.list_box.connect_row_activated(clone!(
+            self.list_view.connect_activate(clone!(
                 #[weak]
                 obj,
-                move |_, row| {
-                    let row = row.downcast_ref::<RecentRow>().unwrap();
-                    obj.emit_item_activated(&row.item());
-                    obj.popdown();
+                move |_, position| {
+                    if let Some(item) = obj.item_at(position) {
+                        obj.emit_item_activated(&item);
+                        obj.popdown();
+                    }
                 }
             ));
 
@@ -81,28 +84,37 @@ mod imp {
                 #[weak]
                 obj,
                 move |_| {
-                    let imp = obj.imp();
-                    if let Some(item) = imp
-                        .sort_model
-                        .get()
-                        .and_then(|sort_model| sort_model.item(0))
-                    {
-                        let item = item.downcast_ref().unwrap();
-                        obj.emit_item_activated(item);
+                    if let Some(item) = obj.selected_or_first_item() {
+                        obj.emit_item_activated(&item);
                         obj.popdown();
                     }
                 }
             ));
 
+            let key_controller = gtk::EventControllerKey::new();
+            key_controller.connect_key_pressed(clone!(
+                #[weak]
+                obj,
+                #[upgrade_or]
+                glib::Propagation::Proceed,
+                move |_, keyval, _, state| obj.handle_search_entry_key_pressed(keyval, state)
+            ));
+            self.search_entry.add_controller(key_controller);
+
             obj.update_search_entry_sensitivity();
             obj.update_stack();
         }
 
         fn signals() -> &'static [Signal] {
             static SIGNALS: LazyLock<Vec<Signal>> = LazyLock::new(|| {
-                vec![Signal::builder("item-activated")
-                    .param_types([RecentItem::static_type()])
-                    .build()]
+                vec![
+                    Signal::builder("item-activated")
+                        .param_types([RecentItem::static_type()])
+                        .build(),
+                    Signal::builder("item-activated-in-new-window")
+                        .param_types([RecentItem::static_type()])
+                        .build(),
+                ]
             });
 
             SIGNALS.as_ref()
@@ -141,6 +153,19 @@ impl RecentPopover {
         )
     }
 
+    pub fn connect_item_activated_in_new_window<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &RecentItem) + 'static,
+    {
+        self.connect_closure(
+            "item-activated-in-new-window",
+            false,
+            closure_local!(|obj: &Self, item: &RecentItem| {
+                f(obj, item);
+            }),
+        )
+    }
+
     /// This must only be called once
     pub fn bind_model(&self, model: &RecentList) {
         let imp = self.imp();
@@ -157,6 +182,27 @@ impl RecentPopover {
 
         let filter = RecentFilter::new();
         let sorter = RecentSorter::new();
+
+        let filter_model = gtk::FilterListModel::new(Some(model.clone()), Some(filter.clone()));
+        filter_model.connect_items_changed(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_, _, _, _| {
+                obj.update_stack();
+            }
+        ));
+        imp.filter_model.set(filter_model.clone()).unwrap();
+
+        // Group the items by age, with the pinned ones on top, unless they
+        // are sorted by how well they match the search.
+        let group_sorter = gtk::CustomSorter::new(|a, b| {
+            let a = a.downcast_ref::<RecentItem>().unwrap();
+            let b = b.downcast_ref::<RecentItem>().unwrap();
+            a.group().cmp(&b.group()).into()
+        });
+        let sort_model = gtk::SortListModel::new(Some(filter_model), Some(sorter.clone()));
+        sort_model.set_section_sorter(Some(&group_sorter));
+
         imp.search_entry.connect_search_changed(clone!(
             #[weak(rename_to = obj)]
             self,
@@ -164,39 +210,79 @@ impl RecentPopover {
             filter,
             #[weak]
             sorter,
+            #[weak]
+            sort_model,
+            #[weak]
+            group_sorter,
             move |search_entry| {
+                let imp = obj.imp();
+
                 let text = search_entry.text();
                 filter.set_search(text.trim());
                 sorter.set_search(text.trim());
+
+                if text.is_empty() {
+                    sort_model.set_section_sorter(Some(&group_sorter));
+                    imp.list_view.set_header_factory(imp.header_factory.get());
+                } else {
+                    sort_model.set_section_sorter(gtk::Sorter::NONE);
+                    imp.list_view.set_header_factory(gtk::ListItemFactory::NONE);
+                }
+
                 obj.update_stack();
+                obj.select_row_at(0);
             }
         ));
 
-        let filter_model = gtk::FilterListModel::new(Some(model.clone()), Some(filter));
-        filter_model.connect_items_changed(clone!(
+        // Rows are recycled, so histories of any length scroll smoothly.
+        let factory = gtk::SignalListItemFactory::new();
+        factory.connect_setup(clone!(
             #[weak(rename_to = obj)]
             self,
-            move |_, _, _, _| {
-                obj.update_stack();
+            move |_, list_item| {
+                let list_item = list_item.downcast_ref::<gtk::ListItem>().unwrap();
+                // The row takes the focus, so it can show its context menu.
+                list_item.set_focusable(false);
+                list_item.set_child(Some(&obj.create_row()));
             }
         ));
-        imp.filter_model.set(filter_model.clone()).unwrap();
-
-        let sort_model = gtk::SortListModel::new(Some(filter_model), Some(sorter));
-        imp.sort_model.set(sort_model.clone()).unwrap();
-
-        imp.list_box.bind_model(
-            Some(&sort_model),
-            clone!(
-                #[weak(rename_to = obj)]
-                self,
-                #[upgrade_or_panic]
-                move |item| obj.create_row(item.downcast_ref().unwrap()).upcast()
-            ),
-        );
+        factory.connect_bind(|_, list_item| {
+            let list_item = list_item.downcast_ref::<gtk::ListItem>().unwrap();
+            let row = list_item.child().and_downcast::<RecentRow>().unwrap();
+            row.set_item(list_item.item().and_downcast_ref::<RecentItem>());
+        });
+        factory.connect_unbind(|_, list_item| {
+            let list_item = list_item.downcast_ref::<gtk::ListItem>().unwrap();
+            let row = list_item.child().and_downcast::<RecentRow>().unwrap();
+            row.set_item(None::<&RecentItem>);
+        });
+        imp.list_view.set_factory(Some(&factory));
+
+        let header_factory = gtk::SignalListItemFactory::new();
+        header_factory.connect_setup(|_, list_header| {
+            let list_header = list_header.downcast_ref::<gtk::ListHeader>().unwrap();
+            let label = gtk::Label::builder()
+                .xalign(0.0)
+                .css_classes(["heading", "dim-label"])
+                .build();
+            list_header.set_child(Some(&label));
+        });
+        header_factory.connect_bind(|_, list_header| {
+            let list_header = list_header.downcast_ref::<gtk::ListHeader>().unwrap();
+            let item = list_header.item().and_downcast::<RecentItem>().unwrap();
+            let label = list_header.child().and_downcast::<gtk::Label>().unwrap();
+            label.set_label(&item.group().title());
+        });
+        imp.list_view.set_header_factory(Some(&header_factory));
+        imp.header_factory.set(header_factory).unwrap();
+
+        let selection = gtk::SingleSelection::new(Some(sort_model));
+        imp.list_view.set_model(Some(&selection));
+        imp.selection.set(selection).unwrap();
 
         self.update_search_entry_sensitivity();
         self.update_stack();
+        self.select_row_at(0);
     }
 
     /// Shows the loading page until the model is bound.
@@ -206,27 +292,149 @@ impl RecentPopover {
         imp.stack.set_visible_child(&*imp.loading_page);
     }
 
+    fn handle_search_entry_key_pressed(
+        &self,
+        keyval: gdk::Key,
+        state: gdk::ModifierType,
+    ) -> glib::Propagation {
+        let imp = self.imp();
+
+        let selected_index = imp
+            .selection
+            .get()
+            .map(|selection| selection.selected())
+            .filter(|&position| position != gtk::INVALID_LIST_POSITION)
+            .map(|position| position as i32);
+        match keyval {
+            gdk::Key::Return | gdk::Key::KP_Enter
+                if state.contains(gdk::ModifierType::CONTROL_MASK) =>
+            {
+                if let Some(item) = self.selected_or_first_item() {
+                    self.emit_item_activated_in_new_window(&item);
+                    self.popdown();
+                }
+                glib::Propagation::Stop
+            }
+            gdk::Key::Down | gdk::Key::KP_Down => {
+                self.select_row_at(selected_index.map_or(0, |index| index + 1));
+                glib::Propagation::Stop
+            }
+            gdk::Key::Up | gdk::Key::KP_Up => {
+                self.select_row_at(selected_index.map_or(0, |index| index - 1));
+                glib::Propagation::Stop
+            }
+            _ if state.contains(gdk::ModifierType::ALT_MASK) => {
+                // Alt+1 to Alt+9 opens the nth result.
+                let item = keyval
+                    .to_unicode()
+                    .and_then(|c| c.to_digit(10))
+                    .filter(|&digit| digit > 0)
+                    .and_then(|digit| self.item_at(digit - 1));
+                if let Some(item) = item {
+                    self.emit_item_activated(&item);
+                    self.popdown();
+                    glib::Propagation::Stop
+                } else {
+                    glib::Propagation::Proceed
+                }
+            }
+            _ => glib::Propagation::Proceed,
+        }
+    }
+
+    /// Selects the row at `index`, clamped to the existing rows, and scrolls
+    /// to it.
+    fn select_row_at(&self, index: i32) {
+        let imp = self.imp();
+
+        let Some(selection) = imp.selection.get() else {
+            return;
+        };
+
+        let n_rows = selection.n_items() as i32;
+        if n_rows == 0 {
+            return;
+        }
+
+        let position = index.clamp(0, n_rows - 1) as u32;
+        selection.set_selected(position);
+        imp.list_view
+            .scroll_to(position, gtk::ListScrollFlags::NONE, None);
+    }
+
+    fn item_at(&self, position: u32) -> Option<RecentItem> {
+        self.imp()
+            .selection
+            .get()?
+            .item(position)
+            .and_downcast::<RecentItem>()
+    }
+
+    fn selected_or_first_item(&self) -> Option<RecentItem> {
+        let selection = self.imp().selection.get()?;
+        selection
+            .selected_item()
+            .or_else(|| selection.item(0))
+            .and_downcast::<RecentItem>()
+    }
+
     fn emit_item_activated(&self, item: &RecentItem) {
         self.emit_by_name::<()>("item-activated", &[item]);
     }
 
-    fn create_row(&self, item: &RecentItem) -> RecentRow {
-        let item = item.downcast_ref().unwrap();
-        let row = RecentRow::new(item);
-        row.connect_remove_request(clone!(
+    fn emit_item_activated_in_new_window(&self, item: &RecentItem) {
+        self.emit_by_name::<()>("item-activated-in-new-window", &[item]);
+    }
+
+    fn create_row(&self) -> RecentRow {
+        let row = RecentRow::new(None);
+        // Activated from its context menu or with the keyboard.
+        row.connect_activate(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |row| {
+                if let Some(item) = row.item() {
+                    obj.emit_item_activated(&item);
+                    obj.popdown();
+                }
+            }
+        ));
+        row.connect_open_in_new_window_request(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |row| {
+                if let Some(item) = row.item() {
+                    obj.emit_item_activated_in_new_window(&item);
+                    obj.popdown();
+                }
+            }
+        ));
+        row.connect_toggle_pin_request(clone!(
             #[weak(rename_to = obj)]
             self,
             move |row| {
                 let imp = obj.imp();
 
-                let uri = row.item().file().uri();
-                imp.model.get().unwrap().remove(&uri);
+                if let Some(item) = row.item() {
+                    imp.model
+                        .get()
+                        .unwrap()
+                        .set_pinned(&item.file().uri(), !item.is_pinned());
+                }
+            }
+        ));
+        row.connect_remove_request(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |row| {
+                let imp = obj.imp();
 
-                let session = Session::instance();
-                session.mark_dirty();
+                if let Some(item) = row.item() {
+                    imp.model.get().unwrap().remove(&item.file().uri());
+                }
             }
         ));
-        row.upcast()
+        row
     }
 
     fn update_search_entry_sensitivity(&self) {
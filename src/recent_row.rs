@@ -2,15 +2,23 @@ use std::time::Duration;
 
 use gettextrs::gettext;
 use gtk::{
+    gdk, gio,
     glib::{self, clone, closure_local, TimeSpan},
     prelude::*,
     subclass::prelude::*,
 };
 
-use crate::{i18n::ngettext_f, recent_item::RecentItem, utils};
+use crate::{
+    i18n::{format_number, gettext_f, ngettext_f},
+    recent_item::RecentItem,
+    thumbnail, utils,
+};
 
 mod imp {
-    use std::{cell::OnceCell, sync::LazyLock};
+    use std::{
+        cell::{OnceCell, RefCell},
+        sync::LazyLock,
+    };
 
     use glib::subclass::Signal;
 
@@ -20,15 +28,27 @@ mod imp {
     #[properties(wrapper_type = super::RecentRow)]
     #[template(resource = "/io/github/seadve/Delineate/ui/recent_row.ui")]
     pub struct RecentRow {
-        #[property(get, set, construct)]
-        pub(super) item: OnceCell<RecentItem>,
+        /// The item shown, which changes as rows are recycled by list views.
+        #[property(get, set = Self::set_item, explicit_notify, nullable)]
+        pub(super) item: RefCell<Option<RecentItem>>,
 
+        #[template_child]
+        pub(super) thumbnail_picture: TemplateChild<gtk::Picture>,
         #[template_child]
         pub(super) title_label: TemplateChild<gtk::Label>,
         #[template_child]
         pub(super) subtitle_label: TemplateChild<gtk::Label>,
         #[template_child]
         pub(super) age_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub(super) unavailable_icon: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub(super) pin_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub(super) context_menu_popover: TemplateChild<gtk::PopoverMenu>,
+
+        pub(super) item_bindings: glib::BindingGroup,
+        pub(super) item_signals: OnceCell<glib::SignalGroup>,
     }
 
     #[glib::object_subclass]
@@ -40,9 +60,35 @@ mod imp {
         fn class_init(klass: &mut Self::Class) {
             klass.bind_template();
 
+            klass.install_action("recent-row.open", None, |obj, _, _| {
+                obj.activate();
+            });
+            klass.install_action("recent-row.open-in-new-window", None, |obj, _, _| {
+                obj.emit_by_name::<()>("open-in-new-window-request", &[]);
+            });
+            klass.install_action("recent-row.copy-path", None, |obj, _, _| {
+                obj.copy_path();
+            });
+            klass.install_action("recent-row.show-context-menu", None, |obj, _, _| {
+                obj.show_context_menu(None);
+            });
+            klass.install_action("recent-row.toggle-pin", None, |obj, _, _| {
+                obj.emit_by_name::<()>("toggle-pin-request", &[]);
+            });
             klass.install_action("recent-row.remove", None, |obj, _, _| {
                 obj.emit_by_name::<()>("remove-request", &[]);
             });
+
+            klass.add_binding_action(
+                gdk::Key::F10,
+                gdk::ModifierType::SHIFT_MASK,
+                "recent-row.show-context-menu",
+            );
+            klass.add_binding_action(
+                gdk::Key::Menu,
+                gdk::ModifierType::empty(),
+                "recent-row.show-context-menu",
+            );
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -56,14 +102,61 @@ mod imp {
             self.parent_constructed();
 
             let obj = self.obj();
-            let item = obj.item();
-            let file = item.file();
 
-            obj.set_tooltip_text(Some(&utils::display_file(&file)));
+            self.context_menu_popover.set_parent(&*obj);
+
+            let gesture_click = gtk::GestureClick::builder().button(0).build();
+            gesture_click.connect_pressed(clone!(
+                #[weak]
+                obj,
+                move |gesture_click, _, x, y| {
+                    let button = gesture_click.current_button();
+                    let state = gesture_click.current_event_state();
+
+                    if button == gdk::BUTTON_SECONDARY {
+                        gesture_click.set_state(gtk::EventSequenceState::Claimed);
+                        obj.show_context_menu(Some((x, y)));
+                    } else if button == gdk::BUTTON_MIDDLE
+                        || (button == gdk::BUTTON_PRIMARY
+                            && state.contains(gdk::ModifierType::CONTROL_MASK))
+                    {
+                        gesture_click.set_state(gtk::EventSequenceState::Claimed);
+                        obj.emit_by_name::<()>("open-in-new-window-request", &[]);
+                    }
+                }
+            ));
+            obj.add_controller(gesture_click);
+
+            self.item_bindings
+                .bind("is-pinned", &*self.pin_button, "active")
+                .sync_create()
+                .build();
+            self.item_bindings
+                .bind("is-pinned", &*self.pin_button, "tooltip-text")
+                .sync_create()
+                .transform_to(|_, value| {
+                    let tooltip_text = if value.get::<bool>().unwrap() {
+                        gettext("Unpin")
+                    } else {
+                        gettext("Pin")
+                    };
+                    Some(tooltip_text.into())
+                })
+                .build();
 
-            self.title_label.set_label(&utils::display_file_stem(&file));
-            self.subtitle_label
-                .set_label(&utils::display_file_parent(&file));
+            let item_signals = glib::SignalGroup::new::<RecentItem>();
+            item_signals.connect_notify_local(
+                Some("is-available"),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_, _| {
+                        obj.update_availability();
+                    }
+                ),
+            );
+            item_signals.set_target(obj.item().as_ref());
+            self.item_signals.set(item_signals).unwrap();
 
             // Update age label every 30 minutes.
             glib::timeout_add_local_full(
@@ -80,19 +173,54 @@ mod imp {
                 ),
             );
 
+            obj.update_labels();
+            obj.update_availability();
             obj.update_age_label();
         }
 
         fn signals() -> &'static [Signal] {
-            static SIGNALS: LazyLock<Vec<Signal>> =
-                LazyLock::new(|| vec![Signal::builder("remove-request").build()]);
+            static SIGNALS: LazyLock<Vec<Signal>> = LazyLock::new(|| {
+                vec![
+                    Signal::builder("open-in-new-window-request").build(),
+                    Signal::builder("toggle-pin-request").build(),
+                    Signal::builder("remove-request").build(),
+                ]
+            });
 
             SIGNALS.as_ref()
         }
+
+        fn dispose(&self) {
+            self.context_menu_popover.unparent();
+        }
     }
 
     impl WidgetImpl for RecentRow {}
     impl ListBoxRowImpl for RecentRow {}
+
+    impl RecentRow {
+        fn set_item(&self, item: Option<RecentItem>) {
+            let obj = self.obj();
+
+            if item == obj.item() {
+                return;
+            }
+
+            self.item_bindings.set_source(item.as_ref());
+            if let Some(item_signals) = self.item_signals.get() {
+                item_signals.set_target(item.as_ref());
+            }
+            self.item.replace(item);
+
+            // The item is also set while constructing, before the rest.
+            if self.item_signals.get().is_some() {
+                obj.update_labels();
+                obj.update_availability();
+                obj.update_age_label();
+            }
+            obj.notify_item();
+        }
+    }
 }
 
 glib::wrapper! {
@@ -101,10 +229,36 @@ glib::wrapper! {
 }
 
 impl RecentRow {
-    pub fn new(item: &RecentItem) -> Self {
+    pub fn new(item: Option<&RecentItem>) -> Self {
         glib::Object::builder().property("item", item).build()
     }
 
+    pub fn connect_open_in_new_window_request<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_closure(
+            "open-in-new-window-request",
+            false,
+            closure_local!(|obj: &Self| {
+                f(obj);
+            }),
+        )
+    }
+
+    pub fn connect_toggle_pin_request<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_closure(
+            "toggle-pin-request",
+            false,
+            closure_local!(|obj: &Self| {
+                f(obj);
+            }),
+        )
+    }
+
     pub fn connect_remove_request<F>(&self, f: F) -> glib::SignalHandlerId
     where
         F: Fn(&Self) + 'static,
@@ -118,13 +272,117 @@ impl RecentRow {
         )
     }
 
-    fn update_age_label(&self) {
+    /// Shows the context menu at the given point, or at the row itself if
+    /// there is none.
+    fn show_context_menu(&self, point: Option<(f64, f64)>) {
+        let imp = self.imp();
+
+        let rect = match point {
+            Some((x, y)) => gdk::Rectangle::new(x as i32, y as i32, 1, 1),
+            None => gdk::Rectangle::new(0, 0, self.width(), self.height()),
+        };
+        imp.context_menu_popover.set_pointing_to(Some(&rect));
+        imp.context_menu_popover.popup();
+    }
+
+    fn copy_path(&self) {
+        let Some(item) = self.item() else {
+            return;
+        };
+
+        let file = item.file();
+        let path = file
+            .path()
+            .map_or_else(|| file.uri().to_string(), |path| path.display().to_string());
+        self.clipboard().set_text(&path);
+    }
+
+    /// Shows the file name and the thumbnail of the item.
+    fn update_labels(&self) {
+        let imp = self.imp();
+
+        imp.thumbnail_picture.set_paintable(gdk::Paintable::NONE);
+
+        let Some(item) = self.item() else {
+            imp.title_label.set_label("");
+            imp.subtitle_label.set_label("");
+            return;
+        };
+
+        let file = item.file();
+        imp.title_label.set_label(&utils::display_file_stem(&file));
+        imp.subtitle_label
+            .set_label(&utils::display_file_parent(&file));
+
+        utils::spawn(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                obj.load_thumbnail(&item).await;
+            }
+        ));
+    }
+
+    async fn load_thumbnail(&self, item: &RecentItem) {
+        let imp = self.imp();
+
+        let file = thumbnail::file_for_uri(&item.file().uri());
+        let bytes = match file.load_bytes_future().await {
+            Ok((bytes, _)) => bytes,
+            Err(err) => {
+                if !err.matches(gio::IOErrorEnum::NotFound) {
+                    tracing::warn!("Failed to load thumbnail: {:?}", err);
+                }
+                return;
+            }
+        };
+
+        // The row may have been recycled for another item while loading.
+        if self.item().as_ref() != Some(item) {
+            return;
+        }
+
+        match gdk::Texture::from_bytes(&bytes) {
+            Ok(texture) => imp.thumbnail_picture.set_paintable(Some(&texture)),
+            Err(err) => tracing::warn!("Failed to load thumbnail: {:?}", err),
+        }
+    }
+
+    fn update_availability(&self) {
         let imp = self.imp();
 
-        let added = self.item().added();
+        let Some(item) = self.item() else {
+            return;
+        };
+        let is_available = item.is_available();
+
+        let display_file = utils::display_file(&item.file());
+        let tooltip_text = if is_available {
+            display_file
+        } else {
+            // Translators: Do NOT translate the contents between '{' and '}', this is a variable name.
+            gettext_f("{path} (Unavailable)", &[("path", &display_file)])
+        };
+        self.set_tooltip_text(Some(&tooltip_text));
+
+        imp.unavailable_icon.set_visible(!is_available);
+
+        if is_available {
+            self.remove_css_class("unavailable");
+        } else {
+            self.add_css_class("unavailable");
+        }
+    }
+
+    fn update_age_label(&self) {
+        let imp = self.imp();
 
-        let now = glib::DateTime::now_utc().unwrap();
-        let diff = now.difference(&added);
+        let Some(item) = self.item() else {
+            imp.age_label.set_label("");
+            return;
+        };
+        let added = item.added();
+        let diff = item.age();
 
         // Copied from GNOME Text Editor's `_editor_date_time_format`
         let label = if diff < TimeSpan(0) {
@@ -138,7 +396,8 @@ impl RecentRow {
         } else if diff < TimeSpan::from_days(7) {
             added.format("%A").unwrap().to_string()
         } else if diff < TimeSpan::from_days(365) {
-            added.format("%B %e").unwrap().to_string()
+            // `%O` uses the digits of the locale, if it has its own.
+            added.format("%B %Oe").unwrap().to_string()
         } else if diff < TimeSpan::from_days(365 + 365 / 2) {
             gettext("About a year ago")
         } else {
@@ -147,7 +406,7 @@ impl RecentRow {
                 "About {n_years} year ago",
                 "About {n_years} years ago",
                 n_years as u32,
-                &[("n_years", &n_years.to_string())],
+                &[("n_years", &format_number(n_years))],
             )
         };
         imp.age_label.set_label(&label);
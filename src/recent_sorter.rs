@@ -32,7 +32,12 @@ mod imp {
             let search = self.search.borrow();
 
             if search.is_empty() {
-                item_2.added().cmp(&item_1.added()).into()
+                // Pinned items are always on top.
+                item_2
+                    .is_pinned()
+                    .cmp(&item_1.is_pinned())
+                    .then_with(|| item_2.added().cmp(&item_1.added()))
+                    .into()
             } else {
                 let score_1 = item_1.fuzzy_match(&search);
                 let score_2 = item_2.fuzzy_match(&search);
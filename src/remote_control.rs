@@ -0,0 +1,123 @@
+use anyhow::{anyhow, Result};
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+};
+
+use crate::{export_format::ExportFormat, file_position::FilePosition, utils, Application};
+
+const INTERFACE_NAME: &str = "io.github.seadve.Delineate";
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="io.github.seadve.Delineate">
+    <method name="OpenFile">
+      <arg type="s" name="uri" direction="in"/>
+      <arg type="u" name="line" direction="in"/>
+    </method>
+    <method name="NewDocumentFromText">
+      <arg type="s" name="text" direction="in"/>
+    </method>
+    <method name="ExportGraph">
+      <arg type="s" name="uri" direction="in"/>
+      <arg type="s" name="format" direction="in"/>
+      <arg type="s" name="destination" direction="in"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+/// Exports a D-Bus interface on the app's object path, so other apps and
+/// scripts can open documents and export graphs.
+///
+/// `OpenFile` takes a 1-based line, or 0 to not move the cursor. `ExportGraph`
+/// takes the format as `svg`, `png`, `png-2x`, or `jpeg`.
+///
+/// This must be called when the app registers itself on the bus, so no method
+/// call arrives before the object is exported.
+pub fn register(
+    app: &Application,
+    connection: &gio::DBusConnection,
+    object_path: &str,
+) -> Result<gio::RegistrationId> {
+    let node_info = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?;
+    let interface_info = node_info
+        .lookup_interface(INTERFACE_NAME)
+        .ok_or_else(|| anyhow!("Missing interface info"))?;
+
+    let id = connection
+        .register_object(object_path, &interface_info)
+        .method_call(clone!(
+            #[weak]
+            app,
+            move |_, _, _, _, method_name, parameters, invocation| {
+                handle_method_call(&app, method_name, &parameters, invocation);
+            }
+        ))
+        .build()?;
+
+    Ok(id)
+}
+
+fn handle_method_call(
+    app: &Application,
+    method_name: &str,
+    parameters: &glib::Variant,
+    invocation: gio::DBusMethodInvocation,
+) {
+    tracing::debug!(method_name, "Received remote method call");
+
+    match method_name {
+        "OpenFile" => {
+            let (uri, line) = parameters.get::<(String, u32)>().unwrap();
+
+            let hint = if line > 0 {
+                FilePosition { line, column: 1 }.to_hint()
+            } else {
+                String::new()
+            };
+            app.open(&[gio::File::for_uri(&uri)], &hint);
+
+            invocation.return_value(None);
+        }
+        "NewDocumentFromText" => {
+            let (text,) = parameters.get::<(String,)>().unwrap();
+
+            app.activate_action("open-draft", Some(&text.to_variant()));
+
+            invocation.return_value(None);
+        }
+        "ExportGraph" => {
+            let (uri, format, destination) = parameters.get::<(String, String, String)>().unwrap();
+
+            let Some(format) = ExportFormat::from_raw(&format).filter(ExportFormat::is_supported)
+            else {
+                invocation.return_error(
+                    gio::IOErrorEnum::InvalidArgument,
+                    &format!("Unknown or unsupported export format `{}`", format),
+                );
+                return;
+            };
+
+            let session = app.session().clone();
+            utils::spawn(async move {
+                let res = async {
+                    let window = session.active_window();
+                    let page = session
+                        .open_file_and_wait(&gio::File::for_uri(&uri), &window)
+                        .await?;
+                    page.export_graph_to(format, &gio::File::for_uri(&destination))
+                        .await
+                };
+                match res.await {
+                    Ok(()) => invocation.return_value(None),
+                    Err(err) => {
+                        tracing::error!("Failed to export graph remotely: {:?}", err);
+                        invocation.return_error(gio::IOErrorEnum::Failed, &format!("{:#}", err));
+                    }
+                }
+            });
+        }
+        _ => unreachable!("unknown method `{}`", method_name),
+    }
+}
@@ -0,0 +1,58 @@
+use adw::prelude::*;
+use gettextrs::gettext;
+use gtk::glib::{self, clone};
+
+const CANCEL_RESPONSE_ID: &str = "cancel";
+const RENAME_RESPONSE_ID: &str = "rename";
+
+/// Asks the user for a new name for a file currently named `current_name`.
+///
+/// The new name may also be a path relative to the file's folder, to move it.
+/// Returns `None` if the user cancelled the dialog.
+pub async fn run(parent: &impl IsA<gtk::Widget>, current_name: &str) -> Option<String> {
+    let dialog = adw::AlertDialog::builder()
+        .heading(gettext("Rename File"))
+        .close_response(CANCEL_RESPONSE_ID)
+        .default_response(RENAME_RESPONSE_ID)
+        .build();
+
+    dialog.add_response(CANCEL_RESPONSE_ID, &gettext("Cancel"));
+    dialog.add_response(RENAME_RESPONSE_ID, &gettext("_Rename"));
+
+    dialog.set_response_appearance(RENAME_RESPONSE_ID, adw::ResponseAppearance::Suggested);
+    dialog.set_response_enabled(RENAME_RESPONSE_ID, false);
+
+    let current_name = current_name.to_string();
+
+    let entry = gtk::Entry::builder()
+        .text(&current_name)
+        .activates_default(true)
+        .build();
+    entry.connect_changed(clone!(
+        #[weak]
+        dialog,
+        #[strong]
+        current_name,
+        move |entry| {
+            let text = entry.text();
+            let is_valid = !text.trim().is_empty() && !text.contains('\0') && text != current_name;
+            dialog.set_response_enabled(RENAME_RESPONSE_ID, is_valid);
+        }
+    ));
+    entry.connect_map(|entry| {
+        // Only select the stem, so the extension is kept while typing.
+        let text = entry.text();
+        let stem_len = text.rfind('.').filter(|&index| index > 0).map_or_else(
+            || text.chars().count(),
+            |index| text[..index].chars().count(),
+        );
+        entry.select_region(0, stem_len as i32);
+    });
+    dialog.set_extra_child(Some(&entry));
+
+    match dialog.choose_future(parent).await.as_str() {
+        CANCEL_RESPONSE_ID => None,
+        RENAME_RESPONSE_ID => Some(entry.text().trim().to_string()),
+        _ => unreachable!(),
+    }
+}
@@ -1,4 +1,4 @@
-use std::{cell::RefCell, error, fmt, rc::Rc};
+use std::{cell::RefCell, error, fmt, rc::Rc, slice};
 
 use adw::prelude::*;
 use anyhow::Result;
@@ -8,10 +8,11 @@ use gtk::{
     glib::{self, clone},
 };
 
-use crate::{document::Document, i18n::gettext_f, utils, window::Window};
+use crate::{document::Document, i18n::gettext_f, session::Session, utils, window::Window};
 
 const CANCEL_RESPONSE_ID: &str = "cancel";
 const DISCARD_RESPONSE_ID: &str = "discard";
+const REVIEW_RESPONSE_ID: &str = "review";
 const SAVE_RESPONSE_ID: &str = "save";
 
 /// Indicates that the user cancelled the operation.
@@ -29,7 +30,8 @@ impl error::Error for Cancelled {}
 struct SaveFileItem<'a> {
     document: &'a Document,
     check_button: gtk::CheckButton,
-    /// Only for draft (new) documents
+    /// Where to save documents that can't be saved to their file, like
+    /// drafts (new documents), and read-only or deleted files
     save_as_file: Option<gio::File>,
 }
 
@@ -66,6 +68,9 @@ async fn run_inner(parent: &impl IsA<gtk::Widget>, unsaved: &[Document]) -> Resu
         DISCARD_RESPONSE_ID,
         &ngettext("_Discard", "_Discard All", unsaved.len() as u32),
     );
+    if unsaved.len() > 1 {
+        dialog.add_response(REVIEW_RESPONSE_ID, &gettext("_Review…"));
+    }
     dialog.add_response(
         SAVE_RESPONSE_ID,
         &ngettext("_Save", "_Save All", unsaved.len() as u32),
@@ -74,60 +79,66 @@ async fn run_inner(parent: &impl IsA<gtk::Widget>, unsaved: &[Document]) -> Resu
     dialog.set_response_appearance(DISCARD_RESPONSE_ID, adw::ResponseAppearance::Destructive);
     dialog.set_response_appearance(SAVE_RESPONSE_ID, adw::ResponseAppearance::Suggested);
 
-    let list_box = gtk::ListBox::new();
-    list_box.add_css_class("boxed-list");
-    dialog.set_extra_child(Some(&list_box));
-
-    let mut items = Vec::new();
-    let check_buttons = Rc::new(RefCell::new(Vec::new()));
+    // Looked up before building the dialog, as it checks what files exist.
+    let mut save_elsewhere_files = Vec::with_capacity(unsaved.len());
     for document in unsaved {
-        debug_assert!(document.is_modified());
-
-        let row = adw::ActionRow::new();
-        list_box.append(&row);
-
-        let check_button = gtk::CheckButton::builder()
-            .valign(gtk::Align::Center)
-            .active(true)
-            .build();
-        row.add_prefix(&check_button);
-        row.set_activatable_widget(Some(&check_button));
-        check_buttons.borrow_mut().push(check_button.clone());
-
-        let title = document.title();
-
-        let item = if let Some(file) = document.file() {
-            row.set_title(&title);
-            row.set_subtitle(&utils::display_file_parent(&file));
+        save_elsewhere_files.push(save_elsewhere_file(document).await);
+    }
 
-            SaveFileItem {
-                document,
-                check_button,
-                save_as_file: None,
-            }
+    // Group the documents by their window if they are from several ones.
+    let windows = Session::instance().windows();
+    let mut groups = Vec::<(Option<Window>, Vec<(&Document, Option<gio::File>)>)>::new();
+    for (document, save_elsewhere_file) in unsaved.iter().zip(save_elsewhere_files) {
+        let window = windows
+            .iter()
+            .find(|window| window_has_document(window, document))
+            .cloned();
+        let entry = (document, save_elsewhere_file);
+        if let Some((_, documents)) = groups.iter_mut().find(|(w, _)| *w == window) {
+            documents.push(entry);
         } else {
-            let title = if title.is_empty() {
-                gettext("Untitled Document")
-            } else {
-                title
-            };
-            row.set_title(&gettext_f("{title} (new)", &[("title", &title)]));
+            groups.push((window, vec![entry]));
+        }
+    }
+
+    let extra_child = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(12)
+        .build();
+    dialog.set_extra_child(Some(&extra_child));
 
-            let file = {
-                let dir = glib::user_special_dir(glib::UserDirectory::Documents)
-                    .unwrap_or_else(glib::home_dir);
-                gio::File::for_path(dir).child(format!("{}.gv", title))
+    let mut items = Vec::new();
+    let check_buttons = Rc::new(RefCell::new(Vec::new()));
+    for (window, documents) in &groups {
+        if groups.len() > 1 {
+            let heading = match window {
+                Some(window) if parent.as_ref() == window.upcast_ref::<gtk::Widget>() => {
+                    gettext("This Window")
+                }
+                Some(window) => {
+                    let number = windows.iter().position(|w| w == window).unwrap() + 1;
+                    gettext_f("Window {number}", &[("number", &number.to_string())])
+                }
+                None => gettext("Other Documents"),
             };
-            row.set_subtitle(&utils::display_file_parent(&file));
+            let label = gtk::Label::builder()
+                .label(heading)
+                .xalign(0.0)
+                .css_classes(["heading"])
+                .build();
+            extra_child.append(&label);
+        }
 
-            SaveFileItem {
-                document,
-                check_button,
-                save_as_file: Some(file),
-            }
-        };
+        let list_box = gtk::ListBox::new();
+        list_box.add_css_class("boxed-list");
+        extra_child.append(&list_box);
 
-        items.push(item);
+        for (document, save_elsewhere_file) in documents {
+            let (row, item) = item_row(document, save_elsewhere_file.clone());
+            list_box.append(&row);
+            check_buttons.borrow_mut().push(item.check_button.clone());
+            items.push(item);
+        }
     }
 
     for button in check_buttons.borrow().iter() {
@@ -154,6 +165,22 @@ async fn run_inner(parent: &impl IsA<gtk::Widget>, unsaved: &[Document]) -> Resu
     match dialog.choose_future(parent).await.as_str() {
         CANCEL_RESPONSE_ID => Err(Cancelled.into()),
         DISCARD_RESPONSE_ID => Ok(()),
+        REVIEW_RESPONSE_ID => {
+            // Ask for each document in turn, showing it first so the user can
+            // see what would be lost. Unchecked documents were already chosen
+            // to be discarded, so don't ask for them again.
+            for item in items.iter().filter(|item| item.check_button.is_active()) {
+                let document = item.document;
+                let window = present_document(document);
+                let parent = match &window {
+                    Some(window) => window.upcast_ref::<gtk::Widget>(),
+                    None => parent.as_ref(),
+                };
+                Box::pin(run_inner(parent, slice::from_ref(document))).await?;
+            }
+
+            Ok(())
+        }
         SAVE_RESPONSE_ID => {
             for item in items {
                 let SaveFileItem {
@@ -178,3 +205,118 @@ async fn run_inner(parent: &impl IsA<gtk::Widget>, unsaved: &[Document]) -> Resu
         _ => unreachable!(),
     }
 }
+
+/// Returns where to save the document if its file can't be written to or
+/// was deleted, under the same name, or a numbered one so no file there is
+/// overwritten. Deleted files are not brought back without asking either.
+async fn save_elsewhere_file(document: &Document) -> Option<gio::File> {
+    let file = document.file()?;
+
+    if !document.is_read_only() && !document.is_deleted() {
+        return None;
+    }
+
+    let save_as_file = utils::default_save_folder().child(
+        file.basename()
+            .unwrap_or_else(|| format!("{}.gv", document.title()).into()),
+    );
+    if utils::file_exists(&save_as_file).await {
+        Some(utils::unique_file(&save_as_file).await)
+    } else {
+        Some(save_as_file)
+    }
+}
+
+/// Returns the row listing the document, with a check button to choose
+/// whether to save it and a button to show it.
+///
+/// `save_elsewhere_file` is where to save the document instead of its file,
+/// if any.
+fn item_row(
+    document: &Document,
+    save_elsewhere_file: Option<gio::File>,
+) -> (adw::ActionRow, SaveFileItem<'_>) {
+    debug_assert!(document.is_modified());
+
+    let row = adw::ActionRow::new();
+
+    let check_button = gtk::CheckButton::builder()
+        .valign(gtk::Align::Center)
+        .active(true)
+        .build();
+    row.add_prefix(&check_button);
+    row.set_activatable_widget(Some(&check_button));
+
+    let show_button = gtk::Button::builder()
+        .icon_name("go-next-symbolic")
+        .tooltip_text(gettext("Show Document"))
+        .valign(gtk::Align::Center)
+        .css_classes(["flat"])
+        .build();
+    show_button.connect_clicked(clone!(
+        #[weak]
+        document,
+        move |_| {
+            present_document(&document);
+        }
+    ));
+    row.add_suffix(&show_button);
+
+    let title = document.title();
+
+    let item = if let Some(file) = document.file() {
+        row.set_title(&title);
+        row.set_subtitle(&utils::display_file_parent(
+            save_elsewhere_file.as_ref().unwrap_or(&file),
+        ));
+
+        SaveFileItem {
+            document,
+            check_button,
+            save_as_file: save_elsewhere_file,
+        }
+    } else {
+        let title = if title.is_empty() {
+            gettext("Untitled Document")
+        } else {
+            title
+        };
+        row.set_title(&gettext_f("{title} (new)", &[("title", &title)]));
+
+        let file = utils::default_save_folder().child(format!("{}.gv", title));
+        row.set_subtitle(&utils::display_file_parent(&file));
+
+        SaveFileItem {
+            document,
+            check_button,
+            save_as_file: Some(file),
+        }
+    };
+
+    (row, item)
+}
+
+fn window_has_document(window: &Window, document: &Document) -> bool {
+    window
+        .pages()
+        .iter()
+        .any(|page| &page.document() == document)
+}
+
+/// Selects the page of the document and presents its window, returning the
+/// window.
+fn present_document(document: &Document) -> Option<Window> {
+    let window = Session::instance()
+        .windows()
+        .into_iter()
+        .find(|window| window_has_document(window, document))?;
+    let page = window
+        .pages()
+        .into_iter()
+        .find(|page| &page.document() == document)?;
+
+    window.set_selected_page(&page);
+    window.present();
+
+    Some(window)
+}
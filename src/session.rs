@@ -1,24 +1,42 @@
-use std::time::Instant;
+use std::{
+    mem,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
-use anyhow::Result;
+use adw::prelude::*;
+use anyhow::{Context, Result};
 use gettextrs::gettext;
 use gtk::{
     gio,
     glib::{self, clone},
-    prelude::*,
     subclass::prelude::*,
 };
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    document::Document, graph_view::LayoutEngine, page::Page, recent_list::RecentList, utils,
-    window::Window, Application, APP_DATA_DIR,
+    crash_report,
+    document::Document,
+    document_portal,
+    file_position::FilePosition,
+    graph_view::LayoutEngine,
+    i18n::ngettext_f,
+    page::Page,
+    recent_list::RecentList,
+    utils::{self, NotTextError},
+    window::Window,
+    Application, APP_DATA_DIR,
 };
 
 const DEFAULT_WINDOW_WIDTH: i32 = 1000;
 const DEFAULT_WINDOW_HEIGHT: i32 = 600;
 
-const AUTO_SAVE_DELAY_SECS: u32 = 3;
+const MAX_CLOSED_WINDOWS: usize = 10;
+
+/// Number of files whose layout engine is remembered, older ones are
+/// forgotten.
+const MAX_REMEMBERED_LAYOUT_ENGINES: usize = 500;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct SelectionState {
@@ -57,13 +75,77 @@ impl SelectionState {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ScrollState {
+    offset: f64,
+    /// Whether the cursor was onscreen, so it can be kept onscreen even if
+    /// the file has changed since
+    is_cursor_onscreen: bool,
+}
+
+impl ScrollState {
+    fn for_page(page: &Page) -> Self {
+        Self {
+            offset: page.scroll_offset(),
+            is_cursor_onscreen: page.is_cursor_onscreen(),
+        }
+    }
+
+    fn restore_on(&self, page: &Page) {
+        page.restore_scroll_offset(self.offset, self.is_cursor_onscreen);
+    }
+}
+
+/// Options of a page that are turned on or off.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct PageToggles {
+    #[serde(default)]
+    auto_reload: bool,
+    #[serde(default)]
+    expect_acyclic: bool,
+    #[serde(default)]
+    shows_graph_grid: bool,
+}
+
+impl PageToggles {
+    fn for_page(page: &Page) -> Self {
+        Self {
+            auto_reload: page.auto_reload(),
+            expect_acyclic: page.expect_acyclic(),
+            shows_graph_grid: page.shows_graph_grid(),
+        }
+    }
+
+    fn restore_on(self, page: &Page) {
+        page.set_auto_reload(self.auto_reload);
+        page.set_expect_acyclic(self.expect_acyclic);
+        page.set_shows_graph_grid(self.shows_graph_grid);
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PageState {
     paned_position: i32,
     is_active: bool,
     uri: Option<String>,
     selection: SelectionState,
+    #[serde(default)]
+    scroll: Option<ScrollState>,
     layout_engine: LayoutEngine,
+    /// Flattened, so states saved before the toggles were grouped still load.
+    #[serde(flatten)]
+    toggles: PageToggles,
+    /// Restored once the file is loaded, as loading another file stops
+    /// sharing them.
+    #[serde(default)]
+    shares_view_settings: bool,
+    /// Contents of the document if it has unsaved changes, used to recover
+    /// them after an unclean shutdown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unsaved_contents: Option<String>,
+    /// Contents of the document if it is a draft, which are always restored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    draft_contents: Option<String>,
 }
 
 impl PageState {
@@ -75,13 +157,41 @@ impl PageState {
             is_active: page.is_active(),
             uri: document.file().map(|f| f.uri().into()),
             selection: SelectionState::for_document(&document),
+            scroll: Some(ScrollState::for_page(page)),
             layout_engine: page.layout_engine(),
+            toggles: PageToggles::for_page(page),
+            shares_view_settings: page.shares_view_settings(),
+            unsaved_contents: (!document.is_draft() && document.is_modified())
+                .then(|| document.contents().into()),
+            draft_contents: (document.is_draft() && document.char_count() > 0)
+                .then(|| document.contents().into()),
+        }
+    }
+
+    /// Like `for_page`, but without the unsaved contents, as those are either
+    /// saved or discarded when closing a page. Draft contents are kept, so
+    /// closed drafts can be reopened.
+    pub fn for_closed_page(page: &Page) -> Self {
+        Self {
+            unsaved_contents: None,
+            ..Self::for_page(page)
         }
     }
 
     pub fn restore_on(&self, page: &Page) {
         page.set_paned_position(self.paned_position);
         page.set_layout_engine(self.layout_engine);
+        self.toggles.restore_on(page);
+
+        if let Some(contents) = &self.draft_contents {
+            let document = page.document();
+            document.set_text(contents);
+            self.selection.restore_on(&document);
+
+            if let Some(scroll_state) = &self.scroll {
+                scroll_state.restore_on(page);
+            }
+        }
 
         if let Some(uri) = &self.uri {
             let file = gio::File::for_uri(uri);
@@ -90,6 +200,10 @@ impl PageState {
                 page,
                 #[strong(rename_to = selection_state)]
                 self.selection,
+                #[strong(rename_to = scroll_state)]
+                self.scroll,
+                #[strong(rename_to = shares_view_settings)]
+                self.shares_view_settings,
                 async move {
                     if let Err(err) = page.load_file(file).await {
                         tracing::error!("Failed to load file for page: {:?}", err);
@@ -97,23 +211,36 @@ impl PageState {
                         return;
                     }
 
-                    // Only restore selection once we have fully loaded the page's document.
+                    page.load_shared_view_settings().await;
+                    if shares_view_settings {
+                        page.set_shares_view_settings(true);
+                    }
+
+                    // Only restore selection and scroll once we have fully loaded the page's document.
                     let document = page.document();
                     selection_state.restore_on(&document);
+
+                    if let Some(scroll_state) = scroll_state {
+                        scroll_state.restore_on(&page);
+                    }
                 }
             ));
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct WindowState {
     width: i32,
     height: i32,
     is_maximized: bool,
     is_active: bool,
     pages: Vec<PageState>,
+    #[serde(default)]
     closed_pages: Vec<PageState>,
+    /// URI of the folder listed in the sidebar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    folder_uri: Option<String>,
 }
 
 impl WindowState {
@@ -131,6 +258,27 @@ impl WindowState {
             is_active: window.is_active(),
             pages: page_states,
             closed_pages: window.closed_pages(),
+            folder_uri: window.folder().map(|folder| folder.uri().to_string()),
+        }
+    }
+
+    /// Like `for_window`, but with the pages stored as closed pages, and
+    /// without empty drafts, as those have nothing worth reopening.
+    fn for_closed_window(window: &Window) -> Self {
+        let page_states = window
+            .pages()
+            .iter()
+            .filter(|page| {
+                let document = page.document();
+                !(document.is_draft() && document.char_count() == 0)
+            })
+            .map(PageState::for_closed_page)
+            .collect::<Vec<_>>();
+
+        WindowState {
+            is_active: false,
+            pages: page_states,
+            ..Self::for_window(window)
         }
     }
 
@@ -139,6 +287,10 @@ impl WindowState {
         window.set_maximized(self.is_maximized);
         window.set_closed_pages(self.closed_pages.clone());
 
+        if let Some(uri) = &self.folder_uri {
+            window.set_folder(Some(&gio::File::for_uri(uri)));
+        }
+
         let mut active_page = None;
         for page_state in &self.pages {
             let page = window.add_new_page();
@@ -159,14 +311,27 @@ impl WindowState {
         if self.pages.is_empty() {
             window.add_new_page();
         }
+
+        window.focus_selected_page();
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LayoutEngineState {
+    uri: String,
+    layout_engine: LayoutEngine,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 struct State {
     default_window_width: i32,
     default_window_height: i32,
     windows: Vec<WindowState>,
+    #[serde(default)]
+    closed_windows: Vec<WindowState>,
+    /// Last used layout engine of files, least recently used first
+    #[serde(default)]
+    layout_engines: Vec<LayoutEngineState>,
 }
 
 mod imp {
@@ -177,15 +342,37 @@ mod imp {
     use super::*;
 
     pub struct Session {
-        pub(super) state_file: gio::File,
+        pub(super) state_path: PathBuf,
+        /// The state before the last save, used if the state file is missing
+        /// or corrupted.
+        pub(super) backup_state_path: PathBuf,
+        /// Exists while the app is running, so if it still exists on startup,
+        /// the app was not shut down properly.
+        pub(super) lock_file: gio::File,
+        /// Whether the lock file was created since it was last removed
+        pub(super) is_locked: Cell<bool>,
 
         pub(super) default_window_width: Cell<i32>,
         pub(super) default_window_height: Cell<i32>,
 
         pub(super) windows: RefCell<Vec<Window>>,
+        /// States of closed windows, most recently closed last
+        pub(super) closed_windows: RefCell<Vec<WindowState>>,
+        /// Last used layout engine of files, keyed by URI, least recently
+        /// used first
+        pub(super) layout_engines: RefCell<IndexMap<String, LayoutEngine>>,
+        /// Initialized once the closed windows and layout engines of the
+        /// saved state are merged into the above, so saving doesn't lose
+        /// them when the windows were not restored.
+        pub(super) remembered_state: OnceCell<()>,
         pub(super) recents: OnceCell<RecentList>,
 
         pub(super) is_dirty: Cell<bool>,
+        /// Whether the recents changed since they were last saved, so they
+        /// are not rewritten whenever the windows change.
+        pub(super) are_recents_dirty: Cell<bool>,
+        /// What was last saved, to skip saving an unchanged state.
+        pub(super) saved_state_bytes: RefCell<Vec<u8>>,
         pub(super) auto_save_source_id: RefCell<Option<glib::SourceId>>,
     }
 
@@ -196,12 +383,20 @@ mod imp {
 
         fn new() -> Self {
             Self {
-                state_file: gio::File::for_path(APP_DATA_DIR.join("state.json")),
+                state_path: APP_DATA_DIR.join("state.json"),
+                backup_state_path: APP_DATA_DIR.join("state.json.bak"),
+                lock_file: gio::File::for_path(APP_DATA_DIR.join("session.lock")),
+                is_locked: Cell::new(false),
                 default_window_width: Cell::new(DEFAULT_WINDOW_WIDTH),
                 default_window_height: Cell::new(DEFAULT_WINDOW_HEIGHT),
                 windows: RefCell::default(),
+                closed_windows: RefCell::default(),
+                layout_engines: RefCell::default(),
+                remembered_state: OnceCell::default(),
                 recents: OnceCell::default(),
                 is_dirty: Cell::default(),
+                are_recents_dirty: Cell::default(),
+                saved_state_bytes: RefCell::default(),
                 auto_save_source_id: RefCell::default(),
             }
         }
@@ -228,10 +423,19 @@ impl Session {
 
         imp.recents
             .get_or_init(|| async {
-                RecentList::load().await.unwrap_or_else(|err| {
+                let recents = RecentList::load().await.unwrap_or_else(|err| {
                     tracing::error!("Failed to load recents: {:?}", err);
                     RecentList::new()
-                })
+                });
+                recents.connect_items_changed(clone!(
+                    #[weak(rename_to = obj)]
+                    self,
+                    move |_, _, _, _| {
+                        obj.imp().are_recents_dirty.set(true);
+                        obj.mark_dirty();
+                    }
+                ));
+                recents
             })
             .await
     }
@@ -262,8 +466,46 @@ impl Session {
         let group = gtk::WindowGroup::new();
         group.add_window(&window);
 
+        window.connect_state_changed(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_| {
+                obj.mark_dirty();
+            }
+        ));
+        window.connect_is_active_notify(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |window| {
+                if window.is_active() {
+                    return;
+                }
+
+                // Another window of the app may become active right after.
+                glib::idle_add_local_once(clone!(
+                    #[weak]
+                    obj,
+                    move || {
+                        if !obj.windows().iter().any(|window| window.is_active()) {
+                            obj.save_if_dirty("focus loss");
+                        }
+                    }
+                ));
+            }
+        ));
+        window.connect_suspended_notify(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |window| {
+                if window.is_suspended() {
+                    obj.save_if_dirty("window suspended");
+                }
+            }
+        ));
+
         imp.windows.borrow_mut().push(window.clone());
 
+        self.acquire_lock();
         self.mark_dirty();
 
         window
@@ -315,91 +557,239 @@ impl Session {
                         tracing::debug!("Failed to save session on last window: {:?}", err);
                     }
 
+                    // The app may keep running in the background, but what
+                    // was open was saved properly, so don't offer recovery if
+                    // it is killed from there. Unless another window was
+                    // opened in the meantime.
+                    if matches!(obj.imp().windows.borrow().as_slice(), [w] if *w == window) {
+                        obj.release_lock();
+                    }
+
                     obj.remove_window_inner(&window);
                 }
             ));
         } else {
+            let window_state = WindowState::for_closed_window(window);
+            if !window_state.pages.is_empty() || !window_state.closed_pages.is_empty() {
+                tracing::debug!(n_pages = window_state.pages.len(), "Saved window state");
+
+                let mut closed_windows = imp.closed_windows.borrow_mut();
+                closed_windows.push(window_state);
+
+                let n_excess = closed_windows.len().saturating_sub(MAX_CLOSED_WINDOWS);
+                closed_windows.drain(..n_excess);
+            }
+            self.update_undo_close_window_action();
+
             self.remove_window_inner(window);
         }
     }
 
+    /// Reopens the most recently closed window, returning `None` if there
+    /// is none.
+    pub fn restore_closed_window(&self) -> Option<Window> {
+        let imp = self.imp();
+
+        let window_state = imp.closed_windows.borrow_mut().pop()?;
+
+        let window = self.add_new_raw_window();
+        window_state.restore_on(&window);
+
+        self.update_undo_close_window_action();
+
+        Some(window)
+    }
+
     fn remove_window_inner(&self, window: &Window) {
         let imp = self.imp();
 
         imp.windows.borrow_mut().retain(|w| w != window);
 
+        // Keep the state saved with the last window, to be restored when the
+        // app is activated again while running in the background.
+        if imp.windows.borrow().is_empty() {
+            if let Some(source_id) = imp.auto_save_source_id.take() {
+                source_id.remove();
+            }
+            imp.is_dirty.set(false);
+            return;
+        }
+
         self.mark_dirty();
     }
 
     pub fn open_files(&self, files: &[gio::File], window: &Window) {
-        match files {
-            [] => {
-                tracing::error!("Tried to open empty list of files");
+        if files.is_empty() {
+            tracing::error!("Tried to open empty list of files");
+            return;
+        }
+
+        // Load the first document in the current page if it is a draft and empty,
+        // and the others in new pages.
+        let mut discardable_page = window
+            .selected_page()
+            .filter(|page| page.document().is_safely_discardable());
+
+        let mut loaded = Vec::<&gio::File>::new();
+        let mut last_existing = None;
+        for file in files {
+            if loaded.iter().any(|f| utils::is_same_file(f, file)) {
+                tracing::debug!(uri = %file.uri(), "Skipped duplicate file");
+                continue;
             }
-            [file] => {
-                // If the document is already loaded in other windows or pages, just present it.
-                for window in self.windows() {
-                    for page in window.pages() {
-                        if page
-                            .document()
-                            .file()
-                            .is_some_and(|f| f.uri() == file.uri())
-                        {
-                            window.set_selected_page(&page);
-                            window.present();
-
-                            tracing::debug!("Shown file in an existing page");
-
-                            return;
-                        }
-                    }
-                }
 
-                // Load the document in the current page if it is a draft and empty, otherwise
-                // create a new page and load the document there.
-                let page = match window.selected_page() {
-                    Some(page) if page.document().is_safely_discardable() => page,
-                    _ => window.add_new_page(),
-                };
-                self.load_file(&page, file.clone());
+            if let Some(existing) = self.find_page_for_file(file) {
+                tracing::debug!(uri = %file.uri(), "Skipped already loaded file");
+                last_existing = Some(existing);
+                continue;
             }
-            files => {
-                // If there are many files, simply load them to new pages.
-                for file in files {
-                    let page = window.add_new_page();
-                    self.load_file(&page, file.clone());
-                }
+
+            let page = discardable_page
+                .take()
+                .unwrap_or_else(|| window.add_new_page());
+            self.load_file(&page, file.clone(), None);
+
+            loaded.push(file);
+        }
+
+        // If all documents are already loaded in other windows or pages, just
+        // present them.
+        if loaded.is_empty() {
+            if let Some((existing_window, page)) = last_existing {
+                show_loaded_page(&existing_window, &page, Some(window));
+
+                tracing::debug!("Shown file in an existing page");
+
+                return;
             }
         }
 
+        Application::get().present_window(window);
+    }
+
+    /// Like `open_files`, but loads the files in a new window instead.
+    pub fn open_files_in_new_window(&self, files: &[gio::File]) {
+        // Don't create an empty window just to present an already loaded file.
+        if let [file] = files {
+            if let Some((window, page)) = self.find_page_for_file(file) {
+                show_loaded_page(&window, &page, None);
+                return;
+            }
+        }
+
+        let window = self.add_new_window();
+        self.open_files(files, &window);
+    }
+
+    /// Like `open_files`, but for a single file, and also moves the cursor to
+    /// `position` once the file is loaded.
+    pub fn open_file_at(&self, file: &gio::File, position: FilePosition, window: &Window) {
+        if let Some((existing_window, page)) = self.find_page_for_file(file) {
+            show_loaded_page(&existing_window, &page, Some(window));
+            page.place_cursor_at(position.line, position.column);
+            return;
+        }
+
+        let page = reusable_or_new_page(window);
+        self.load_file(&page, file.clone(), Some(position));
+
+        Application::get().present_window(window);
+    }
+
+    /// Like `open_file_at`, but waits for the file to be loaded, and returns
+    /// the page where it is shown.
+    pub async fn open_file_and_wait(&self, file: &gio::File, window: &Window) -> Result<Page> {
+        utils::resolve_file_identity(file).await;
+        if let Some((window, page)) = self.find_page_for_file(file) {
+            window.set_selected_page(&page);
+            window.present();
+            return Ok(page);
+        }
+
+        let page = reusable_or_new_page(window);
         window.present();
+
+        self.load_file_inner(&page, file.clone()).await?;
+
+        Ok(page)
     }
 
-    pub async fn restore(&self) -> Result<()> {
+    /// Like `open_file_at`, but loads the file in a new window instead.
+    pub fn open_file_at_in_new_window(&self, file: &gio::File, position: FilePosition) {
+        if let Some((window, page)) = self.find_page_for_file(file) {
+            show_loaded_page(&window, &page, None);
+            page.place_cursor_at(position.line, position.column);
+            return;
+        }
+
+        let window = self.add_new_window();
+        self.open_file_at(file, position, &window);
+    }
+
+    /// Returns the page, and its window, where the file is loaded.
+    fn find_page_for_file(&self, file: &gio::File) -> Option<(Window, Page)> {
+        self.windows().into_iter().find_map(|window| {
+            let page = window.pages().into_iter().find(|page| {
+                page.document()
+                    .file()
+                    .is_some_and(|f| utils::is_same_file(&f, file))
+            })?;
+            Some((window, page))
+        })
+    }
+
+    /// Restores the session from the state file.
+    ///
+    /// If `restore_windows` is false, only an empty window is shown, unless
+    /// the app was not shut down properly, so unsaved changes can still be
+    /// recovered.
+    pub async fn restore(&self, restore_windows: bool) -> Result<()> {
         let imp = self.imp();
 
         let now = Instant::now();
 
-        let state = match imp.state_file.load_bytes_future().await {
-            Ok((bytes, _)) => serde_json::from_slice::<State>(&bytes)?,
-            Err(err) => {
-                if !err.matches(gio::IOErrorEnum::NotFound) {
-                    return Err(err.into());
-                }
-
-                State::default()
-            }
-        };
+        let mut state = self.load_saved_state().await?;
         tracing::trace!(?state, "State loaded");
 
+        // Checked before windows are added, as those create the lock file.
+        let is_unclean_shutdown = imp.lock_file.query_exists(gio::Cancellable::NONE);
+
         imp.default_window_width.set(state.default_window_width);
         imp.default_window_height.set(state.default_window_height);
 
+        imp.remembered_state
+            .get_or_init(|| async {
+                self.merge_remembered_state(
+                    mem::take(&mut state.closed_windows),
+                    mem::take(&mut state.layout_engines),
+                );
+            })
+            .await;
+
+        let window_states = if restore_windows || is_unclean_shutdown {
+            state.windows.as_slice()
+        } else {
+            tracing::debug!("Skipped restoring windows");
+            &[]
+        };
+
+        let mut recoverable = Vec::new();
         let mut active_window = None;
-        for window_state in &state.windows {
+        for window_state in window_states {
+            let _span = tracing::debug_span!("restore_window", n_pages = window_state.pages.len())
+                .entered();
+
             let window = self.add_new_raw_window();
             window_state.restore_on(&window);
 
+            if is_unclean_shutdown {
+                for (page, page_state) in window.pages().into_iter().zip(&window_state.pages) {
+                    if let Some(contents) = &page_state.unsaved_contents {
+                        recoverable.push((page, contents.clone()));
+                    }
+                }
+            }
+
             if window_state.is_active {
                 let prev_value = active_window.replace(window);
                 debug_assert!(prev_value.is_none());
@@ -410,16 +800,149 @@ impl Session {
             window.present();
         }
 
-        if state.windows.is_empty() {
+        if window_states.is_empty() {
             let window = self.add_new_window();
             window.present();
         }
 
         tracing::debug!(elapsed = ?now.elapsed(), "Session restored");
 
+        if !recoverable.is_empty() {
+            tracing::debug!(n_pages = recoverable.len(), "Found recoverable pages");
+
+            utils::spawn(clone!(
+                #[weak(rename_to = obj)]
+                self,
+                async move {
+                    obj.offer_recovery(recoverable).await;
+                }
+            ));
+        }
+
         Ok(())
     }
 
+    /// Loads the state at the state file, falling back to its backup.
+    async fn load_saved_state(&self) -> Result<State> {
+        let imp = self.imp();
+
+        let state = match load_state(&imp.state_path).await {
+            Ok(Some(state)) => state,
+            Ok(None) => load_state(&imp.backup_state_path)
+                .await?
+                .unwrap_or_default(),
+            Err(err) => {
+                tracing::warn!("Failed to load state, falling back to backup: {:?}", err);
+
+                load_state(&imp.backup_state_path).await?.ok_or(err)?
+            }
+        };
+        Ok(state)
+    }
+
+    /// Loads the closed windows and layout engines of the saved state, if
+    /// not yet loaded, like when files are opened without restoring the
+    /// session.
+    async fn load_remembered_state(&self) {
+        self.imp()
+            .remembered_state
+            .get_or_init(|| async {
+                match self.load_saved_state().await {
+                    Ok(state) => {
+                        self.merge_remembered_state(state.closed_windows, state.layout_engines);
+                    }
+                    Err(err) => tracing::error!("Failed to load remembered state: {:?}", err),
+                }
+            })
+            .await;
+    }
+
+    /// Merges what the saved state remembers into what was remembered since,
+    /// which is more recent.
+    fn merge_remembered_state(
+        &self,
+        closed_windows: Vec<WindowState>,
+        layout_engines: Vec<LayoutEngineState>,
+    ) {
+        let imp = self.imp();
+
+        let mut all_closed_windows = closed_windows;
+        all_closed_windows.append(&mut imp.closed_windows.borrow_mut());
+        let n_excess = all_closed_windows.len().saturating_sub(MAX_CLOSED_WINDOWS);
+        all_closed_windows.drain(..n_excess);
+        imp.closed_windows.replace(all_closed_windows);
+
+        let mut all_layout_engines = layout_engines
+            .into_iter()
+            .map(|state| (state.uri, state.layout_engine))
+            .collect::<IndexMap<_, _>>();
+        for (uri, layout_engine) in imp.layout_engines.take() {
+            all_layout_engines.shift_remove(&uri);
+            all_layout_engines.insert(uri, layout_engine);
+        }
+        let n_excess = all_layout_engines
+            .len()
+            .saturating_sub(MAX_REMEMBERED_LAYOUT_ENGINES);
+        all_layout_engines.drain(..n_excess);
+        imp.layout_engines.replace(all_layout_engines);
+
+        self.update_undo_close_window_action();
+    }
+
+    /// Remembers `layout_engine` as the last used one for the file at `uri`,
+    /// so it is used again when the file is opened.
+    pub fn remember_layout_engine(&self, uri: String, layout_engine: LayoutEngine) {
+        let imp = self.imp();
+
+        let mut layout_engines = imp.layout_engines.borrow_mut();
+        layout_engines.shift_remove(&uri);
+        layout_engines.insert(uri, layout_engine);
+
+        let n_excess = layout_engines
+            .len()
+            .saturating_sub(MAX_REMEMBERED_LAYOUT_ENGINES);
+        layout_engines.drain(..n_excess);
+        drop(layout_engines);
+
+        self.mark_dirty();
+    }
+
+    /// Creates the lock file, if not yet created, so it can be told on the
+    /// next startup whether the app was shut down properly.
+    ///
+    /// This is done whenever a window is added, as the lock is released when
+    /// the last one is closed, even if the app keeps running in the background.
+    fn acquire_lock(&self) {
+        let imp = self.imp();
+
+        if imp.is_locked.replace(true) {
+            return;
+        }
+
+        if let Err(err) = imp.lock_file.replace_contents(
+            &[],
+            None,
+            false,
+            gio::FileCreateFlags::REPLACE_DESTINATION,
+            gio::Cancellable::NONE,
+        ) {
+            tracing::warn!("Failed to create session lock file: {:?}", err);
+        }
+    }
+
+    /// Removes the lock file, marking that the app was shut down properly.
+    pub fn release_lock(&self) {
+        let imp = self.imp();
+
+        imp.is_locked.set(false);
+
+        if let Err(err) = imp.lock_file.delete(gio::Cancellable::NONE) {
+            if !err.matches(gio::IOErrorEnum::NotFound) {
+                tracing::warn!("Failed to delete session lock file: {:?}", err);
+            }
+        }
+    }
+
     pub async fn save(&self) -> Result<()> {
         let imp = self.imp();
 
@@ -427,6 +950,9 @@ impl Session {
 
         let now = Instant::now();
 
+        // Otherwise, what the saved state remembers would be overwritten.
+        self.load_remembered_state().await;
+
         let window_states = imp
             .windows
             .borrow()
@@ -435,31 +961,107 @@ impl Session {
             .collect::<Vec<_>>();
         let state = State {
             windows: window_states,
+            closed_windows: imp.closed_windows.borrow().clone(),
+            layout_engines: imp
+                .layout_engines
+                .borrow()
+                .iter()
+                .map(|(uri, layout_engine)| LayoutEngineState {
+                    uri: uri.clone(),
+                    layout_engine: *layout_engine,
+                })
+                .collect(),
             default_window_width: imp.default_window_width.get(),
             default_window_height: imp.default_window_height.get(),
         };
         tracing::trace!(?state, "State stored");
 
+        self.update_crash_context();
+
+        // Moving the cursor or scrolling often ends up where it was.
         let bytes = serde_json::to_vec(&state)?;
-        imp.state_file
-            .replace_contents_future(
-                bytes,
-                None,
-                false,
-                gio::FileCreateFlags::REPLACE_DESTINATION,
+        if bytes == *imp.saved_state_bytes.borrow() {
+            tracing::debug!("Session state unchanged, not saved");
+        } else {
+            utils::write_file_atomically(
+                &imp.state_path,
+                bytes.clone(),
+                Some(&imp.backup_state_path),
             )
-            .await
-            .map_err(|(_, err)| err)?;
+            .await?;
+            imp.saved_state_bytes.replace(bytes);
+        }
 
-        self.recents().await.save().await?;
+        // Recents that were never loaded can't have changed.
+        if let Some(recents) = imp.recents.get() {
+            if imp.are_recents_dirty.replace(false) {
+                if let Err(err) = recents.save().await {
+                    imp.are_recents_dirty.set(true);
+                    return Err(err);
+                }
+            }
+        }
 
         tracing::debug!(elapsed = ?now.elapsed(), "Session saved");
 
         Ok(())
     }
 
-    // FIXME Ideally, this should be an internal method and called when State fields change.
-    pub fn mark_dirty(&self) {
+    /// Updates what is included in crash reports about the open documents.
+    fn update_crash_context(&self) {
+        let windows = self.windows();
+        let pages = windows
+            .iter()
+            .flat_map(|window| window.pages())
+            .collect::<Vec<_>>();
+        let n_modified = pages
+            .iter()
+            .filter(|page| page.document().is_modified())
+            .count();
+
+        let summary = format!(
+            "{} windows, {} documents, {} modified",
+            windows.len(),
+            pages.len(),
+            n_modified
+        );
+        let open_uris = pages
+            .iter()
+            .filter_map(|page| page.document().file())
+            .map(|file| file.uri().to_string())
+            .collect();
+        crash_report::set_context(summary, open_uris);
+    }
+
+    /// Saves the session right away if there is a pending auto save, so less
+    /// is lost on crashes.
+    fn save_if_dirty(&self, reason: &'static str) {
+        let imp = self.imp();
+
+        if !imp.is_dirty.get() {
+            return;
+        }
+
+        if let Some(source_id) = imp.auto_save_source_id.take() {
+            source_id.remove();
+        }
+
+        utils::spawn(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            async move {
+                tracing::debug!(reason, "Saving session early");
+
+                if let Err(err) = obj.save().await {
+                    tracing::debug!("Failed to save session early: {:?}", err);
+                }
+            }
+        ));
+    }
+
+    /// Schedules saving the session, called whenever the state of the
+    /// windows or the recents change.
+    fn mark_dirty(&self) {
         let imp = self.imp();
 
         if imp.is_dirty.get() {
@@ -473,7 +1075,7 @@ impl Session {
         }
 
         let source_id = glib::timeout_add_seconds_local_once(
-            AUTO_SAVE_DELAY_SECS,
+            Application::get().settings().session_save_delay_secs(),
             clone!(
                 #[weak(rename_to = obj)]
                 self,
@@ -493,25 +1095,161 @@ impl Session {
         imp.auto_save_source_id.replace(Some(source_id));
     }
 
-    fn load_file(&self, page: &Page, file: gio::File) {
+    async fn offer_recovery(&self, recoverable: Vec<(Page, String)>) {
+        const DISCARD_RESPONSE_ID: &str = "discard";
+        const RECOVER_RESPONSE_ID: &str = "recover";
+
+        let dialog = adw::AlertDialog::builder()
+            .heading(gettext("Recover Unsaved Changes?"))
+            .body(ngettext_f(
+                "Delineate did not close properly. Unsaved changes to {n_documents} document can be recovered.",
+                "Delineate did not close properly. Unsaved changes to {n_documents} documents can be recovered.",
+                recoverable.len() as u32,
+                &[("n_documents", &recoverable.len().to_string())],
+            ))
+            .close_response(DISCARD_RESPONSE_ID)
+            .default_response(RECOVER_RESPONSE_ID)
+            .build();
+
+        dialog.add_response(DISCARD_RESPONSE_ID, &gettext("_Discard"));
+        dialog.add_response(RECOVER_RESPONSE_ID, &gettext("_Recover"));
+
+        dialog.set_response_appearance(DISCARD_RESPONSE_ID, adw::ResponseAppearance::Destructive);
+        dialog.set_response_appearance(RECOVER_RESPONSE_ID, adw::ResponseAppearance::Suggested);
+
+        let window = self.active_window();
+        if dialog.choose_future(&window).await != RECOVER_RESPONSE_ID {
+            return;
+        }
+
+        for (page, contents) in recoverable {
+            page.recover_contents(&contents).await;
+        }
+    }
+
+    fn update_undo_close_window_action(&self) {
+        let is_empty = self.imp().closed_windows.borrow().is_empty();
+
+        let app = Application::get();
+        let action = app
+            .lookup_action("undo-close-window")
+            .and_downcast::<gio::SimpleAction>()
+            .unwrap();
+        action.set_enabled(!is_empty);
+    }
+
+    fn load_file(&self, page: &Page, file: gio::File, position: Option<FilePosition>) {
         utils::spawn(clone!(
             #[weak(rename_to = obj)]
             self,
             #[weak]
             page,
             async move {
-                // Add to recents immediately, so huge files won't be delayed in being added.
-                obj.recents().await.add(file.uri().to_string());
+                if let Err(err) = obj.load_file_inner(&page, file.clone()).await {
+                    if let Some(err) = err.downcast_ref::<NotTextError>() {
+                        page.add_not_text_toast(&file, err.is_image);
+                        return;
+                    }
 
-                if let Err(err) = page.load_file(file).await {
                     tracing::error!("Failed to open file: {:?}", err);
                     page.add_message_toast(&gettext("Failed to open file"));
+                    return;
                 }
 
-                obj.mark_dirty();
+                if let Some(position) = position {
+                    page.place_cursor_at(position.line, position.column);
+                }
             }
         ));
     }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(uri = %file.uri()))]
+    async fn load_file_inner(&self, page: &Page, file: gio::File) -> Result<()> {
+        // Resolved first, so the recent shows where the file really is.
+        document_portal::resolve_host_path(&file).await;
+
+        // Add to recents immediately, so huge files won't be delayed in being added.
+        self.recents().await.add(file.uri().to_string());
+
+        if document_portal::is_portal_file(&file) {
+            let file = file.clone();
+            utils::spawn(async move {
+                if let Err(err) = document_portal::make_persistent(&file).await {
+                    tracing::warn!("Failed to make document persistent: {:?}", err);
+                }
+            });
+        }
+
+        let uri = file.uri();
+        if let Err(err) = page.load_file(file).await {
+            // These were never meant to be opened.
+            if err.is::<NotTextError>() {
+                self.recents().await.remove(&uri);
+            }
+            return Err(err);
+        }
+
+        self.load_remembered_state().await;
+        let layout_engine = self
+            .imp()
+            .layout_engines
+            .borrow()
+            .get(uri.as_str())
+            .copied();
+        if let Some(layout_engine) = layout_engine {
+            page.set_layout_engine(layout_engine);
+        }
+        page.load_shared_view_settings().await;
+
+        Ok(())
+    }
+}
+
+/// Returns the selected page of the window if it can be replaced without
+/// losing anything, or else a new page.
+fn reusable_or_new_page(window: &Window) -> Page {
+    window
+        .selected_page()
+        .filter(|page| page.document().is_safely_discardable())
+        .unwrap_or_else(|| window.add_new_page())
+}
+
+/// Selects and presents the page of an already loaded file. If enabled,
+/// the page is moved to `requesting_window` first, if it is in another one.
+fn show_loaded_page(loaded_window: &Window, page: &Page, requesting_window: Option<&Window>) {
+    let app = Application::get();
+
+    let window = match requesting_window {
+        Some(requesting_window)
+            if requesting_window != loaded_window
+                && app.settings().move_open_files_to_active_window() =>
+        {
+            tracing::debug!("Moving already loaded page to the requesting window");
+
+            loaded_window.move_page_to(page, requesting_window);
+            requesting_window
+        }
+        _ => {
+            loaded_window.set_selected_page(page);
+            loaded_window
+        }
+    };
+
+    app.present_window(window);
+}
+
+/// Loads the state at `path`, returning `None` if it doesn't exist.
+async fn load_state(path: &Path) -> Result<Option<State>> {
+    let file = gio::File::for_path(path);
+    match file.load_bytes_future().await {
+        Ok((bytes, _)) => {
+            let state = serde_json::from_slice::<State>(&bytes)
+                .with_context(|| format!("Failed to parse state at {}", path.display()))?;
+            Ok(Some(state))
+        }
+        Err(err) if err.matches(gio::IOErrorEnum::NotFound) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
 }
 
 impl Default for Session {
@@ -0,0 +1,220 @@
+use gtk::{gio, glib, prelude::*};
+
+use crate::{config::APP_ID, graph_view::LayoutEngine};
+
+const RESTORE_SESSION_KEY: &str = "restore-session";
+const SESSION_SAVE_DELAY_KEY: &str = "session-save-delay";
+const OPEN_IN_NEW_WINDOW_KEY: &str = "open-in-new-window";
+const MOVE_OPEN_FILES_TO_ACTIVE_WINDOW_KEY: &str = "move-open-files-to-active-window";
+const RUN_IN_BACKGROUND_KEY: &str = "run-in-background";
+const DEFAULT_LAYOUT_ENGINE_KEY: &str = "default-layout-engine";
+const GRAPH_TEXT_SCALE_KEY: &str = "graph-text-scale";
+const APPLY_GRAPH_STYLE_TO_EXPORTS_KEY: &str = "apply-graph-style-to-exports";
+const INCREMENTAL_LAYOUT_KEY: &str = "incremental-layout";
+const RECENTS_MAX_COUNT_KEY: &str = "recents-max-count";
+const RECENTS_MAX_AGE_DAYS_KEY: &str = "recents-max-age-days";
+const RECENT_COLORS_KEY: &str = "recent-colors";
+
+/// Number of recently picked colors that are remembered.
+const MAX_RECENT_COLORS: usize = 8;
+
+/// Typed wrapper around the app's `gio::Settings`.
+#[derive(Debug, Clone)]
+pub struct Settings(gio::Settings);
+
+impl Settings {
+    pub fn new() -> Self {
+        Self(gio::Settings::new(APP_ID))
+    }
+
+    pub fn restore_session(&self) -> bool {
+        self.0.boolean(RESTORE_SESSION_KEY)
+    }
+
+    pub fn bind_restore_session<'a>(
+        &'a self,
+        object: &'a impl IsA<glib::Object>,
+        property: &'a str,
+    ) -> gio::BindingBuilder<'a> {
+        self.0.bind(RESTORE_SESSION_KEY, object, property)
+    }
+
+    /// Returns the delay before saving the session after a change, in seconds.
+    pub fn session_save_delay_secs(&self) -> u32 {
+        self.0.uint(SESSION_SAVE_DELAY_KEY)
+    }
+
+    pub fn bind_session_save_delay_secs<'a>(
+        &'a self,
+        object: &'a impl IsA<glib::Object>,
+        property: &'a str,
+    ) -> gio::BindingBuilder<'a> {
+        self.0.bind(SESSION_SAVE_DELAY_KEY, object, property)
+    }
+
+    pub fn open_in_new_window(&self) -> bool {
+        self.0.boolean(OPEN_IN_NEW_WINDOW_KEY)
+    }
+
+    pub fn bind_open_in_new_window<'a>(
+        &'a self,
+        object: &'a impl IsA<glib::Object>,
+        property: &'a str,
+    ) -> gio::BindingBuilder<'a> {
+        self.0.bind(OPEN_IN_NEW_WINDOW_KEY, object, property)
+    }
+
+    pub fn move_open_files_to_active_window(&self) -> bool {
+        self.0.boolean(MOVE_OPEN_FILES_TO_ACTIVE_WINDOW_KEY)
+    }
+
+    pub fn bind_move_open_files_to_active_window<'a>(
+        &'a self,
+        object: &'a impl IsA<glib::Object>,
+        property: &'a str,
+    ) -> gio::BindingBuilder<'a> {
+        self.0
+            .bind(MOVE_OPEN_FILES_TO_ACTIVE_WINDOW_KEY, object, property)
+    }
+
+    pub fn run_in_background(&self) -> bool {
+        self.0.boolean(RUN_IN_BACKGROUND_KEY)
+    }
+
+    pub fn bind_run_in_background<'a>(
+        &'a self,
+        object: &'a impl IsA<glib::Object>,
+        property: &'a str,
+    ) -> gio::BindingBuilder<'a> {
+        self.0.bind(RUN_IN_BACKGROUND_KEY, object, property)
+    }
+
+    pub fn connect_run_in_background_changed<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.0
+            .connect_changed(Some(RUN_IN_BACKGROUND_KEY), move |settings, _| {
+                f(&Self(settings.clone()));
+            })
+    }
+
+    pub fn default_layout_engine(&self) -> LayoutEngine {
+        let raw = self.0.string(DEFAULT_LAYOUT_ENGINE_KEY);
+        LayoutEngine::from_raw(&raw).unwrap_or_else(|| {
+            tracing::warn!("Unknown layout engine `{}`", raw);
+            LayoutEngine::Dot
+        })
+    }
+
+    pub fn set_default_layout_engine(&self, engine: LayoutEngine) {
+        if let Err(err) = self
+            .0
+            .set_string(DEFAULT_LAYOUT_ENGINE_KEY, engine.as_raw())
+        {
+            tracing::warn!("Failed to set default layout engine: {:?}", err);
+        }
+    }
+
+    pub fn graph_text_scale(&self) -> f64 {
+        self.0.double(GRAPH_TEXT_SCALE_KEY)
+    }
+
+    pub fn bind_graph_text_scale<'a>(
+        &'a self,
+        object: &'a impl IsA<glib::Object>,
+        property: &'a str,
+    ) -> gio::BindingBuilder<'a> {
+        self.0.bind(GRAPH_TEXT_SCALE_KEY, object, property)
+    }
+
+    pub fn connect_graph_text_scale_changed<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.0
+            .connect_changed(Some(GRAPH_TEXT_SCALE_KEY), move |settings, _| {
+                f(&Self(settings.clone()));
+            })
+    }
+
+    pub fn apply_graph_style_to_exports(&self) -> bool {
+        self.0.boolean(APPLY_GRAPH_STYLE_TO_EXPORTS_KEY)
+    }
+
+    pub fn bind_apply_graph_style_to_exports<'a>(
+        &'a self,
+        object: &'a impl IsA<glib::Object>,
+        property: &'a str,
+    ) -> gio::BindingBuilder<'a> {
+        self.0
+            .bind(APPLY_GRAPH_STYLE_TO_EXPORTS_KEY, object, property)
+    }
+
+    /// Whether graphs are laid out starting from the previous positions of
+    /// their nodes, with engines that support it.
+    pub fn incremental_layout(&self) -> bool {
+        self.0.boolean(INCREMENTAL_LAYOUT_KEY)
+    }
+
+    pub fn bind_incremental_layout<'a>(
+        &'a self,
+        object: &'a impl IsA<glib::Object>,
+        property: &'a str,
+    ) -> gio::BindingBuilder<'a> {
+        self.0.bind(INCREMENTAL_LAYOUT_KEY, object, property)
+    }
+
+    pub fn disconnect(&self, handler_id: glib::SignalHandlerId) {
+        self.0.disconnect(handler_id);
+    }
+
+    pub fn recents_max_count(&self) -> u32 {
+        self.0.uint(RECENTS_MAX_COUNT_KEY)
+    }
+
+    pub fn bind_recents_max_count<'a>(
+        &'a self,
+        object: &'a impl IsA<glib::Object>,
+        property: &'a str,
+    ) -> gio::BindingBuilder<'a> {
+        self.0.bind(RECENTS_MAX_COUNT_KEY, object, property)
+    }
+
+    /// Returns the maximum age of recents in days, where 0 means no limit.
+    pub fn recents_max_age_days(&self) -> u32 {
+        self.0.uint(RECENTS_MAX_AGE_DAYS_KEY)
+    }
+
+    pub fn bind_recents_max_age_days<'a>(
+        &'a self,
+        object: &'a impl IsA<glib::Object>,
+        property: &'a str,
+    ) -> gio::BindingBuilder<'a> {
+        self.0.bind(RECENTS_MAX_AGE_DAYS_KEY, object, property)
+    }
+
+    /// Returns the recently picked colors, most recent first.
+    pub fn recent_colors(&self) -> Vec<glib::GString> {
+        self.0.strv(RECENT_COLORS_KEY).into_iter().collect()
+    }
+
+    /// Moves `color` to the front of the recent colors, forgetting the
+    /// oldest ones if there are too many.
+    pub fn add_recent_color(&self, color: &str) {
+        let mut colors = self.recent_colors();
+        colors.retain(|c| c != color);
+        colors.insert(0, color.into());
+        colors.truncate(MAX_RECENT_COLORS);
+
+        if let Err(err) = self.0.set_strv(RECENT_COLORS_KEY, colors) {
+            tracing::warn!("Failed to set recent colors: {:?}", err);
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,130 @@
+//! Small previews of graphs, shown next to recent documents.
+
+use std::{collections::HashSet, path::PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use gtk::{gdk_pixbuf, gio, glib, prelude::*};
+
+use crate::{utils, APP_CACHE_DIR};
+
+/// Maximum width and height of thumbnails, large enough to still look sharp
+/// on high density displays.
+const MAX_SIZE: i32 = 128;
+
+/// Returns the file where the thumbnail for the file at `uri` is cached,
+/// which may not exist yet.
+pub fn file_for_uri(uri: &str) -> gio::File {
+    gio::File::for_path(path_for_uri(uri))
+}
+
+/// Renders the graph in `svg_bytes` to a thumbnail for the file at `uri`.
+pub async fn save(uri: &str, svg_bytes: &glib::Bytes) -> Result<()> {
+    let loader = gdk_pixbuf::PixbufLoader::new();
+    loader.connect_size_prepared(|loader, width, height| {
+        let scale = (MAX_SIZE as f64 / width.max(height) as f64).min(1.0);
+        loader.set_size(
+            ((width as f64 * scale) as i32).max(1),
+            ((height as f64 * scale) as i32).max(1),
+        );
+    });
+    loader
+        .write_bytes(svg_bytes)
+        .context("Failed to write SVG bytes")?;
+    loader.close().context("Failed to close loader")?;
+    let pixbuf = loader.pixbuf().context("Loader has no pixbuf")?;
+
+    let buffer = pixbuf.save_to_bufferv("png", &[])?;
+
+    let path = path_for_uri(uri);
+    let dir = path.parent().unwrap().to_path_buf();
+    gio::spawn_blocking(move || {
+        match gio::File::for_path(dir).make_directory_with_parents(gio::Cancellable::NONE) {
+            Err(err) if !err.matches(gio::IOErrorEnum::Exists) => Err(err),
+            _ => Ok(()),
+        }
+    })
+    .await
+    .map_err(|_| anyhow!("Creating directory thread panicked"))??;
+    utils::write_file_atomically(&path, buffer, None).await?;
+
+    tracing::debug!(uri, "Thumbnail saved");
+
+    Ok(())
+}
+
+/// Deletes the thumbnail for the file at `uri`, if any.
+pub async fn delete(uri: &str) -> Result<()> {
+    if let Err(err) = file_for_uri(uri)
+        .delete_future(glib::Priority::DEFAULT)
+        .await
+    {
+        if !err.matches(gio::IOErrorEnum::NotFound) {
+            return Err(err.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Deletes the thumbnails of files other than the ones at `uris`, like ones
+/// left behind by files that are no longer recent.
+pub async fn prune<'a>(uris: impl IntoIterator<Item = &'a str>) -> Result<()> {
+    let kept = uris
+        .into_iter()
+        .map(file_name_for_uri)
+        .collect::<HashSet<_>>();
+
+    let dir = gio::File::for_path(dir_path());
+    let enumerator = match dir
+        .enumerate_children_future(
+            gio::FILE_ATTRIBUTE_STANDARD_NAME,
+            gio::FileQueryInfoFlags::NONE,
+            glib::Priority::LOW,
+        )
+        .await
+    {
+        Ok(enumerator) => enumerator,
+        Err(err) if err.matches(gio::IOErrorEnum::NotFound) => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+
+    loop {
+        let infos = enumerator
+            .next_files_future(64, glib::Priority::LOW)
+            .await?;
+        if infos.is_empty() {
+            break;
+        }
+
+        for info in infos {
+            let name = info.name();
+            if name.to_str().is_some_and(|name| kept.contains(name)) {
+                continue;
+            }
+
+            let file = dir.child(&name);
+            if let Err(err) = file.delete_future(glib::Priority::LOW).await {
+                if !err.matches(gio::IOErrorEnum::NotFound) {
+                    return Err(err.into());
+                }
+            }
+
+            tracing::debug!(name = %name.display(), "Orphaned thumbnail deleted");
+        }
+    }
+
+    Ok(())
+}
+
+fn path_for_uri(uri: &str) -> PathBuf {
+    dir_path().join(file_name_for_uri(uri))
+}
+
+fn file_name_for_uri(uri: &str) -> String {
+    let checksum = glib::compute_checksum_for_string(glib::ChecksumType::Md5, uri).unwrap();
+    format!("{}.png", checksum)
+}
+
+fn dir_path() -> PathBuf {
+    APP_CACHE_DIR.join("thumbnails")
+}
@@ -1,9 +1,41 @@
-use std::{future::Future, path::Path};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    error, fmt, fs,
+    future::Future,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
+use anyhow::{anyhow, Context, Result};
 use gettextrs::gettext;
 use gtk::{gio, glib, prelude::*};
 
-use crate::config::PROFILE;
+use crate::{config::PROFILE, document_portal};
+
+/// Number of bytes read from the start of a file to tell what it contains.
+const SNIFF_N_BYTES: usize = 8192;
+
+thread_local! {
+    /// Canonical paths of native files, by URI, once resolved with
+    /// [`resolve_file_identity`].
+    static CANONICAL_PATHS: RefCell<HashMap<String, PathBuf>> = RefCell::default();
+}
+
+/// Indicates that a file was not loaded because it is not text.
+#[derive(Debug)]
+pub struct NotTextError {
+    /// Whether the file is an image, which other apps can show instead.
+    pub is_image: bool,
+}
+
+impl fmt::Display for NotTextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("File is not text")
+    }
+}
+
+impl error::Error for NotTextError {}
 
 pub fn application_name() -> String {
     gettext("Delineate")
@@ -32,27 +64,408 @@ where
     spawn_with_priority(glib::Priority::default(), fut)
 }
 
-pub fn graphviz_file_filters() -> gio::ListStore {
+/// Filters for dialogs that open DOT files, which may also be any text file.
+pub fn graphviz_open_file_filters() -> gio::ListStore {
+    let text_filter = gtk::FileFilter::new();
+    text_filter.set_name(Some(&gettext("All Text Files")));
+    text_filter.add_mime_type("text/*");
+
+    let filters = graphviz_save_file_filters();
+    filters.append(&text_filter);
+    filters
+}
+
+/// Filters for dialogs that save DOT files.
+pub fn graphviz_save_file_filters() -> gio::ListStore {
     let filter = gtk::FileFilter::new();
     // Translators: DOT is an acronym, do not translate.
     filter.set_name(Some(&gettext("Graphviz DOT Files")));
     filter.add_mime_type("text/vnd.graphviz");
+    filter.add_suffix("gv");
+    filter.add_suffix("dot");
+    filter.add_pattern("*.gv.txt");
 
     let filters = gio::ListStore::new::<gtk::FileFilter>();
     filters.append(&filter);
     filters
 }
 
+/// Returns whether `text` starts like a DOT graph, that is, with an optional
+/// `strict` followed by the `graph` or `digraph` keyword, ignoring comments.
+pub fn looks_like_dot(text: &str) -> bool {
+    let rest = trim_start_comments(text);
+    let rest = strip_keyword(rest, "strict").map_or(rest, trim_start_comments);
+    strip_keyword(rest, "digraph")
+        .or_else(|| strip_keyword(rest, "graph"))
+        .is_some()
+}
+
+/// Returns the ID of the graph that `text` starts with, if it has one, in
+/// `strict digraph "Name" {` for example.
+///
+/// This only looks at the header, so it is cheap enough to run as the text
+/// is edited, even when the rest of it is not valid DOT.
+pub fn dot_graph_id(text: &str) -> Option<String> {
+    let rest = trim_start_comments(text);
+    let rest = strip_keyword(rest, "strict").map_or(rest, trim_start_comments);
+    let rest = strip_keyword(rest, "digraph").or_else(|| strip_keyword(rest, "graph"))?;
+    let rest = trim_start_comments(rest);
+
+    if let Some(quoted) = rest.strip_prefix('"') {
+        let mut id = String::new();
+        let mut chars = quoted.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(id).filter(|id| !id.is_empty()),
+                '\\' => match chars.next()? {
+                    '"' => id.push('"'),
+                    '\n' => {}
+                    other => {
+                        id.push('\\');
+                        id.push(other);
+                    }
+                },
+                _ => id.push(c),
+            }
+        }
+        // The closing quote is not typed yet.
+        return None;
+    }
+
+    let id_len = rest
+        .find(|c: char| !c.is_alphanumeric() && c != '_' && c != '.')
+        .unwrap_or(rest.len());
+    Some(&rest[..id_len])
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+}
+
+/// Returns `text` without the leading whitespace and comments.
+fn trim_start_comments(text: &str) -> &str {
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start();
+
+        if let Some(after) = rest.strip_prefix("//").or_else(|| rest.strip_prefix('#')) {
+            rest = after.split_once('\n').map_or("", |(_, after)| after);
+        } else if let Some(after) = rest.strip_prefix("/*") {
+            rest = after.split_once("*/").map_or("", |(_, after)| after);
+        } else {
+            return rest;
+        }
+    }
+}
+
+/// Returns the rest of `text` after `keyword`, ignoring its case, if `text`
+/// starts with it as a whole word.
+fn strip_keyword<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+    let prefix = text.get(..keyword.len())?;
+    let rest = &text[keyword.len()..];
+
+    let is_whole_word = !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_');
+    (prefix.eq_ignore_ascii_case(keyword) && is_whole_word).then_some(rest)
+}
+
+/// Returns whether `a` and `b` point to the same file, resolving symlinks and
+/// relative path components for native files.
+///
+/// This doesn't touch the file system, so files whose identity was not
+/// resolved with [`resolve_file_identity`] are only compared by URI.
+pub fn is_same_file(a: &gio::File, b: &gio::File) -> bool {
+    if a.equal(b) {
+        return true;
+    }
+
+    // The same file may be exported by the document portal more than once.
+    if let (Some(a_host_path), Some(b_host_path)) =
+        (document_portal::host_path(a), document_portal::host_path(b))
+    {
+        return a_host_path == b_host_path;
+    }
+
+    CANONICAL_PATHS.with_borrow(|canonical_paths| {
+        match (
+            canonical_paths.get(a.uri().as_str()),
+            canonical_paths.get(b.uri().as_str()),
+        ) {
+            (Some(a_path), Some(b_path)) => a_path == b_path,
+            _ => false,
+        }
+    })
+}
+
+/// Looks up what [`is_same_file`] compares `file` by, without blocking, and
+/// caches it.
+pub async fn resolve_file_identity(file: &gio::File) {
+    document_portal::resolve_host_path(file).await;
+
+    let Some(path) = file.path() else {
+        return;
+    };
+
+    let uri = file.uri().to_string();
+    if CANONICAL_PATHS.with_borrow(|canonical_paths| canonical_paths.contains_key(&uri)) {
+        return;
+    }
+
+    match gio::spawn_blocking(move || fs::canonicalize(path)).await {
+        Ok(Ok(canonical_path)) => {
+            CANONICAL_PATHS.with_borrow_mut(|canonical_paths| {
+                canonical_paths.insert(uri, canonical_path);
+            });
+        }
+        Ok(Err(err)) => tracing::debug!(%uri, "Failed to canonicalize path: {:?}", err),
+        Err(_) => tracing::error!("Canonicalizing thread panicked"),
+    }
+}
+
+/// Mounts the volume enclosing the file if it is not yet mounted, as needed
+/// by non-native files, like those on `sftp://` or `smb://`.
+pub async fn ensure_file_mounted(
+    file: &gio::File,
+    parent: Option<&impl IsA<gtk::Window>>,
+) -> Result<(), glib::Error> {
+    if file.is_native() {
+        return Ok(());
+    }
+
+    let mount_operation = gtk::MountOperation::new(parent);
+    match file
+        .mount_enclosing_volume_future(gio::MountMountFlags::NONE, Some(&mount_operation))
+        .await
+    {
+        Ok(()) => {
+            tracing::debug!(uri = %file.uri(), "Mounted enclosing volume");
+            Ok(())
+        }
+        Err(err)
+            if err.matches(gio::IOErrorEnum::AlreadyMounted)
+                || err.matches(gio::IOErrorEnum::NotSupported) =>
+        {
+            Ok(())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns a [`NotTextError`] if the start of the file shows that it is not
+/// text, like images or PDFs dropped onto a window.
+///
+/// Text in legacy encodings is not rejected, as the content type of such
+/// files is unknown rather than binary.
+pub async fn ensure_text_file(file: &gio::File) -> Result<()> {
+    let stream = file.read_future(glib::Priority::DEFAULT).await?;
+    let bytes = stream
+        .read_bytes_future(SNIFF_N_BYTES, glib::Priority::DEFAULT)
+        .await?;
+
+    if bytes.is_empty() {
+        return Ok(());
+    }
+
+    // Only the contents are used, as the extension of dropped files can't
+    // be trusted to match.
+    let (content_type, _) = gio::content_type_guess(None::<&Path>, &bytes);
+    let is_text = has_utf16_or_utf32_bom(&bytes)
+        || (!bytes.contains(&0)
+            && (gio::content_type_is_unknown(&content_type)
+                || gio::content_type_is_a(&content_type, "text/plain")));
+
+    if !is_text {
+        tracing::debug!(uri = %file.uri(), %content_type, "File is not text");
+
+        let is_image = gio::content_type_get_mime_type(&content_type)
+            .is_some_and(|mime_type| mime_type.starts_with("image/"));
+        return Err(NotTextError { is_image }.into());
+    }
+
+    Ok(())
+}
+
+/// Whether `bytes` start with the byte order mark of UTF-16 or UTF-32, whose
+/// text contains NUL bytes.
+fn has_utf16_or_utf32_bom(bytes: &[u8]) -> bool {
+    const BOMS: [&[u8]; 4] = [
+        &[0xFF, 0xFE, 0x00, 0x00],
+        &[0x00, 0x00, 0xFE, 0xFF],
+        &[0xFF, 0xFE],
+        &[0xFE, 0xFF],
+    ];
+
+    BOMS.iter().any(|bom| bytes.starts_with(bom))
+}
+
+/// Moves the most recently trashed file that was originally at `file` back
+/// to its original location.
+pub async fn restore_from_trash(file: &gio::File) -> Result<()> {
+    let original_path = file.path().context("File is not native")?;
+
+    let trash = gio::File::for_uri("trash:///");
+    let enumerator = trash
+        .enumerate_children_future(
+            "standard::name,trash::orig-path,trash::deletion-date",
+            gio::FileQueryInfoFlags::NONE,
+            glib::Priority::DEFAULT,
+        )
+        .await?;
+
+    let mut newest: Option<(gio::FileInfo, glib::GString)> = None;
+    loop {
+        let infos = enumerator
+            .next_files_future(32, glib::Priority::DEFAULT)
+            .await?;
+
+        if infos.is_empty() {
+            break;
+        }
+
+        for info in infos {
+            if info
+                .attribute_byte_string("trash::orig-path")
+                .is_none_or(|orig_path| Path::new(orig_path.as_str()) != original_path)
+            {
+                continue;
+            }
+
+            // The deletion date is in ISO 8601, so it can be compared as is.
+            let deletion_date = info
+                .attribute_string("trash::deletion-date")
+                .unwrap_or_default();
+            if newest
+                .as_ref()
+                .is_none_or(|(_, newest_date)| deletion_date > *newest_date)
+            {
+                newest = Some((info, deletion_date));
+            }
+        }
+    }
+
+    let (info, _) = newest.context("File not found in trash")?;
+    let trashed_file = enumerator.child(&info);
+
+    let (move_fut, _) =
+        trashed_file.move_future(file, gio::FileCopyFlags::NONE, glib::Priority::DEFAULT);
+    move_fut.await?;
+
+    Ok(())
+}
+
+/// Replaces the contents of the file at `path` with `bytes`, such that it
+/// either has the old or the new contents even if the app crashes midway.
+///
+/// If `backup_path` is given, the old contents are moved there.
+pub async fn write_file_atomically(
+    path: &Path,
+    bytes: Vec<u8>,
+    backup_path: Option<&Path>,
+) -> Result<()> {
+    let path = path.to_path_buf();
+    let backup_path = backup_path.map(|p| p.to_path_buf());
+
+    gio::spawn_blocking(move || -> io::Result<()> {
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+
+        let mut tmp_file = fs::File::create(&tmp_path)?;
+        tmp_file.write_all(&bytes)?;
+        tmp_file.sync_all()?;
+        drop(tmp_file);
+
+        if let Some(backup_path) = &backup_path {
+            match fs::rename(&path, backup_path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+
+        fs::rename(&tmp_path, &path)?;
+
+        // Make sure that the renames are also persisted.
+        if let Some(parent) = path.parent() {
+            fs::File::open(parent)?.sync_all()?;
+        }
+
+        Ok(())
+    })
+    .await
+    .map_err(|_| anyhow!("Writing thread panicked"))??;
+
+    Ok(())
+}
+
+/// Returns the folder where documents are suggested to be saved when there
+/// is no better place, like for drafts or files that can't be written to.
+pub fn default_save_folder() -> gio::File {
+    let dir = glib::user_special_dir(glib::UserDirectory::Documents).unwrap_or_else(glib::home_dir);
+    gio::File::for_path(dir)
+}
+
+/// Returns whether `file` exists, like [`gio::prelude::FileExt::query_exists`],
+/// but without blocking.
+pub async fn file_exists(file: &gio::File) -> bool {
+    file.query_info_future(
+        gio::FILE_ATTRIBUTE_STANDARD_TYPE,
+        gio::FileQueryInfoFlags::NONE,
+        glib::Priority::DEFAULT,
+    )
+    .await
+    .is_ok()
+}
+
+/// Returns a file next to `file` whose name is numbered, like `graph (2).svg`
+/// for `graph.svg`, that doesn't exist yet.
+pub async fn unique_file(file: &gio::File) -> gio::File {
+    let (Some(parent), Some(basename)) = (file.parent(), file.basename()) else {
+        return file.clone();
+    };
+    let basename = basename.to_string_lossy();
+
+    let mut n = 2;
+    loop {
+        let numbered_file = parent.child(numbered_file_name(&basename, n));
+        if !file_exists(&numbered_file).await {
+            return numbered_file;
+        }
+        n += 1;
+    }
+}
+
+/// Inserts `n` before the extension of `file_name`.
+fn numbered_file_name(file_name: &str, n: u32) -> String {
+    // Leading dots are part of the name of hidden files.
+    match file_name.rfind('.').filter(|&index| index > 0) {
+        Some(index) => format!("{} ({}){}", &file_name[..index], n, &file_name[index..]),
+        None => format!("{} ({})", file_name, n),
+    }
+}
+
 pub fn display_file_stem(file: &gio::File) -> String {
-    file.path()
-        .unwrap()
+    let Some(basename) = file.basename() else {
+        return file.parse_name().to_string();
+    };
+
+    basename
         .file_stem()
         .unwrap_or_default()
         .to_string_lossy()
         .to_string()
 }
 
+pub fn display_file_basename(file: &gio::File) -> String {
+    let Some(basename) = file.basename() else {
+        return file.parse_name().to_string();
+    };
+
+    basename.to_string_lossy().to_string()
+}
+
 pub fn display_file_parent(file: &gio::File) -> String {
+    if let Some(host_path) = document_portal::host_path(file) {
+        return host_path
+            .parent()
+            .map_or_else(|| "/".to_string(), display_path);
+    }
+
     if let Some(parent) = file.parent() {
         display_file(&parent)
     } else {
@@ -61,10 +474,14 @@ pub fn display_file_parent(file: &gio::File) -> String {
 }
 
 pub fn display_file(file: &gio::File) -> String {
-    if file.is_native() {
-        display_path(&file.path().unwrap())
+    if let Some(host_path) = document_portal::host_path(file) {
+        return display_path(&host_path);
+    }
+
+    if let Some(path) = file.path() {
+        display_path(&path)
     } else {
-        file.uri().to_string()
+        file.parse_name().to_string()
     }
 }
 
@@ -84,3 +501,80 @@ fn display_path(path: &Path) -> String {
 
     path_display
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn graph_id() {
+        assert_eq!(
+            dot_graph_id("digraph Name { a -> b }").as_deref(),
+            Some("Name")
+        );
+        assert_eq!(dot_graph_id("strict  graph\tG_1{}").as_deref(), Some("G_1"));
+        assert_eq!(dot_graph_id("DiGraph 1.5 {").as_deref(), Some("1.5"));
+        assert_eq!(
+            dot_graph_id("// Comment\n/* a */ digraph /* b */ \"My \\\"Graph\\\"\" {").as_deref(),
+            Some("My \"Graph\"")
+        );
+        assert_eq!(dot_graph_id("digraph Na"), Some("Na".to_string()));
+    }
+
+    #[test]
+    fn no_graph_id() {
+        assert_eq!(dot_graph_id("digraph { a -> b }"), None);
+        assert_eq!(dot_graph_id("digraph {Name}"), None);
+        assert_eq!(dot_graph_id("digraph \"\" {"), None);
+        assert_eq!(dot_graph_id("digraph \"Unfinished"), None);
+        assert_eq!(dot_graph_id("digraphs Name {"), None);
+        assert_eq!(dot_graph_id("strict Name {"), None);
+        assert_eq!(dot_graph_id("a -> b"), None);
+        assert_eq!(dot_graph_id(""), None);
+    }
+
+    #[test]
+    fn dot() {
+        assert!(looks_like_dot("digraph { a -> b }"));
+        assert!(looks_like_dot("graph{a -- b}"));
+        assert!(looks_like_dot("strict digraph G {}"));
+        assert!(looks_like_dot("strict\n\tgraph {}"));
+        assert!(looks_like_dot("// Comment\n# Line\n/* Block */ digraph {}"));
+        assert!(looks_like_dot("/* a */ strict /* b */ graph {}"));
+        assert!(looks_like_dot("DiGraph {}"));
+        assert!(looks_like_dot("STRICT GRAPH {}"));
+    }
+
+    #[test]
+    fn not_dot() {
+        assert!(!looks_like_dot("Hello, world!"));
+        assert!(!looks_like_dot("graphs are fun"));
+        assert!(!looks_like_dot("digraphs {}"));
+        assert!(!looks_like_dot("strict"));
+        assert!(!looks_like_dot("strict node {}"));
+        assert!(!looks_like_dot("a -> b"));
+        assert!(!looks_like_dot("// digraph {}"));
+        assert!(!looks_like_dot("/* digraph {} */ a"));
+        assert!(!looks_like_dot(""));
+    }
+
+    #[test]
+    fn numbered() {
+        assert_eq!(numbered_file_name("graph.svg", 2), "graph (2).svg");
+        assert_eq!(numbered_file_name("my.graph.png", 3), "my.graph (3).png");
+        assert_eq!(numbered_file_name("graph", 2), "graph (2)");
+        assert_eq!(numbered_file_name(".graph", 2), ".graph (2)");
+    }
+
+    #[test]
+    fn utf16_or_utf32_bom() {
+        assert!(has_utf16_or_utf32_bom(b"\xFF\xFEa\x00"));
+        assert!(has_utf16_or_utf32_bom(b"\xFE\xFF\x00a"));
+        assert!(has_utf16_or_utf32_bom(b"\xFF\xFE\x00\x00a\x00\x00\x00"));
+        assert!(has_utf16_or_utf32_bom(b"\x00\x00\xFE\xFF\x00\x00\x00a"));
+        assert!(!has_utf16_or_utf32_bom(b"\xEF\xBB\xBFdigraph {}"));
+        assert!(!has_utf16_or_utf32_bom(b"digraph {}"));
+        assert!(!has_utf16_or_utf32_bom(b"\x89PNG\r\n\x1A\n\x00"));
+        assert!(!has_utf16_or_utf32_bom(b""));
+    }
+}
@@ -0,0 +1,132 @@
+//! Preview settings shared in a hidden sidecar file next to a document, at
+//! `.NAME.delineate.json`, so others opening the same file, like from a
+//! shared folder or a repository, get the same preview without the settings
+//! being written in the DOT source.
+//!
+//! Only the layout engine is applied. Other keys, like ones written by newer
+//! versions, are kept as is when the settings are saved again.
+
+use anyhow::{Context, Result};
+use gtk::{gio, glib, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::graph_view::LayoutEngine;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewSettings {
+    /// Stored like in the `layout` attribute, so the file is easy to edit by
+    /// hand.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    layout_engine: Option<String>,
+    /// Keys that are not known, so they are not lost when saving
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ViewSettings {
+    pub fn set_layout_engine(&mut self, layout_engine: LayoutEngine) {
+        self.layout_engine = Some(layout_engine.as_raw().to_string());
+    }
+
+    /// Returns the layout engine, or `None` if it is not set or unknown, like
+    /// one from a newer version.
+    pub fn layout_engine(&self) -> Option<LayoutEngine> {
+        self.layout_engine
+            .as_deref()
+            .and_then(LayoutEngine::from_raw)
+    }
+}
+
+/// Returns the sidecar file of `file`, or `None` if it has no parent, like
+/// the root directory.
+pub fn sidecar_file(file: &gio::File) -> Option<gio::File> {
+    let parent = file.parent()?;
+    let name = file.basename()?;
+    Some(parent.child(format!(".{}.delineate.json", name.display())))
+}
+
+/// Loads the settings shared with `file`, returning `None` if there are none.
+pub async fn load(file: &gio::File) -> Result<Option<ViewSettings>> {
+    let sidecar = sidecar_file(file).context("File has no parent")?;
+    match sidecar.load_bytes_future().await {
+        Ok((bytes, _)) => {
+            let settings = serde_json::from_slice(&bytes)
+                .with_context(|| format!("Invalid view settings at {}", sidecar.uri()))?;
+            Ok(Some(settings))
+        }
+        Err(err) if err.matches(gio::IOErrorEnum::NotFound) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Shares `layout_engine` with `file`, keeping the other settings.
+pub async fn save_layout_engine(file: &gio::File, layout_engine: LayoutEngine) -> Result<()> {
+    let mut settings = load(file).await?.unwrap_or_default();
+    settings.set_layout_engine(layout_engine);
+    save(file, &settings).await
+}
+
+async fn save(file: &gio::File, settings: &ViewSettings) -> Result<()> {
+    let sidecar = sidecar_file(file).context("File has no parent")?;
+    let bytes = serde_json::to_vec_pretty(settings)?;
+    sidecar
+        .replace_contents_future(bytes, None, false, gio::FileCreateFlags::NONE)
+        .await
+        .map_err(|(_, err)| err)?;
+    Ok(())
+}
+
+/// Deletes the settings shared with `file`, if any.
+pub async fn delete(file: &gio::File) -> Result<()> {
+    let sidecar = sidecar_file(file).context("File has no parent")?;
+    match sidecar.delete_future(glib::Priority::DEFAULT).await {
+        Ok(()) => Ok(()),
+        Err(err) if err.matches(gio::IOErrorEnum::NotFound) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidecar_path() {
+        let file = gio::File::for_path("/home/user/graphs/deps.gv");
+        assert_eq!(
+            sidecar_file(&file).unwrap().path().unwrap(),
+            std::path::Path::new("/home/user/graphs/.deps.gv.delineate.json")
+        );
+        assert!(sidecar_file(&gio::File::for_path("/")).is_none());
+    }
+
+    #[test]
+    fn serialization() {
+        let mut settings = ViewSettings::default();
+        settings.set_layout_engine(LayoutEngine::Neato);
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(json, r#"{"layout_engine":"neato"}"#);
+        assert_eq!(
+            serde_json::from_str::<ViewSettings>(&json).unwrap(),
+            settings
+        );
+
+        let unknown =
+            serde_json::from_str::<ViewSettings>(r#"{"layout_engine":"nop2","rankdir":"LR"}"#)
+                .unwrap();
+        assert_eq!(unknown.layout_engine(), None);
+    }
+
+    #[test]
+    fn unknown_keys_round_trip() {
+        let mut settings = serde_json::from_str::<ViewSettings>(
+            r#"{"layout_engine":"dot","rankdir":"LR","theme":{"name":"dark"}}"#,
+        )
+        .unwrap();
+        settings.set_layout_engine(LayoutEngine::Circo);
+        assert_eq!(
+            serde_json::to_string(&settings).unwrap(),
+            r#"{"layout_engine":"circo","rankdir":"LR","theme":{"name":"dark"}}"#
+        );
+    }
+}
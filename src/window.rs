@@ -1,16 +1,25 @@
+use std::slice;
+
 use adw::{prelude::*, subclass::prelude::*};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use gettextrs::gettext;
 use gtk::{
     gdk, gio,
-    glib::{self, clone},
+    glib::{self, clone, closure_local},
 };
 
 use crate::{
     application::Application,
     config::APP_ID,
+    csv_import_dialog, document_switcher,
     export_format::ExportFormat,
+    find_in_documents, graph_view,
+    i18n::gettext_f,
+    import::{self, csv::Table, Graph, ImportFormat},
     page::Page,
+    recent_list::RecentList,
+    recent_row::RecentRow,
+    recent_sorter::RecentSorter,
     save_changes_dialog,
     session::{PageState, Session},
     utils,
@@ -18,18 +27,39 @@ use crate::{
 
 // TODO
 // * Find and replace
-// * modified file on disk handling
 // * Bird's eye view of graph
 // * Full screen view of graph
-// * Drag and drop on tabs
 // * dot language server, hover info, color picker, autocompletion, snippets, renames, etc.
 
 const PAGE_IS_MODIFIED_HANDLER_ID_KEY: &str = "delineate-page-is-modified-handler-id";
+const PAGE_STATE_CHANGED_HANDLER_ID_KEY: &str = "delineate-page-state-changed-handler-id";
+
+/// Number of closed pages that can be reopened, older ones are forgotten.
+const MAX_CLOSED_PAGES: usize = 25;
+
+/// Number of recent documents shown when there are no pages.
+const MAX_GREETER_RECENTS: u32 = 5;
+
+/// Zoom levels of the window's UI, in percent. Each, except the default, must
+/// have a matching `ui-zoom-<level>` style class in `style.css`.
+const UI_ZOOM_LEVELS: [u32; 11] = [50, 67, 80, 90, 100, 110, 120, 133, 150, 170, 200];
+const DEFAULT_UI_ZOOM_LEVEL: u32 = 100;
+
+/// Distance from the top edge, in pixels, at which the bars are revealed
+/// while fullscreen.
+const FULLSCREEN_REVEAL_EDGE_HEIGHT: f64 = 6.0;
 
 mod imp {
-    use std::cell::{OnceCell, RefCell};
+    use std::{
+        cell::{Cell, OnceCell, RefCell},
+        sync::LazyLock,
+    };
+
+    use glib::subclass::Signal;
 
-    use crate::{drag_overlay::DragOverlay, recent_popover::RecentPopover};
+    use crate::{
+        drag_overlay::DragOverlay, folder_sidebar::FolderSidebar, recent_popover::RecentPopover,
+    };
 
     use super::*;
 
@@ -41,22 +71,45 @@ mod imp {
         #[template_child]
         pub(super) tab_overview: TemplateChild<adw::TabOverview>,
         #[template_child]
+        pub(super) export_graph_submenu: TemplateChild<gio::Menu>,
+        #[template_child]
+        pub(super) share_graph_submenu: TemplateChild<gio::Menu>,
+        #[template_child]
+        pub(super) move_to_window_section: TemplateChild<gio::Menu>,
+        #[template_child]
+        pub(super) toolbar_view: TemplateChild<adw::ToolbarView>,
+        #[template_child]
         pub(super) recent_popover: TemplateChild<RecentPopover>,
         #[template_child]
+        pub(super) folder_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
         pub(super) document_modified_status: TemplateChild<gtk::Label>,
         #[template_child]
+        pub(super) document_read_only_status: TemplateChild<gtk::Image>,
+        #[template_child]
         pub(super) document_title_label: TemplateChild<gtk::Label>,
         #[template_child]
+        pub(super) leave_fullscreen_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub(super) tab_button: TemplateChild<adw::TabButton>,
         #[template_child]
+        pub(super) all_pages_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub(super) split_view: TemplateChild<adw::OverlaySplitView>,
+        #[template_child]
+        pub(super) folder_sidebar: TemplateChild<FolderSidebar>,
+        #[template_child]
         pub(super) drag_overlay: TemplateChild<DragOverlay>,
         #[template_child]
         pub(super) stack: TemplateChild<gtk::Stack>,
         #[template_child]
         pub(super) empty_page: TemplateChild<adw::StatusPage>,
         #[template_child]
+        pub(super) greeter_list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
         pub(super) tab_view: TemplateChild<adw::TabView>,
 
+        pub(super) ui_zoom_level: Cell<u32>,
         pub(super) inhibit_cookie: RefCell<Option<u32>>,
         pub(super) closed_pages: RefCell<Vec<PageState>>,
         pub(super) selected_page_signals: OnceCell<glib::SignalGroup>,
@@ -88,6 +141,46 @@ mod imp {
                 }
             });
 
+            klass.install_action_async("win.open-folder", None, |obj, _, _| async move {
+                if let Err(err) = obj.open_folder().await {
+                    if !err
+                        .downcast_ref::<glib::Error>()
+                        .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                    {
+                        tracing::error!("Failed to open folder: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to open folder"));
+                    }
+                }
+            });
+
+            klass.install_action("win.close-folder", None, |obj, _, _| {
+                obj.set_folder(None);
+            });
+
+            klass.install_action_async("win.import-graph", None, |obj, _, _| async move {
+                if let Err(err) = obj.import_graph().await {
+                    if !err
+                        .downcast_ref::<glib::Error>()
+                        .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                    {
+                        tracing::error!("Failed to import graph: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to import graph"));
+                    }
+                }
+            });
+
+            klass.install_action_async("win.import-csv", None, |obj, _, _| async move {
+                if let Err(err) = obj.import_csv().await {
+                    if !err
+                        .downcast_ref::<glib::Error>()
+                        .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                    {
+                        tracing::error!("Failed to import CSV: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to import CSV"));
+                    }
+                }
+            });
+
             klass.install_action_async("win.save-document", None, |obj, _, _| async move {
                 let page = obj.selected_page().unwrap();
                 debug_assert!(page.can_save());
@@ -100,7 +193,12 @@ mod imp {
                         tracing::error!("Failed to save document: {:?}", err);
                         obj.add_message_toast(&gettext("Failed to save document"));
                     }
+                    return;
                 }
+
+                let session = Session::instance();
+                let file = page.document().file().unwrap();
+                session.recents().await.add(file.uri().into());
             });
 
             klass.install_action_async("win.save-document-as", None, |obj, _, _| async move {
@@ -115,9 +213,73 @@ mod imp {
                         tracing::error!("Failed to save document as: {:?}", err);
                         obj.add_message_toast(&gettext("Failed to save document as"));
                     }
+                    return;
+                }
+
+                let session = Session::instance();
+                let file = page.document().file().unwrap();
+                session.recents().await.add(file.uri().into());
+            });
+
+            klass.install_action_async("win.save-document-copy", None, |obj, _, _| async move {
+                let page = obj.selected_page().unwrap();
+                debug_assert!(page.can_save());
+
+                if let Err(err) = page.save_document_copy().await {
+                    if !err
+                        .downcast_ref::<glib::Error>()
+                        .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                    {
+                        tracing::error!("Failed to save document copy: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to save document copy"));
+                    }
+                }
+            });
+
+            klass.install_action_async("win.rename-document", None, |obj, _, _| async move {
+                let page = obj.selected_page().unwrap();
+                debug_assert!(page.can_rename());
+
+                let document = page.document();
+                let old_uri = document.file().unwrap().uri();
+
+                if let Err(err) = page.rename_document().await {
+                    if !err
+                        .downcast_ref::<glib::Error>()
+                        .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                    {
+                        tracing::error!("Failed to rename document: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to rename document"));
+                    }
+                    return;
+                }
+
+                let session = Session::instance();
+                let recents = session.recents().await;
+                recents.remove(&old_uri);
+                recents.add(document.file().unwrap().uri().into());
+            });
+
+            klass.install_action_async("win.trash-document", None, |obj, _, _| async move {
+                let page = obj.selected_page().unwrap();
+                debug_assert!(page.can_trash());
+
+                if let Err(err) = obj.trash_page_document(&page).await {
+                    if !err
+                        .downcast_ref::<glib::Error>()
+                        .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                    {
+                        tracing::error!("Failed to move document to trash: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to move document to trash"));
+                    }
                 }
             });
 
+            klass.install_action("win.duplicate-document", None, |obj, _, _| {
+                let page = obj.selected_page().unwrap();
+                obj.duplicate_page(&page);
+            });
+
             klass.install_action_async(
                 "win.discard-document-changes",
                 None,
@@ -146,18 +308,23 @@ mod imp {
                 },
             );
 
+            klass.install_action("win.copy-file-path", None, |obj, _, _| {
+                let page = obj.selected_page().unwrap();
+
+                if let Err(err) = page.copy_file_path() {
+                    tracing::error!("Failed to copy file path: {:?}", err);
+                    obj.add_message_toast(&gettext("Failed to copy file path"));
+                }
+            });
+
             klass.install_action_async(
                 "win.export-graph",
                 Some(&String::static_variant_type()),
                 |obj, _, arg| async move {
                     let raw_format = arg.unwrap().get::<String>().unwrap();
 
-                    let format = match raw_format.as_str() {
-                        "svg" => ExportFormat::Svg,
-                        "png" => ExportFormat::Png,
-                        "jpeg" => ExportFormat::Jpeg,
-                        _ => unreachable!("unknown format `{}`", raw_format),
-                    };
+                    let format = ExportFormat::from_raw(&raw_format)
+                        .unwrap_or_else(|| unreachable!("unknown format `{}`", raw_format));
 
                     let page = obj.selected_page().unwrap();
                     debug_assert!(page.can_export_graph());
@@ -174,6 +341,127 @@ mod imp {
                 },
             );
 
+            klass.install_action_async(
+                "win.export-graph-with-engine",
+                None,
+                |obj, _, _| async move {
+                    let page = obj.selected_page().unwrap();
+                    debug_assert!(page.can_export_graph());
+
+                    if let Err(err) = page.export_graph_with_engine().await {
+                        if !err
+                            .downcast_ref::<glib::Error>()
+                            .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                        {
+                            tracing::error!("Failed to export graph: {:?}", err);
+                            obj.add_message_toast(&gettext("Failed to export graph"));
+                        }
+                    }
+                },
+            );
+
+            klass.install_action_async("win.export-graph-multiple", None, |obj, _, _| async move {
+                let page = obj.selected_page().unwrap();
+                debug_assert!(page.can_export_graph());
+
+                if let Err(err) = page.export_graph_multiple().await {
+                    if !err
+                        .downcast_ref::<glib::Error>()
+                        .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                    {
+                        tracing::error!("Failed to export graph: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to export graph"));
+                    }
+                }
+            });
+
+            klass.install_action_async(
+                "win.share-graph",
+                Some(&String::static_variant_type()),
+                |obj, _, arg| async move {
+                    let raw_format = arg.unwrap().get::<String>().unwrap();
+
+                    let format = ExportFormat::from_raw(&raw_format)
+                        .unwrap_or_else(|| unreachable!("unknown format `{}`", raw_format));
+
+                    let page = obj.selected_page().unwrap();
+                    debug_assert!(page.can_export_graph());
+
+                    if let Err(err) = page.share_graph(format).await {
+                        if !err
+                            .downcast_ref::<glib::Error>()
+                            .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                        {
+                            tracing::error!("Failed to share graph: {:?}", err);
+                            obj.add_message_toast(&gettext("Failed to share graph"));
+                        }
+                    }
+                },
+            );
+
+            klass.install_action_async(
+                "win.export-adjacency-matrix",
+                None,
+                |obj, _, _| async move {
+                    let page = obj.selected_page().unwrap();
+                    debug_assert!(page.can_export_graph());
+
+                    if let Err(err) = page.export_adjacency_matrix().await {
+                        if !err
+                            .downcast_ref::<glib::Error>()
+                            .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                        {
+                            tracing::error!("Failed to export adjacency matrix: {:?}", err);
+                            obj.add_message_toast(&gettext("Failed to export adjacency matrix"));
+                        }
+                    }
+                },
+            );
+
+            klass.install_action_async("win.insert-file", None, |obj, _, _| async move {
+                let page = obj.selected_page().unwrap();
+                debug_assert!(page.can_save());
+
+                if let Err(err) = page.insert_file().await {
+                    if !err
+                        .downcast_ref::<glib::Error>()
+                        .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                    {
+                        tracing::error!("Failed to insert file: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to insert file"));
+                    }
+                }
+            });
+
+            klass.install_action("win.extract-subgraph", None, |obj, _, _| {
+                let page = obj.selected_page().unwrap();
+
+                match page.extract_subgraph_at_cursor() {
+                    Ok(Some(contents)) => {
+                        let new_page = obj.add_new_page();
+                        new_page.set_layout_engine(page.layout_engine());
+                        new_page.document().set_text(&contents);
+                    }
+                    Ok(None) => {
+                        obj.add_message_toast(&gettext("Place the cursor inside a named subgraph"));
+                    }
+                    Err(err) => {
+                        tracing::error!("Failed to extract subgraph: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to extract subgraph"));
+                    }
+                }
+            });
+
+            klass.install_action("win.copy-graph-as-mermaid", None, |obj, _, _| {
+                let page = obj.selected_page().unwrap();
+                debug_assert!(page.can_export_graph());
+
+                if let Err(err) = page.copy_graph_as_mermaid() {
+                    tracing::error!("Failed to convert graph to Mermaid: {:?}", err);
+                    obj.add_message_toast(&gettext("Failed to convert graph to Mermaid"));
+                }
+            });
+
             klass.install_action(
                 "win.select-page",
                 Some(&i32::static_variant_type()),
@@ -187,6 +475,12 @@ mod imp {
                 },
             );
 
+            klass.install_action("win.select-last-page", None, |obj, _, _| {
+                if let Some(page) = obj.pages().last() {
+                    obj.set_selected_page(page);
+                }
+            });
+
             klass.install_action("win.move-page-to-left", None, |obj, _, _| {
                 let imp = obj.imp();
                 if let Some(page) = obj.selected_page() {
@@ -216,6 +510,26 @@ mod imp {
                         .transfer_page(&tab_page, &new_window.imp().tab_view, 0);
                 }
             });
+            klass.install_action(
+                "win.move-page-to-window",
+                Some(&u32::static_variant_type()),
+                |obj, _, arg| {
+                    let window_id = arg.unwrap().get::<u32>().unwrap();
+                    let Some(window) = Session::instance()
+                        .windows()
+                        .into_iter()
+                        .find(|window| window.id() == window_id)
+                    else {
+                        tracing::warn!(window_id, "Window to move page to not found");
+                        return;
+                    };
+
+                    if let Some(page) = obj.selected_page() {
+                        obj.move_page_to(&page, &window);
+                        window.present();
+                    }
+                },
+            );
             klass.install_action_async("win.close-other-pages", None, |obj, _, _| async move {
                 if let Some(page) = obj.selected_page() {
                     let pages = obj.pages();
@@ -226,6 +540,19 @@ mod imp {
                     }
                 }
             });
+            klass.install_action_async("win.close-pages-after", None, |obj, _, _| async move {
+                if let Some(page) = obj.selected_page() {
+                    let pages = obj.pages();
+                    let pages_to_close = pages
+                        .into_iter()
+                        .skip_while(|p| p != &page)
+                        .skip(1)
+                        .collect::<Vec<_>>();
+                    if !pages_to_close.is_empty() {
+                        obj.request_close_pages(&pages_to_close).await;
+                    }
+                }
+            });
             klass.install_action_async("win.close-page", None, |obj, _, _| async move {
                 if let Some(page) = obj.selected_page() {
                     obj.request_close_pages(&[page]).await;
@@ -239,9 +566,60 @@ mod imp {
                 }
             });
 
+            klass.install_action_async("win.switch-document", None, |obj, _, _| async move {
+                match document_switcher::run(&obj).await {
+                    Some(document_switcher::Choice::Page(window, page)) => {
+                        window.set_selected_page(&page);
+                        window.present();
+                    }
+                    Some(document_switcher::Choice::File(file)) => {
+                        Session::instance().open_files(&[file], &obj);
+                    }
+                    None => {}
+                }
+            });
+            klass.install_action_async("win.find-in-documents", None, |obj, _, _| async move {
+                let Some(choice) = find_in_documents::run(&obj).await else {
+                    return;
+                };
+
+                match choice.target {
+                    find_in_documents::Target::Page(window, page) => {
+                        window.set_selected_page(&page);
+                        window.present();
+                        page.place_cursor_at(choice.position.line, choice.position.column);
+                        page.focus_editor();
+                    }
+                    find_in_documents::Target::File(file) => {
+                        Session::instance().open_file_at(&file, choice.position, &obj);
+                    }
+                }
+            });
+
+            klass.install_property_action("win.toggle-fullscreen", "fullscreened");
+
+            klass.install_action("win.zoom-ui-in", None, |obj, _, _| {
+                let current = obj.imp().ui_zoom_level.get();
+                if let Some(&level) = UI_ZOOM_LEVELS.iter().find(|&&level| level > current) {
+                    obj.set_ui_zoom_level(level);
+                }
+            });
+            klass.install_action("win.zoom-ui-out", None, |obj, _, _| {
+                let current = obj.imp().ui_zoom_level.get();
+                if let Some(&level) = UI_ZOOM_LEVELS.iter().rev().find(|&&level| level < current) {
+                    obj.set_ui_zoom_level(level);
+                }
+            });
+            klass.install_action("win.reset-ui-zoom", None, |obj, _, _| {
+                obj.set_ui_zoom_level(DEFAULT_UI_ZOOM_LEVEL);
+            });
+
             klass.install_action("win.undo-close-page", None, |obj, _, _| {
                 obj.restore_closed_page();
             });
+            klass.install_action("win.undo-close-all-pages", None, |obj, _, _| {
+                obj.restore_all_closed_pages();
+            });
 
             klass.add_binding_action(
                 gdk::Key::T,
@@ -263,6 +641,49 @@ mod imp {
                 gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK,
                 "win.save-document-as",
             );
+            // Plain Ctrl+plus and Ctrl+minus are taken by the graph zoom.
+            for key in [gdk::Key::plus, gdk::Key::equal, gdk::Key::KP_Add] {
+                klass.add_binding_action(
+                    key,
+                    gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::ALT_MASK,
+                    "win.zoom-ui-in",
+                );
+            }
+            for key in [gdk::Key::minus, gdk::Key::KP_Subtract] {
+                klass.add_binding_action(
+                    key,
+                    gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::ALT_MASK,
+                    "win.zoom-ui-out",
+                );
+            }
+            for key in [gdk::Key::_0, gdk::Key::KP_0] {
+                klass.add_binding_action(
+                    key,
+                    gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::ALT_MASK,
+                    "win.reset-ui-zoom",
+                );
+            }
+
+            klass.add_binding_action(
+                gdk::Key::F11,
+                gdk::ModifierType::empty(),
+                "win.toggle-fullscreen",
+            );
+            klass.add_binding_action(
+                gdk::Key::P,
+                gdk::ModifierType::CONTROL_MASK,
+                "win.switch-document",
+            );
+            klass.add_binding_action(
+                gdk::Key::F,
+                gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK,
+                "win.find-in-documents",
+            );
+            klass.add_binding_action(
+                gdk::Key::F2,
+                gdk::ModifierType::empty(),
+                "win.rename-document",
+            );
 
             add_select_page_binding(klass, gdk::Key::_1, 0);
             add_select_page_binding(klass, gdk::Key::_2, 1);
@@ -272,7 +693,11 @@ mod imp {
             add_select_page_binding(klass, gdk::Key::_6, 5);
             add_select_page_binding(klass, gdk::Key::_7, 6);
             add_select_page_binding(klass, gdk::Key::_8, 7);
-            add_select_page_binding(klass, gdk::Key::_9, 8);
+
+            // Like in browsers, the last digit always selects the last page.
+            for modifier in [gdk::ModifierType::CONTROL_MASK, gdk::ModifierType::ALT_MASK] {
+                klass.add_binding_action(gdk::Key::_9, modifier, "win.select-last-page");
+            }
 
             klass.add_binding_action(
                 gdk::Key::Page_Up,
@@ -305,10 +730,24 @@ mod imp {
                 "win.close-page-or-window",
             );
 
-            klass.add_binding_action(
+            klass.add_binding(
                 gdk::Key::T,
                 gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK,
-                "win.undo-close-page",
+                |obj| {
+                    // Like in browsers, reopen the last closed window once
+                    // there are no more closed pages in this window.
+                    if !obj.imp().closed_pages.borrow().is_empty() {
+                        obj.restore_closed_page();
+                        return glib::Propagation::Stop;
+                    }
+
+                    let session = Session::instance();
+                    if session.restore_closed_window().is_some() {
+                        glib::Propagation::Stop
+                    } else {
+                        glib::Propagation::Proceed
+                    }
+                },
             );
         }
 
@@ -329,6 +768,9 @@ mod imp {
 
             self.empty_page.set_icon_name(Some(APP_ID));
 
+            remove_unsupported_format_items(&self.export_graph_submenu);
+            remove_unsupported_format_items(&self.share_graph_submenu);
+
             let selected_page_signals = glib::SignalGroup::new::<Page>();
             selected_page_signals.connect_notify_local(
                 Some("title"),
@@ -350,6 +792,16 @@ mod imp {
                     }
                 ),
             );
+            selected_page_signals.connect_notify_local(
+                Some("is-read-only"),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_, _| {
+                        obj.update_read_only_status();
+                    }
+                ),
+            );
             selected_page_signals.connect_notify_local(
                 Some("can-save"),
                 clone!(
@@ -390,6 +842,26 @@ mod imp {
                     }
                 ),
             );
+            selected_page_signals.connect_notify_local(
+                Some("can-trash"),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_, _| {
+                        obj.update_trash_action();
+                    }
+                ),
+            );
+            selected_page_signals.connect_notify_local(
+                Some("can-rename"),
+                clone!(
+                    #[weak]
+                    obj,
+                    move |_, _| {
+                        obj.update_rename_action();
+                    }
+                ),
+            );
             self.selected_page_signals
                 .set(selected_page_signals)
                 .unwrap();
@@ -424,12 +896,37 @@ mod imp {
                 move |_| {
                     obj.update_stack_page();
                     obj.update_selected_page_signals_target();
+                    obj.focus_selected_page();
+                    obj.emit_state_changed();
                 }
             ));
-            self.tab_view.connect_create_window(clone!(
+            self.tab_view.connect_page_attached(clone!(
                 #[weak]
                 obj,
-                #[upgrade_or_panic]
+                move |_, tab_page, _| {
+                    let page = tab_page.child().downcast::<Page>().unwrap();
+                    obj.handle_tab_view_page_attached(&page);
+                }
+            ));
+            self.tab_view.connect_page_detached(clone!(
+                #[weak]
+                obj,
+                move |_, tab_page, _| {
+                    let page = tab_page.child().downcast::<Page>().unwrap();
+                    obj.handle_tab_view_page_detached(&page);
+                }
+            ));
+            self.tab_view.connect_page_reordered(clone!(
+                #[weak]
+                obj,
+                move |_, _, _| {
+                    obj.emit_state_changed();
+                }
+            ));
+            self.tab_view.connect_create_window(clone!(
+                #[weak]
+                obj,
+                #[upgrade_or_panic]
                 move |_| {
                     let session = Session::instance();
 
@@ -449,6 +946,7 @@ mod imp {
                     if let Some(tab_page) = tab_page {
                         let page = tab_page.child().downcast::<Page>().unwrap();
                         obj.set_selected_page(&page);
+                        obj.update_move_to_window_section();
                     }
                 }
             ));
@@ -468,6 +966,34 @@ mod imp {
                 .transform_to(|_, n_pages: i32| Some(n_pages > 0))
                 .sync_create()
                 .build();
+            self.tab_view.connect_n_pages_notify(clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.update_select_page_actions();
+                }
+            ));
+
+            self.greeter_list_box.connect_row_activated(clone!(
+                #[weak]
+                obj,
+                move |_, row| {
+                    let row = row.downcast_ref::<RecentRow>().unwrap();
+                    if let Some(item) = row.item() {
+                        let session = Session::instance();
+                        session.open_files(&[item.file()], &obj);
+                    }
+                }
+            ));
+
+            self.folder_sidebar.connect_file_activated(clone!(
+                #[weak]
+                obj,
+                move |_, file| {
+                    let session = Session::instance();
+                    session.open_files(slice::from_ref(file), &obj);
+                }
+            ));
 
             self.recent_popover.connect_item_activated(clone!(
                 #[weak]
@@ -478,6 +1004,12 @@ mod imp {
                 }
             ));
 
+            self.recent_popover
+                .connect_item_activated_in_new_window(|_, item| {
+                    let session = Session::instance();
+                    session.open_files_in_new_window(&[item.file()]);
+                });
+
             self.recent_popover.begin_loading();
             utils::spawn(clone!(
                 #[weak]
@@ -487,16 +1019,62 @@ mod imp {
                     let session = Session::instance();
                     let recents = session.recents().await;
                     imp.recent_popover.bind_model(recents);
+                    obj.bind_greeter_model(recents);
                 }
             ));
 
+            obj.connect_fullscreened_notify(|obj| {
+                obj.update_fullscreen();
+            });
+
+            self.all_pages_button.set_create_popup_func(clone!(
+                #[weak]
+                obj,
+                move |button| {
+                    button.set_menu_model(Some(&obj.all_pages_menu()));
+                }
+            ));
+
+            let motion_controller = gtk::EventControllerMotion::new();
+            motion_controller.connect_motion(clone!(
+                #[weak]
+                obj,
+                move |_, _, y| {
+                    obj.handle_motion(y);
+                }
+            ));
+            obj.add_controller(motion_controller);
+
+            for property_name in ["default-width", "default-height", "maximized", "is-active"] {
+                obj.connect_notify_local(Some(property_name), |obj, _| {
+                    obj.emit_state_changed();
+                });
+            }
+
             obj.update_stack_page();
             obj.update_selected_page_signals_target();
             obj.update_undo_close_page_action();
+            obj.update_select_page_actions();
+            obj.update_folder_actions();
+            obj.update_fullscreen();
+            obj.set_ui_zoom_level(DEFAULT_UI_ZOOM_LEVEL);
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: LazyLock<Vec<Signal>> =
+                LazyLock::new(|| vec![Signal::builder("state-changed").build()]);
+
+            SIGNALS.as_ref()
         }
     }
 
-    impl WidgetImpl for Window {}
+    impl WidgetImpl for Window {
+        fn map(&self) {
+            self.parent_map();
+
+            graph_view::init_after_first_frame(&*self.obj());
+        }
+    }
 
     impl WindowImpl for Window {
         fn close_request(&self) -> glib::Propagation {
@@ -550,6 +1128,21 @@ impl Window {
         glib::Object::builder().property("application", app).build()
     }
 
+    /// Emitted when state stored in the session, like the window size or the
+    /// state of one of the pages, changes.
+    pub fn connect_state_changed<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_closure(
+            "state-changed",
+            false,
+            closure_local!(|obj: &Self| {
+                f(obj);
+            }),
+        )
+    }
+
     pub fn add_toast(&self, toast: adw::Toast) {
         self.imp().toast_overlay.add_toast(toast);
     }
@@ -568,6 +1161,12 @@ impl Window {
         page.bind_property("title", &tab_page, "title")
             .sync_create()
             .build();
+        page.bind_property("tooltip", &tab_page, "tooltip")
+            .sync_create()
+            .build();
+        page.bind_property("keyword", &tab_page, "keyword")
+            .sync_create()
+            .build();
         page.bind_property("is-busy", &tab_page, "loading")
             .sync_create()
             .build();
@@ -583,24 +1182,8 @@ impl Window {
             })
             .build();
 
-        unsafe {
-            let is_modified_handler_id = page.connect_is_modified_notify(clone!(
-                #[weak(rename_to = obj)]
-                self,
-                move |_| {
-                    obj.update_inhibit();
-                }
-            ));
-            page.set_data(PAGE_IS_MODIFIED_HANDLER_ID_KEY, is_modified_handler_id);
-        }
-
-        self.update_inhibit();
-
         imp.tab_view.set_selected_page(&tab_page);
 
-        let session = Session::instance();
-        session.mark_dirty();
-
         page
     }
 
@@ -646,6 +1229,26 @@ impl Window {
         imp.tab_view.unblock_signal(handler_id);
     }
 
+    /// Shows the contents in a draft, in the current page if it is a draft and
+    /// empty, otherwise in a new page.
+    pub fn open_draft(&self, contents: &str) {
+        let page = match self.selected_page() {
+            Some(page) if page.document().is_safely_discardable() => page,
+            _ => self.add_new_page(),
+        };
+        page.document().set_text(contents);
+
+        self.present();
+    }
+
+    /// Moves the keyboard focus to the editor of the selected page, instead
+    /// of leaving it on whatever was focused before, like the tab bar.
+    pub fn focus_selected_page(&self) {
+        if let Some(page) = self.selected_page() {
+            page.focus_editor();
+        }
+    }
+
     pub fn pages(&self) -> Vec<Page> {
         self.imp()
             .tab_view
@@ -683,9 +1286,25 @@ impl Window {
         imp.tab_view.set_selected_page(&tab_page);
     }
 
-    pub fn set_closed_pages(&self, page_states: Vec<PageState>) {
+    /// Moves the page to the end of the other window, and selects it there.
+    pub fn move_page_to(&self, page: &Page, window: &Window) {
+        let imp = self.imp();
+
+        let other_tab_view = &window.imp().tab_view;
+
+        let tab_page = imp.tab_view.page(page);
+        imp.tab_view
+            .transfer_page(&tab_page, other_tab_view, other_tab_view.n_pages());
+
+        window.set_selected_page(page);
+    }
+
+    pub fn set_closed_pages(&self, mut page_states: Vec<PageState>) {
         let imp = self.imp();
 
+        let n_excess = page_states.len().saturating_sub(MAX_CLOSED_PAGES);
+        page_states.drain(..n_excess);
+
         imp.closed_pages.replace(page_states);
         self.update_undo_close_page_action();
     }
@@ -696,10 +1315,27 @@ impl Window {
         imp.closed_pages.borrow().clone()
     }
 
+    /// Returns the folder whose graphs are listed in the sidebar, if any.
+    pub fn folder(&self) -> Option<gio::File> {
+        self.imp().folder_sidebar.folder()
+    }
+
+    /// Lists the graphs in `folder` in the sidebar, or hides the sidebar if
+    /// it is `None`.
+    pub fn set_folder(&self, folder: Option<&gio::File>) {
+        let imp = self.imp();
+
+        imp.folder_sidebar.set_folder(folder);
+        imp.split_view.set_show_sidebar(folder.is_some());
+
+        self.update_folder_actions();
+        self.emit_state_changed();
+    }
+
     async fn open_document(&self) -> Result<()> {
         let dialog = gtk::FileDialog::builder()
             .title(gettext("Open Document"))
-            .filters(&utils::graphviz_file_filters())
+            .filters(&utils::graphviz_open_file_filters())
             .modal(true)
             .build();
         let file = dialog.open_future(Some(self)).await?;
@@ -710,28 +1346,173 @@ impl Window {
         Ok(())
     }
 
+    async fn open_folder(&self) -> Result<()> {
+        let dialog = gtk::FileDialog::builder()
+            .title(gettext("Open Folder"))
+            .modal(true)
+            .build();
+        if let Some(folder) = self.folder() {
+            dialog.set_initial_folder(Some(&folder));
+        }
+        let folder = dialog.select_folder_future(Some(self)).await?;
+
+        self.set_folder(Some(&folder));
+
+        tracing::debug!(uri = %folder.uri(), "Folder opened");
+
+        Ok(())
+    }
+
+    /// Converts a graph from another format to DOT and opens it in a new draft
+    /// page.
+    async fn import_graph(&self) -> Result<()> {
+        let dialog = gtk::FileDialog::builder()
+            .title(gettext("Import Graph"))
+            .accept_label(gettext("_Import"))
+            .filters(&import::file_filters())
+            .modal(true)
+            .build();
+        let file = dialog.open_future(Some(self)).await?;
+
+        let format = ImportFormat::for_file(&file).context("Unknown import format")?;
+
+        let (bytes, _) = file.load_contents_future().await?;
+        let graph = format.parse(&String::from_utf8_lossy(&bytes))?;
+        self.show_imported_graph(&graph);
+
+        tracing::debug!(uri = %file.uri(), ?format, "Graph imported");
+
+        Ok(())
+    }
+
+    async fn import_csv(&self) -> Result<()> {
+        let dialog = gtk::FileDialog::builder()
+            .title(gettext("Import CSV"))
+            .accept_label(gettext("_Import"))
+            .filters(&import::csv_file_filters())
+            .modal(true)
+            .build();
+        let file = dialog.open_future(Some(self)).await?;
+
+        let (bytes, _) = file.load_contents_future().await?;
+        let table = Table::parse(&String::from_utf8_lossy(&bytes))?;
+
+        let Some(mapping) = csv_import_dialog::run(self, &table).await else {
+            return Err(glib::Error::new(gtk::DialogError::Dismissed, "Import cancelled").into());
+        };
+
+        let graph = table.to_graph(&mapping);
+        self.show_imported_graph(&graph);
+
+        tracing::debug!(uri = %file.uri(), ?mapping, "CSV imported");
+
+        Ok(())
+    }
+
+    /// Shows `graph` as DOT in the selected page if it can be discarded,
+    /// otherwise in a new page.
+    fn show_imported_graph(&self, graph: &Graph) {
+        let page = match self.selected_page() {
+            Some(page) if page.document().is_safely_discardable() => page,
+            _ => self.add_new_page(),
+        };
+        page.document().set_text(&graph.to_dot());
+    }
+
     fn remove_page(&self, page: &Page) {
+        self.remove_page_inner(page, true);
+    }
+
+    fn remove_page_inner(&self, page: &Page, add_to_closed_pages: bool) {
         let imp = self.imp();
 
-        if !page.document().is_draft() {
-            let page_state = PageState::for_page(page);
+        // Empty drafts have nothing worth reopening.
+        let document = page.document();
+        if add_to_closed_pages && !(document.is_draft() && document.char_count() == 0) {
+            let page_state = PageState::for_closed_page(page);
             tracing::debug!(?page_state, "Saved page state");
 
-            imp.closed_pages.borrow_mut().push(page_state);
+            let mut closed_pages = imp.closed_pages.borrow_mut();
+            closed_pages.push(page_state);
+
+            let n_excess = closed_pages.len().saturating_sub(MAX_CLOSED_PAGES);
+            closed_pages.drain(..n_excess);
+            drop(closed_pages);
+
             self.update_undo_close_page_action();
+            self.emit_state_changed();
         }
+    }
 
-        unsafe {
-            let is_modified_handler_id = page
-                .steal_data::<glib::SignalHandlerId>(PAGE_IS_MODIFIED_HANDLER_ID_KEY)
-                .unwrap();
-            page.disconnect(is_modified_handler_id);
+    /// Moves the page's file to the trash and closes the page, discarding any
+    /// unsaved changes.
+    async fn trash_page_document(&self, page: &Page) -> Result<()> {
+        let imp = self.imp();
+
+        let document = page.document();
+        let file = document.file().context("No file")?;
+
+        if !confirm_trash(self, &page.title(), document.is_modified()).await {
+            return Err(glib::Error::new(gtk::DialogError::Dismissed, "Trash cancelled").into());
         }
 
+        file.trash_future(glib::Priority::DEFAULT).await?;
+
+        tracing::debug!(uri = %file.uri(), "File moved to trash");
+
+        let handler_id = imp.tab_view_close_page_handler_id.get().unwrap();
+        imp.tab_view.block_signal(handler_id);
+        imp.tab_view.close_page(&imp.tab_view.page(page));
+        imp.tab_view.unblock_signal(handler_id);
+
+        // The file is gone, so the page can't be restored with undo close page.
+        self.remove_page_inner(page, false);
+
         let session = Session::instance();
-        session.mark_dirty();
+        session.recents().await.remove(&file.uri());
 
-        self.update_inhibit();
+        let toast = adw::Toast::builder()
+            .title(gettext_f(
+                "“{title}” moved to trash",
+                &[("title", &page.title())],
+            ))
+            .button_label(gettext("Undo"))
+            .build();
+        toast.connect_button_clicked(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_| {
+                let file = file.clone();
+                utils::spawn(async move {
+                    if let Err(err) = utils::restore_from_trash(&file).await {
+                        tracing::error!("Failed to restore file from trash: {:?}", err);
+                        obj.add_message_toast(&gettext("Failed to restore file from trash"));
+                        return;
+                    }
+
+                    Session::instance().open_files(&[file], &obj);
+                });
+            }
+        ));
+        self.add_toast(toast);
+
+        Ok(())
+    }
+
+    /// Opens the contents of the page in a new draft page next to it.
+    fn duplicate_page(&self, page: &Page) {
+        let imp = self.imp();
+
+        let new_page = self.add_new_page();
+        new_page.set_layout_engine(page.layout_engine());
+        new_page.document().set_text(&page.document().contents());
+
+        let tab_page = imp.tab_view.page(page);
+        let new_tab_page = imp.tab_view.page(&new_page);
+        imp.tab_view
+            .reorder_page(&new_tab_page, imp.tab_view.page_position(&tab_page) + 1);
+
+        tracing::debug!(title = %page.title(), "Duplicated page");
     }
 
     fn restore_closed_page(&self) {
@@ -742,13 +1523,124 @@ impl Window {
             let page = self.add_new_page();
             page_state.restore_on(&page);
 
-            let session = Session::instance();
-            session.mark_dirty();
-
             self.update_undo_close_page_action();
         }
     }
 
+    /// Reopens all closed pages, in the order they were closed.
+    fn restore_all_closed_pages(&self) {
+        let imp = self.imp();
+
+        let page_states = imp.closed_pages.take();
+        for page_state in &page_states {
+            let page = self.add_new_page();
+            page_state.restore_on(&page);
+        }
+
+        tracing::debug!(n_pages = page_states.len(), "Restored all closed pages");
+
+        self.update_undo_close_page_action();
+    }
+
+    fn bind_greeter_model(&self, recents: &RecentList) {
+        let imp = self.imp();
+
+        let sort_model = gtk::SortListModel::new(Some(recents.clone()), Some(RecentSorter::new()));
+        let slice_model = gtk::SliceListModel::new(Some(sort_model), 0, MAX_GREETER_RECENTS);
+        slice_model.connect_items_changed(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |slice_model, _, _, _| {
+                obj.imp()
+                    .greeter_list_box
+                    .set_visible(slice_model.n_items() > 0);
+            }
+        ));
+
+        imp.greeter_list_box.bind_model(
+            Some(&slice_model),
+            clone!(
+                #[weak]
+                recents,
+                #[upgrade_or_panic]
+                move |item| {
+                    let row = RecentRow::new(item.downcast_ref());
+                    row.connect_open_in_new_window_request(|row| {
+                        if let Some(item) = row.item() {
+                            let session = Session::instance();
+                            session.open_files_in_new_window(&[item.file()]);
+                        }
+                    });
+                    row.connect_toggle_pin_request(clone!(
+                        #[weak]
+                        recents,
+                        move |row| {
+                            if let Some(item) = row.item() {
+                                recents.set_pinned(&item.file().uri(), !item.is_pinned());
+                            }
+                        }
+                    ));
+                    row.connect_remove_request(clone!(
+                        #[weak]
+                        recents,
+                        move |row| {
+                            if let Some(item) = row.item() {
+                                recents.remove(&item.file().uri());
+                            }
+                        }
+                    ));
+                    row.upcast()
+                }
+            ),
+        );
+        imp.greeter_list_box.set_visible(slice_model.n_items() > 0);
+    }
+
+    // Pages may be moved between windows, either by dragging their tab or
+    // with the tab menu, so anything connected to a page must be connected
+    // here rather than when the page is created.
+    fn handle_tab_view_page_attached(&self, page: &Page) {
+        unsafe {
+            let is_modified_handler_id = page.connect_is_modified_notify(clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_| {
+                    obj.update_inhibit();
+                }
+            ));
+            page.set_data(PAGE_IS_MODIFIED_HANDLER_ID_KEY, is_modified_handler_id);
+
+            let state_changed_handler_id = page.connect_state_changed(clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |_| {
+                    obj.emit_state_changed();
+                }
+            ));
+            page.set_data(PAGE_STATE_CHANGED_HANDLER_ID_KEY, state_changed_handler_id);
+        }
+
+        self.update_inhibit();
+        self.emit_state_changed();
+    }
+
+    fn handle_tab_view_page_detached(&self, page: &Page) {
+        unsafe {
+            let is_modified_handler_id = page
+                .steal_data::<glib::SignalHandlerId>(PAGE_IS_MODIFIED_HANDLER_ID_KEY)
+                .unwrap();
+            page.disconnect(is_modified_handler_id);
+
+            let state_changed_handler_id = page
+                .steal_data::<glib::SignalHandlerId>(PAGE_STATE_CHANGED_HANDLER_ID_KEY)
+                .unwrap();
+            page.disconnect(state_changed_handler_id);
+        }
+
+        self.update_inhibit();
+        self.emit_state_changed();
+    }
+
     fn handle_tab_view_close_page(&self, tab_page: &adw::TabPage) -> glib::Propagation {
         let page = tab_page.child().downcast::<Page>().unwrap();
 
@@ -794,6 +1686,105 @@ impl Window {
         true
     }
 
+    /// Scales the editor font of all pages in this window.
+    fn set_ui_zoom_level(&self, level: u32) {
+        let imp = self.imp();
+
+        debug_assert!(UI_ZOOM_LEVELS.contains(&level));
+
+        let prev_level = imp.ui_zoom_level.replace(level);
+        self.remove_css_class(&format!("ui-zoom-{}", prev_level));
+        self.add_css_class(&format!("ui-zoom-{}", level));
+
+        self.action_set_enabled(
+            "win.zoom-ui-in",
+            level < UI_ZOOM_LEVELS[UI_ZOOM_LEVELS.len() - 1],
+        );
+        self.action_set_enabled("win.zoom-ui-out", level > UI_ZOOM_LEVELS[0]);
+        self.action_set_enabled("win.reset-ui-zoom", level != DEFAULT_UI_ZOOM_LEVEL);
+
+        tracing::debug!(level, "UI zoom level changed");
+    }
+
+    /// Reveals the bars when hovering the top edge while fullscreen, and
+    /// hides them again once the pointer leaves them.
+    fn handle_motion(&self, y: f64) {
+        let imp = self.imp();
+
+        if !self.is_fullscreen() {
+            return;
+        }
+
+        if y <= FULLSCREEN_REVEAL_EDGE_HEIGHT {
+            imp.toolbar_view.set_reveal_top_bars(true);
+        } else if imp.toolbar_view.reveals_top_bars()
+            && y > imp.toolbar_view.top_bar_height() as f64
+        {
+            imp.toolbar_view.set_reveal_top_bars(false);
+        }
+    }
+
+    fn update_fullscreen(&self) {
+        let imp = self.imp();
+
+        let is_fullscreen = self.is_fullscreen();
+        imp.toolbar_view
+            .set_extend_content_to_top_edge(is_fullscreen);
+        imp.toolbar_view.set_reveal_top_bars(!is_fullscreen);
+        imp.leave_fullscreen_button.set_visible(is_fullscreen);
+    }
+
+    /// Returns a menu listing all pages, to select one of them.
+    fn all_pages_menu(&self) -> gio::Menu {
+        let menu = gio::Menu::new();
+
+        for (index, page) in self.pages().iter().enumerate() {
+            let title = if page.is_modified() {
+                format!("• {}", page.title())
+            } else {
+                page.title()
+            };
+            // Prevent underscores from being interpreted as mnemonics.
+            let label = title.replace('_', "__");
+
+            let item = gio::MenuItem::new(Some(&label), None);
+            item.set_action_and_target_value(
+                Some("win.select-page"),
+                Some(&(index as i32).to_variant()),
+            );
+            menu.append_item(&item);
+        }
+
+        menu
+    }
+
+    /// Lists the other windows, by the title of their selected page, as
+    /// targets to move the selected page to.
+    fn update_move_to_window_section(&self) {
+        let imp = self.imp();
+
+        imp.move_to_window_section.remove_all();
+
+        for window in Session::instance().windows() {
+            if &window == self {
+                continue;
+            }
+
+            let title = window
+                .selected_page()
+                .map_or_else(|| gettext("Empty Window"), |page| page.title());
+            // Prevent underscores from being interpreted as mnemonics.
+            let label = title.replace('_', "__");
+
+            let item = gio::MenuItem::new(Some(&label), None);
+            item.set_action_and_target_value(
+                Some("win.move-page-to-window"),
+                Some(&window.id().to_variant()),
+            );
+            imp.move_to_window_section.append_item(&item);
+        }
+    }
+
     fn update_inhibit(&self) {
         let imp = self.imp();
 
@@ -836,10 +1827,13 @@ impl Window {
 
         self.update_title();
         self.update_modified_status();
+        self.update_read_only_status();
         self.update_save_action();
         self.update_discard_changes_action();
         self.update_export_graph_action();
         self.update_open_containing_folder_action();
+        self.update_rename_action();
+        self.update_trash_action();
     }
 
     fn update_title(&self) {
@@ -868,10 +1862,22 @@ impl Window {
         imp.document_modified_status.set_visible(is_modified);
     }
 
+    fn update_read_only_status(&self) {
+        let imp = self.imp();
+        let is_read_only = self
+            .selected_page()
+            .map(|page| page.is_read_only())
+            .unwrap_or_default();
+        imp.document_read_only_status.set_visible(is_read_only);
+    }
+
     fn update_save_action(&self) {
         let can_save = self.selected_page().is_some_and(|page| page.can_save());
         self.action_set_enabled("win.save-document", can_save);
         self.action_set_enabled("win.save-document-as", can_save);
+        self.action_set_enabled("win.save-document-copy", can_save);
+        self.action_set_enabled("win.duplicate-document", can_save);
+        self.action_set_enabled("win.insert-file", can_save);
     }
 
     fn update_discard_changes_action(&self) {
@@ -886,6 +1892,11 @@ impl Window {
             .selected_page()
             .is_some_and(|page| page.can_export_graph());
         self.action_set_enabled("win.export-graph", can_export_graph);
+        self.action_set_enabled("win.export-graph-multiple", can_export_graph);
+        self.action_set_enabled("win.export-graph-with-engine", can_export_graph);
+        self.action_set_enabled("win.share-graph", can_export_graph);
+        self.action_set_enabled("win.export-adjacency-matrix", can_export_graph);
+        self.action_set_enabled("win.copy-graph-as-mermaid", can_export_graph);
     }
 
     fn update_open_containing_folder_action(&self) {
@@ -893,27 +1904,105 @@ impl Window {
             .selected_page()
             .is_some_and(|page| page.can_open_containing_folder());
         self.action_set_enabled("win.open-containing-folder", can_open_containing_folder);
+        self.action_set_enabled("win.copy-file-path", can_open_containing_folder);
+    }
+
+    fn update_trash_action(&self) {
+        let can_trash = self.selected_page().is_some_and(|page| page.can_trash());
+        self.action_set_enabled("win.trash-document", can_trash);
+    }
+
+    fn update_rename_action(&self) {
+        let can_rename = self.selected_page().is_some_and(|page| page.can_rename());
+        self.action_set_enabled("win.rename-document", can_rename);
+    }
+
+    fn emit_state_changed(&self) {
+        self.emit_by_name::<()>("state-changed", &[]);
+    }
+
+    fn update_select_page_actions(&self) {
+        let has_pages = self.n_pages() > 0;
+        self.action_set_enabled("win.select-page", has_pages);
+        self.action_set_enabled("win.select-last-page", has_pages);
+    }
+
+    fn update_folder_actions(&self) {
+        let imp = self.imp();
+
+        let has_folder = self.folder().is_some();
+        imp.folder_button.set_visible(has_folder);
+        self.action_set_enabled("win.close-folder", has_folder);
     }
 
     fn update_undo_close_page_action(&self) {
         let is_empty = self.imp().closed_pages.borrow().is_empty();
         self.action_set_enabled("win.undo-close-page", !is_empty);
+        self.action_set_enabled("win.undo-close-all-pages", !is_empty);
     }
 }
 
+/// Binds both Ctrl and Alt with `key` to select the page at `page_index`.
+///
+/// If there is no such page, the key press is left for other handlers.
 fn add_select_page_binding(
     klass: &mut <imp::Window as ObjectSubclass>::Class,
     key: gdk::Key,
     page_index: i32,
 ) {
-    klass.add_shortcut(
-        &gtk::Shortcut::builder()
-            .trigger(&gtk::KeyvalTrigger::new(
-                key,
-                gdk::ModifierType::CONTROL_MASK,
-            ))
-            .action(&gtk::NamedAction::new("win.select-page"))
-            .arguments(&page_index.into())
-            .build(),
-    );
+    for modifier in [gdk::ModifierType::CONTROL_MASK, gdk::ModifierType::ALT_MASK] {
+        klass.add_binding(key, modifier, move |obj| {
+            if page_index >= obj.n_pages() {
+                return glib::Propagation::Proceed;
+            }
+
+            WidgetExt::activate_action(obj, "win.select-page", Some(&page_index.to_variant()))
+                .unwrap();
+            glib::Propagation::Stop
+        });
+    }
+}
+
+/// Returns `true` if the user agreed to move the file to the trash.
+async fn confirm_trash(window: &Window, title: &str, is_modified: bool) -> bool {
+    const CANCEL_RESPONSE_ID: &str = "cancel";
+    const TRASH_RESPONSE_ID: &str = "trash";
+
+    let body = if is_modified {
+        gettext_f(
+            "“{title}” will be moved to the trash and its unsaved changes will be permanently lost.",
+            &[("title", title)],
+        )
+    } else {
+        gettext_f("“{title}” will be moved to the trash.", &[("title", title)])
+    };
+
+    let dialog = adw::AlertDialog::builder()
+        .heading(gettext("Move to Trash?"))
+        .body(body)
+        .close_response(CANCEL_RESPONSE_ID)
+        .default_response(CANCEL_RESPONSE_ID)
+        .build();
+
+    dialog.add_response(CANCEL_RESPONSE_ID, &gettext("Cancel"));
+    dialog.add_response(TRASH_RESPONSE_ID, &gettext("_Move to Trash"));
+
+    dialog.set_response_appearance(TRASH_RESPONSE_ID, adw::ResponseAppearance::Destructive);
+
+    dialog.choose_future(window).await == TRASH_RESPONSE_ID
+}
+
+/// Removes the items of the menu that target an export format that can't be
+/// used at runtime.
+fn remove_unsupported_format_items(menu: &gio::Menu) {
+    for index in (0..menu.n_items()).rev() {
+        let is_unsupported = menu
+            .item_attribute_value(index, gio::MENU_ATTRIBUTE_TARGET, None)
+            .and_then(|target| target.get::<String>())
+            .and_then(|raw_format| ExportFormat::from_raw(&raw_format))
+            .is_some_and(|format| !format.is_supported());
+        if is_unsupported {
+            menu.remove(index);
+        }
+    }
 }